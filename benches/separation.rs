@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use saunds_v2::audio::AudioProcessor;
+
+const FFT_SIZES: [usize; 3] = [1024, 2048, 4096];
+
+/// Deterministic two-tone test signal, long enough to span many FFT windows.
+fn test_signal(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / 44100.0;
+            0.5 * (2.0 * std::f32::consts::PI * 110.0 * t).sin()
+                + 0.25 * (2.0 * std::f32::consts::PI * 5000.0 * t).sin()
+        })
+        .collect()
+}
+
+fn bench_separate_frequencies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("separate_frequencies");
+
+    for seconds in [1usize, 10] {
+        let samples = test_signal(44100 * seconds);
+        group.throughput(Throughput::Elements(samples.len() as u64));
+
+        for window_size in FFT_SIZES {
+            let processor = AudioProcessor::new().unwrap().with_window_size(window_size);
+            let id = BenchmarkId::new(format!("fft{}", window_size), format!("{}s", seconds));
+            group.bench_with_input(id, &samples, |b, samples| {
+                b.iter(|| processor.separate_frequencies(black_box(samples), 200.0, 2000.0).unwrap())
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_separate_frequencies);
+criterion_main!(benches);
@@ -0,0 +1,111 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
+use saunds_v2::audio::{stft, AudioProcessor};
+
+const FFT_SIZES: [usize; 4] = [512, 1024, 2048, 4096];
+
+/// Builds an MPEG-1 Layer III stream (128 kbps, 44.1 kHz, stereo) of
+/// `frames` silent frames. There is no MP3 encoder in the dependency tree,
+/// but all-zero side info is a valid frame that still exercises the full
+/// synthesis filterbank in the decoder.
+fn silent_mp3(frames: usize) -> Vec<u8> {
+    const FRAME_LEN: usize = 417;
+    let mut data = Vec::with_capacity(frames * FRAME_LEN);
+    for _ in 0..frames {
+        data.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        data.resize(data.len() + FRAME_LEN - 4, 0);
+    }
+    data
+}
+
+fn test_frame(len: usize) -> Vec<f32> {
+    (0..len).map(|i| (i as f32 * 0.05).sin()).collect()
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut processor = AudioProcessor::new().unwrap();
+    let mut group = c.benchmark_group("decode_mp3");
+
+    // 1152 samples per frame: roughly 1 and 10 seconds of audio
+    for frames in [38usize, 383] {
+        let data = silent_mp3(frames);
+        group.throughput(Throughput::Elements((frames * 1152) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(frames), &data, |b, data| {
+            b.iter(|| processor.decode_mp3(black_box(data.as_slice())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_windowing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_window");
+
+    for size in FFT_SIZES {
+        let window_func = stft::sqrt_hann_window(size);
+        let input = test_frame(size);
+        let mut frame = vec![0.0; size];
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| stft::apply_window(black_box(&input), 0, &window_func, &mut frame))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_masking(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fft_mask");
+
+    for size in FFT_SIZES {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(size);
+        let ifft = planner.plan_fft_inverse(size);
+
+        let source = test_frame(size);
+        let mut input = source.clone();
+        let mut spectrum = fft.make_output_vec();
+        fft.process(&mut input, &mut spectrum).unwrap();
+
+        let mut band = vec![Complex::new(0.0, 0.0); spectrum.len()];
+        let mut output = ifft.make_output_vec();
+        let mut fft_scratch = fft.make_scratch_vec();
+        let mut ifft_scratch = ifft.make_scratch_vec();
+        let bins = size / 100..size / 10;
+
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::new("mask", size), |b| {
+            b.iter(|| stft::apply_band_mask(black_box(&spectrum), bins.clone(), &mut band))
+        });
+        group.bench_function(BenchmarkId::new("forward_mask_inverse", size), |b| {
+            b.iter(|| {
+                input.copy_from_slice(&source);
+                fft.process_with_scratch(&mut input, &mut spectrum, &mut fft_scratch).unwrap();
+                stft::apply_band_mask(&spectrum, bins.clone(), &mut band);
+                ifft.process_with_scratch(&mut band, &mut output, &mut ifft_scratch).unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_overlap_add(c: &mut Criterion) {
+    let mut group = c.benchmark_group("overlap_add");
+
+    for size in FFT_SIZES {
+        let window_func = stft::sqrt_hann_window(size);
+        let frame = test_frame(size);
+        let mut output = vec![0.0; size];
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| stft::overlap_add(black_box(&frame), 0, &window_func, 1.0 / size as f32, &mut output))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_windowing, bench_masking, bench_overlap_add);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+/// Generates the `saunds.Separator` service from a manual description,
+/// so building needs no `protoc`. Keep it in step with
+/// `proto/saunds.proto`.
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let service = Service::builder()
+        .name("Separator")
+        .package("saunds")
+        .method(
+            Method::builder()
+                .name("split")
+                .route_name("Split")
+                .input_type("crate::grpc::SplitRequest")
+                .output_type("crate::grpc::SplitResponse")
+                .codec_path("tonic::codec::ProstCodec")
+                .client_streaming()
+                .server_streaming()
+                .build(),
+        )
+        .build();
+    Builder::new().compile(&[service]);
+}
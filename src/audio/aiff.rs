@@ -0,0 +1,93 @@
+//! AIFF writer, with AIFF-C for float samples.
+
+use anyhow::{Context, Result};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::buffer::interleave;
+use super::wav::{Data, Spec};
+
+/// AIFF-C version 1 timestamp, the only version defined.
+const AIFC_VERSION: u32 = 0xa280_5140;
+
+/// A sample rate as the 80-bit IEEE 754 extended float AIFF requires.
+fn extended(value: f64) -> [u8; 10] {
+    let mut bytes = [0u8; 10];
+    if value <= 0.0 {
+        return bytes;
+    }
+    let exponent = value.log2().floor() as i32;
+    let mantissa = (value / 2f64.powi(exponent) * (1u64 << 63) as f64) as u64;
+    bytes[..2].copy_from_slice(&((exponent + 16383) as u16).to_be_bytes());
+    bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+/// Writes `data` big-endian: integer codes as plain AIFF, floats as
+/// AIFF-C `fl32`.
+pub fn write(path: &Path, spec: Spec, data: Data) -> Result<()> {
+    let channels = spec.channels.max(1) as usize;
+    let width = spec.bits_per_sample.div_ceil(8) as usize;
+    let data_len = match &data {
+        Data::Float(_) => data.len() * 4,
+        Data::Int(codes) => codes.len() * width,
+    };
+    let frames = data_len / width / channels;
+
+    let mut comm = Vec::with_capacity(40);
+    comm.extend_from_slice(&(channels as u16).to_be_bytes());
+    comm.extend_from_slice(&(frames as u32).to_be_bytes());
+    comm.extend_from_slice(&spec.bits_per_sample.to_be_bytes());
+    comm.extend_from_slice(&extended(spec.sample_rate as f64));
+    if spec.float {
+        let name = b"32-bit floating point";
+        comm.extend_from_slice(b"fl32");
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name);
+        // Pascal strings are padded to an even total length
+        if name.len().is_multiple_of(2) {
+            comm.push(0);
+        }
+    }
+
+    let ssnd_len = 8 + data_len;
+    let mut form_len = 4 + 8 + comm.len() + 8 + ssnd_len + ssnd_len % 2;
+    if spec.float {
+        form_len += 12;
+    }
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(b"FORM")?;
+    writer.write_all(&(form_len as u32).to_be_bytes())?;
+    writer.write_all(if spec.float { b"AIFC" } else { b"AIFF" })?;
+    if spec.float {
+        writer.write_all(b"FVER")?;
+        writer.write_all(&4u32.to_be_bytes())?;
+        writer.write_all(&AIFC_VERSION.to_be_bytes())?;
+    }
+    writer.write_all(b"COMM")?;
+    writer.write_all(&(comm.len() as u32).to_be_bytes())?;
+    writer.write_all(&comm)?;
+
+    writer.write_all(b"SSND")?;
+    writer.write_all(&(ssnd_len as u32).to_be_bytes())?;
+    // Offset and block size, both unused
+    writer.write_all(&[0; 8])?;
+    match data {
+        Data::Float(channels) => {
+            for sample in interleave(channels) {
+                writer.write_all(&sample.to_be_bytes())?;
+            }
+        }
+        Data::Int(codes) => {
+            for code in codes {
+                writer.write_all(&code.to_be_bytes()[4 - width..])?;
+            }
+        }
+    }
+    if ssnd_len % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,271 @@
+//! Delay estimation between two recordings of the same material using the
+//! generalized cross-correlation with phase transform (GCC-PHAT), whether
+//! one is the other with its polarity flipped, and the clock drift between
+//! devices recording the same event.
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
+
+use super::resample;
+
+/// Result of a delay estimate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelayEstimate {
+    /// Number of frames the target lags behind the reference. Negative when
+    /// the target leads.
+    pub frames: isize,
+    /// Height of the correlation peak relative to the mean correlation
+    /// magnitude; values near 1 mean no clear match was found.
+    pub peak_ratio: f32,
+    /// Fraction of a frame to add to `frames`, from a parabola through
+    /// the correlation peak and its neighbours
+    pub fraction: f64,
+    /// Whether the target matches the reference with its polarity flipped,
+    /// i.e. the correlation peak is negative.
+    pub inverted: bool,
+}
+
+/// Reference frames correlated per transform when a search limit lets the
+/// recordings be correlated in blocks, as a multiple of the limit.
+const BLOCK_DELAYS: usize = 8;
+/// Shortest such block.
+const MIN_BLOCK: usize = 1 << 16;
+
+/// Estimates how far `target` lags behind `reference` (both mono), limiting
+/// the search to `max_delay` frames in either direction if given. With a
+/// limit, long recordings are correlated block by block in transforms
+/// sized by the limit rather than by the recordings.
+pub fn estimate_delay(reference: &[f32], target: &[f32], max_delay: Option<usize>) -> Result<DelayEstimate> {
+    if reference.is_empty() || target.is_empty() {
+        bail!("Cannot align empty recordings");
+    }
+
+    let whole = (reference.len() + target.len()).next_power_of_two();
+    let (n, correlation) = match max_delay {
+        Some(delay) if (block_len(delay) + 2 * delay).next_power_of_two() < whole => {
+            let block = block_len(delay);
+            let n = (block + 2 * delay).next_power_of_two();
+            // Each reference block is set `delay` frames into its buffer and
+            // the target from `delay` frames before the block, so lags within
+            // the limit land where they do for the whole recordings
+            let blocks = (0..reference.len()).step_by(block).filter_map(|start| {
+                let from = start.saturating_sub(delay);
+                let to = (start + block + delay).min(target.len());
+                (from < to).then(|| {
+                    let reference = &reference[start..(start + block).min(reference.len())];
+                    ((delay, reference), (from + delay - start, &target[from..to]))
+                })
+            });
+            (n, correlate(n, blocks)?)
+        }
+        _ => (whole, correlate(whole, std::iter::once(((0, reference), (0, target))))?),
+    };
+
+    // Lag m is stored at index m for m >= 0 and at n + m for m < 0
+    let max_lag = max_delay.unwrap_or(usize::MAX);
+    let max_positive = (target.len() - 1).min(max_lag) as isize;
+    let max_negative = (reference.len() - 1).min(max_lag) as isize;
+
+    let (mut best_lag, mut best_value, mut inverted) = (0isize, f32::MIN, false);
+    let mut total = 0.0f64;
+    let mut count = 0usize;
+    for lag in -max_negative..=max_positive {
+        let index = if lag >= 0 { lag as usize } else { (n as isize + lag) as usize };
+        let value = correlation[index].abs();
+        total += value as f64;
+        count += 1;
+        if value > best_value {
+            best_value = value;
+            best_lag = lag;
+            inverted = correlation[index] < 0.0;
+        }
+    }
+
+    let mean = (total / count.max(1) as f64) as f32;
+    let at = |lag: isize| correlation[if lag >= 0 { lag as usize } else { (n as isize + lag) as usize }].abs() as f64;
+    let fraction = if best_lag > -max_negative && best_lag < max_positive {
+        let (left, peak, right) = (at(best_lag - 1), at(best_lag), at(best_lag + 1));
+        let curvature = left - 2.0 * peak + right;
+        if curvature < 0.0 { (0.5 * (left - right) / curvature).clamp(-0.5, 0.5) } else { 0.0 }
+    } else {
+        0.0
+    };
+    Ok(DelayEstimate {
+        frames: best_lag,
+        fraction,
+        peak_ratio: if mean > 0.0 { best_value / mean } else { 0.0 },
+        inverted,
+    })
+}
+
+fn block_len(max_delay: usize) -> usize {
+    BLOCK_DELAYS.saturating_mul(max_delay).max(MIN_BLOCK)
+}
+
+/// GCC-PHAT of the reference and target buffers `blocks` yields, each an
+/// offset into an `n`-frame buffer and the frames placed there. The blocks'
+/// cross-spectra are summed before the phase transform. Lag m is at index
+/// m for m >= 0 and at n + m for m < 0.
+fn correlate<'a>(n: usize, blocks: impl Iterator<Item = ((usize, &'a [f32]), (usize, &'a [f32]))>) -> Result<Vec<f32>> {
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(n);
+    let ifft = planner.plan_fft_inverse(n);
+
+    let mut reference_spectrum = fft.make_output_vec();
+    let mut target_spectrum = fft.make_output_vec();
+    let mut buffer = fft.make_input_vec();
+    let mut cross = vec![Complex::new(0.0f32, 0.0); reference_spectrum.len()];
+
+    for ((reference_offset, reference), (target_offset, target)) in blocks {
+        buffer.fill(0.0);
+        buffer[reference_offset..reference_offset + reference.len()].copy_from_slice(reference);
+        fft.process(&mut buffer, &mut reference_spectrum)
+            .with_context(|| "Failed to transform reference")?;
+        buffer.fill(0.0);
+        buffer[target_offset..target_offset + target.len()].copy_from_slice(target);
+        fft.process(&mut buffer, &mut target_spectrum)
+            .with_context(|| "Failed to transform target")?;
+        for ((sum, t), r) in cross.iter_mut().zip(&target_spectrum).zip(&reference_spectrum) {
+            *sum += t * r.conj();
+        }
+    }
+
+    // Phase transform: keep only the phase of the cross-spectrum so the
+    // peak stays sharp regardless of the recordings' spectral balance
+    for c in &mut cross {
+        let magnitude = c.norm();
+        *c = if magnitude > 1e-12 { *c / magnitude } else { Complex::new(0.0, 0.0) };
+    }
+    // The inverse real FFT expects purely real DC and Nyquist bins
+    cross[0].im = 0.0;
+    if let Some(last) = cross.last_mut() {
+        last.im = 0.0;
+    }
+
+    let mut correlation = ifft.make_output_vec();
+    ifft.process(&mut cross, &mut correlation)
+        .with_context(|| "Failed to compute cross-correlation")?;
+    Ok(correlation)
+}
+
+/// Shifts interleaved `samples` earlier by `delay` frames (later when
+/// negative, padding with silence) and trims or pads the result to `frames`.
+pub fn apply_delay(samples: &[f32], channels: usize, delay: isize, frames: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    let mut aligned = vec![0.0; frames * channels];
+    let available = samples.len() / channels;
+
+    for (frame, out) in aligned.chunks_mut(channels).enumerate() {
+        let source = frame as isize + delay;
+        if source >= 0 && (source as usize) < available {
+            let start = source as usize * channels;
+            out.copy_from_slice(&samples[start..start + channels]);
+        }
+    }
+
+    aligned
+}
+
+/// Offset and clock drift of a target against a reference recording of the
+/// same event, as from two devices with their own sample clocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriftEstimate {
+    /// Frames the target lags behind the reference at the reference's
+    /// first frame
+    pub offset_frames: f64,
+    /// How much faster the target's clock runs, in parts per million;
+    /// the lag grows by this many frames per million reference frames
+    pub drift_ppm: f64,
+    /// Segments the delay was measured on
+    pub segments: usize,
+}
+
+impl DriftEstimate {
+    /// Target frame lined up with reference frame `frame`.
+    pub fn target_position(&self, frame: f64) -> f64 {
+        self.offset_frames + frame * (1.0 + self.drift_ppm * 1e-6)
+    }
+}
+
+/// Most segments the delay is measured on.
+const MAX_SEGMENTS: usize = 32;
+/// Rounds of measuring the segments again on the target resampled by the
+/// drift estimated so far.
+const REFINE_PASSES: usize = 2;
+/// Weakest correlation peak a delay or polarity is trusted at.
+pub const MIN_PEAK_RATIO: f32 = 5.0;
+
+/// Estimates the offset and clock drift of `target` against `reference`
+/// (both mono) from their delay in `segment`-frame stretches spread over
+/// the reference, fitted with a line.
+pub fn estimate_drift(reference: &[f32], target: &[f32], segment: usize, max_delay: Option<usize>) -> Result<DriftEstimate> {
+    let segment = segment.min(reference.len() / 4);
+    if segment == 0 {
+        bail!("Recordings are too short to measure drift on");
+    }
+    let overall = estimate_delay(reference, target, max_delay)?;
+    if overall.peak_ratio < MIN_PEAK_RATIO {
+        bail!("No clear match between the recordings (peak ratio {:.1}); they may not hold the same event", overall.peak_ratio);
+    }
+
+    // Each segment is first searched around the delay of the one before,
+    // as the drift moves it only a little between them
+    let margin = segment / 2;
+    let count = ((reference.len() - segment) / segment + 1).min(MAX_SEGMENTS);
+    let spacing = if count > 1 { (reference.len() - segment) / (count - 1) } else { 0 };
+    let starts: Vec<usize> = (0..count).map(|i| i * spacing).collect();
+    let mut expected = overall.frames;
+    let mut points = Vec::new();
+    for &start in &starts {
+        let from = (start as isize + expected - margin as isize).max(0) as usize;
+        let to = (from + segment + 2 * margin).min(target.len());
+        if to < from + segment {
+            continue;
+        }
+        let estimate = estimate_delay(&reference[start..start + segment], &target[from..to], Some(2 * margin))?;
+        if estimate.peak_ratio < MIN_PEAK_RATIO {
+            continue;
+        }
+        expected = estimate.frames + from as isize - start as isize;
+        // The delay measured is the one at the middle of the segment
+        points.push(((start + segment / 2) as f64, expected as f64));
+    }
+    let mut drift = fit_drift(&points, count)?;
+
+    // Drift within a segment smears its correlation peak, so the segments
+    // are measured again on the target resampled by the estimate so far,
+    // leaving only the residual
+    for _ in 0..REFINE_PASSES {
+        let mut points = Vec::new();
+        for &start in &starts {
+            let positions: Vec<f64> = (0..segment + 2 * margin)
+                .map(|i| drift.target_position(start as f64 + i as f64 - margin as f64))
+                .collect();
+            let warped = resample::warp(target, 1, &positions);
+            let estimate = estimate_delay(&reference[start..start + segment], &warped, Some(2 * margin))?;
+            if estimate.peak_ratio < MIN_PEAK_RATIO {
+                continue;
+            }
+            let middle = (start + segment / 2) as f64;
+            let residual = (estimate.frames - margin as isize) as f64 + estimate.fraction;
+            points.push((middle, drift.target_position(middle + residual) - middle));
+        }
+        drift = fit_drift(&points, count)?;
+    }
+    Ok(drift)
+}
+
+/// Fits a line through the delays measured at each `(frame, delay)`.
+fn fit_drift(points: &[(f64, f64)], count: usize) -> Result<DriftEstimate> {
+    if points.len() < 2 {
+        bail!("Only {} of {} segments matched clearly; at least 2 are needed to measure drift", points.len(), count);
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x) * (x - mean_x)).sum();
+    let slope = covariance / variance;
+    Ok(DriftEstimate { offset_frames: mean_y - slope * mean_x, drift_ppm: slope * 1e6, segments: points.len() })
+}
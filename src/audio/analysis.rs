@@ -0,0 +1,340 @@
+//! Fractional-octave band level analysis, noise floor estimation and
+//! long-term spectra.
+
+use anyhow::{bail, Context, Result};
+use realfft::RealFftPlanner;
+use serde::Serialize;
+
+use super::spectral::{band_levels, frame_magnitudes};
+use super::{stft::hann_window, weighting::Weighting, AudioBuffer};
+
+/// Octave ratio for base-10 band edges (IEC 61260-1).
+const OCTAVE_RATIO: f32 = 1.995_262_3;
+/// Lowest nominal band considered.
+const LOWEST_CENTER: f32 = 12.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BandLevel {
+    pub center: f32,
+    pub lower: f32,
+    pub upper: f32,
+    /// Band level in dBFS, where a full-scale sine reads 0 dB.
+    pub level_db: f32,
+}
+
+/// Exact mid-band frequencies and edges of the 1/`fraction`-octave bands
+/// from 12.5 Hz up to the last band that fits below Nyquist.
+pub fn band_edges(fraction: u32, sample_rate: u32) -> Result<Vec<(f32, f32, f32)>> {
+    if fraction != 1 && fraction != 3 {
+        bail!("Unsupported octave fraction 1/{}; expected 1 or 3", fraction);
+    }
+
+    let b = fraction as f32;
+    let nyquist = sample_rate as f32 / 2.0;
+    let half_band = OCTAVE_RATIO.powf(0.5 / b);
+
+    // Centers are 1 kHz * G^(x/b); start at the first index at or above the lowest band
+    let first = (b * (LOWEST_CENTER / 1000.0).ln() / OCTAVE_RATIO.ln()).round() as i32;
+    Ok((first..)
+        .map(|x| 1000.0 * OCTAVE_RATIO.powf(x as f32 / b))
+        .map(|center| (center, center / half_band, center * half_band))
+        .take_while(|&(_, _, upper)| upper <= nyquist)
+        .collect())
+}
+
+/// Measures the mono signal's level in each 1/`fraction`-octave band,
+/// applying `weighting` to the spectrum first.
+pub fn octave_band_levels(
+    samples: &[f32],
+    sample_rate: u32,
+    fraction: u32,
+    weighting: Weighting,
+) -> Result<Vec<BandLevel>> {
+    let edges = band_edges(fraction, sample_rate)?;
+    if samples.is_empty() {
+        bail!("Cannot analyze an empty signal");
+    }
+
+    // A single Hann-windowed transform over the whole signal resolves even
+    // the narrowest low bands; normalizing by the window energy keeps the
+    // bin energies summing to the signal's mean square
+    let n = samples.len().next_power_of_two();
+    let window = hann_window(samples.len());
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(n);
+    let mut input = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    for ((slot, &sample), &w) in input.iter_mut().zip(samples).zip(&window) {
+        *slot = sample * w;
+    }
+    fft.process(&mut input, &mut spectrum)
+        .with_context(|| "Failed to compute spectrum")?;
+
+    let df = sample_rate as f32 / n as f32;
+    let window_energy: f64 = window.iter().map(|&w| (w * w) as f64).sum();
+    let energy_scale = 2.0 / (n as f64 * window_energy);
+
+    Ok(edges
+        .into_iter()
+        .map(|(center, lower, upper)| {
+            let first = (lower / df).ceil() as usize;
+            let last = ((upper / df).ceil() as usize).min(spectrum.len());
+            let mean_square: f64 = (first..last)
+                .map(|k| {
+                    let gain = 10f64.powf(weighting.gain_db(k as f32 * df) as f64 / 10.0);
+                    spectrum[k].norm_sqr() as f64 * gain
+                })
+                .sum::<f64>()
+                * energy_scale;
+            // Referenced to a full-scale sine, whose mean square is 1/2
+            let level_db = (10.0 * (2.0 * mean_square).max(1e-20).log10()) as f32;
+            BandLevel { center, lower, upper, level_db }
+        })
+        .collect())
+}
+
+/// STFT frame length of the noise floor estimate.
+const NOISE_FLOOR_WINDOW: usize = 2048;
+/// Time constant each band's power is smoothed with before taking minima.
+const NOISE_SMOOTHING_SECONDS: f32 = 0.03;
+/// Bands narrower than this many FFT bins are smoothed for longer, as
+/// their power fluctuates more and its minima would read low.
+const NOISE_SMOOTHING_BINS: f32 = 16.0;
+/// Length of the stretches whose minima are taken.
+const NOISE_MINIMUM_SECONDS: f32 = 1.5;
+
+/// Estimates the mono signal's noise floor in each octave band by minimum
+/// statistics: each band's smoothed power is reduced to its minimum over
+/// every 1.5 s stretch, and the median of those minima is reported, so
+/// pauses between phrases set the floor but a single dropout doesn't.
+pub fn noise_floor(samples: &[f32], sample_rate: u32) -> Result<Vec<BandLevel>> {
+    let edges = band_edges(1, sample_rate)?;
+    let cutoffs: Vec<f32> = edges.iter().map(|&(_, lower, _)| lower).chain(edges.last().map(|&(.., upper)| upper)).collect();
+    let levels: Vec<Vec<f32>> = frame_magnitudes(samples, NOISE_FLOOR_WINDOW)?
+        .iter()
+        .map(|frame| band_levels(frame, NOISE_FLOOR_WINDOW, sample_rate, &cutoffs))
+        .collect();
+
+    let hop_seconds = (NOISE_FLOOR_WINDOW / 2) as f32 / sample_rate as f32;
+    let bin_hz = sample_rate as f32 / NOISE_FLOOR_WINDOW as f32;
+    let stretch = ((NOISE_MINIMUM_SECONDS / hop_seconds).round() as usize).max(1);
+    Ok(edges
+        .iter()
+        .enumerate()
+        .map(|(band, &(center, lower, upper))| {
+            let bins = ((upper - lower) / bin_hz).max(1.0);
+            let seconds = NOISE_SMOOTHING_SECONDS * (NOISE_SMOOTHING_BINS / bins).max(1.0);
+            let smoothing = (-hop_seconds / seconds).exp();
+            let mut power = None;
+            let smoothed: Vec<f32> = levels
+                .iter()
+                .map(|frame| {
+                    let current = 10f32.powf(frame[band + 1] / 10.0);
+                    let next = power.map_or(current, |previous: f32| smoothing * previous + (1.0 - smoothing) * current);
+                    power = Some(next);
+                    next
+                })
+                .collect();
+            let mut minima: Vec<f32> =
+                smoothed.chunks(stretch).map(|chunk| chunk.iter().copied().fold(f32::INFINITY, f32::min)).collect();
+            minima.sort_by(f32::total_cmp);
+            let floor = minima.get(minima.len() / 2).copied().unwrap_or(0.0);
+            BandLevel { center, lower, upper, level_db: 10.0 * floor.max(1e-12).log10() }
+        })
+        .collect())
+}
+
+/// FFT size for long-term spectra; fine enough to resolve bass fundamentals.
+pub const LONG_TERM_FFT_SIZE: usize = 8192;
+/// Resolution of the smoothed log-frequency grid, in points per octave.
+const GRID_POINTS_PER_OCTAVE: f32 = 24.0;
+/// Width of the smoothing window, in octaves.
+const SMOOTHING_OCTAVES: f32 = 1.0 / 6.0;
+/// Valleys less prominent than this are ignored.
+const MIN_VALLEY_DEPTH_DB: f32 = 6.0;
+
+/// A dip in the smoothed long-term spectrum.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Valley {
+    pub frequency: f32,
+    pub level_db: f32,
+    /// Prominence: depth below the lower of the highest points on either
+    /// side, searching each side until the spectrum falls below the valley.
+    pub depth_db: f32,
+}
+
+/// Welch-averaged power spectrum of the mono signal, one value per bin of
+/// a `fft_size` transform, scaled so a full-scale sine peaks near 0 dB.
+pub fn long_term_spectrum(samples: &[f32], fft_size: usize) -> Result<Vec<f32>> {
+    if samples.is_empty() {
+        bail!("Cannot analyze an empty signal");
+    }
+
+    let hop = fft_size / 2;
+    let window = hann_window(fft_size);
+    let window_energy: f32 = window.iter().map(|w| w * w).sum();
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(fft_size);
+    let mut frame = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    let mut power = vec![0.0f32; spectrum.len()];
+
+    let mut frames = 0;
+    let mut start = 0;
+    loop {
+        let chunk = &samples[start..(start + fft_size).min(samples.len())];
+        frame.fill(0.0);
+        for ((slot, &sample), &w) in frame.iter_mut().zip(chunk).zip(&window) {
+            *slot = sample * w;
+        }
+        fft.process(&mut frame, &mut spectrum)
+            .with_context(|| "Failed to compute spectrum")?;
+        for (p, bin) in power.iter_mut().zip(&spectrum) {
+            *p += bin.norm_sqr();
+        }
+        frames += 1;
+
+        start += hop;
+        if start + fft_size > samples.len() + hop {
+            break;
+        }
+    }
+
+    let scale = 2.0 / (frames as f32 * window_energy);
+    power.iter_mut().for_each(|p| *p *= scale);
+    Ok(power)
+}
+
+/// Samples a [`long_term_spectrum`] as (frequency, dB) pairs on a
+/// logarithmic grid from 20 Hz to Nyquist, averaging the power over
+/// `smoothing_octaves` around each point.
+pub fn smoothed_spectrum(power: &[f32], sample_rate: u32, smoothing_octaves: f32) -> Vec<(f32, f32)> {
+    let df = sample_rate as f32 / (2 * (power.len() - 1)) as f32;
+    let nyquist = sample_rate as f32 / 2.0;
+    let half_window = 2f32.powf(smoothing_octaves / 2.0);
+
+    (0..)
+        .map(|i| 20.0 * 2f32.powf(i as f32 / GRID_POINTS_PER_OCTAVE))
+        .take_while(|&f| f * half_window < nyquist)
+        .map(|f| {
+            let first = (f / half_window / df).round() as usize;
+            let last = ((f * half_window / df).round() as usize).max(first + 1).min(power.len());
+            let mean = power[first..last].iter().sum::<f32>() / (last - first) as f32;
+            (f, 10.0 * mean.max(1e-12).log10())
+        })
+        .collect()
+}
+
+/// Finds valleys in the long-term spectrum, smoothed over 1/6 octave on a
+/// logarithmic grid from 20 Hz to Nyquist, deepest first.
+pub fn spectral_valleys(samples: &[f32], sample_rate: u32) -> Result<Vec<Valley>> {
+    let power = long_term_spectrum(samples, LONG_TERM_FFT_SIZE)?;
+    let grid = smoothed_spectrum(&power, sample_rate, SMOOTHING_OCTAVES);
+
+    let mut valleys: Vec<Valley> = (1..grid.len().saturating_sub(1))
+        .filter(|&i| grid[i].1 < grid[i - 1].1 && grid[i].1 <= grid[i + 1].1)
+        .map(|i| {
+            let level = grid[i].1;
+            let peak = |side: &mut dyn Iterator<Item = &(f32, f32)>| {
+                side.map(|&(_, db)| db).take_while(|&db| db >= level).fold(level, f32::max)
+            };
+            let left = peak(&mut grid[..i].iter().rev());
+            let right = peak(&mut grid[i + 1..].iter());
+            Valley { frequency: grid[i].0, level_db: level, depth_db: left.min(right) - level }
+        })
+        .filter(|valley| valley.depth_db >= MIN_VALLEY_DEPTH_DB)
+        .collect();
+
+    valleys.sort_by(|a, b| b.depth_db.total_cmp(&a.depth_db));
+    Ok(valleys)
+}
+
+/// Frame length of the onset envelope used for tempo estimation.
+const ONSET_FRAME: usize = 1024;
+/// Hop between onset envelope frames.
+const ONSET_HOP: usize = 512;
+/// Tempo search range (BPM).
+const TEMPO_RANGE: std::ops::RangeInclusive<f32> = 60.0..=200.0;
+/// Tempo the search is biased towards to resolve octave ambiguity.
+const PREFERRED_TEMPO: f32 = 120.0;
+
+/// Estimates the tempo of a mono signal in BPM from the autocorrelation of
+/// its spectral flux, or `None` if the signal is too short or has no onsets.
+/// Lags are weighted by a one-octave log-Gaussian around 120 BPM so the
+/// beat wins over its multiples.
+pub fn estimate_tempo(samples: &[f32], sample_rate: u32) -> Result<Option<f32>> {
+    let frame_rate = sample_rate as f32 / ONSET_HOP as f32;
+    let min_lag = (60.0 / TEMPO_RANGE.end() * frame_rate).floor() as usize;
+    let max_lag = (60.0 / TEMPO_RANGE.start() * frame_rate).ceil() as usize;
+    if samples.len() < ONSET_FRAME + 2 * max_lag * ONSET_HOP {
+        return Ok(None);
+    }
+
+    let window = hann_window(ONSET_FRAME);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(ONSET_FRAME);
+    let mut frame = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    let mut previous = vec![0.0f32; spectrum.len()];
+
+    // Half-wave rectified rise in log-compressed magnitude, summed over bins
+    let mut onsets = Vec::new();
+    for start in (0..=samples.len() - ONSET_FRAME).step_by(ONSET_HOP) {
+        for ((slot, &sample), &w) in frame.iter_mut().zip(&samples[start..start + ONSET_FRAME]).zip(&window) {
+            *slot = sample * w;
+        }
+        fft.process(&mut frame, &mut spectrum)
+            .with_context(|| "Failed to compute spectrum")?;
+        let mut flux = 0.0;
+        for (last, bin) in previous.iter_mut().zip(&spectrum) {
+            let magnitude = (1.0 + 100.0 * bin.norm()).ln();
+            flux += (magnitude - *last).max(0.0);
+            *last = magnitude;
+        }
+        onsets.push(flux);
+    }
+    // The first frame rises from silence
+    onsets[0] = 0.0;
+
+    let mean = onsets.iter().sum::<f32>() / onsets.len() as f32;
+    onsets.iter_mut().for_each(|x| *x -= mean);
+    let autocorrelation = |lag: usize| -> f32 {
+        onsets.iter().zip(&onsets[lag..]).map(|(a, b)| a * b).sum::<f32>() / (onsets.len() - lag) as f32
+    };
+    let scores: Vec<f32> = (min_lag - 1..=max_lag + 1)
+        .map(|lag| {
+            let bpm = 60.0 * frame_rate / lag as f32;
+            let octaves = (bpm / PREFERRED_TEMPO).log2();
+            autocorrelation(lag) * (-0.5 * octaves * octaves).exp()
+        })
+        .collect();
+
+    let best = (1..scores.len() - 1).max_by(|&a, &b| scores[a].total_cmp(&scores[b])).expect("lag range is non-empty");
+    if scores[best] <= 0.0 {
+        return Ok(None);
+    }
+
+    // Parabolic interpolation between neighbouring lags
+    let (left, centre, right) = (scores[best - 1], scores[best], scores[best + 1]);
+    let curvature = left - 2.0 * centre + right;
+    let offset = if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 };
+    let lag = (min_lag - 1 + best) as f32 + offset;
+    Ok(Some(60.0 * frame_rate / lag))
+}
+
+/// Pearson correlation between the channels of stereo audio:
+/// 1 for mono, 0 for unrelated channels and -1 when the channels cancel in
+/// a mono fold-down. Silence reads as 1.
+pub fn stereo_correlation(buffer: &AudioBuffer) -> f32 {
+    let (mut lr, mut ll, mut rr) = (0.0f64, 0.0f64, 0.0f64);
+    for (&l, &r) in buffer.channel(0).iter().zip(buffer.channel(1)) {
+        let (l, r) = (l as f64, r as f64);
+        lr += l * r;
+        ll += l * l;
+        rr += r * r;
+    }
+    if ll == 0.0 || rr == 0.0 {
+        return 1.0;
+    }
+    (lr / (ll * rr).sqrt()) as f32
+}
@@ -0,0 +1,157 @@
+//! Second-order IIR sections and cascades of them.
+
+use num_complex::Complex;
+use realfft::num_traits::AsPrimitive;
+use std::f64::consts::PI;
+
+/// Digital biquad with coefficients normalized so that a0 = 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl Biquad {
+    /// Maps the analog section `(b2 s² + b1 s + b0) / (a2 s² + a1 s + a0)`,
+    /// given as `[x0, x1, x2]`, to the digital domain with the bilinear
+    /// transform.
+    pub fn from_analog(numerator: [f64; 3], denominator: [f64; 3], sample_rate: u32) -> Self {
+        let k = 2.0 * sample_rate as f64;
+        let map = |[c0, c1, c2]: [f64; 3]| {
+            [
+                c2 * k * k + c1 * k + c0,
+                2.0 * (c0 - c2 * k * k),
+                c2 * k * k - c1 * k + c0,
+            ]
+        };
+        let [b0, b1, b2] = map(numerator);
+        let [a0, a1, a2] = map(denominator);
+        Self { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+
+    /// Peaking EQ from the Audio EQ Cookbook: `gain_db` of boost or cut
+    /// centered on `frequency` Hz with quality factor `q`.
+    pub fn peaking(frequency: f32, q: f32, gain_db: f32, sample_rate: u32) -> Self {
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        let alpha = w.sin() / (2.0 * q as f64);
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * w.cos() / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * w.cos() / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+
+    /// Second-order low-pass from the Audio EQ Cookbook.
+    pub fn lowpass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        let alpha = w.sin() / (2.0 * q as f64);
+        let a0 = 1.0 + alpha;
+        let b1 = (1.0 - w.cos()) / a0;
+        Self { b0: b1 / 2.0, b1, b2: b1 / 2.0, a1: -2.0 * w.cos() / a0, a2: (1.0 - alpha) / a0 }
+    }
+
+    /// Second-order high-pass from the Audio EQ Cookbook.
+    pub fn highpass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        let alpha = w.sin() / (2.0 * q as f64);
+        let a0 = 1.0 + alpha;
+        let b0 = (1.0 + w.cos()) / 2.0 / a0;
+        Self { b0, b1: -2.0 * b0, b2: b0, a1: -2.0 * w.cos() / a0, a2: (1.0 - alpha) / a0 }
+    }
+
+    /// Constant 0 dB peak gain band-pass from the Audio EQ Cookbook.
+    pub fn bandpass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        let alpha = w.sin() / (2.0 * q as f64);
+        let a0 = 1.0 + alpha;
+        Self { b0: alpha / a0, b1: 0.0, b2: -alpha / a0, a1: -2.0 * w.cos() / a0, a2: (1.0 - alpha) / a0 }
+    }
+
+    /// Complex frequency response at `frequency` Hz.
+    pub fn response(&self, frequency: f32, sample_rate: u32) -> Complex<f64> {
+        self.response_at(2.0 * PI * frequency as f64 / sample_rate as f64)
+    }
+
+    /// Complex frequency response at `w` radians per sample.
+    fn response_at(&self, w: f64) -> Complex<f64> {
+        let z1 = Complex::from_polar(1.0, -w);
+        let z2 = z1 * z1;
+        (self.b0 + z1 * self.b1 + z2 * self.b2) / (1.0 + z1 * self.a1 + z2 * self.a2)
+    }
+
+    /// Scales the numerator, changing the section's gain by `gain`.
+    pub fn scaled(self, gain: f64) -> Self {
+        Self { b0: self.b0 * gain, b1: self.b1 * gain, b2: self.b2 * gain, ..self }
+    }
+}
+
+/// Analog angular frequency whose bilinear image lands at `frequency` Hz.
+pub fn prewarp(frequency: f64, sample_rate: u32) -> f64 {
+    2.0 * sample_rate as f64 * (PI * frequency / sample_rate as f64).tan()
+}
+
+/// Magnitude response of a cascade in dB.
+pub fn cascade_response_db(cascade: &[Biquad], frequency: f32, sample_rate: u32) -> f32 {
+    let response: Complex<f64> = cascade
+        .iter()
+        .map(|section| section.response(frequency, sample_rate))
+        .product();
+    (20.0 * response.norm().log10()) as f32
+}
+
+/// Group delay of a cascade at `frequency` Hz in samples: the negative
+/// slope of its phase response.
+pub fn group_delay_samples(cascade: &[Biquad], frequency: f32, sample_rate: u32) -> f64 {
+    let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+    let dw = 1e-6;
+    // The ratio of neighbouring responses has a small phase, so its
+    // argument needs no unwrapping
+    let ratio: Complex<f64> = cascade
+        .iter()
+        .map(|section| section.response_at(w + dw) / section.response_at(w - dw))
+        .product();
+    -ratio.arg() / (2.0 * dw)
+}
+
+/// Delay line of one biquad for sample-by-sample processing, in
+/// transposed direct form II so coefficients can change between samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BiquadState {
+    s1: f64,
+    s2: f64,
+}
+
+impl BiquadState {
+    pub fn process(&mut self, section: &Biquad, x: f64) -> f64 {
+        let y = section.b0 * x + self.s1;
+        self.s1 = section.b1 * x - section.a1 * y + self.s2;
+        self.s2 = section.b2 * x - section.a2 * y;
+        y
+    }
+}
+
+/// Runs interleaved `samples` through the cascade in place, with separate
+/// filter state per channel. Each section's output is stored back at the
+/// precision of `T`.
+pub fn filter_interleaved<T>(cascade: &[Biquad], samples: &mut [T], channels: usize)
+where
+    T: AsPrimitive<f64>,
+    f64: AsPrimitive<T>,
+{
+    let channels = channels.max(1);
+    for channel in 0..channels {
+        for section in cascade {
+            let mut state = BiquadState::default();
+            for sample in samples.iter_mut().skip(channel).step_by(channels) {
+                *sample = state.process(section, sample.as_()).as_();
+            }
+        }
+    }
+}
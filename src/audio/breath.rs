@@ -0,0 +1,103 @@
+//! Breath detection for voiceover cleanup: quiet, noise-like bursts that
+//! stand out from the room tone between phrases.
+
+use anyhow::{bail, Result};
+use std::ops::Range;
+
+use super::spectral::{band_levels, frame_magnitudes};
+
+/// STFT frame length of the detector; frames advance by half of it.
+const WINDOW_SIZE: usize = 1024;
+/// Band breaths are measured in, leaving out rumble and the top octave.
+const BAND_HZ: (f32, f32) = (100.0, 8000.0);
+/// Percentiles of frame levels taken as the speech level and as the room
+/// tone between phrases.
+const SPEECH_PERCENTILE: f32 = 0.95;
+const ROOM_PERCENTILE: f32 = 0.1;
+/// A breath must stand this far above the room tone.
+const FLOOR_MARGIN_DB: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreathSettings {
+    /// How far below the speech level a breath stays at most (dB)
+    pub threshold_db: f32,
+    /// Spectral flatness from which a frame counts as noise-like, between
+    /// 0 for a pure tone and about 0.56 for white noise
+    pub flatness: f32,
+    /// Shortest and longest breath (ms)
+    pub min_ms: f32,
+    pub max_ms: f32,
+}
+
+impl Default for BreathSettings {
+    fn default() -> Self {
+        Self { threshold_db: 18.0, flatness: 0.25, min_ms: 100.0, max_ms: 1000.0 }
+    }
+}
+
+/// Ratio of the geometric to the arithmetic mean of the powers of `bins`.
+fn flatness(bins: &[f32]) -> f32 {
+    let powers: Vec<f32> = bins.iter().map(|magnitude| (magnitude * magnitude).max(1e-20)).collect();
+    let log_mean = powers.iter().map(|p| p.ln()).sum::<f32>() / powers.len() as f32;
+    log_mean.exp() / (powers.iter().sum::<f32>() / powers.len() as f32)
+}
+
+/// Finds breaths in the mono signal, as sample ranges: runs of noise-like
+/// frames at least `threshold_db` below the speech level but clear of the
+/// room tone, lasting between the shortest and longest breath.
+pub fn detect(samples: &[f32], sample_rate: u32, settings: BreathSettings) -> Result<Vec<Range<usize>>> {
+    if samples.is_empty() {
+        bail!("Cannot detect breaths in an empty signal");
+    }
+    let bin_hz = sample_rate as f32 / WINDOW_SIZE as f32;
+    let bins = (BAND_HZ.0 / bin_hz).round() as usize..((BAND_HZ.1.min(sample_rate as f32 / 2.0) / bin_hz) as usize);
+    let frames: Vec<(f32, f32)> = frame_magnitudes(samples, WINDOW_SIZE)?
+        .iter()
+        .map(|frame| (band_levels(frame, WINDOW_SIZE, sample_rate, &[BAND_HZ.0, BAND_HZ.1])[1], flatness(&frame[bins.clone()])))
+        .collect();
+
+    let mut levels: Vec<f32> = frames.iter().map(|&(level, _)| level).collect();
+    levels.sort_by(f32::total_cmp);
+    let percentile = |fraction: f32| levels[((levels.len() - 1) as f32 * fraction) as usize];
+    let (speech_db, floor_db) = (percentile(SPEECH_PERCENTILE), percentile(ROOM_PERCENTILE));
+    let ceiling_db = speech_db - settings.threshold_db;
+
+    // Frame i is centred on sample i * hop
+    let hop = WINDOW_SIZE / 2;
+    let (min_frames, max_frames) = (
+        (settings.min_ms / 1000.0 * sample_rate as f32 / hop as f32).ceil() as usize,
+        (settings.max_ms / 1000.0 * sample_rate as f32 / hop as f32) as usize,
+    );
+    let mut breaths = Vec::new();
+    let mut start = None;
+    for (i, &(level, flat)) in frames.iter().chain([&(f32::INFINITY, 0.0)]).enumerate() {
+        let candidate = level <= ceiling_db && level >= floor_db + FLOOR_MARGIN_DB && flat >= settings.flatness;
+        match (candidate, start) {
+            (true, None) => start = Some(i),
+            (false, Some(first)) => {
+                if (min_frames..=max_frames).contains(&(i - first)) {
+                    breaths.push((first * hop).saturating_sub(hop / 2)..((i - 1) * hop + hop / 2).min(samples.len()));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    Ok(breaths)
+}
+
+/// Turns interleaved `samples` down by `attenuation_db` over each of the
+/// `regions`, fading over `fade_ms` at their edges.
+pub fn attenuate(samples: &mut [f32], channels: usize, sample_rate: u32, regions: &[Range<usize>], attenuation_db: f32, fade_ms: f32) {
+    let channels = channels.max(1);
+    let depth = 1.0 - 10f32.powf(-attenuation_db / 20.0);
+    let fade = ((fade_ms / 1000.0 * sample_rate as f32) as usize).max(1);
+    for region in regions {
+        let fade = fade.min(region.len() / 2).max(1);
+        for frame in region.clone() {
+            let edge = (frame - region.start).min(region.end - 1 - frame);
+            let gain = 1.0 - depth * (edge as f32 / fade as f32).min(1.0);
+            samples[frame * channels..(frame + 1) * channels].iter_mut().for_each(|sample| *sample *= gain);
+        }
+    }
+}
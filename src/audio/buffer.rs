@@ -0,0 +1,131 @@
+//! Planar sample buffers: each channel in its own contiguous run, with the
+//! sample rate and channel layout carried along, so per-channel processing
+//! borrows channels instead of copying them out of interleaved frames.
+
+use anyhow::{bail, Result};
+
+use super::channels::{speaker_names, Ambisonics};
+
+/// What an [`AudioBuffer`]'s channels carry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Speaker feeds in WAV channel order for the channel count
+    #[default]
+    Speakers,
+    /// First-order ambisonic components
+    Ambisonic(Ambisonics),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBuffer {
+    channels: Vec<Vec<f32>>,
+    sample_rate: u32,
+    layout: Layout,
+}
+
+impl AudioBuffer {
+    /// Takes ownership of `channels`, which must all be the same length.
+    pub fn from_planar(channels: Vec<Vec<f32>>, sample_rate: u32) -> Result<Self> {
+        if channels.is_empty() {
+            bail!("An audio buffer needs at least one channel");
+        }
+        if let Some(channel) = channels.iter().position(|channel| channel.len() != channels[0].len()) {
+            bail!("Channel {} has {} samples but channel 0 has {}", channel, channels[channel].len(), channels[0].len());
+        }
+        Ok(Self { channels, sample_rate, layout: Layout::Speakers })
+    }
+
+    /// Splits interleaved `samples` into channels. A trailing partial frame
+    /// is dropped.
+    pub fn from_interleaved(samples: &[f32], channels: usize, sample_rate: u32) -> Self {
+        let count = channels.max(1);
+        let frames = samples.len() / count;
+        let channels = (0..count).map(|channel| samples.iter().skip(channel).step_by(count).take(frames).copied().collect()).collect();
+        Self { channels, sample_rate, layout: Layout::Speakers }
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// A buffer of `channels` with this buffer's sample rate and layout,
+    /// for the output of processing it.
+    pub fn like(&self, channels: Vec<Vec<f32>>) -> Result<Self> {
+        Ok(Self::from_planar(channels, self.sample_rate)?.with_layout(self.layout))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Samples per channel.
+    pub fn frames(&self) -> usize {
+        self.channels[0].len()
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate as f64
+    }
+
+    pub fn channel(&self, channel: usize) -> &[f32] {
+        &self.channels[channel]
+    }
+
+    pub fn channel_mut(&mut self, channel: usize) -> &mut [f32] {
+        &mut self.channels[channel]
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = &[f32]> {
+        self.channels.iter().map(Vec::as_slice)
+    }
+
+    pub fn channels_mut(&mut self) -> impl Iterator<Item = &mut [f32]> {
+        self.channels.iter_mut().map(Vec::as_mut_slice)
+    }
+
+    pub fn into_planar(self) -> Vec<Vec<f32>> {
+        self.channels
+    }
+
+    /// Names of the channels: speaker positions, or ambisonic components.
+    pub fn channel_names(&self) -> Vec<String> {
+        match self.layout {
+            Layout::Ambisonic(convention) if self.num_channels() == 4 => {
+                convention.component_names().iter().map(|name| name.to_string()).collect()
+            }
+            _ => speaker_names(self.num_channels()),
+        }
+    }
+
+    /// Samples in interleaved frames, as the file formats store them.
+    pub fn to_interleaved(&self) -> Vec<f32> {
+        interleave(&self.channels).collect()
+    }
+
+    /// Average of the channels.
+    pub fn mixdown(&self) -> Vec<f32> {
+        let count = self.num_channels() as f32;
+        let mut mono = self.channels[0].clone();
+        for channel in &self.channels[1..] {
+            mono.iter_mut().zip(channel).for_each(|(sum, &sample)| *sum += sample);
+        }
+        mono.iter_mut().for_each(|sample| *sample /= count);
+        mono
+    }
+}
+
+/// Samples of `channels` frame by frame, as the file formats store them.
+/// Channels longer than the shortest are cut to its length.
+pub fn interleave<C: AsRef<[f32]>>(channels: &[C]) -> impl Iterator<Item = f32> + '_ {
+    let frames = channels.iter().map(|channel| channel.as_ref().len()).min().unwrap_or(0);
+    (0..frames).flat_map(move |frame| channels.iter().map(move |channel| channel.as_ref()[frame]))
+}
@@ -0,0 +1,57 @@
+//! Core Audio Format writer for linear PCM and float samples.
+
+use anyhow::{Context, Result};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::buffer::interleave;
+use super::wav::{Data, Spec};
+
+/// `mFormatFlags` bit marking float samples; big-endian is the default.
+const FLAG_FLOAT: u32 = 1;
+
+/// Writes `data` big-endian as `lpcm`.
+pub fn write(path: &Path, spec: Spec, data: Data) -> Result<()> {
+    let channels = spec.channels.max(1) as u32;
+    let width = spec.bits_per_sample.div_ceil(8) as usize;
+    let data_len = match &data {
+        Data::Float(_) => data.len() * 4,
+        Data::Int(codes) => codes.len() * width,
+    };
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(b"caff")?;
+    // File version 1, no flags
+    writer.write_all(&1u16.to_be_bytes())?;
+    writer.write_all(&0u16.to_be_bytes())?;
+
+    writer.write_all(b"desc")?;
+    writer.write_all(&32i64.to_be_bytes())?;
+    writer.write_all(&(spec.sample_rate as f64).to_be_bytes())?;
+    writer.write_all(b"lpcm")?;
+    writer.write_all(&(if spec.float { FLAG_FLOAT } else { 0 }).to_be_bytes())?;
+    writer.write_all(&(width as u32 * channels).to_be_bytes())?;
+    // Frames per packet
+    writer.write_all(&1u32.to_be_bytes())?;
+    writer.write_all(&channels.to_be_bytes())?;
+    writer.write_all(&(spec.bits_per_sample as u32).to_be_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&(4 + data_len as i64).to_be_bytes())?;
+    // Edit count
+    writer.write_all(&0u32.to_be_bytes())?;
+    match data {
+        Data::Float(channels) => {
+            for sample in interleave(channels) {
+                writer.write_all(&sample.to_be_bytes())?;
+            }
+        }
+        Data::Int(codes) => {
+            for code in codes {
+                writer.write_all(&code.to_be_bytes()[4 - width..])?;
+            }
+        }
+    }
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
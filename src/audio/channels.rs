@@ -0,0 +1,217 @@
+//! Speaker layouts of multichannel files, in WAV channel order, and
+//! ambisonic channel conventions.
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::str::FromStr;
+
+use super::AudioBuffer;
+
+/// WAV channel mask bits for front left/right/center, LFE, back
+/// left/right and side left/right.
+const FRONT: u32 = 0x3;
+const CENTER_LFE: u32 = 0xc;
+const BACK: u32 = 0x30;
+const SIDE: u32 = 0x600;
+
+/// Speaker position names of each channel, for naming per-channel files.
+/// Layouts without a standard order number their channels from 1.
+pub fn speaker_names(channels: usize) -> Vec<String> {
+    let names: &[&str] = match channels {
+        1 => &["M"],
+        2 => &["L", "R"],
+        4 => &["L", "R", "Ls", "Rs"],
+        6 => &["L", "R", "C", "LFE", "Ls", "Rs"],
+        8 => &["L", "R", "C", "LFE", "Lrs", "Rrs", "Ls", "Rs"],
+        _ => return (1..=channels).map(|channel| format!("ch{}", channel)).collect(),
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// WAVE_FORMAT_EXTENSIBLE speaker mask for the layouts
+/// [`speaker_names`] knows, 0 (unassigned) otherwise.
+pub fn channel_mask(channels: usize) -> u32 {
+    match channels {
+        1 => 0x4,
+        2 => FRONT,
+        4 => FRONT | BACK,
+        6 => FRONT | CENTER_LFE | BACK,
+        8 => FRONT | CENTER_LFE | BACK | SIDE,
+        _ => 0,
+    }
+}
+
+/// Channel order and normalization of first-order ambisonic (B-format)
+/// files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ambisonics {
+    /// ACN order (W Y Z X) with SN3D normalization
+    Ambix,
+    /// Furse-Malham order (W X Y Z) with W at -3 dB
+    Fuma,
+}
+
+impl Ambisonics {
+    /// Component names in file channel order.
+    pub fn component_names(self) -> [&'static str; 4] {
+        match self {
+            Ambisonics::Ambix => ["W", "Y", "Z", "X"],
+            Ambisonics::Fuma => ["W", "X", "Y", "Z"],
+        }
+    }
+}
+
+/// Routing of input channels to output channels with gains, for
+/// downmixes, fold-downs and stem layouts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMatrix {
+    /// Linear gain of each input channel in each output channel, indexed
+    /// `[output][input]`
+    pub gains: Vec<Vec<f32>>,
+}
+
+impl ChannelMatrix {
+    /// Parses a matrix file: one line per output channel holding the
+    /// linear gain of every input channel, whitespace separated. Blank
+    /// lines and `#` comments are ignored.
+    pub fn parse_rows(text: &str) -> Result<Self> {
+        let gains = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|gain| gain.parse::<f32>().with_context(|| format!("Invalid gain '{}' in matrix row '{}'", gain, line)))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if gains.is_empty() {
+            bail!("Channel matrix has no rows");
+        }
+        if gains.iter().any(|row| row.len() != gains[0].len()) {
+            bail!("Every channel matrix row needs one gain per input channel");
+        }
+        Ok(Self { gains })
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.gains.len()
+    }
+
+    /// Mixes the channels of `input` into the matrix's outputs.
+    pub fn apply(&self, input: &AudioBuffer) -> Result<AudioBuffer> {
+        let width = self.gains.iter().map(Vec::len).max().unwrap_or(0);
+        if width > input.num_channels() {
+            bail!("Channel matrix reads input channel {} but the input has {} channels", width - 1, input.num_channels());
+        }
+        let outputs = self
+            .gains
+            .iter()
+            .map(|row| {
+                let mut output = vec![0.0f32; input.frames()];
+                for (&gain, channel) in row.iter().zip(input.channels()) {
+                    output.iter_mut().zip(channel).for_each(|(out, &sample)| *out += gain * sample);
+                }
+                output
+            })
+            .collect();
+        AudioBuffer::from_planar(outputs, input.sample_rate())
+    }
+}
+
+/// `IN:OUT[:GAIN_DB]` routes separated by commas, e.g. `0:0,0:1` for mono
+/// to stereo or `0:0:-3,1:0:-3` for a stereo fold-down. Routes to the same
+/// output add up.
+impl FromStr for ChannelMatrix {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut routes = Vec::new();
+        for route in value.split(',') {
+            let parts: Vec<&str> = route.trim().split(':').collect();
+            let (input, output, gain_db) = match parts[..] {
+                [input, output] => (input, output, "0"),
+                [input, output, gain_db] => (input, output, gain_db),
+                _ => bail!("Expected IN:OUT or IN:OUT:GAIN_DB, got '{}'", route),
+            };
+            let index = |text: &str| text.trim().parse::<usize>().with_context(|| format!("Invalid channel '{}' in '{}'", text, route));
+            let gain_db: f32 = gain_db.trim().parse().with_context(|| format!("Invalid gain '{}' in '{}'", gain_db, route))?;
+            routes.push((index(input)?, index(output)?, 10f32.powf(gain_db / 20.0)));
+        }
+
+        let inputs = routes.iter().map(|&(input, _, _)| input + 1).max().unwrap_or(0);
+        let outputs = routes.iter().map(|&(_, output, _)| output + 1).max().unwrap_or(0);
+        let mut gains = vec![vec![0.0; inputs]; outputs];
+        for (input, output, gain) in routes {
+            gains[output][input] += gain;
+        }
+        Ok(Self { gains })
+    }
+}
+
+/// A delay of one channel, from `CHANNEL=TIME` such as `R=+0.3ms`, for
+/// tape azimuth errors and misaligned microphones. The channel is a
+/// speaker name or a 0-based index; the time is in s, ms, us or samples,
+/// and negative times advance the channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelDelay {
+    pub channel: String,
+    pub amount: f64,
+    /// Whether `amount` counts samples rather than seconds
+    pub in_samples: bool,
+}
+
+impl ChannelDelay {
+    /// Index of the channel among `channels`.
+    pub fn index(&self, channels: usize) -> Result<usize> {
+        if let Ok(index) = self.channel.parse::<usize>() {
+            if index >= channels {
+                bail!("Can't delay channel {} of a {}-channel input", index, channels);
+            }
+            return Ok(index);
+        }
+        let names = speaker_names(channels);
+        names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(&self.channel))
+            .with_context(|| format!("No channel '{}' in a {}-channel input; use one of {} or an index", self.channel, channels, names.join(", ")))
+    }
+
+    /// The delay in samples at `sample_rate`.
+    pub fn frames(&self, sample_rate: u32) -> f64 {
+        if self.in_samples {
+            self.amount
+        } else {
+            self.amount * sample_rate as f64
+        }
+    }
+}
+
+impl FromStr for ChannelDelay {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some((channel, time)) = value.split_once('=') else {
+            bail!("Expected CHANNEL=TIME, e.g. R=+0.3ms, got '{}'", value);
+        };
+        let time = time.trim();
+        let (number, scale, in_samples) = if let Some(number) = time.strip_suffix("samples") {
+            (number, 1.0, true)
+        } else if let Some(number) = time.strip_suffix("ms") {
+            (number, 1e-3, false)
+        } else if let Some(number) = time.strip_suffix("us") {
+            (number, 1e-6, false)
+        } else if let Some(number) = time.strip_suffix('s') {
+            (number, 1.0, false)
+        } else {
+            bail!("Delay '{}' needs a unit: s, ms, us or samples", time);
+        };
+        let amount: f64 = number.trim().parse().with_context(|| format!("Invalid delay '{}'", time))?;
+        if !amount.is_finite() {
+            bail!("Invalid delay '{}'", time);
+        }
+        Ok(Self { channel: channel.trim().to_string(), amount: amount * scale, in_samples })
+    }
+}
@@ -0,0 +1,167 @@
+//! Heuristic chapter detection for long-form speech such as podcasts and
+//! audiobooks: chapters start after long silences and where the programme
+//! changes between speech and music.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// Level frames per second.
+const FRAME_RATE: usize = 50;
+/// Frames classified together as speech or music.
+const WINDOW_FRAMES: usize = FRAME_RATE;
+/// Windows either side a window's class is voted over.
+const SMOOTH_WINDOWS: usize = 2;
+/// Percentile of frame levels taken as the programme level.
+const PROGRAMME_PERCENTILE: f32 = 0.95;
+/// Frames below this are silent whatever the programme level (dBFS).
+const ABSOLUTE_SILENCE_DB: f32 = -90.0;
+/// A chapter starting after a silence starts this long before the sound
+/// resumes, as far back as the middle of the silence.
+const LEAD_IN_SECONDS: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChapterSettings {
+    /// How far below the programme level a frame counts as silent (dB)
+    pub silence_db: f32,
+    /// Shortest silence that separates chapters (s)
+    pub min_silence: f64,
+    /// Shortest chapter (s)
+    pub min_length: f64,
+    /// Share of a window's frames below half its mean energy from which it
+    /// counts as speech; syllables and pauses make speech dip far more
+    /// often than music
+    pub low_energy_ratio: f32,
+    /// Start chapters where speech turns to music and back, not only after
+    /// silences
+    pub transitions: bool,
+}
+
+impl Default for ChapterSettings {
+    fn default() -> Self {
+        Self { silence_db: 40.0, min_silence: 2.0, min_length: 30.0, low_energy_ratio: 0.25, transitions: true }
+    }
+}
+
+/// What a chapter mostly holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Content {
+    Speech,
+    Music,
+    Silence,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chapter {
+    pub title: String,
+    /// Start and end (s)
+    pub start: f64,
+    pub end: f64,
+    pub content: Content,
+}
+
+/// Splits the mono signal into chapters at long silences and, if enabled,
+/// at changes between speech and music, keeping every chapter at least
+/// `min_length` long.
+pub fn detect(mono: &[f32], sample_rate: u32, settings: ChapterSettings) -> Result<Vec<Chapter>> {
+    let hop = (sample_rate as usize / FRAME_RATE).max(1);
+    if mono.len() < hop {
+        bail!("Cannot detect chapters in less than {} ms of audio", 1000 / FRAME_RATE);
+    }
+    let duration = mono.len() as f64 / sample_rate as f64;
+    let seconds = |frame: usize| (frame * hop) as f64 / sample_rate as f64;
+
+    let energies: Vec<f32> = mono.chunks(hop).map(|chunk| chunk.iter().map(|x| x * x).sum::<f32>() / chunk.len() as f32).collect();
+    let levels: Vec<f32> = energies.iter().map(|&energy| 10.0 * energy.max(1e-20).log10()).collect();
+    let mut sorted = levels.clone();
+    sorted.sort_by(f32::total_cmp);
+    let programme_db = sorted[((sorted.len() - 1) as f32 * PROGRAMME_PERCENTILE) as usize];
+    let silence_db = (programme_db - settings.silence_db).max(ABSOLUTE_SILENCE_DB);
+    let silent: Vec<bool> = levels.iter().map(|&level| level < silence_db).collect();
+
+    // Silences inside the programme, not before or after it
+    let mut boundaries = Vec::new();
+    let min_silence = (settings.min_silence * FRAME_RATE as f64).ceil() as usize;
+    let mut start = None;
+    for (i, &quiet) in silent.iter().enumerate() {
+        match (quiet, start) {
+            (true, None) => start = Some(i),
+            (false, Some(first)) => {
+                if first > 0 && i - first >= min_silence {
+                    boundaries.push(((seconds(first) + seconds(i)) / 2.0).max(seconds(i) - LEAD_IN_SECONDS));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    let classes = classify(&energies, &silent, settings.low_energy_ratio);
+    if settings.transitions {
+        // Changes between speech and music, across any silence between them
+        let mut previous = None;
+        for (i, &class) in classes.iter().enumerate().filter(|(_, &class)| class != Content::Silence) {
+            if previous.is_some_and(|previous| previous != class) {
+                boundaries.push(seconds(i * WINDOW_FRAMES));
+            }
+            previous = Some(class);
+        }
+    }
+
+    // Earlier boundaries win over later ones too close to them
+    boundaries.sort_by(f64::total_cmp);
+    let mut starts = vec![0.0];
+    for boundary in boundaries {
+        if boundary - starts[starts.len() - 1] >= settings.min_length && duration - boundary >= settings.min_length {
+            starts.push(boundary);
+        }
+    }
+    let ends: Vec<f64> = starts.iter().skip(1).copied().chain([duration]).collect();
+    Ok(starts
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(i, (&start, end))| {
+            let window = |seconds: f64| (seconds * FRAME_RATE as f64) as usize / WINDOW_FRAMES;
+            let windows = &classes[window(start).min(classes.len())..(window(end) + 1).min(classes.len())];
+            Chapter { title: format!("Chapter {}", i + 1), start, end, content: majority(windows.iter().copied()) }
+        })
+        .collect())
+}
+
+/// Speech, music or silence in each one-second window, by majority over
+/// its neighbours.
+fn classify(energies: &[f32], silent: &[bool], low_energy_ratio: f32) -> Vec<Content> {
+    let raw: Vec<Content> = energies
+        .chunks(WINDOW_FRAMES)
+        .zip(silent.chunks(WINDOW_FRAMES))
+        .map(|(window, silent)| {
+            if 2 * silent.iter().filter(|&&quiet| quiet).count() > silent.len() {
+                return Content::Silence;
+            }
+            let mean = window.iter().sum::<f32>() / window.len() as f32;
+            let low = window.iter().filter(|&&energy| energy < 0.5 * mean).count() as f32 / window.len() as f32;
+            if low >= low_energy_ratio { Content::Speech } else { Content::Music }
+        })
+        .collect();
+    (0..raw.len())
+        .map(|i| match raw[i] {
+            Content::Silence => Content::Silence,
+            _ => majority(raw[i.saturating_sub(SMOOTH_WINDOWS)..(i + SMOOTH_WINDOWS + 1).min(raw.len())].iter().copied()),
+        })
+        .collect()
+}
+
+/// The commonest of speech and music in `classes`, or silence if neither
+/// occurs. Ties go to speech.
+fn majority(classes: impl Iterator<Item = Content>) -> Content {
+    let (speech, music) = classes.fold((0, 0), |(speech, music), class| match class {
+        Content::Speech => (speech + 1, music),
+        Content::Music => (speech, music + 1),
+        Content::Silence => (speech, music),
+    });
+    match (speech, music) {
+        (0, 0) => Content::Silence,
+        (speech, music) if music > speech => Content::Music,
+        _ => Content::Speech,
+    }
+}
@@ -0,0 +1,308 @@
+//! Decoders and encoders behind a registry keyed by file extension, so
+//! formats can be added, or replaced by user-supplied codecs, without
+//! touching [`AudioProcessor`](super::AudioProcessor).
+
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+use super::{aiff, caf, dither, intermediate, mp3, wav, AudioBuffer, DecodeErrorPolicy, DecodeStats, Dither};
+
+/// Samples normalized to [-1.0, 1.0], one channel per plane, at their
+/// sample rate.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub buffer: AudioBuffer,
+    /// Frames skipped or padded over corrupt data, for formats that
+    /// resynchronize
+    pub stats: DecodeStats,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeOptions {
+    pub error_policy: DecodeErrorPolicy,
+}
+
+/// Everything about the output an encoder may need; formats ignore what
+/// they can't store.
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    /// Channels, rate, and the bit depth to quantize to
+    pub spec: wav::Spec,
+    pub dither: Dither,
+    /// Seeds the dither noise and stream serial numbers of each file
+    pub seed: u64,
+    /// Broadcast Wave metadata
+    pub bext: Option<wav::Bext>,
+    pub force_rf64: bool,
+    pub opus_bitrate_kbps: u32,
+}
+
+pub trait Decoder: Send + Sync {
+    /// Name of the format, for logs and errors.
+    fn name(&self) -> &str;
+    /// Lowercase file extensions the decoder reads.
+    fn extensions(&self) -> &[&str];
+    fn decode(&self, reader: &mut dyn Read, options: &DecodeOptions) -> Result<Decoded>;
+}
+
+pub trait Encoder: Send + Sync {
+    /// Name of the format, for logs and errors.
+    fn name(&self) -> &str;
+    /// Lowercase file extensions the encoder writes.
+    fn extensions(&self) -> &[&str];
+    /// Writes `buffer`, whose channels and rate match `options.spec`.
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()>;
+}
+
+/// Codecs by file extension. Codecs registered later take precedence over
+/// earlier ones for the extensions they share. Files whose extension no
+/// decoder claims are read as MP3, and written as WAV when no encoder does.
+#[derive(Clone)]
+pub struct Registry {
+    decoders: Vec<Arc<dyn Decoder>>,
+    encoders: Vec<Arc<dyn Encoder>>,
+}
+
+impl Default for Registry {
+    /// The built-in formats.
+    fn default() -> Self {
+        Self::empty()
+            .with_decoder(Mp3)
+            .with_decoder(Wav)
+            .with_decoder(Intermediate)
+            .with_encoder(Wav)
+            .with_encoder(Aiff)
+            .with_encoder(Caf)
+            .with_encoder(Intermediate)
+            .with_encoder(Opus)
+    }
+}
+
+impl Registry {
+    /// A registry without any codecs.
+    pub fn empty() -> Self {
+        Self { decoders: Vec::new(), encoders: Vec::new() }
+    }
+
+    pub fn with_decoder(mut self, decoder: impl Decoder + 'static) -> Self {
+        self.decoders.push(Arc::new(decoder));
+        self
+    }
+
+    pub fn with_encoder(mut self, encoder: impl Encoder + 'static) -> Self {
+        self.encoders.push(Arc::new(encoder));
+        self
+    }
+
+    /// Decoder for `path`, by its extension.
+    pub fn decoder(&self, path: &Path) -> Result<&dyn Decoder> {
+        find(&self.decoders, path, "mp3").map(|decoder| decoder.as_ref()).with_context(|| format!("No decoder for {}", path.display()))
+    }
+
+    /// Encoder for `path`, by its extension.
+    pub fn encoder(&self, path: &Path) -> Result<&dyn Encoder> {
+        find(&self.encoders, path, "wav").map(|encoder| encoder.as_ref()).with_context(|| format!("No encoder for {}", path.display()))
+    }
+}
+
+/// Extensions of a decoder or encoder, for looking them up by either.
+trait Extensions {
+    fn claims(&self, extension: &str) -> bool;
+}
+
+impl Extensions for Arc<dyn Decoder> {
+    fn claims(&self, extension: &str) -> bool {
+        self.extensions().contains(&extension)
+    }
+}
+
+impl Extensions for Arc<dyn Encoder> {
+    fn claims(&self, extension: &str) -> bool {
+        self.extensions().contains(&extension)
+    }
+}
+
+/// The last codec claiming `path`'s extension, or else `fallback`.
+fn find<'a, T: Extensions>(codecs: &'a [T], path: &Path, fallback: &str) -> Option<&'a T> {
+    let extension = path.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+    codecs
+        .iter()
+        .rev()
+        .find(|codec| codec.claims(&extension))
+        .or_else(|| codecs.iter().rev().find(|codec| codec.claims(fallback)))
+}
+
+/// Channels as PCM for `spec`: floats as they are, or dithered integers.
+fn pcm<'a>(path: &Path, channels: &'a [&'a [f32]], options: &EncodeOptions, codes: &'a mut Vec<i32>) -> wav::Data<'a> {
+    if options.spec.float {
+        return wav::Data::Float(channels);
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let clipped = channels.iter().map(|channel| channel.iter().filter(|sample| sample.abs() > 1.0).count()).sum();
+    super::warn_clipped(&name, clipped);
+    let mut rng = dither::Rng::new(dither::stream_seed(options.seed, &name));
+    *codes = dither::quantize(channels, options.spec.bits_per_sample, options.dither, &mut rng);
+    wav::Data::Int(codes)
+}
+
+/// RIFF, RF64 and BW64 WAV.
+pub struct Wav;
+
+impl Decoder for Wav {
+    fn name(&self) -> &str {
+        "WAV"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["wav"]
+    }
+
+    fn decode(&self, reader: &mut dyn Read, _: &DecodeOptions) -> Result<Decoded> {
+        let (spec, channels) = wav::read(reader)?;
+        info!("Loaded {} frames ({} Hz, {} channels)", channels[0].len(), spec.sample_rate, spec.channels);
+        Ok(Decoded { buffer: AudioBuffer::from_planar(channels, spec.sample_rate)?, stats: DecodeStats::default() })
+    }
+}
+
+impl Encoder for Wav {
+    fn name(&self) -> &str {
+        "WAV"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["wav"]
+    }
+
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let channels: Vec<&[f32]> = buffer.channels().collect();
+        let mut codes = Vec::new();
+        let data = pcm(path, &channels, options, &mut codes);
+        wav::write(path, options.spec, data, options.bext.as_ref(), options.force_rf64)
+    }
+}
+
+pub struct Aiff;
+
+impl Encoder for Aiff {
+    fn name(&self) -> &str {
+        "AIFF"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aif", "aiff", "aifc"]
+    }
+
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let channels: Vec<&[f32]> = buffer.channels().collect();
+        let mut codes = Vec::new();
+        let data = pcm(path, &channels, options, &mut codes);
+        aiff::write(path, options.spec, data)
+    }
+}
+
+pub struct Caf;
+
+impl Encoder for Caf {
+    fn name(&self) -> &str {
+        "CAF"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["caf"]
+    }
+
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let channels: Vec<&[f32]> = buffer.channels().collect();
+        let mut codes = Vec::new();
+        let data = pcm(path, &channels, options, &mut codes);
+        caf::write(path, options.spec, data)
+    }
+}
+
+/// MP3, resynchronizing over corrupt data as the error policy says.
+pub struct Mp3;
+
+impl Decoder for Mp3 {
+    fn name(&self) -> &str {
+        "MP3"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mp3"]
+    }
+
+    fn decode(&self, reader: &mut dyn Read, options: &DecodeOptions) -> Result<Decoded> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).with_context(|| "Failed to read MP3 data")?;
+        let decoded = mp3::decode(&data, options.error_policy)?;
+        if decoded.stats.frames == 0 {
+            bail!("No decodable MP3 frames found");
+        }
+        Ok(Decoded { buffer: AudioBuffer::from_planar(decoded.channels, decoded.sample_rate)?, stats: decoded.stats })
+    }
+}
+
+/// The saunds intermediate format, whose f64 samples are narrowed on
+/// reading.
+pub struct Intermediate;
+
+impl Decoder for Intermediate {
+    fn name(&self) -> &str {
+        "intermediate"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[intermediate::EXTENSION]
+    }
+
+    fn decode(&self, reader: &mut dyn Read, _: &DecodeOptions) -> Result<Decoded> {
+        let (header, channels) = intermediate::read(reader)?;
+        info!("Loaded {} frames ({} Hz, {} channels)", header.frames, header.sample_rate, header.channels);
+        let channels = channels.into_iter().map(|channel| channel.into_iter().map(|sample| sample as f32).collect()).collect();
+        Ok(Decoded { buffer: AudioBuffer::from_planar(channels, header.sample_rate)?, stats: DecodeStats::default() })
+    }
+}
+
+impl Encoder for Intermediate {
+    fn name(&self) -> &str {
+        "intermediate"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[intermediate::EXTENSION]
+    }
+
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let channels: Vec<&[f32]> = buffer.channels().collect();
+        intermediate::write(path, options.spec.sample_rate, &channels)
+    }
+}
+
+/// Ogg Opus, when built with the opus feature.
+pub struct Opus;
+
+impl Encoder for Opus {
+    fn name(&self) -> &str {
+        "Opus"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["opus"]
+    }
+
+    #[cfg(feature = "opus")]
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let serial = dither::stream_seed(options.seed, &name) as u32;
+        let channels: Vec<&[f32]> = buffer.channels().collect();
+        super::opus::write(path, &channels, options.spec.sample_rate, options.opus_bitrate_kbps, serial)
+    }
+
+    #[cfg(not(feature = "opus"))]
+    fn encode(&self, _: &Path, _: &AudioBuffer, _: &EncodeOptions) -> Result<()> {
+        bail!("Opus output requires building with the opus feature");
+    }
+}
@@ -0,0 +1,161 @@
+//! Constant-Q transform and chroma features.
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
+use std::f64::consts::PI;
+
+/// Kernel spectrum bins below this fraction of the peak are dropped.
+const KERNEL_THRESHOLD: f64 = 0.005;
+/// Kernel spectra are evaluated this many `fft_size / len` bins either side
+/// of the center, covering the main lobe and first sidelobes.
+const KERNEL_LOBES: f64 = 8.0;
+/// Reference pitch for chroma bins (A4).
+const A4_HZ: f32 = 440.0;
+
+/// Geometrically spaced analysis bins from `min_frequency`, with
+/// `bins_per_octave` bins per octave. Each bin's window is as long as Q
+/// periods of its center frequency, so resolution is constant in octaves:
+/// long windows in the bass, short ones in the treble.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cqt {
+    pub min_frequency: f32,
+    pub bins_per_octave: usize,
+    pub octaves: usize,
+}
+
+impl Cqt {
+    /// Center frequency of each bin.
+    pub fn frequencies(&self) -> Vec<f32> {
+        (0..self.bins_per_octave * self.octaves)
+            .map(|k| self.min_frequency * 2f32.powf(k as f32 / self.bins_per_octave as f32))
+            .collect()
+    }
+
+    /// Ratio of center frequency to bandwidth shared by all bins.
+    pub fn q(&self) -> f32 {
+        1.0 / (2f32.powf(1.0 / self.bins_per_octave as f32) - 1.0)
+    }
+
+    /// Magnitudes of the mono `samples` in frames centered every `hop`
+    /// samples, as `[frame][bin]`, scaled so a full-scale sine at a bin's
+    /// center frequency reads 1.
+    ///
+    /// Uses sparse spectral kernels (Brown and Puckette): each frame takes
+    /// one FFT as long as the longest kernel, and each bin is the product
+    /// with the few FFT bins where its kernel has energy.
+    pub fn transform(&self, samples: &[f32], sample_rate: u32, hop: usize) -> Result<Vec<Vec<f32>>> {
+        if self.bins_per_octave == 0 || self.octaves == 0 || hop == 0 {
+            bail!("Bins per octave, octaves and hop must be positive");
+        }
+        let frequencies = self.frequencies();
+        let nyquist = sample_rate as f32 / 2.0;
+        if let Some(&highest) = frequencies.last().filter(|&&f| f >= nyquist) {
+            bail!("Highest CQT bin ({:.0} Hz) must be below Nyquist ({} Hz)", highest, nyquist);
+        }
+
+        let longest = (self.q() * sample_rate as f32 / frequencies[0]).ceil() as usize;
+        let fft_size = longest.next_power_of_two();
+        let kernels = self.sparse_kernels(&frequencies, sample_rate, fft_size);
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(fft_size);
+        let mut frame = fft.make_input_vec();
+        let mut spectrum = fft.make_output_vec();
+
+        (0..samples.len().div_ceil(hop))
+            .map(|index| {
+                // Frame centered on the hop position, zero-padded at the edges
+                let start = (index * hop) as isize - (fft_size / 2) as isize;
+                for (n, slot) in frame.iter_mut().enumerate() {
+                    let at = start + n as isize;
+                    *slot = if at >= 0 { samples.get(at as usize).copied().unwrap_or(0.0) } else { 0.0 };
+                }
+                fft.process(&mut frame, &mut spectrum)
+                    .with_context(|| format!("Failed to compute CQT frame {}", index))?;
+                Ok(kernels
+                    .iter()
+                    .map(|kernel| kernel.iter().map(|&(bin, k)| spectrum[bin] * k).sum::<Complex<f32>>().norm())
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Spectra of the windowed complex exponential of each bin, centered in
+    /// an `fft_size` frame, keeping only bins above [`KERNEL_THRESHOLD`] of
+    /// the peak. Scaled to read amplitude directly.
+    fn sparse_kernels(&self, frequencies: &[f32], sample_rate: u32, fft_size: usize) -> Vec<Vec<(usize, Complex<f32>)>> {
+        frequencies
+            .iter()
+            .map(|&f| {
+                let len = ((self.q() * sample_rate as f32 / f).ceil() as usize).clamp(1, fft_size);
+                let omega = 2.0 * PI * f as f64 / sample_rate as f64;
+                let offset = (fft_size - len) / 2;
+                // Hann window energy sum is len / 2
+                let norm = 4.0 / len as f64;
+
+                // The Hann-windowed exponential is a sum of three plain
+                // exponentials, so each DFT bin has a closed form. Only the
+                // main lobe and the first sidelobes matter.
+                let centre = f as f64 * fft_size as f64 / sample_rate as f64;
+                let reach = (KERNEL_LOBES * fft_size as f64 / len as f64).ceil() + 2.0;
+                let first = (centre - reach).max(0.0) as usize;
+                let last = ((centre + reach) as usize).min(fft_size / 2);
+                let spectrum: Vec<(usize, Complex<f64>)> = (first..=last)
+                    .map(|bin| {
+                        let theta = omega - 2.0 * PI * bin as f64 / fft_size as f64;
+                        let step = 2.0 * PI / len as f64;
+                        let windowed = 0.5 * geometric_sum(theta, len)
+                            - 0.25 * geometric_sum(theta + step, len)
+                            - 0.25 * geometric_sum(theta - step, len);
+                        let shift = -omega * len as f64 / 2.0
+                            - 2.0 * PI * bin as f64 * offset as f64 / fft_size as f64;
+                        (bin, norm * windowed * Complex::from_polar(1.0, shift))
+                    })
+                    .collect();
+
+                // Correlating with the frame is a product with the conjugate
+                // kernel spectrum, divided by the FFT size
+                let peak = spectrum.iter().fold(0.0f64, |peak, (_, k)| peak.max(k.norm()));
+                spectrum
+                    .into_iter()
+                    .filter(|(_, k)| k.norm() > KERNEL_THRESHOLD * peak)
+                    .map(|(bin, k)| {
+                        let k = k.conj() / fft_size as f64;
+                        (bin, Complex::new(k.re as f32, k.im as f32))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// `sum(exp(i theta n))` for `n` in `0..len`.
+fn geometric_sum(theta: f64, len: usize) -> Complex<f64> {
+    let ratio = Complex::from_polar(1.0, theta);
+    if (ratio - 1.0).norm() < 1e-12 {
+        Complex::new(len as f64, 0.0)
+    } else {
+        (Complex::new(1.0, 0.0) - Complex::from_polar(1.0, theta * len as f64)) / (Complex::new(1.0, 0.0) - ratio)
+    }
+}
+
+/// Folds a magnitude spectrum sampled at `frequencies` into 12 pitch
+/// classes (C first) of summed power, normalized to a maximum of 1. Bins
+/// below 20 Hz are ignored.
+pub fn chroma(frequencies: &[f32], magnitudes: &[f32]) -> [f32; 12] {
+    let mut classes = [0.0f32; 12];
+    for (&f, &m) in frequencies.iter().zip(magnitudes) {
+        if f < 20.0 {
+            continue;
+        }
+        // MIDI note number: C is a multiple of 12
+        let note = (12.0 * (f / A4_HZ).log2() + 69.0).round() as i64;
+        classes[note.rem_euclid(12) as usize] += m * m;
+    }
+    let max = classes.iter().fold(0.0f32, |max, &c| max.max(c));
+    if max > 0.0 {
+        classes.iter_mut().for_each(|c| *c /= max);
+    }
+    classes
+}
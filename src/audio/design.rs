@@ -0,0 +1,411 @@
+//! IIR filter design: analog prototypes mapped to biquad cascades with the
+//! bilinear transform.
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use num_complex::Complex;
+use std::f64::consts::PI;
+
+use super::biquad::{cascade_response_db, prewarp, Biquad};
+
+type Complex64 = Complex<f64>;
+
+/// Highest supported prototype order.
+pub const MAX_ORDER: usize = 16;
+/// Roots with an imaginary part below this are treated as real.
+const REAL_TOLERANCE: f64 = 1e-10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FilterFamily {
+    /// Maximally flat passband, -3 dB at the cutoff
+    #[default]
+    Butterworth,
+    /// Equiripple passband, steeper roll-off; the cutoff is the passband edge
+    Chebyshev1,
+    /// Flat passband, equiripple stopband; the cutoff is the stopband edge
+    Chebyshev2,
+    /// Equiripple in both bands, steepest roll-off; the cutoff is the passband edge
+    Elliptic,
+}
+
+/// Family, order and ripple specification of a low- or high-pass filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterDesign {
+    pub family: FilterFamily,
+    pub order: usize,
+    /// Peak-to-peak passband ripple for Chebyshev I and elliptic designs (dB)
+    pub passband_ripple_db: f64,
+    /// Minimum stopband attenuation for Chebyshev II and elliptic designs (dB)
+    pub stopband_attenuation_db: f64,
+}
+
+impl Default for FilterDesign {
+    fn default() -> Self {
+        Self {
+            family: FilterFamily::Butterworth,
+            order: 2,
+            passband_ripple_db: 1.0,
+            stopband_attenuation_db: 60.0,
+        }
+    }
+}
+
+/// Zeros, poles and gain of a transfer function.
+struct Zpk {
+    zeros: Vec<Complex64>,
+    poles: Vec<Complex64>,
+    gain: f64,
+}
+
+impl FilterDesign {
+    pub fn validate(&self) -> Result<()> {
+        if self.order == 0 || self.order > MAX_ORDER {
+            bail!("Filter order must be between 1 and {}, got {}", MAX_ORDER, self.order);
+        }
+        if self.passband_ripple_db.is_nan() || self.passband_ripple_db <= 0.0 {
+            bail!("Passband ripple must be positive, got {} dB", self.passband_ripple_db);
+        }
+        if self.stopband_attenuation_db.is_nan() || self.stopband_attenuation_db <= self.passband_ripple_db {
+            bail!(
+                "Stopband attenuation ({} dB) must exceed the passband ripple ({} dB)",
+                self.stopband_attenuation_db, self.passband_ripple_db
+            );
+        }
+        Ok(())
+    }
+
+    /// Low-pass cascade with its characteristic frequency at `cutoff` Hz.
+    pub fn lowpass(&self, cutoff: f32, sample_rate: u32) -> Result<Vec<Biquad>> {
+        self.validate()?;
+        let w = prewarp(cutoff as f64, sample_rate);
+        let Zpk { zeros, poles, gain } = self.prototype();
+
+        // s -> s / w
+        let degree = poles.len() as i32 - zeros.len() as i32;
+        let analog = Zpk {
+            zeros: zeros.iter().map(|z| z * w).collect(),
+            poles: poles.iter().map(|p| p * w).collect(),
+            gain: gain * w.powi(degree),
+        };
+        Ok(to_sections(bilinear(analog, sample_rate, -1.0)))
+    }
+
+    /// High-pass cascade with its characteristic frequency at `cutoff` Hz.
+    pub fn highpass(&self, cutoff: f32, sample_rate: u32) -> Result<Vec<Biquad>> {
+        self.validate()?;
+        let w = prewarp(cutoff as f64, sample_rate);
+        let Zpk { zeros, poles, gain } = self.prototype();
+
+        // s -> w / s, moving the excess zeros from infinity to the origin
+        let ratio: Complex64 = zeros.iter().map(|z| -z).product::<Complex64>() / poles.iter().map(|p| -p).product::<Complex64>();
+        let mut mapped_zeros: Vec<Complex64> = zeros.iter().map(|z| w / z).collect();
+        mapped_zeros.resize(poles.len(), Complex64::new(0.0, 0.0));
+        let analog = Zpk {
+            zeros: mapped_zeros,
+            poles: poles.iter().map(|p| w / p).collect(),
+            gain: gain * ratio.re,
+        };
+        Ok(to_sections(bilinear(analog, sample_rate, 1.0)))
+    }
+
+    /// Band-pass cascade between `low` and `high` Hz, of twice the design
+    /// order.
+    pub fn bandpass(&self, low: f32, high: f32, sample_rate: u32) -> Result<Vec<Biquad>> {
+        self.validate()?;
+        let (center_sq, bandwidth) = band_edges(low, high, sample_rate);
+        let Zpk { zeros, poles, gain } = self.prototype();
+
+        // s -> (s² + w0²) / (s bw): every root splits into two, and the
+        // excess zeros at infinity gain partners at the origin
+        let split = |root: &Complex64| quadratic_roots(-root * bandwidth, center_sq);
+        let degree = poles.len() - zeros.len();
+        let mut mapped_zeros: Vec<Complex64> = zeros.iter().flat_map(split).collect();
+        mapped_zeros.extend(std::iter::repeat_n(Complex64::new(0.0, 0.0), degree));
+        let analog = Zpk {
+            zeros: mapped_zeros,
+            poles: poles.iter().flat_map(split).collect(),
+            gain: gain * bandwidth.powi(degree as i32),
+        };
+        Ok(to_sections(bilinear(analog, sample_rate, -1.0)))
+    }
+
+    /// Band-stop cascade rejecting `low` to `high` Hz, of twice the design
+    /// order.
+    pub fn bandstop(&self, low: f32, high: f32, sample_rate: u32) -> Result<Vec<Biquad>> {
+        self.validate()?;
+        let (center_sq, bandwidth) = band_edges(low, high, sample_rate);
+        let Zpk { zeros, poles, gain } = self.prototype();
+
+        // s -> s bw / (s² + w0²): the excess zeros land on ±j w0
+        let split = |root: &Complex64| quadratic_roots(-bandwidth / root, center_sq);
+        let ratio: Complex64 = zeros.iter().map(|z| -z).product::<Complex64>() / poles.iter().map(|p| -p).product::<Complex64>();
+        let notch = Complex64::new(0.0, center_sq.sqrt());
+        let mut mapped_zeros: Vec<Complex64> = zeros.iter().flat_map(split).collect();
+        for _ in zeros.len()..poles.len() {
+            mapped_zeros.extend([notch, notch.conj()]);
+        }
+        let analog = Zpk {
+            zeros: mapped_zeros,
+            poles: poles.iter().flat_map(split).collect(),
+            gain: gain * ratio.re,
+        };
+        Ok(to_sections(bilinear(analog, sample_rate, -1.0)))
+    }
+
+    /// Analog low-pass prototype with its characteristic frequency at 1 rad/s.
+    fn prototype(&self) -> Zpk {
+        let n = self.order;
+        match self.family {
+            FilterFamily::Butterworth => {
+                let poles = (0..n)
+                    .map(|k| Complex64::from_polar(1.0, PI * (2 * k + n + 1) as f64 / (2 * n) as f64))
+                    .collect();
+                Zpk { zeros: Vec::new(), poles, gain: 1.0 }
+            }
+            FilterFamily::Chebyshev1 => {
+                let eps = (10f64.powf(self.passband_ripple_db / 10.0) - 1.0).sqrt();
+                let mu = (1.0 / eps).asinh() / n as f64;
+                let poles: Vec<Complex64> = (0..n)
+                    .map(|k| {
+                        let theta = PI * (2 * k + 1) as f64 / (2 * n) as f64;
+                        Complex64::new(-mu.sinh() * theta.sin(), mu.cosh() * theta.cos())
+                    })
+                    .collect();
+                let mut gain = poles.iter().map(|p| -p).product::<Complex64>().re;
+                if n.is_multiple_of(2) {
+                    gain /= (1.0 + eps * eps).sqrt();
+                }
+                Zpk { zeros: Vec::new(), poles, gain }
+            }
+            FilterFamily::Chebyshev2 => {
+                let eps = 1.0 / (10f64.powf(self.stopband_attenuation_db / 10.0) - 1.0).sqrt();
+                let mu = (1.0 / eps).asinh() / n as f64;
+                let thetas = (0..n).map(|k| PI * (2 * k + 1) as f64 / (2 * n) as f64);
+                let zeros: Vec<Complex64> = thetas
+                    .clone()
+                    .filter(|theta| theta.cos().abs() > REAL_TOLERANCE)
+                    .map(|theta| Complex64::new(0.0, 1.0 / theta.cos()))
+                    .collect();
+                let poles: Vec<Complex64> = thetas
+                    .map(|theta| 1.0 / Complex64::new(-mu.sinh() * theta.sin(), mu.cosh() * theta.cos()))
+                    .collect();
+                let gain = (poles.iter().map(|p| -p).product::<Complex64>()
+                    / zeros.iter().map(|z| -z).product::<Complex64>())
+                .re;
+                Zpk { zeros, poles, gain }
+            }
+            FilterFamily::Elliptic => elliptic_prototype(n, self.passband_ripple_db, self.stopband_attenuation_db),
+        }
+    }
+}
+
+/// Squared center frequency and bandwidth of a prewarped band, in rad/s.
+fn band_edges(low: f32, high: f32, sample_rate: u32) -> (f64, f64) {
+    let (low, high) = (prewarp(low as f64, sample_rate), prewarp(high as f64, sample_rate));
+    (low * high, high - low)
+}
+
+/// Roots of s² + b s + c.
+fn quadratic_roots(b: Complex64, c: f64) -> [Complex64; 2] {
+    let root = (b * b - 4.0 * c).sqrt();
+    [(-b + root) / 2.0, (-b - root) / 2.0]
+}
+
+/// Elliptic (Cauer) prototype following the classical construction from
+/// Jacobi elliptic functions.
+fn elliptic_prototype(n: usize, ripple_db: f64, attenuation_db: f64) -> Zpk {
+    let eps_sq = 10f64.powf(ripple_db / 10.0) - 1.0;
+    if n == 1 {
+        let pole = -(1.0 / eps_sq).sqrt();
+        return Zpk { zeros: Vec::new(), poles: vec![Complex64::new(pole, 0.0)], gain: -pole };
+    }
+
+    // Selectivity parameter m from the degree equation
+    // K(m) / K(1 - m) = n K(k1²) / K(1 - k1²)
+    let k1_sq = eps_sq / (10f64.powf(attenuation_db / 10.0) - 1.0);
+    let k1_ratio = ellipk(k1_sq) / ellipk(1.0 - k1_sq);
+    let target = n as f64 * k1_ratio;
+    let (mut lo, mut hi) = (0.0f64, 1.0f64);
+    for _ in 0..200 {
+        let m = 0.5 * (lo + hi);
+        if ellipk(m) / ellipk(1.0 - m) < target {
+            lo = m;
+        } else {
+            hi = m;
+        }
+    }
+    let m = 0.5 * (lo + hi);
+    let capk = ellipk(m);
+
+    let js: Vec<f64> = ((1 - n % 2)..n).step_by(2).map(|j| j as f64).collect();
+    let jacobi: Vec<(f64, f64, f64)> = js.iter().map(|&j| ellipj(j * capk / n as f64, m)).collect();
+
+    let mut zeros = Vec::new();
+    for &(s, _, _) in &jacobi {
+        if s.abs() > REAL_TOLERANCE {
+            let zero = Complex64::new(0.0, 1.0 / (m.sqrt() * s));
+            zeros.push(zero);
+            zeros.push(zero.conj());
+        }
+    }
+
+    let r = arc_jac_sc1((1.0 / eps_sq).sqrt(), k1_sq);
+    let v0 = capk * r / (n as f64 * ellipk(k1_sq));
+    let (sv, cv, dv) = ellipj(v0, 1.0 - m);
+    let mut poles = Vec::new();
+    for &(s, c, d) in &jacobi {
+        let pole = -Complex64::new(c * d * sv * cv, s * dv) / (1.0 - (d * sv).powi(2));
+        poles.push(pole);
+        if pole.im.abs() > REAL_TOLERANCE * pole.norm() {
+            poles.push(pole.conj());
+        }
+    }
+
+    let mut gain = (poles.iter().map(|p| -p).product::<Complex64>()
+        / zeros.iter().map(|z| -z).product::<Complex64>())
+    .re;
+    if n.is_multiple_of(2) {
+        gain /= (1.0 + eps_sq).sqrt();
+    }
+    Zpk { zeros, poles, gain }
+}
+
+/// Complete elliptic integral of the first kind with parameter `m`.
+fn ellipk(m: f64) -> f64 {
+    let (mut a, mut b) = (1.0f64, (1.0 - m).max(0.0).sqrt());
+    while (a - b).abs() > 1e-15 * a {
+        (a, b) = (0.5 * (a + b), (a * b).sqrt());
+    }
+    PI / (2.0 * a)
+}
+
+/// Jacobi elliptic functions sn, cn and dn of `u` with parameter `m`, by
+/// the arithmetic-geometric mean.
+fn ellipj(u: f64, m: f64) -> (f64, f64, f64) {
+    if m < 1e-12 {
+        return (u.sin(), u.cos(), 1.0);
+    }
+
+    let mut a = vec![1.0f64];
+    let mut c = vec![m.sqrt()];
+    let mut b = (1.0 - m).sqrt();
+    while c.last().is_some_and(|&c| c.abs() > 1e-15) && a.len() < 32 {
+        let an = *a.last().unwrap();
+        c.push(0.5 * (an - b));
+        a.push(0.5 * (an + b));
+        b = (an * b).sqrt();
+    }
+
+    let steps = a.len() - 1;
+    let mut phi = 2f64.powi(steps as i32) * a[steps] * u;
+    for i in (1..=steps).rev() {
+        phi = 0.5 * (phi + (c[i] * phi.sin() / a[i]).asin());
+    }
+    let sn = phi.sin();
+    (sn, phi.cos(), (1.0 - m * sn * sn).sqrt())
+}
+
+/// Inverse of the Jacobi sn function for complex arguments, by descending
+/// Landen transformations.
+fn arc_jac_sn(w: Complex64, m: f64) -> Complex64 {
+    let complement = |x: Complex64| ((1.0 - x) * (1.0 + x)).sqrt();
+
+    let mut ks = vec![m.sqrt()];
+    while *ks.last().unwrap() != 0.0 && ks.len() < 16 {
+        let k = *ks.last().unwrap();
+        let k_prime = ((1.0 - k) * (1.0 + k)).sqrt();
+        ks.push((1.0 - k_prime) / (1.0 + k_prime));
+    }
+
+    let capk = ks[1..].iter().map(|k| 1.0 + k).product::<f64>() * PI / 2.0;
+    let mut wn = w;
+    for pair in ks.windows(2) {
+        wn = 2.0 * wn / ((1.0 + pair[1]) * (1.0 + complement(pair[0] * wn)));
+    }
+    capk * (2.0 / PI) * wn.asin()
+}
+
+/// Real inverse of the Jacobi sc function.
+fn arc_jac_sc1(w: f64, m: f64) -> f64 {
+    arc_jac_sn(Complex64::new(0.0, w), m).im
+}
+
+/// Maps an analog filter to the z-plane. Excess poles get zeros at
+/// `infinity_zero` (-1 for low-pass, +1 for high-pass mappings).
+fn bilinear(analog: Zpk, sample_rate: u32, infinity_zero: f64) -> Zpk {
+    let k = 2.0 * sample_rate as f64;
+    let map = |root: &Complex64| (k + root) / (k - root);
+
+    let mut zeros: Vec<Complex64> = analog.zeros.iter().map(map).collect();
+    zeros.resize(analog.poles.len(), Complex64::new(infinity_zero, 0.0));
+    let gain = analog.gain
+        * (analog.zeros.iter().map(|z| k - z).product::<Complex64>()
+            / analog.poles.iter().map(|p| k - p).product::<Complex64>())
+        .re;
+
+    Zpk { zeros, poles: analog.poles.iter().map(map).collect(), gain }
+}
+
+/// Groups roots into conjugate pairs, pairs of real roots and at most one
+/// single real root, as polynomial coefficients `[c1, c2]` of
+/// `1 + c1 z⁻¹ + c2 z⁻²`, with the group's largest root magnitude.
+fn root_groups(roots: &[Complex64]) -> Vec<(f64, [f64; 2])> {
+    let mut groups: Vec<(f64, [f64; 2])> = roots
+        .iter()
+        .filter(|r| r.im > REAL_TOLERANCE)
+        .map(|r| (r.norm(), [-2.0 * r.re, r.norm_sqr()]))
+        .collect();
+
+    let mut reals: Vec<f64> = roots.iter().filter(|r| r.im.abs() <= REAL_TOLERANCE).map(|r| r.re).collect();
+    reals.sort_by(|a, b| b.abs().total_cmp(&a.abs()));
+    for pair in reals.chunks(2) {
+        match *pair {
+            [a, b] => groups.push((a.abs().max(b.abs()), [-(a + b), a * b])),
+            [a] => groups.push((a.abs(), [-a, 0.0])),
+            _ => unreachable!(),
+        }
+    }
+
+    groups.sort_by(|a, b| b.0.total_cmp(&a.0));
+    groups
+}
+
+/// Factors a digital filter into biquads, pairing the poles closest to
+/// the unit circle with the nearest zeros.
+fn to_sections(digital: Zpk) -> Vec<Biquad> {
+    let poles = root_groups(&digital.poles);
+    let mut zeros = root_groups(&digital.zeros);
+
+    let mut sections: Vec<Biquad> = poles
+        .iter()
+        .map(|&(_, [a1, a2])| {
+            let pole_root = Complex64::new(-a1 / 2.0, (a2 - a1 * a1 / 4.0).max(0.0).sqrt());
+            let nearest = (0..zeros.len())
+                .min_by(|&i, &j| {
+                    let distance = |index: usize| {
+                        let [b1, b2] = zeros[index].1;
+                        let zero_root = Complex64::new(-b1 / 2.0, (b2 - b1 * b1 / 4.0).max(0.0).sqrt());
+                        (zero_root - pole_root).norm()
+                    };
+                    distance(i).total_cmp(&distance(j))
+                })
+                .expect("every pole group has a matching zero group");
+            let (_, [b1, b2]) = zeros.remove(nearest);
+            Biquad { b0: 1.0, b1, b2, a1, a2 }
+        })
+        .collect();
+
+    if let Some(first) = sections.first_mut() {
+        *first = first.scaled(digital.gain);
+    }
+    sections
+}
+
+/// Smallest attenuation of the cascade over `from..to` Hz, in dB.
+pub fn stopband_attenuation_db(cascade: &[Biquad], from: f32, to: f32, sample_rate: u32) -> f32 {
+    const POINTS: usize = 512;
+    (0..=POINTS)
+        .map(|i| from * (to / from).powf(i as f32 / POINTS as f32))
+        .map(|frequency| -cascade_response_db(cascade, frequency, sample_rate))
+        .fold(f32::INFINITY, f32::min)
+}
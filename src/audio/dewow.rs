@@ -0,0 +1,217 @@
+//! De-wow: flattens the slow speed drift of tape and disc transfers by
+//! resampling at a varying rate. The speed comes from a pilot tone of
+//! known frequency, or from how far the whole spectrum shifts over time.
+
+use anyhow::{bail, Context, Result};
+use realfft::RealFftPlanner;
+
+use super::biquad::Biquad;
+use super::iir::filtfilt;
+use super::resample::warp;
+use super::stft::{apply_window, sqrt_hann_window};
+use super::wow_flutter;
+
+/// Speed changes faster than this are left alone.
+const BANDWIDTH_HZ: f32 = 10.0;
+/// Least share of the recording's power a pilot tone needs.
+const MIN_PILOT_SHARE: f64 = 0.01;
+
+/// Frames of the spectral tracker.
+const TRACK_WINDOW: usize = 8192;
+const TRACK_HOP: usize = 1024;
+/// Log-frequency grid the spectra are compared on, with steps of about
+/// 0.2%.
+const GRID_LOW_HZ: f64 = 100.0;
+const GRID_HIGH_HZ: f64 = 8000.0;
+const GRID_STEP: f64 = 0.002;
+/// Largest speed deviation the spectral tracker searches.
+const MAX_DEVIATION: f64 = 0.03;
+/// Level below each frame's peak that the spectra are floored at.
+const FLOOR_DB: f64 = -40.0;
+/// Rounds of aligning the frames to their average.
+const ALIGN_PASSES: usize = 2;
+/// Frames this far below the loudest aren't tracked.
+const SILENT_DB: f64 = -60.0;
+
+/// Playback speed through a recording relative to the correct speed;
+/// above 1 the material plays fast and sharp.
+#[derive(Debug, Clone)]
+pub struct SpeedCurve {
+    /// Speed at `start + i / rate` seconds, for each index `i`
+    pub speeds: Vec<f64>,
+    pub rate: f64,
+    pub start: f64,
+}
+
+impl SpeedCurve {
+    /// Speed at `seconds`, interpolated and held at either end.
+    pub fn at(&self, seconds: f64) -> f64 {
+        let position = ((seconds - self.start) * self.rate).max(0.0);
+        let index = position.floor() as usize;
+        match (self.speeds.get(index), self.speeds.get(index + 1)) {
+            (Some(a), Some(b)) => a + (b - a) * position.fract(),
+            (Some(a), None) => *a,
+            _ => self.speeds.last().copied().unwrap_or(1.0),
+        }
+    }
+
+    /// Lowest and highest speed.
+    pub fn range(&self) -> (f64, f64) {
+        self.speeds.iter().fold((f64::MAX, f64::MIN), |(low, high), &speed| (low.min(speed), high.max(speed)))
+    }
+
+    /// Keeps only the speed changes below [`BANDWIDTH_HZ`].
+    fn smoothed(mut self) -> Self {
+        let cutoff = BANDWIDTH_HZ.min(self.rate as f32 / 4.0);
+        if self.speeds.len() > 1 {
+            self.speeds = filtfilt(&[Biquad::lowpass(cutoff, std::f32::consts::FRAC_1_SQRT_2, self.rate as u32)], &self.speeds, 1);
+        }
+        self
+    }
+}
+
+/// Speed from a pilot tone recorded at `pilot_hz`, relative to that
+/// frequency, so the overall speed is corrected as well.
+pub fn from_pilot(mono: &[f32], sample_rate: u32, pilot_hz: f32) -> Result<SpeedCurve> {
+    let track = wow_flutter::track(mono, sample_rate, pilot_hz)?;
+    if track.share < MIN_PILOT_SHARE || track.frequencies.is_empty() {
+        bail!("No {} Hz pilot tone found", pilot_hz);
+    }
+    let speeds = track.frequencies.iter().map(|f| f / pilot_hz as f64).collect();
+    Ok(SpeedCurve { speeds, rate: track.rate, start: 0.5 / track.rate }.smoothed())
+}
+
+/// Speed from how far each frame's log-frequency spectrum is shifted
+/// against the recording's average, relative to the average speed.
+pub fn from_spectrum(mono: &[f32], sample_rate: u32) -> Result<SpeedCurve> {
+    if mono.len() < 2 * TRACK_WINDOW {
+        bail!("Tracking the speed needs at least {} samples, got {}", 2 * TRACK_WINDOW, mono.len());
+    }
+    let df = sample_rate as f64 / TRACK_WINDOW as f64;
+    let high = GRID_HIGH_HZ.min(0.45 * sample_rate as f64);
+    let grid: Vec<f64> = (0..)
+        .map(|j| GRID_LOW_HZ * (j as f64 * GRID_STEP).exp())
+        .take_while(|&hz| hz < high)
+        .map(|hz| hz / df)
+        .collect();
+
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(TRACK_WINDOW);
+    let mut frame = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    let window: Vec<f32> = sqrt_hann_window::<f32>(TRACK_WINDOW).iter().map(|w| w * w).collect();
+
+    // Frame i is centred on sample i * TRACK_HOP
+    let mut spectra = Vec::new();
+    let mut energies = Vec::new();
+    for center in (0..mono.len()).step_by(TRACK_HOP) {
+        apply_window(mono, center as isize - TRACK_WINDOW as isize / 2, &window, &mut frame);
+        fft.process(&mut frame, &mut spectrum).with_context(|| "Failed to transform a tracking frame")?;
+        let magnitudes: Vec<f64> = spectrum.iter().map(|bin| bin.norm() as f64).collect();
+        energies.push(magnitudes.iter().map(|m| m * m).sum::<f64>());
+        // Levels far below the frame's peak are leakage and noise
+        let floor = magnitudes.iter().copied().fold(0.0, f64::max) * 10f64.powf(FLOOR_DB / 20.0) + 1e-12;
+        let mut levels: Vec<f64> = grid
+            .iter()
+            .map(|&bin| {
+                let (index, fraction) = (bin.floor() as usize, bin.fract());
+                let magnitude = magnitudes[index] + (magnitudes[index + 1] - magnitudes[index]) * fraction;
+                magnitude.max(floor).ln()
+            })
+            .collect();
+        let mean = levels.iter().sum::<f64>() / levels.len() as f64;
+        levels.iter_mut().for_each(|level| *level -= mean);
+        spectra.push(levels);
+    }
+
+    let loudest = energies.iter().copied().fold(0.0, f64::max);
+    if loudest == 0.0 {
+        bail!("Can't track the speed of silence");
+    }
+    let threshold = loudest * 10f64.powf(SILENT_DB / 10.0);
+    let tracked: Vec<bool> = energies.iter().map(|&energy| energy > threshold).collect();
+
+    // The average of drifting spectra is smeared towards the extremes of
+    // the drift, so it's rebuilt from the frames aligned by the previous
+    // estimate
+    let mut shifts = vec![Some(0.0); spectra.len()];
+    for _ in 0..ALIGN_PASSES {
+        let reference = average(&spectra, &tracked, &shifts);
+        shifts = spectra
+            .iter()
+            .zip(&tracked)
+            .map(|(levels, &tracked)| if tracked { Some(shift(levels, &reference)) } else { None })
+            .collect();
+    }
+
+    // Untracked frames take the speed of the nearest tracked ones
+    let Some(first) = shifts.iter().flatten().next().copied() else {
+        bail!("Can't track the speed of silence");
+    };
+    let mut last = first;
+    for shift in &mut shifts {
+        last = *shift.get_or_insert(last);
+    }
+    let speeds: Vec<f64> = shifts.into_iter().flatten().map(f64::exp).collect();
+    let mean = speeds.iter().sum::<f64>() / speeds.len() as f64;
+    let speeds = speeds.into_iter().map(|speed| speed / mean).collect();
+    Ok(SpeedCurve { speeds, rate: sample_rate as f64 / TRACK_HOP as f64, start: 0.0 }.smoothed())
+}
+
+/// Average of the tracked `spectra`, each moved back by its shift.
+fn average(spectra: &[Vec<f64>], tracked: &[bool], shifts: &[Option<f64>]) -> Vec<f64> {
+    let len = spectra.first().map_or(0, Vec::len);
+    let mut reference = vec![0.0; len];
+    for ((levels, _), shift) in spectra.iter().zip(tracked).zip(shifts).filter(|((_, &tracked), _)| tracked) {
+        let lag = (shift.unwrap_or(0.0) / GRID_STEP).round() as isize;
+        for (j, sum) in reference.iter_mut().enumerate() {
+            if let Some(level) = levels.get((j as isize + lag) as usize) {
+                *sum += level;
+            }
+        }
+    }
+    reference
+}
+
+/// Shift in natural-log frequency that best lines `levels` up with
+/// `reference`, refined between grid steps with a parabola through the
+/// peak.
+fn shift(levels: &[f64], reference: &[f64]) -> f64 {
+    let max_lag = ((1.0 + MAX_DEVIATION).ln() / GRID_STEP).ceil() as isize;
+    let len = reference.len() as isize;
+    let scores: Vec<f64> = (-max_lag..=max_lag)
+        .map(|lag| ((-lag).max(0)..len - lag.max(0)).map(|j| levels[(j + lag) as usize] * reference[j as usize]).sum())
+        .collect();
+    let best = (0..scores.len()).max_by(|&a, &b| scores[a].total_cmp(&scores[b])).unwrap_or(max_lag as usize);
+    let offset = if best > 0 && best + 1 < scores.len() {
+        let (left, peak, right) = (scores[best - 1], scores[best], scores[best + 1]);
+        let curvature = left - 2.0 * peak + right;
+        if curvature < 0.0 { 0.5 * (left - right) / curvature } else { 0.0 }
+    } else {
+        0.0
+    };
+    (best as f64 - max_lag as f64 + offset) * GRID_STEP
+}
+
+/// Resamples interleaved `samples` so the material plays at constant
+/// speed. The output is shorter where the speed was below 1 on average
+/// and longer where it was above.
+pub fn correct(samples: &[f32], channels: usize, sample_rate: u32, curve: &SpeedCurve) -> Vec<f32> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    // Each input frame covers `speed` frames of the original material
+    let mut positions = Vec::new();
+    let mut original = 0.0;
+    for frame in 0..frames {
+        let speed = curve.at((frame as f64 + 0.5) / sample_rate as f64);
+        let next = original + speed;
+        // Output frames falling within this input frame
+        let mut target = positions.len() as f64;
+        while target < next {
+            positions.push(frame as f64 + (target - original) / speed);
+            target += 1.0;
+        }
+        original = next;
+    }
+    warp(samples, channels, &positions)
+}
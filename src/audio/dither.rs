@@ -0,0 +1,91 @@
+//! Word length reduction for integer outputs, with dither drawn from a
+//! seeded generator so renders are reproducible.
+
+use clap::ValueEnum;
+
+use super::buffer::interleave;
+
+/// Sample format of written files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BitDepth {
+    /// 16-bit integer PCM
+    #[value(name = "16")]
+    Int16,
+    /// 24-bit integer PCM
+    #[value(name = "24")]
+    Int24,
+    /// 32-bit float, written without quantization
+    #[default]
+    #[value(name = "32f")]
+    Float32,
+}
+
+impl BitDepth {
+    /// Integer word length, or `None` for float output.
+    pub fn integer_bits(self) -> Option<u16> {
+        match self {
+            BitDepth::Int16 => Some(16),
+            BitDepth::Int24 => Some(24),
+            BitDepth::Float32 => None,
+        }
+    }
+}
+
+/// Noise added before rounding to an integer word length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Dither {
+    /// Triangular noise of ±1 LSB, which decorrelates the rounding error
+    /// from the signal
+    #[default]
+    Tpdf,
+    /// Plain rounding
+    None,
+}
+
+/// SplitMix64: small, fast and identical on every platform, so a seed
+/// always reproduces the same noise.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in [-0.5, 0.5).
+    pub fn uniform(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+    }
+}
+
+/// Seed for one of several independent streams derived from `seed`, e.g.
+/// one per output file, so files rendered together don't share noise.
+pub fn stream_seed(seed: u64, stream: &str) -> u64 {
+    // FNV-1a
+    stream.bytes().fold(seed ^ 0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Rounds the samples of `channels`, in [-1, 1], to interleaved signed
+/// `bits`-bit integer codes, clipping anything outside the range.
+pub fn quantize(channels: &[&[f32]], bits: u16, dither: Dither, rng: &mut Rng) -> Vec<i32> {
+    let scale = (1i64 << (bits - 1)) as f32;
+    interleave(channels)
+        .map(|x| {
+            let noise = match dither {
+                Dither::Tpdf => rng.uniform() + rng.uniform(),
+                Dither::None => 0.0,
+            };
+            (x * scale + noise).round().clamp(-scale, scale - 1.0) as i32
+        })
+        .collect()
+}
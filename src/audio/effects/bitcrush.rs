@@ -0,0 +1,75 @@
+//! Lo-fi degradation: bit-depth reduction and sample-and-hold decimation.
+
+use anyhow::Result;
+
+use super::{Effect, Params};
+use crate::audio::AudioBuffer;
+use crate::audio::biquad::{filter_interleaved, Biquad};
+use crate::audio::design::{FilterDesign, FilterFamily};
+
+/// Elliptic anti-alias low-pass ahead of the sample-and-hold.
+const ANTI_ALIAS: FilterDesign = FilterDesign {
+    family: FilterFamily::Elliptic,
+    order: 8,
+    passband_ripple_db: 0.1,
+    stopband_attenuation_db: 80.0,
+};
+/// Anti-alias cutoff as a fraction of the reduced Nyquist frequency.
+const ANTI_ALIAS_FRACTION: f32 = 0.9;
+
+/// Quantizes to `bits` bits and holds each sample for `sample_rate / rate`
+/// samples. Unless `antialias=off`, content above the reduced Nyquist
+/// frequency is filtered out first; bypassing the filter lets it fold back
+/// as audible aliasing. `mix` blends with the dry signal.
+pub struct Bitcrusher {
+    levels: f32,
+    step: f64,
+    anti_alias: Option<Vec<Biquad>>,
+    mix: f32,
+}
+
+impl Bitcrusher {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let bits = params.get("bits", 8.0, 1.0..=24.0)?;
+        let rate = params.get("rate", sample_rate as f32, 100.0..=sample_rate as f32)?;
+        let anti_alias = match params.choice("antialias", &["on", "off"])? {
+            "on" if rate < sample_rate as f32 => {
+                Some(ANTI_ALIAS.lowpass(ANTI_ALIAS_FRACTION * rate / 2.0, sample_rate)?)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            levels: 2f32.powf(bits.round() - 1.0),
+            step: rate as f64 / sample_rate as f64,
+            anti_alias,
+            mix: params.get("mix", 1.0, 0.0..=1.0)?,
+        })
+    }
+}
+
+impl Effect for Bitcrusher {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        for channel in buffer.channels_mut() {
+            let mut crushed = channel.to_vec();
+            if let Some(cascade) = &self.anti_alias {
+                filter_interleaved(cascade, &mut crushed, 1);
+            }
+
+            // A new sample is taken whenever the phase wraps
+            let mut phase = 1.0f64;
+            let mut held = 0.0f32;
+            for x in &mut crushed {
+                if phase >= 1.0 {
+                    phase -= 1.0;
+                    held = (*x * self.levels).round().clamp(-self.levels, self.levels - 1.0) / self.levels;
+                }
+                phase += self.step;
+                *x = held;
+            }
+
+            for (sample, wet) in channel.iter_mut().zip(&crushed) {
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix;
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Feedback delay with filtered repeats and tempo sync.
+
+use anyhow::{bail, Result};
+
+use super::{Effect, Params, TempoSync};
+use crate::audio::biquad::{Biquad, BiquadState};
+use crate::audio::design::FilterDesign;
+use crate::audio::AudioBuffer;
+
+/// Echo with `time` ms between repeats, or a note value given by `sync`
+/// at `bpm` (detected from the input when omitted). Each repeat passes
+/// through a `lowcut`/`highcut` band-pass before being fed back at
+/// `feedback`, so later echoes get progressively thinner and darker. `mix`
+/// blends the echoes with the dry signal.
+pub struct Delay {
+    time_ms: f32,
+    sync: TempoSync,
+    feedback: f32,
+    mix: f32,
+    filter: Vec<Biquad>,
+    sample_rate: u32,
+}
+
+impl Delay {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let time_ms = params.get("time", 375.0, 1.0..=4000.0)?;
+        let sync = TempoSync::from_params(params)?;
+
+        let nyquist_limit = sample_rate as f32 * 0.45;
+        let lowcut = params.get("lowcut", 100.0, 20.0..=nyquist_limit)?;
+        let highcut = params.get("highcut", 8000f32.min(nyquist_limit), 200.0..=nyquist_limit)?;
+        if lowcut >= highcut {
+            bail!("lowcut ({} Hz) must be below highcut ({} Hz)", lowcut, highcut);
+        }
+        let design = FilterDesign::default();
+        let mut filter = design.highpass(lowcut, sample_rate)?;
+        filter.extend(design.lowpass(highcut, sample_rate)?);
+
+        Ok(Self {
+            time_ms,
+            sync,
+            feedback: params.get("feedback", 0.4, 0.0..=0.95)?,
+            mix: params.get("mix", 0.3, 0.0..=1.0)?,
+            filter,
+            sample_rate,
+        })
+    }
+
+    /// Delay in samples, detecting the tempo from `buffer` if needed.
+    fn delay_samples(&self, buffer: &AudioBuffer) -> usize {
+        let seconds = self.sync.note_seconds(buffer).unwrap_or(self.time_ms * 0.001);
+        ((seconds * self.sample_rate as f32).round() as usize).max(1)
+    }
+}
+
+impl Effect for Delay {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        let length = self.delay_samples(buffer);
+
+        for channel in buffer.channels_mut() {
+            let mut line = vec![0.0f32; length];
+            let mut states = vec![BiquadState::default(); self.filter.len()];
+            for (i, sample) in channel.iter_mut().enumerate() {
+                let slot = &mut line[i % length];
+                let wet = self
+                    .filter
+                    .iter()
+                    .zip(states.iter_mut())
+                    .fold(*slot as f64, |x, (section, state)| state.process(section, x)) as f32;
+                *slot = *sample + self.feedback * wet;
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix;
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Peaking EQ whose gain follows the level of its own band.
+
+use anyhow::Result;
+
+use super::{time_constant, Effect, Params};
+use crate::audio::biquad::{Biquad, BiquadState};
+use crate::audio::AudioBuffer;
+
+/// Filter coefficients are recomputed once per block of this many frames.
+const BLOCK: usize = 16;
+
+/// A static peaking EQ plus a compressor acting on the same band: while the
+/// band's level exceeds the threshold, the EQ gain drops by the excess
+/// times `1 - 1/ratio`, up to `range` dB.
+pub struct DynamicEq {
+    frequency: f32,
+    q: f32,
+    gain_db: f32,
+    threshold_db: f32,
+    ratio: f32,
+    range_db: f32,
+    attack: f32,
+    release: f32,
+    sample_rate: u32,
+}
+
+impl DynamicEq {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let nyquist = sample_rate as f32 / 2.0;
+        Ok(Self {
+            frequency: params.get("freq", 1000.0, 20.0..=nyquist * 0.95)?,
+            q: params.get("q", 1.0, 0.1..=20.0)?,
+            gain_db: params.get("gain", 0.0, -24.0..=24.0)?,
+            threshold_db: params.get("threshold", -20.0, -90.0..=0.0)?,
+            ratio: params.get("ratio", 2.0, 1.0..=100.0)?,
+            range_db: params.get("range", 12.0, 0.0..=48.0)?,
+            attack: time_constant(params.get("attack", 5.0, 0.0..=1000.0)?, sample_rate),
+            release: time_constant(params.get("release", 100.0, 0.0..=5000.0)?, sample_rate),
+            sample_rate,
+        })
+    }
+
+    /// EQ gain for a band envelope level in dB.
+    fn gain_for(&self, level_db: f32) -> f32 {
+        let excess = (level_db - self.threshold_db).max(0.0);
+        self.gain_db - (excess * (1.0 - 1.0 / self.ratio)).min(self.range_db)
+    }
+}
+
+impl Effect for DynamicEq {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        // Linked peak detection across channels keeps the image stable
+        let detector = Biquad::bandpass(self.frequency, self.q, self.sample_rate);
+        let mut peaks = vec![0.0f32; buffer.frames()];
+        for channel in buffer.channels() {
+            let mut state = BiquadState::default();
+            for (peak, &x) in peaks.iter_mut().zip(channel) {
+                *peak = peak.max(state.process(&detector, x as f64).abs() as f32);
+            }
+        }
+
+        let mut envelope = 0.0f32;
+        let mut sections = Vec::with_capacity(peaks.len().div_ceil(BLOCK));
+        for (index, &peak) in peaks.iter().enumerate() {
+            let coefficient = if peak > envelope { self.attack } else { self.release };
+            envelope = peak + coefficient * (envelope - peak);
+            if index % BLOCK == 0 {
+                let level_db = 20.0 * envelope.max(1e-9).log10();
+                sections.push(Biquad::peaking(self.frequency, self.q, self.gain_for(level_db), self.sample_rate));
+            }
+        }
+
+        for channel in buffer.channels_mut() {
+            let mut state = BiquadState::default();
+            for (index, sample) in channel.iter_mut().enumerate() {
+                *sample = state.process(&sections[index / BLOCK], *sample as f64) as f32;
+            }
+        }
+    }
+}
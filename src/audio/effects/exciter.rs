@@ -0,0 +1,46 @@
+//! Harmonic exciter: saturate the high band and blend the result back in.
+
+use anyhow::Result;
+
+use super::{Effect, Params};
+use crate::audio::biquad::Biquad;
+use crate::audio::design::FilterDesign;
+use crate::audio::iir::filtfilt;
+use crate::audio::AudioBuffer;
+
+/// Adds harmonics above `freq` Hz: the high band is isolated with the
+/// zero-phase crossover filter, driven through a tanh curve, high-passed
+/// again to drop intermodulation products below the band, and mixed into
+/// the dry signal at `mix`.
+pub struct Exciter {
+    drive: f32,
+    mix: f32,
+    highpass: Vec<Biquad>,
+}
+
+impl Exciter {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let frequency = params.get("freq", 3000.0, 500.0..=sample_rate as f32 * 0.45)?;
+        let drive_db = params.get("drive", 12.0, 0.0..=48.0)?;
+        Ok(Self {
+            drive: 10f32.powf(drive_db / 20.0),
+            mix: params.get("mix", 0.25, 0.0..=1.0)?,
+            highpass: FilterDesign::default().highpass(frequency, sample_rate)?,
+        })
+    }
+}
+
+impl Effect for Exciter {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        for channel in buffer.channels_mut() {
+            let high = filtfilt(&self.highpass, channel, 1);
+            // Unity gain for small signals, so drive only sets how hard it clips
+            let saturated: Vec<f32> = high.iter().map(|x| (self.drive * x).tanh() / self.drive).collect();
+            let harmonics = filtfilt(&self.highpass, &saturated, 1);
+
+            for (sample, added) in channel.iter_mut().zip(&harmonics) {
+                *sample += self.mix * added;
+            }
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Resonant filter whose cutoff, Q and gain can follow automation curves,
+//! for filter sweeps.
+
+use anyhow::Result;
+
+use super::{Automation, Effect, Params};
+use crate::audio::biquad::{Biquad, BiquadState};
+use crate::audio::AudioBuffer;
+
+/// Samples between coefficient updates.
+const HOP: usize = 32;
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Peak,
+}
+
+/// Second-order `type` filter at `cutoff` Hz with quality `q`; `gain` (dB)
+/// applies to the peak type only. Automated parameters are re-evaluated
+/// every hop while the filter state carries over, so sweeps don't click.
+pub struct SweepFilter {
+    kind: Kind,
+    cutoff: Automation,
+    q: Automation,
+    gain_db: Automation,
+    sample_rate: u32,
+}
+
+impl SweepFilter {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let kind = match params.choice("type", &["lowpass", "highpass", "bandpass", "peak"])? {
+            "lowpass" => Kind::Lowpass,
+            "highpass" => Kind::Highpass,
+            "bandpass" => Kind::Bandpass,
+            _ => Kind::Peak,
+        };
+        Ok(Self {
+            kind,
+            cutoff: params.automated("cutoff", 1000.0, 10.0..=20000.0)?,
+            q: params.automated("q", std::f32::consts::FRAC_1_SQRT_2, 0.1..=20.0)?,
+            gain_db: params.automated("gain", 0.0, -24.0..=24.0)?,
+            sample_rate,
+        })
+    }
+
+    fn section(&self, seconds: f32) -> Biquad {
+        // Keep the cutoff clear of Nyquist at low sample rates
+        let cutoff = self.cutoff.at(seconds).min(0.45 * self.sample_rate as f32);
+        let q = self.q.at(seconds);
+        match self.kind {
+            Kind::Lowpass => Biquad::lowpass(cutoff, q, self.sample_rate),
+            Kind::Highpass => Biquad::highpass(cutoff, q, self.sample_rate),
+            Kind::Bandpass => Biquad::bandpass(cutoff, q, self.sample_rate),
+            Kind::Peak => Biquad::peaking(cutoff, q, self.gain_db.at(seconds), self.sample_rate),
+        }
+    }
+}
+
+impl Effect for SweepFilter {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        let sections: Vec<Biquad> = (0..buffer.frames().div_ceil(HOP))
+            .map(|hop| self.section((hop * HOP) as f32 / self.sample_rate as f32))
+            .collect();
+        for channel in buffer.channels_mut() {
+            let mut state = BiquadState::default();
+            for (block, section) in channel.chunks_mut(HOP).zip(&sections) {
+                for sample in block {
+                    *sample = state.process(section, *sample as f64) as f32;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Dialogue leveler: slow gain rides towards a target RMS level, followed
+//! by a peak limiter.
+
+use anyhow::Result;
+
+use super::{Effect, Params};
+use crate::audio::{limiter, AudioBuffer};
+
+/// Lookahead and release of the closing peak limiter (ms).
+const LIMITER_LOOKAHEAD_MS: f32 = 5.0;
+const LIMITER_RELEASE_MS: f32 = 100.0;
+
+/// Rides the gain so the RMS level over a sliding `window` (seconds)
+/// stays at `target` dBFS, boosting or cutting by at most `range` dB.
+/// Stretches quieter than `gate` dBFS, such as pauses between sentences,
+/// hold the gain instead of pulling room noise up. Gain changes are
+/// smoothed over half the window, and a limiter then keeps peaks under
+/// `ceiling` dBFS.
+pub struct Leveler {
+    target_db: f32,
+    window: usize,
+    range_db: f32,
+    gate_db: f32,
+    ceiling_db: f32,
+}
+
+impl Leveler {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let window = params.get("window", 3.0, 0.1..=30.0)?;
+        Ok(Self {
+            target_db: params.get("target", -20.0, -60.0..=0.0)?,
+            window: ((window * sample_rate as f32) as usize).max(1),
+            range_db: params.get("range", 12.0, 0.0..=40.0)?,
+            gate_db: params.get("gate", -50.0, -120.0..=0.0)?,
+            ceiling_db: params.get("ceiling", -1.0, -24.0..=0.0)?,
+        })
+    }
+}
+
+impl Effect for Leveler {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        let channels = buffer.num_channels();
+        let frames = buffer.frames();
+
+        // Mean square of each frame's channels, summed for sliding windows
+        let mut power = vec![0.0f64; frames];
+        for channel in buffer.channels() {
+            power.iter_mut().zip(channel).for_each(|(sum, &x)| *sum += x as f64 * x as f64);
+        }
+        let mut prefix = vec![0.0f64; frames + 1];
+        for (i, &power) in power.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + power / channels as f64;
+        }
+
+        // Half a window's one-pole smoothing, in dB so boosts and cuts ride alike
+        let smoothing = (-2.0 / self.window as f32).exp();
+        let half = self.window / 2;
+        let mut held = 0.0f32;
+        let mut gain_db = None;
+        let mut gains = Vec::with_capacity(frames);
+        for i in 0..frames {
+            let (start, end) = (i.saturating_sub(half), (i + half + 1).min(frames));
+            let power = (prefix[end] - prefix[start]) / (end - start) as f64;
+            let level_db = 10.0 * (power.max(1e-12) as f32).log10();
+            if level_db >= self.gate_db {
+                held = (self.target_db - level_db).clamp(-self.range_db, self.range_db);
+            }
+            let smoothed = gain_db.map_or(held, |previous: f32| held + smoothing * (previous - held));
+            gain_db = Some(smoothed);
+            gains.push(10f32.powf(smoothed / 20.0));
+        }
+        for channel in buffer.channels_mut() {
+            channel.iter_mut().zip(&gains).for_each(|(sample, gain)| *sample *= gain);
+        }
+
+        limiter::limit(buffer, self.ceiling_db, LIMITER_LOOKAHEAD_MS, LIMITER_RELEASE_MS);
+    }
+
+    fn sets_level(&self) -> bool {
+        true
+    }
+}
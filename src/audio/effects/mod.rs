@@ -0,0 +1,321 @@
+//! Effect stages that run over whole planar buffers, configured from
+//! compact `name:key=value,...` specs on the command line.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use super::analysis::estimate_tempo;
+use super::AudioBuffer;
+
+pub mod bitcrush;
+pub mod delay;
+pub mod dynamic_eq;
+pub mod exciter;
+pub mod filter;
+pub mod leveler;
+pub mod modulation;
+pub mod reverb;
+#[cfg(feature = "rnnoise")]
+pub mod rnnoise;
+pub mod saturation;
+pub mod transient;
+pub mod tremolo;
+pub mod widener;
+
+/// Sample rate stages are test-built at when parsed.
+const VALIDATION_SAMPLE_RATE: u32 = 192_000;
+
+/// A processing stage applied in place to a buffer, at the sample rate it
+/// was built for.
+pub trait Effect {
+    fn process(&mut self, buffer: &mut AudioBuffer);
+
+    /// Whether the stage brings audio to an absolute level, which
+    /// auto-gain then leaves alone.
+    fn sets_level(&self) -> bool {
+        false
+    }
+}
+
+/// Parsed `name:key=value,...` stage description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageSpec {
+    pub name: String,
+    params: BTreeMap<String, String>,
+}
+
+impl FromStr for StageSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let (name, params) = spec.split_once(':').unwrap_or((spec, ""));
+        let params = params
+            .split(',')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                let (key, value) = param
+                    .split_once('=')
+                    .with_context(|| format!("Expected key=value in stage parameters, got '{}'", param))?;
+                Ok((key.to_string(), value.to_string()))
+            })
+            .collect::<Result<_>>()?;
+
+        // Catch unknown names and parameters while parsing arguments; the
+        // real sample rate is only known once the input is loaded
+        let stage = Self { name: name.to_string(), params };
+        stage
+            .build(VALIDATION_SAMPLE_RATE)
+            .map_err(|e| anyhow!("Invalid effect stage '{}': {:#}", spec, e))?;
+        Ok(stage)
+    }
+}
+
+impl StageSpec {
+    /// Instantiates the effect for audio at `sample_rate`.
+    pub fn build(&self, sample_rate: u32) -> Result<Box<dyn Effect>> {
+        let mut params = Params { values: self.params.clone() };
+        let effect: Box<dyn Effect> = match self.name.as_str() {
+            "auto-pan" => Box::new(tremolo::AutoPan::from_params(&mut params, sample_rate)?),
+            "bitcrush" => Box::new(bitcrush::Bitcrusher::from_params(&mut params, sample_rate)?),
+            "chorus" => Box::new(modulation::ModulatedDelay::chorus(&mut params, sample_rate)?),
+            #[cfg(feature = "rnnoise")]
+            "denoise" => Box::new(rnnoise::Denoise::from_params(&mut params, sample_rate)?),
+            "delay" => Box::new(delay::Delay::from_params(&mut params, sample_rate)?),
+            "dynamic-eq" => Box::new(dynamic_eq::DynamicEq::from_params(&mut params, sample_rate)?),
+            "exciter" => Box::new(exciter::Exciter::from_params(&mut params, sample_rate)?),
+            "filter" => Box::new(filter::SweepFilter::from_params(&mut params, sample_rate)?),
+            "flanger" => Box::new(modulation::ModulatedDelay::flanger(&mut params, sample_rate)?),
+            "level" => Box::new(leveler::Leveler::from_params(&mut params, sample_rate)?),
+            "phaser" => Box::new(modulation::Phaser::from_params(&mut params, sample_rate)?),
+            "reverb" => Box::new(reverb::Reverb::from_params(&mut params, sample_rate)?),
+            "saturate" => Box::new(saturation::Saturation::from_params(&mut params, sample_rate)?),
+            "transient" => Box::new(transient::TransientShaper::from_params(&mut params, sample_rate)?),
+            "tremolo" => Box::new(tremolo::Tremolo::from_params(&mut params, sample_rate)?),
+            "widen" => Box::new(widener::Widener::from_params(&mut params, sample_rate)?),
+            other => bail!("Unknown effect '{}'", other),
+        };
+        params.finish()?;
+        Ok(effect)
+    }
+}
+
+/// Named chains of stages, as the specs they expand to.
+const PRESETS: &[(&str, &[&str])] = &[(
+    "telephone",
+    &[
+        "dynamic-eq:freq=1200,q=0.5,threshold=-30,ratio=4,range=9",
+        "saturate:drive=9,mix=0.5",
+        // The 300 Hz - 3.4 kHz voice band at 24 dB per octave, after the
+        // distortion so its harmonics are band-limited too
+        "filter:type=highpass,cutoff=300",
+        "filter:type=highpass,cutoff=300",
+        "filter:type=lowpass,cutoff=3400",
+        "filter:type=lowpass,cutoff=3400",
+    ],
+)];
+
+/// Names of the built-in presets.
+pub fn preset_names() -> impl Iterator<Item = &'static str> {
+    PRESETS.iter().map(|&(name, _)| name)
+}
+
+/// Stages of the preset called `name`.
+pub fn preset(name: &str) -> Result<Vec<StageSpec>> {
+    let Some((_, specs)) = PRESETS.iter().find(|&&(preset, _)| preset == name) else {
+        bail!("Unknown preset '{}'; expected one of {}", name, preset_names().collect::<Vec<_>>().join(", "));
+    };
+    specs.iter().map(|spec| spec.parse()).collect()
+}
+
+/// Stage parameters, consumed as the effect reads them so leftovers can be
+/// reported as typos.
+pub struct Params {
+    values: BTreeMap<String, String>,
+}
+
+impl Params {
+    /// Takes `key`, falling back to `default`, and checks it lies in `range`.
+    pub fn get(&mut self, key: &str, default: f32, range: std::ops::RangeInclusive<f32>) -> Result<f32> {
+        if self.values.get(key).is_some_and(|value| value.contains(':')) {
+            bail!("'{}' cannot be automated", key);
+        }
+        let value = match self.values.remove(key) {
+            Some(value) => value
+                .parse()
+                .with_context(|| format!("Invalid value for '{}': '{}'", key, value))?,
+            None => default,
+        };
+        if !range.contains(&value) {
+            bail!("{} must be between {} and {}, got {}", key, range.start(), range.end(), value);
+        }
+        Ok(value)
+    }
+
+    /// Takes `key` as a constant or an automation curve written as
+    /// `seconds:value` breakpoints separated by `;`, checking every value
+    /// lies in `range`.
+    pub fn automated(&mut self, key: &str, default: f32, range: std::ops::RangeInclusive<f32>) -> Result<Automation> {
+        let Some(text) = self.values.get(key).filter(|value| value.contains(':')).cloned() else {
+            return Ok(Automation::Constant(self.get(key, default, range)?));
+        };
+        self.values.remove(key);
+
+        let mut points: Vec<(f32, f32)> = Vec::new();
+        for point in text.split(';').filter(|point| !point.is_empty()) {
+            let (time, value) = point
+                .split_once(':')
+                .with_context(|| format!("Expected seconds:value in automation for '{}', got '{}'", key, point))?;
+            let time: f32 = time
+                .trim_end_matches('s')
+                .parse()
+                .with_context(|| format!("Invalid automation time for '{}': '{}'", key, time))?;
+            let value: f32 = value
+                .parse()
+                .with_context(|| format!("Invalid automation value for '{}': '{}'", key, value))?;
+            if !range.contains(&value) {
+                bail!("{} must be between {} and {}, got {}", key, range.start(), range.end(), value);
+            }
+            if !time.is_finite() || time < 0.0 || points.last().is_some_and(|&(last, _)| time < last) {
+                bail!("Automation times for '{}' must be non-negative and ascending", key);
+            }
+            points.push((time, value));
+        }
+        if points.is_empty() {
+            bail!("Automation for '{}' has no breakpoints", key);
+        }
+        Ok(Automation::Curve(points))
+    }
+
+    /// Takes `key` as one of `choices`, falling back to the first.
+    pub fn choice<'a>(&mut self, key: &str, choices: &[&'a str]) -> Result<&'a str> {
+        match self.values.remove(key) {
+            None => Ok(choices[0]),
+            Some(value) => choices
+                .iter()
+                .find(|&&choice| choice == value)
+                .copied()
+                .with_context(|| format!("{} must be one of {}, got '{}'", key, choices.join(", "), value)),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(key) = self.values.keys().next() {
+            bail!("Unknown parameter '{}'", key);
+        }
+        Ok(())
+    }
+}
+
+/// A parameter that may change over the course of a render.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Automation {
+    Constant(f32),
+    /// `(seconds, value)` breakpoints in time order, interpolated linearly
+    /// and held before the first and after the last
+    Curve(Vec<(f32, f32)>),
+}
+
+impl Automation {
+    pub fn at(&self, seconds: f32) -> f32 {
+        let points = match self {
+            Automation::Constant(value) => return *value,
+            Automation::Curve(points) => points,
+        };
+        let next = points.partition_point(|&(time, _)| time <= seconds);
+        match (next.checked_sub(1).map(|i| points[i]), points.get(next)) {
+            (Some((t0, v0)), Some(&(t1, v1))) => v0 + (v1 - v0) * (seconds - t0) / (t1 - t0),
+            (Some((_, value)), None) | (None, Some(&(_, value))) => value,
+            (None, None) => unreachable!("curves have at least one breakpoint"),
+        }
+    }
+}
+
+/// One-pole smoothing coefficient reaching ~63% of a step in `ms`.
+pub fn time_constant(ms: f32, sample_rate: u32) -> f32 {
+    if ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (ms * 0.001 * sample_rate as f32)).exp()
+    }
+}
+
+/// Note values accepted by `sync`, with their length in beats.
+const NOTE_VALUES: [(&str, f32); 10] = [
+    ("off", 0.0),
+    ("1/1", 4.0),
+    ("1/2", 2.0),
+    ("1/4", 1.0),
+    ("1/8", 0.5),
+    ("1/16", 0.25),
+    ("1/4d", 1.5),
+    ("1/8d", 0.75),
+    ("1/4t", 2.0 / 3.0),
+    ("1/8t", 1.0 / 3.0),
+];
+
+/// Optional tempo sync from the `sync` note value and `bpm` parameters.
+/// Without `bpm` the tempo is detected from the audio being processed.
+#[derive(Debug, Clone, Copy)]
+pub struct TempoSync {
+    beats: Option<f32>,
+    bpm: Option<f32>,
+}
+
+impl TempoSync {
+    pub fn from_params(params: &mut Params) -> Result<Self> {
+        let sync = params.choice("sync", &NOTE_VALUES.map(|(name, _)| name))?;
+        let beats = NOTE_VALUES.iter().find(|(name, _)| *name == sync).map(|&(_, beats)| beats).filter(|&b| b > 0.0);
+        let bpm = params.get("bpm", 0.0, 0.0..=300.0)?;
+        if bpm > 0.0 && beats.is_none() {
+            bail!("bpm requires a sync note value");
+        }
+        Ok(Self { beats, bpm: (bpm > 0.0).then_some(bpm) })
+    }
+
+    /// Length of the synced note in seconds, or `None` when sync is off or
+    /// no tempo could be detected in `buffer`.
+    pub fn note_seconds(&self, buffer: &AudioBuffer) -> Option<f32> {
+        let beats = self.beats?;
+        let bpm = self.bpm.or_else(|| match estimate_tempo(&buffer.mixdown(), buffer.sample_rate()) {
+            Ok(Some(bpm)) => Some(bpm),
+            Ok(None) => {
+                warn!("Could not detect a tempo; ignoring sync");
+                None
+            }
+            Err(e) => {
+                warn!("Tempo detection failed ({}); ignoring sync", e);
+                None
+            }
+        })?;
+        info!("Syncing to {:.1} BPM", bpm);
+        Some(beats * 60.0 / bpm)
+    }
+}
+
+/// RMS level over all of `buffer`'s channels, or `None` for silence.
+fn rms(buffer: &AudioBuffer) -> Option<f64> {
+    let sum: f64 = buffer.channels().flat_map(|channel| channel.iter().map(|&x| x as f64 * x as f64)).sum();
+    let power = sum / (buffer.frames() * buffer.num_channels()).max(1) as f64;
+    (power > 1e-20).then(|| power.sqrt())
+}
+
+/// Runs each stage over `buffer` in order. With `autogain`, each stage's
+/// output is scaled back to the RMS level of its input so a chain doesn't
+/// build up level changes, except after stages that set the level.
+pub fn apply_chain(stages: &[StageSpec], buffer: &mut AudioBuffer, autogain: bool) -> Result<()> {
+    for stage in stages {
+        info!("Applying {} stage", stage.name);
+        let before = rms(buffer);
+        let mut effect = stage.build(buffer.sample_rate())?;
+        effect.process(buffer);
+
+        if let (true, false, Some(before), Some(after)) = (autogain, effect.sets_level(), before, rms(buffer)) {
+            let gain = before / after;
+            info!("Auto-gain after {} stage: {:+.1} dB", stage.name, 20.0 * gain.log10());
+            buffer.channels_mut().flatten().for_each(|x| *x = (*x as f64 * gain) as f32);
+        }
+    }
+    Ok(())
+}
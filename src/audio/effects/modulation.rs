@@ -0,0 +1,196 @@
+//! LFO-driven modulation effects: chorus and flanger on a modulated delay
+//! line, and a phaser built from a swept allpass chain.
+
+use anyhow::{bail, Result};
+use std::f32::consts::PI;
+
+use super::{Effect, Params};
+use crate::audio::AudioBuffer;
+
+/// Phase offset between successive channels, in cycles, to widen stereo.
+const CHANNEL_PHASE_OFFSET: f32 = 0.25;
+
+/// LFO waveform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Sine,
+    Triangle,
+    Square,
+}
+
+impl Shape {
+    /// Reads the `shape` parameter, defaulting to sine.
+    pub fn from_params(params: &mut Params) -> Result<Self> {
+        Ok(match params.choice("shape", &["sine", "triangle", "square"])? {
+            "triangle" => Shape::Triangle,
+            "square" => Shape::Square,
+            _ => Shape::Sine,
+        })
+    }
+}
+
+/// Oscillator returning values in [0, 1], starting from 0.
+#[derive(Debug, Clone, Copy)]
+pub struct Lfo {
+    shape: Shape,
+    phase: f32,
+    increment: f32,
+}
+
+impl Lfo {
+    pub fn new(rate: f32, phase: f32, sample_rate: u32) -> Self {
+        Self { shape: Shape::Sine, phase: phase.fract(), increment: rate / sample_rate as f32 }
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn tick(&mut self) -> f32 {
+        let value = match self.shape {
+            Shape::Sine => 0.5 - 0.5 * (2.0 * PI * self.phase).cos(),
+            Shape::Triangle => 1.0 - (1.0 - 2.0 * self.phase).abs(),
+            Shape::Square => if self.phase < 0.5 { 0.0 } else { 1.0 },
+        };
+        self.phase = (self.phase + self.increment).fract();
+        value
+    }
+}
+
+/// Delay line read at fractional positions with linear interpolation.
+struct DelayLine {
+    buffer: Vec<f32>,
+    write: usize,
+}
+
+impl DelayLine {
+    fn new(max_delay: usize) -> Self {
+        Self { buffer: vec![0.0; max_delay + 2], write: 0 }
+    }
+
+    /// Sample written `delay` samples ago.
+    fn read(&self, delay: f32) -> f32 {
+        let len = self.buffer.len();
+        let position = self.write as f32 + len as f32 - delay;
+        let index = position.floor() as usize;
+        let fraction = position - position.floor();
+        let a = self.buffer[index % len];
+        let b = self.buffer[(index + 1) % len];
+        a + fraction * (b - a)
+    }
+
+    fn push(&mut self, sample: f32) {
+        self.buffer[self.write] = sample;
+        self.write = (self.write + 1) % self.buffer.len();
+    }
+}
+
+/// Chorus or flanger: the signal is delayed by `delay` ms plus up to
+/// `depth` ms swept at `rate` Hz, fed back at `feedback` and blended with
+/// the dry signal at `mix`. The chorus uses longer delays and no feedback;
+/// the flanger short ones with feedback, giving a swept comb.
+pub struct ModulatedDelay {
+    rate: f32,
+    delay: f32,
+    depth: f32,
+    feedback: f32,
+    mix: f32,
+    sample_rate: u32,
+}
+
+impl ModulatedDelay {
+    pub fn chorus(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        Self::from_params(params, sample_rate, [0.8, 15.0, 3.0, 0.0])
+    }
+
+    pub fn flanger(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        Self::from_params(params, sample_rate, [0.25, 1.0, 2.0, 0.5])
+    }
+
+    fn from_params(params: &mut Params, sample_rate: u32, [rate, delay, depth, feedback]: [f32; 4]) -> Result<Self> {
+        let ms = sample_rate as f32 * 0.001;
+        Ok(Self {
+            rate: params.get("rate", rate, 0.01..=10.0)?,
+            delay: params.get("delay", delay, 0.1..=50.0)? * ms,
+            depth: params.get("depth", depth, 0.0..=20.0)? * ms,
+            feedback: params.get("feedback", feedback, -0.95..=0.95)?,
+            mix: params.get("mix", 0.5, 0.0..=1.0)?,
+            sample_rate,
+        })
+    }
+}
+
+impl Effect for ModulatedDelay {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        for (index, channel) in buffer.channels_mut().enumerate() {
+            let mut lfo = Lfo::new(self.rate, index as f32 * CHANNEL_PHASE_OFFSET, self.sample_rate);
+            let mut line = DelayLine::new((self.delay + self.depth).ceil() as usize);
+            for sample in channel {
+                let wet = line.read(self.delay + self.depth * lfo.tick());
+                line.push(*sample + self.feedback * wet);
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix;
+            }
+        }
+    }
+}
+
+/// Phaser: `stages` first-order allpasses whose corner sweeps
+/// logarithmically between `min` and `max` Hz at `rate` Hz, with the chain
+/// output fed back at `feedback`. Mixed with the dry signal at `mix`, the
+/// phase shift produces moving notches.
+pub struct Phaser {
+    stages: usize,
+    rate: f32,
+    min: f32,
+    max: f32,
+    feedback: f32,
+    mix: f32,
+    sample_rate: u32,
+}
+
+impl Phaser {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let stages = params.choice("stages", &["4", "2", "6", "8", "12"])?.parse().expect("choices are integers");
+        let nyquist_limit = sample_rate as f32 * 0.45;
+        let min = params.get("min", 200.0, 20.0..=nyquist_limit)?;
+        let max = params.get("max", 2000f32.min(nyquist_limit), 20.0..=nyquist_limit)?;
+        if min > max {
+            bail!("min ({} Hz) must not exceed max ({} Hz)", min, max);
+        }
+        Ok(Self {
+            stages,
+            rate: params.get("rate", 0.5, 0.01..=10.0)?,
+            min,
+            max,
+            feedback: params.get("feedback", 0.0, 0.0..=0.9)?,
+            mix: params.get("mix", 0.5, 0.0..=1.0)?,
+            sample_rate,
+        })
+    }
+}
+
+impl Effect for Phaser {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        let span = (self.max / self.min).ln();
+        for (index, channel) in buffer.channels_mut().enumerate() {
+            let mut lfo = Lfo::new(self.rate, index as f32 * CHANNEL_PHASE_OFFSET, self.sample_rate);
+            let mut states = vec![0.0f32; self.stages];
+            let mut last = 0.0f32;
+            for sample in channel {
+                let corner = self.min * (span * lfo.tick()).exp();
+                let t = (PI * corner / self.sample_rate as f32).tan();
+                let coefficient = (t - 1.0) / (t + 1.0);
+
+                let mut x = *sample + self.feedback * last;
+                for state in states.iter_mut() {
+                    let y = coefficient * x + *state;
+                    *state = x - coefficient * y;
+                    x = y;
+                }
+                last = x;
+                *sample = *sample * (1.0 - self.mix) + x * self.mix;
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Freeverb-style algorithmic reverb.
+
+use anyhow::Result;
+
+use super::{Effect, Params};
+use crate::audio::AudioBuffer;
+
+/// Comb delays in samples at 44.1 kHz (Jezar's Freeverb tunings).
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// Allpass delays in samples at 44.1 kHz.
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+/// Extra delay for odd channels so they decorrelate from even ones.
+const STEREO_SPREAD: usize = 23;
+/// Gain into the tank, keeping the eight summed combs near unity.
+const INPUT_GAIN: f32 = 0.015;
+/// Wet gain compensating for `INPUT_GAIN`.
+const WET_GAIN: f32 = 3.0;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// Feedback comb with a one-pole lowpass in the loop.
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    store: f32,
+}
+
+impl Comb {
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.store = output * (1.0 - damping) + self.store * damping;
+        self.buffer[self.index] = input + self.store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+/// Schroeder allpass diffuser.
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+/// One channel's tank: parallel combs into series allpasses.
+struct Tank {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl Tank {
+    fn new(spread: usize, sample_rate: u32) -> Self {
+        let scale = |samples: usize| ((samples + spread) as f64 * sample_rate as f64 / 44100.0).round().max(1.0) as usize;
+        Self {
+            combs: COMB_TUNINGS
+                .iter()
+                .map(|&n| Comb { buffer: vec![0.0; scale(n)], index: 0, store: 0.0 })
+                .collect(),
+            allpasses: ALLPASS_TUNINGS
+                .iter()
+                .map(|&n| Allpass { buffer: vec![0.0; scale(n)], index: 0 })
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let combed = self.combs.iter_mut().map(|comb| comb.process(input, feedback, damping)).sum();
+        self.allpasses.iter_mut().fold(combed, |x, allpass| allpass.process(x))
+    }
+}
+
+/// Reverb with `room` size and high-frequency `damping` (both 0–1), a
+/// `predelay` in ms before the tank, and a wet/dry `mix`. The tail is cut
+/// at the end of the input; the output keeps the input's length.
+pub struct Reverb {
+    feedback: f32,
+    damping: f32,
+    mix: f32,
+    predelay: usize,
+    sample_rate: u32,
+}
+
+impl Reverb {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let room = params.get("room", 0.5, 0.0..=1.0)?;
+        let damping = params.get("damping", 0.5, 0.0..=1.0)?;
+        let mix = params.get("mix", 0.3, 0.0..=1.0)?;
+        let predelay_ms = params.get("predelay", 0.0, 0.0..=500.0)?;
+        Ok(Self {
+            feedback: 0.7 + 0.28 * room,
+            damping: 0.4 * damping,
+            mix,
+            predelay: (predelay_ms * 0.001 * sample_rate as f32).round() as usize,
+            sample_rate,
+        })
+    }
+}
+
+impl Effect for Reverb {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        // All channels share the mono sum as tank input, delayed by the predelay
+        let mut mono = vec![0.0f32; buffer.frames()];
+        for channel in buffer.channels() {
+            mono.iter_mut().zip(channel).for_each(|(sum, &x)| *sum += x);
+        }
+        mono.iter_mut().for_each(|x| *x *= INPUT_GAIN);
+
+        for (index, channel) in buffer.channels_mut().enumerate() {
+            let mut tank = Tank::new(index % 2 * STEREO_SPREAD, self.sample_rate);
+            for (i, sample) in channel.iter_mut().enumerate() {
+                let input = if i >= self.predelay { mono[i - self.predelay] } else { 0.0 };
+                let wet = tank.process(input, self.feedback, self.damping);
+                *sample = *sample * (1.0 - self.mix) + wet * WET_GAIN * self.mix;
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Speech denoising with RNNoise, the recurrent network from Xiph.org.
+//! Linked against the system `librnnoise` when built with the `rnnoise`
+//! feature.
+
+use anyhow::Result;
+use std::os::raw::c_void;
+
+use super::{Effect, Params};
+use crate::audio::resample::resample;
+use crate::audio::AudioBuffer;
+
+/// Rate RNNoise's model was trained at; other rates are resampled to it.
+const MODEL_RATE: u32 = 48_000;
+/// Samples per RNNoise frame at [`MODEL_RATE`], also the delay it adds.
+const FRAME_SIZE: usize = 480;
+/// RNNoise expects samples on a 16-bit integer scale.
+const SCALE: f32 = 32768.0;
+
+#[link(name = "rnnoise")]
+extern "C" {
+    fn rnnoise_create(model: *mut c_void) -> *mut c_void;
+    fn rnnoise_destroy(state: *mut c_void);
+    fn rnnoise_process_frame(state: *mut c_void, out: *mut f32, input: *const f32) -> f32;
+}
+
+/// One channel's network state.
+struct State(*mut c_void);
+
+impl State {
+    fn new() -> Self {
+        // SAFETY: a null model selects the built-in weights
+        Self(unsafe { rnnoise_create(std::ptr::null_mut()) })
+    }
+
+    /// Denoises `signal` in place, compensating the network's one-frame
+    /// delay.
+    fn process(&mut self, signal: &mut [f32]) {
+        let mut input: Vec<f32> = signal.iter().map(|x| x * SCALE).collect();
+        input.resize(signal.len().div_ceil(FRAME_SIZE) * FRAME_SIZE + FRAME_SIZE, 0.0);
+        let mut output = vec![0.0f32; input.len()];
+        for (input, output) in input.chunks_exact(FRAME_SIZE).zip(output.chunks_exact_mut(FRAME_SIZE)) {
+            // SAFETY: both buffers hold exactly one frame
+            unsafe { rnnoise_process_frame(self.0, output.as_mut_ptr(), input.as_ptr()) };
+        }
+        for (sample, denoised) in signal.iter_mut().zip(&output[FRAME_SIZE..]) {
+            *sample = denoised / SCALE;
+        }
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        // SAFETY: created by rnnoise_create and not used after this
+        unsafe { rnnoise_destroy(self.0) }
+    }
+}
+
+/// Removes noise from speech with RNNoise, each channel on its own.
+/// Audio not at 48 kHz is resampled for the network and back. `mix`
+/// blends with the dry signal.
+pub struct Denoise {
+    sample_rate: u32,
+    mix: f32,
+}
+
+impl Denoise {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        Ok(Self { sample_rate, mix: params.get("mix", 1.0, 0.0..=1.0)? })
+    }
+}
+
+impl Effect for Denoise {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        for channel in buffer.channels_mut() {
+            let mut wet = resample(channel, 1, self.sample_rate, MODEL_RATE);
+            State::new().process(&mut wet);
+            let wet = resample(&wet, 1, MODEL_RATE, self.sample_rate);
+            for (sample, wet) in channel.iter_mut().zip(&wet) {
+                *sample += self.mix * (wet - *sample);
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Attack/sustain transient shaper driven by envelope differences.
+
+use anyhow::Result;
+
+use super::{time_constant, Effect, Params};
+use crate::audio::AudioBuffer;
+
+/// Peak envelope follower with separate attack and release smoothing.
+#[derive(Debug, Clone, Copy)]
+struct Envelope {
+    attack: f32,
+    release: f32,
+    value: f32,
+}
+
+impl Envelope {
+    fn new(attack_ms: f32, release_ms: f32, sample_rate: u32) -> Self {
+        Self {
+            attack: time_constant(attack_ms, sample_rate),
+            release: time_constant(release_ms, sample_rate),
+            value: 0.0,
+        }
+    }
+
+    fn follow(&mut self, input: f32) -> f32 {
+        let coefficient = if input > self.value { self.attack } else { self.release };
+        self.value = input + coefficient * (self.value - input);
+        self.value
+    }
+}
+
+/// Boosts or cuts onsets and tails independently. Onsets are where a fast
+/// attack envelope runs ahead of a slow one; tails are where a slow release
+/// envelope lingers above a fast one. The gain scales with how far apart
+/// each pair is, up to the `attack` and `sustain` settings in dB. `speed`
+/// (ms) sets how long a hit counts as an onset.
+pub struct TransientShaper {
+    attack_db: f32,
+    sustain_db: f32,
+    fast_attack: Envelope,
+    slow_attack: Envelope,
+    fast_release: Envelope,
+    slow_release: Envelope,
+}
+
+impl TransientShaper {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let attack_db = params.get("attack", 0.0, -24.0..=24.0)?;
+        let sustain_db = params.get("sustain", 0.0, -24.0..=24.0)?;
+        let speed = params.get("speed", 20.0, 1.0..=200.0)?;
+        Ok(Self {
+            attack_db,
+            sustain_db,
+            fast_attack: Envelope::new(0.5, speed, sample_rate),
+            slow_attack: Envelope::new(speed, speed, sample_rate),
+            fast_release: Envelope::new(0.5, speed, sample_rate),
+            slow_release: Envelope::new(0.5, speed * 5.0, sample_rate),
+        })
+    }
+}
+
+impl Effect for TransientShaper {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        let mut peaks = vec![0.0f32; buffer.frames()];
+        for channel in buffer.channels() {
+            peaks.iter_mut().zip(channel).for_each(|(peak, x)| *peak = peak.max(x.abs()));
+        }
+
+        let mut gains = Vec::with_capacity(peaks.len());
+        for peak in peaks {
+            let fast = self.fast_attack.follow(peak);
+            let slow = self.slow_attack.follow(peak);
+            let transient = if fast > 1e-9 { (1.0 - slow / fast).max(0.0) } else { 0.0 };
+
+            let short = self.fast_release.follow(peak);
+            let long = self.slow_release.follow(peak);
+            let sustain = if long > 1e-9 { (1.0 - short / long).max(0.0) } else { 0.0 };
+
+            gains.push(10f32.powf((self.attack_db * transient + self.sustain_db * sustain) / 20.0));
+        }
+        for channel in buffer.channels_mut() {
+            channel.iter_mut().zip(&gains).for_each(|(sample, gain)| *sample *= gain);
+        }
+    }
+}
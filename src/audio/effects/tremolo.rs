@@ -0,0 +1,91 @@
+//! Amplitude modulation: tremolo and stereo auto-pan.
+
+use anyhow::Result;
+use std::f32::consts::FRAC_PI_2;
+use tracing::warn;
+
+use super::modulation::{Lfo, Shape};
+use super::{Effect, Params, TempoSync};
+use crate::audio::AudioBuffer;
+
+/// LFO settings shared by both stages: `rate` Hz or a `sync` note value
+/// per cycle, `depth` (0–1) and `shape`.
+struct Modulation {
+    rate: f32,
+    sync: TempoSync,
+    depth: f32,
+    shape: Shape,
+    sample_rate: u32,
+}
+
+impl Modulation {
+    fn from_params(params: &mut Params, sample_rate: u32, depth: f32) -> Result<Self> {
+        Ok(Self {
+            rate: params.get("rate", 4.0, 0.05..=20.0)?,
+            sync: TempoSync::from_params(params)?,
+            depth: params.get("depth", depth, 0.0..=1.0)?,
+            shape: Shape::from_params(params)?,
+            sample_rate,
+        })
+    }
+
+    fn lfo(&self, buffer: &AudioBuffer) -> Lfo {
+        let rate = self.sync.note_seconds(buffer).map_or(self.rate, |seconds| 1.0 / seconds);
+        Lfo::new(rate, 0.0, self.sample_rate).with_shape(self.shape)
+    }
+}
+
+/// Tremolo: gain dips by up to `depth` once per LFO cycle, on all
+/// channels together.
+pub struct Tremolo {
+    modulation: Modulation,
+}
+
+impl Tremolo {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        Ok(Self { modulation: Modulation::from_params(params, sample_rate, 0.5)? })
+    }
+}
+
+impl Effect for Tremolo {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        // Every channel runs a copy of the same LFO, so they move together
+        let lfo = self.modulation.lfo(buffer);
+        for channel in buffer.channels_mut() {
+            let mut lfo = lfo;
+            channel.iter_mut().for_each(|sample| *sample *= 1.0 - self.modulation.depth * lfo.tick());
+        }
+    }
+}
+
+/// Auto-pan: moves stereo audio between the speakers with an equal-power
+/// law, swinging `depth` of the way to each side. Other channel layouts
+/// pass through unchanged.
+pub struct AutoPan {
+    modulation: Modulation,
+}
+
+impl AutoPan {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        Ok(Self { modulation: Modulation::from_params(params, sample_rate, 1.0)? })
+    }
+}
+
+impl Effect for AutoPan {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if buffer.num_channels() != 2 {
+            warn!("Auto-pan needs stereo input; leaving {} channel audio unchanged", buffer.num_channels());
+            return;
+        }
+        let lfo = self.modulation.lfo(buffer);
+        for (index, channel) in buffer.channels_mut().enumerate() {
+            let mut lfo = lfo;
+            for sample in channel {
+                // Pan position from 0 (left) to 1 (right), centred on 0.5
+                let position = 0.5 + self.modulation.depth * (lfo.tick() - 0.5);
+                let angle = position * FRAC_PI_2;
+                *sample *= std::f32::consts::SQRT_2 * if index == 0 { angle.cos() } else { angle.sin() };
+            }
+        }
+    }
+}
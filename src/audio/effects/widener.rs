@@ -0,0 +1,74 @@
+//! Stereo widening by mid/side gain or Haas delay.
+
+use anyhow::Result;
+use tracing::{info, warn};
+
+use super::{Effect, Params};
+use crate::audio::analysis::stereo_correlation;
+use crate::audio::AudioBuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Scale the side signal by `width`
+    MidSide,
+    /// Delay the right channel by `delay` ms
+    Haas,
+}
+
+/// Widens stereo audio, either by scaling the side signal by `width`
+/// (`mode=ms`, 0 folds to mono) or by delaying the right channel `delay` ms
+/// (`mode=haas`). Logs the channel correlation before and after, warning
+/// when the result would lose level or cancel in mono. Other channel
+/// layouts pass through unchanged.
+pub struct Widener {
+    mode: Mode,
+    width: f32,
+    delay: usize,
+}
+
+impl Widener {
+    pub fn from_params(params: &mut Params, sample_rate: u32) -> Result<Self> {
+        let mode = match params.choice("mode", &["ms", "haas"])? {
+            "haas" => Mode::Haas,
+            _ => Mode::MidSide,
+        };
+        let width = params.get("width", 1.5, 0.0..=4.0)?;
+        let delay_ms = params.get("delay", 10.0, 0.0..=40.0)?;
+        Ok(Self { mode, width, delay: (delay_ms * 0.001 * sample_rate as f32).round() as usize })
+    }
+}
+
+impl Effect for Widener {
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if buffer.num_channels() != 2 {
+            warn!("Widener needs stereo input; leaving {} channel audio unchanged", buffer.num_channels());
+            return;
+        }
+        let before = stereo_correlation(buffer);
+
+        let mut channels = buffer.channels_mut();
+        let (left, right) = (channels.next().expect("stereo"), channels.next().expect("stereo"));
+        match self.mode {
+            Mode::MidSide => {
+                for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                    let mid = 0.5 * (*l + *r);
+                    let side = 0.5 * (*l - *r) * self.width;
+                    *l = mid + side;
+                    *r = mid - side;
+                }
+            }
+            Mode::Haas => {
+                let delay = self.delay.min(right.len());
+                right.copy_within(..right.len() - delay, delay);
+                right[..delay].fill(0.0);
+            }
+        }
+        drop(channels);
+
+        let after = stereo_correlation(buffer);
+        info!("Stereo correlation: {:.2} before widening, {:.2} after", before, after);
+        if after < 0.0 {
+            warn!("Negative correlation: the widened audio will partly cancel when summed to mono");
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Picking a short excerpt of a long recording, for quick previews and
+//! reports.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use std::ops::Range;
+
+use super::{mixdown, spectral, AudioBuffer};
+
+/// STFT frame length for scoring windows.
+const WINDOW_SIZE: usize = 2048;
+/// Levels this far below the loudest window count as silence (dB).
+const RANGE_DB: f32 = 60.0;
+
+/// How an excerpt is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Pick {
+    /// Loud and spectrally broad, so most bands have something in them
+    #[default]
+    Interesting,
+    /// Most energy, whatever its spectrum
+    Loudest,
+}
+
+impl Pick {
+    /// A `length`-frame stretch of `buffer` as a frame range.
+    pub fn pick(self, buffer: &AudioBuffer, length: usize) -> Result<Range<usize>> {
+        match self {
+            Pick::Interesting => most_interesting(&buffer.mixdown(), 1, buffer.sample_rate(), length),
+            Pick::Loudest => {
+                let energy = |frame: usize| buffer.channels().map(|channel| (channel[frame] as f64).powi(2)).sum();
+                Ok(loudest_stretch((0..buffer.frames()).map(energy), length))
+            }
+        }
+    }
+}
+
+/// The `length`-frame stretch of interleaved `samples` with the most
+/// energy across all channels, as a frame range. Recordings no longer than
+/// `length` are returned whole.
+pub fn loudest(samples: &[f32], channels: usize, length: usize) -> Range<usize> {
+    let energies = samples
+        .chunks_exact(channels.max(1))
+        .map(|frame| frame.iter().map(|&sample| (sample as f64) * (sample as f64)).sum());
+    loudest_stretch(energies, length)
+}
+
+/// [`loudest`] given the energy of each frame.
+fn loudest_stretch(energies: impl ExactSizeIterator<Item = f64>, length: usize) -> Range<usize> {
+    let frames = energies.len();
+    if frames <= length {
+        return 0..frames;
+    }
+
+    // Energy of the first `i` frames, so any stretch's energy is a difference
+    let mut cumulative = Vec::with_capacity(frames + 1);
+    let mut total = 0.0f64;
+    cumulative.push(total);
+    for energy in energies {
+        total += energy;
+        cumulative.push(total);
+    }
+    let start = best_stretch(&cumulative, length);
+    start..start + length
+}
+
+/// Start of the `length`-long stretch with the largest sum, given the
+/// cumulative sums of its parts. Ties go to the earliest stretch.
+fn best_stretch(cumulative: &[f64], length: usize) -> usize {
+    let sum = |start: usize| cumulative[start + length] - cumulative[start];
+    (0..cumulative.len().saturating_sub(length))
+        .max_by(|&a, &b| sum(a).total_cmp(&sum(b)).then(b.cmp(&a)))
+        .unwrap_or(0)
+}
+
+/// The most representative `length`-frame stretch: STFT windows score
+/// their level relative to the loudest window, times how evenly their
+/// energy spreads over octave bands, and the stretch whose windows score
+/// highest wins. Recordings no longer than `length` are returned whole.
+pub fn most_interesting(samples: &[f32], channels: usize, sample_rate: u32, length: usize) -> Result<Range<usize>> {
+    let mono = mixdown(samples, channels.max(1));
+    let frames = mono.len();
+    if frames <= length {
+        return Ok(0..frames);
+    }
+
+    let nyquist = sample_rate as f32 / 2.0;
+    let cutoffs: Vec<f32> =
+        (0..).map(|octave| 88.0 * 2f32.powi(octave)).take_while(|&cutoff| cutoff < nyquist).collect();
+    let windows: Vec<(f32, f32)> = spectral::frame_magnitudes(&mono, WINDOW_SIZE)?
+        .iter()
+        .map(|magnitudes| {
+            let powers: Vec<f32> = spectral::band_levels(magnitudes, WINDOW_SIZE, sample_rate, &cutoffs)
+                .iter()
+                .map(|&level| 10f32.powf(level / 10.0))
+                .collect();
+            let total: f32 = powers.iter().sum();
+            // Normalized entropy of the band energies: 0 for one band, 1 for all alike
+            let entropy: f32 = powers.iter().map(|&power| power / total).filter(|&p| p > 0.0).map(|p| -p * p.ln()).sum();
+            (10.0 * total.log10(), entropy / (powers.len() as f32).ln().max(f32::EPSILON))
+        })
+        .collect();
+    let loudest = windows.iter().map(|&(level, _)| level).fold(f32::NEG_INFINITY, f32::max);
+
+    let mut cumulative = vec![0.0f64];
+    for &(level, breadth) in &windows {
+        let score = ((level - loudest + RANGE_DB) / RANGE_DB).clamp(0.0, 1.0) * breadth;
+        cumulative.push(cumulative.last().unwrap() + score as f64);
+    }
+    // Frame `i` is centred on sample `i * hop`
+    let hop = WINDOW_SIZE / 2;
+    let span = length.div_ceil(hop).min(windows.len());
+    let start = (best_stretch(&cumulative, span) * hop).min(frames - length);
+    Ok(start..start + length)
+}
@@ -0,0 +1,311 @@
+//! Optional GPU backend for the STFT, enabled with the `gpu` feature.
+//!
+//! Windows are uploaded in batches and the forward FFT, band masking and
+//! inverse FFT all run on the device in a single submission per batch.
+//! Only windowing and overlap-add happen on the CPU, so the output matches
+//! `AudioProcessor::separate_frequencies` up to floating point rounding.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::sync::mpsc;
+use tracing::info;
+use wgpu::util::DeviceExt;
+
+use super::stft::{apply_window, frame_offsets, overlap_add, sqrt_hann_window};
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// Default number of windows transformed per dispatch.
+pub const DEFAULT_BATCH_SIZE: usize = 512;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FftParams {
+    n: u32,
+    ns: u32,
+    count: u32,
+    sign: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MaskParams {
+    n: u32,
+    count: u32,
+    low_end: u32,
+    high_start: u32,
+}
+
+pub struct GpuStft {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    window_size: usize,
+    batch_size: usize,
+    fft_pipeline: wgpu::ComputePipeline,
+    mask_pipeline: wgpu::ComputePipeline,
+    // Ping-pong buffers, each large enough for both bands of a full batch
+    buffers: [wgpu::Buffer; 2],
+    staging: wgpu::Buffer,
+    mask_params: wgpu::Buffer,
+    forward_stages: Vec<wgpu::BindGroup>,
+    mask_stage: wgpu::BindGroup,
+    inverse_stages: Vec<wgpu::BindGroup>,
+}
+
+impl GpuStft {
+    pub fn new(window_size: usize, batch_size: usize) -> Result<Self> {
+        if !window_size.is_power_of_two() || window_size < 2 {
+            bail!("GPU FFT size must be a power of two, got {}", window_size);
+        }
+
+        // Both bands of the batch are inverse transformed in one dispatch
+        let max_batch = (MAX_WORKGROUPS * WORKGROUP_SIZE) as usize / window_size;
+        let batch_size = batch_size.clamp(1, max_batch);
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .with_context(|| "No GPU adapter available")?;
+
+        let adapter_info = adapter.get_info();
+        info!("Using GPU adapter: {} ({:?})", adapter_info.name, adapter_info.backend);
+
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            bail!("GPU adapter {} does not support compute shaders", adapter_info.name);
+        }
+
+        let buffer_size = (2 * batch_size * window_size * std::mem::size_of::<[f32; 2]>()) as u64;
+        let limits = adapter.limits();
+        if buffer_size > limits.max_storage_buffer_binding_size as u64 {
+            bail!(
+                "GPU batch of {} windows needs {} bytes per buffer, adapter allows {}",
+                batch_size, buffer_size, limits.max_storage_buffer_binding_size
+            );
+        }
+
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("saunds-stft"),
+            required_limits: limits,
+            ..Default::default()
+        }))
+        .with_context(|| "Failed to open GPU device")?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("stft"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/stft.wgsl").into()),
+        });
+        let fft_pipeline = Self::pipeline(&device, &module, "fft_stage");
+        let mask_pipeline = Self::pipeline(&device, &module, "split_bands");
+
+        let storage = |label| device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let buffers = [storage("stft-a"), storage("stft-b")];
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("stft-staging"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mask_params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("mask-params"),
+            size: std::mem::size_of::<MaskParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Each stage reads one buffer and writes the other. With S stages per
+        // transform, the forward result lands in buffers[S % 2], the mask
+        // writes into the other buffer and the inverse result always ends up
+        // in buffers[1].
+        let stages = window_size.trailing_zeros() as usize;
+        let bind = |pipeline: &wgpu::ComputePipeline, params: &wgpu::Buffer, src: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: buffers[src].as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 2, resource: buffers[1 - src].as_entire_binding() },
+                ],
+            })
+        };
+        let fft_stage = |stage: usize, count: usize, sign: f32, src: usize| {
+            let params = FftParams {
+                n: window_size as u32,
+                ns: 1 << stage,
+                count: count as u32,
+                sign,
+            };
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("fft-params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            bind(&fft_pipeline, &buffer, src)
+        };
+
+        let forward_stages = (0..stages)
+            .map(|s| fft_stage(s, batch_size, -1.0, s % 2))
+            .collect();
+        let mask_stage = bind(&mask_pipeline, &mask_params, stages % 2);
+        let inverse_stages = (0..stages)
+            .map(|s| fft_stage(s, 2 * batch_size, 1.0, (stages + 1 + s) % 2))
+            .collect();
+
+        Ok(Self {
+            device,
+            queue,
+            window_size,
+            batch_size,
+            fft_pipeline,
+            mask_pipeline,
+            buffers,
+            staging,
+            mask_params,
+            forward_stages,
+            mask_stage,
+            inverse_stages,
+        })
+    }
+
+    fn pipeline(device: &wgpu::Device, module: &wgpu::ShaderModule, entry_point: &str) -> wgpu::ComputePipeline {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: None,
+            module,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: None,
+        })
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Runs the two-band split with 50% overlapping windows, keeping bins
+    /// below `low_end` in the low band and from `high_start` upwards in the
+    /// high band.
+    pub fn separate(&self, samples: &[f32], low_end: usize, high_start: usize) -> Result<(Vec<f32>, Vec<f32>)> {
+        let n = self.window_size;
+        let window_func = sqrt_hann_window(n);
+        let scale = 1.0 / n as f32;
+
+        self.queue.write_buffer(&self.mask_params, 0, bytemuck::bytes_of(&MaskParams {
+            n: n as u32,
+            count: self.batch_size as u32,
+            low_end: low_end.min(u32::MAX as usize) as u32,
+            high_start: high_start.min(u32::MAX as usize) as u32,
+        }));
+
+        let mut low_freq = vec![0.0; samples.len()];
+        let mut high_freq = vec![0.0; samples.len()];
+        let mut frames = vec![[0.0f32; 2]; self.batch_size * n];
+        let mut frame = vec![0.0; n];
+        let mut band_frame = vec![0.0; n];
+        let offsets: Vec<isize> = frame_offsets(samples.len(), n / 2).collect();
+
+        for (batch_index, batch) in offsets.chunks(self.batch_size).enumerate() {
+            frames.fill([0.0; 2]);
+            for (w, &offset) in batch.iter().enumerate() {
+                apply_window(samples, offset, &window_func, &mut frame);
+                for (dst, &src) in frames[w * n..(w + 1) * n].iter_mut().zip(&frame) {
+                    dst[0] = src;
+                }
+            }
+            self.queue.write_buffer(&self.buffers[0], 0, bytemuck::cast_slice(&frames));
+
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                let forward_groups = workgroups(n / 2 * self.batch_size);
+                let band_groups = workgroups(n * self.batch_size);
+                let inverse_groups = workgroups(n / 2 * 2 * self.batch_size);
+
+                pass.set_pipeline(&self.fft_pipeline);
+                for stage in &self.forward_stages {
+                    pass.set_bind_group(0, stage, &[]);
+                    pass.dispatch_workgroups(forward_groups, 1, 1);
+                }
+                pass.set_pipeline(&self.mask_pipeline);
+                pass.set_bind_group(0, &self.mask_stage, &[]);
+                pass.dispatch_workgroups(band_groups, 1, 1);
+                pass.set_pipeline(&self.fft_pipeline);
+                for stage in &self.inverse_stages {
+                    pass.set_bind_group(0, stage, &[]);
+                    pass.dispatch_workgroups(inverse_groups, 1, 1);
+                }
+            }
+            encoder.copy_buffer_to_buffer(&self.buffers[1], 0, &self.staging, 0, self.staging.size());
+            self.queue.submit(Some(encoder.finish()));
+
+            let (tx, rx) = mpsc::channel();
+            self.staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+            self.device
+                .poll(wgpu::PollType::wait_indefinitely())
+                .with_context(|| format!("GPU device lost while processing batch {}", batch_index + 1))?;
+            rx.recv()
+                .map_err(|_| anyhow!("GPU readback was cancelled"))?
+                .with_context(|| "Failed to map GPU readback buffer")?;
+
+            {
+                let view = self.staging.slice(..).get_mapped_range();
+                let bands: &[[f32; 2]] = bytemuck::cast_slice(&view);
+                let (low_windows, high_windows) = bands.split_at(self.batch_size * n);
+
+                // Overlap-add to output
+                for (w, &offset) in batch.iter().enumerate() {
+                    for (dst, src) in band_frame.iter_mut().zip(&low_windows[w * n..(w + 1) * n]) {
+                        *dst = src[0];
+                    }
+                    overlap_add(&band_frame, offset, &window_func, scale, &mut low_freq);
+                    for (dst, src) in band_frame.iter_mut().zip(&high_windows[w * n..(w + 1) * n]) {
+                        *dst = src[0];
+                    }
+                    overlap_add(&band_frame, offset, &window_func, scale, &mut high_freq);
+                }
+            }
+            self.staging.unmap();
+        }
+
+        info!("GPU frequency separation complete. Processed {} windows", offsets.len());
+        Ok((low_freq, high_freq))
+    }
+
+    /// Splits `samples` into one band per gap between ascending `cutoffs`,
+    /// given as FFT bins. Each cutoff takes one two-band pass; as the mask
+    /// is linear, a band between two cutoffs is the difference of the low
+    /// bands below them.
+    pub fn split_bands(&self, samples: &[f32], cutoffs: &[usize]) -> Result<Vec<Vec<f32>>> {
+        let mut bands = Vec::with_capacity(cutoffs.len() + 1);
+        let mut below = vec![0.0; samples.len()];
+        let mut above = samples.to_vec();
+        for &cutoff in cutoffs {
+            let (low, high) = self.separate(samples, cutoff, cutoff)?;
+            bands.push(low.iter().zip(&below).map(|(low, below)| low - below).collect());
+            (below, above) = (low, high);
+        }
+        bands.push(above);
+        Ok(bands)
+    }
+}
+
+fn workgroups(invocations: usize) -> u32 {
+    invocations.div_ceil(WORKGROUP_SIZE as usize) as u32
+}
@@ -0,0 +1,110 @@
+//! ID3v2 chapter tags (the CHAP and CTOC frames of the ID3v2 Chapter Frame
+//! Addendum), which podcast players show as a chapter list.
+
+use anyhow::{bail, Result};
+
+use super::chapters::Chapter;
+use super::mp3::id3v2_len;
+
+/// Element ID of the table of contents.
+const TOC_ID: &str = "toc";
+
+/// Copy of the MP3 stream `data` with `chapters` in its ID3v2 tag. Frames of
+/// an existing ID3v2.3 or 2.4 tag are kept, apart from earlier chapters.
+pub fn write_chapters(data: &[u8], chapters: &[Chapter]) -> Result<Vec<u8>> {
+    if chapters.len() > u8::MAX as usize {
+        bail!("An ID3 table of contents holds at most {} chapters, got {}", u8::MAX, chapters.len());
+    }
+    let existing = id3v2_len(data);
+    let (version, mut body) = if existing > 0 { existing_frames(&data[..existing])? } else { (3, Vec::new()) };
+
+    let ids: Vec<String> = (0..chapters.len()).map(|i| format!("chp{}", i)).collect();
+    let mut toc = element_id(TOC_ID);
+    // Top-level and ordered
+    toc.push(0x03);
+    toc.push(chapters.len() as u8);
+    ids.iter().for_each(|id| toc.extend(element_id(id)));
+    body.extend(frame(b"CTOC", &toc, version));
+
+    for (chapter, id) in chapters.iter().zip(&ids) {
+        let mut chap = element_id(id);
+        for seconds in [chapter.start, chapter.end] {
+            chap.extend(((seconds * 1000.0).round() as u32).to_be_bytes());
+        }
+        // Byte offsets, unused
+        chap.extend([0xff; 8]);
+        chap.extend(frame(b"TIT2", &text(&chapter.title), version));
+        body.extend(frame(b"CHAP", &chap, version));
+    }
+
+    let mut tagged = b"ID3".to_vec();
+    tagged.extend([version, 0, 0]);
+    tagged.extend(syncsafe(body.len()));
+    tagged.extend(body);
+    tagged.extend_from_slice(&data[existing..]);
+    Ok(tagged)
+}
+
+/// Version of the ID3v2 `tag` and its frames other than chapters.
+fn existing_frames(tag: &[u8]) -> Result<(u8, Vec<u8>)> {
+    let (version, flags) = (tag[3], tag[5]);
+    if !(3..=4).contains(&version) {
+        bail!("Cannot add chapters to an ID3v2.{} tag; only 2.3 and 2.4 are supported", version);
+    }
+    if flags & 0x80 != 0 {
+        bail!("Cannot add chapters to an unsynchronised ID3v2 tag");
+    }
+    let mut pos = 10;
+    // The extended header is dropped, as its CRC wouldn't match any more
+    if flags & 0x40 != 0 && tag.len() >= 14 {
+        let size = u32::from_be_bytes(tag[10..14].try_into().unwrap()) as usize;
+        pos += if version == 3 { size + 4 } else { unsyncsafe(&tag[10..14]) };
+    }
+    let mut kept = Vec::new();
+    while pos + 10 <= tag.len() && tag[pos] != 0 {
+        let size = if version == 3 { u32::from_be_bytes(tag[pos + 4..pos + 8].try_into().unwrap()) as usize } else { unsyncsafe(&tag[pos + 4..pos + 8]) };
+        let end = (pos + 10 + size).min(tag.len());
+        if !matches!(&tag[pos..pos + 4], b"CHAP" | b"CTOC") {
+            kept.extend_from_slice(&tag[pos..end]);
+        }
+        pos = end;
+    }
+    Ok((version, kept))
+}
+
+/// A frame with its header; sizes are plain in ID3v2.3 and syncsafe in 2.4.
+fn frame(id: &[u8; 4], body: &[u8], version: u8) -> Vec<u8> {
+    let mut frame = id.to_vec();
+    if version == 3 {
+        frame.extend((body.len() as u32).to_be_bytes());
+    } else {
+        frame.extend(syncsafe(body.len()));
+    }
+    frame.extend([0, 0]);
+    frame.extend_from_slice(body);
+    frame
+}
+
+fn element_id(id: &str) -> Vec<u8> {
+    let mut bytes = id.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+/// Text frame contents: ISO-8859-1 where it fits, otherwise UTF-16 with a
+/// byte order mark.
+fn text(value: &str) -> Vec<u8> {
+    if value.chars().all(|c| (c as u32) < 0x100) {
+        std::iter::once(0).chain(value.chars().map(|c| c as u8)).collect()
+    } else {
+        [1, 0xff, 0xfe].into_iter().chain(value.encode_utf16().flat_map(u16::to_le_bytes)).collect()
+    }
+}
+
+fn syncsafe(value: usize) -> [u8; 4] {
+    std::array::from_fn(|i| ((value >> (7 * (3 - i))) & 0x7f) as u8)
+}
+
+fn unsyncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, &b| (acc << 7) | (b & 0x7f) as usize)
+}
@@ -0,0 +1,265 @@
+//! Time-domain band splitting with IIR filters, as an alternative to the
+//! STFT masks.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use realfft::num_traits::{AsPrimitive, Float};
+use tracing::info;
+
+use super::biquad::{filter_interleaved, group_delay_samples, Biquad};
+use super::design::{stopband_attenuation_db, FilterDesign};
+use super::{AudioBuffer, Precision};
+
+/// How band splits are computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FilterMode {
+    /// Brick-wall STFT masks
+    #[default]
+    Fft,
+    /// Causal crossovers running each filter twice, Linkwitz-Riley style
+    Iir,
+    /// Forward-backward filtering: the same magnitude response as `iir`
+    /// without phase shift
+    ZeroPhase,
+}
+
+/// Residual impulse response level at which a filter counts as settled.
+const SETTLED_LEVEL: f64 = 1e-4;
+
+/// Number of samples after which the cascade's impulse response has
+/// decayed below [`SETTLED_LEVEL`], judged from its slowest pole.
+pub fn settling_samples(cascade: &[Biquad]) -> usize {
+    let radius = cascade
+        .iter()
+        .map(|section| {
+            let discriminant = section.a1 * section.a1 - 4.0 * section.a2;
+            if discriminant < 0.0 {
+                section.a2.sqrt()
+            } else {
+                let root = discriminant.sqrt();
+                ((-section.a1 + root) / 2.0).abs().max(((-section.a1 - root) / 2.0).abs())
+            }
+        })
+        .fold(0.0, f64::max);
+
+    if radius < 1.0 {
+        (SETTLED_LEVEL.ln() / radius.ln()).ceil() as usize
+    } else {
+        usize::MAX
+    }
+}
+
+/// Filters interleaved samples forward and then backward through the
+/// cascade, squaring its magnitude response and cancelling its phase.
+/// Each channel is extended by odd reflection at both ends so the filter
+/// has settled by the time it reaches the signal.
+pub fn filtfilt<T>(cascade: &[Biquad], samples: &[T], channels: usize) -> Vec<T>
+where
+    T: Float + AsPrimitive<f64>,
+    f64: AsPrimitive<T>,
+{
+    let channels = channels.max(1);
+    let mut output = vec![T::zero(); samples.len()];
+    let settling = settling_samples(cascade);
+
+    for channel in 0..channels.min(samples.len()) {
+        let signal: Vec<T> = samples.iter().skip(channel).step_by(channels).copied().collect();
+        let frames = signal.len();
+        let pad = settling.min(frames - 1);
+        let (first, last) = (signal[0], signal[frames - 1]);
+
+        let mut extended = Vec::with_capacity(frames + 2 * pad);
+        extended.extend(signal[1..=pad].iter().rev().map(|&x| first + first - x));
+        extended.extend_from_slice(&signal);
+        extended.extend(signal[frames - 1 - pad..frames - 1].iter().rev().map(|&x| last + last - x));
+
+        filter_interleaved(cascade, &mut extended, 1);
+        extended.reverse();
+        filter_interleaved(cascade, &mut extended, 1);
+        extended.reverse();
+
+        for (out, &value) in output.iter_mut().skip(channel).step_by(channels).zip(&extended[pad..pad + frames]) {
+            *out = value;
+        }
+    }
+
+    output
+}
+
+/// Applies the cascade twice, or forward and backward in zero-phase mode.
+fn filter_twice<T>(cascade: &[Biquad], samples: &[T], channels: usize, mode: FilterMode) -> Vec<T>
+where
+    T: Float + AsPrimitive<f64>,
+    f64: AsPrimitive<T>,
+{
+    match mode {
+        FilterMode::ZeroPhase => filtfilt(cascade, samples, channels),
+        _ => {
+            let mut output = samples.to_vec();
+            filter_interleaved(cascade, &mut output, channels);
+            filter_interleaved(cascade, &mut output, channels);
+            output
+        }
+    }
+}
+
+/// [`filter_twice`] with intermediate results held at `precision`.
+fn filter_twice_at(cascade: &[Biquad], samples: &[f32], channels: usize, mode: FilterMode, precision: Precision) -> Vec<f32> {
+    match precision {
+        Precision::Single => filter_twice(cascade, samples, channels, mode),
+        Precision::Double => {
+            let wide: Vec<f64> = samples.iter().map(|&x| x as f64).collect();
+            filter_twice(cascade, &wide, channels, mode).into_iter().map(|x| x as f32).collect()
+        }
+    }
+}
+
+/// IIR band splitter for interleaved or planar audio.
+#[derive(Debug, Clone, Copy)]
+pub struct Crossover {
+    pub mode: FilterMode,
+    pub design: FilterDesign,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub precision: Precision,
+}
+
+impl Crossover {
+    fn nyquist(&self) -> f32 {
+        self.sample_rate as f32 / 2.0
+    }
+
+    /// Content of `samples` below `cutoff` Hz.
+    pub fn lowpass(&self, samples: &[f32], cutoff: f32) -> Result<Vec<f32>> {
+        Ok(self.lowpass_each(&[samples], self.channels, cutoff)?.remove(0))
+    }
+
+    /// Content of `samples` above `cutoff` Hz. In zero-phase mode this is
+    /// exactly the input minus [`lowpass`](Self::lowpass), so the two
+    /// always sum back to the input.
+    pub fn highpass(&self, samples: &[f32], cutoff: f32) -> Result<Vec<f32>> {
+        Ok(self.highpass_each(&[samples], self.channels, cutoff)?.remove(0))
+    }
+
+    /// [`lowpass`](Self::lowpass) of each channel of a planar buffer.
+    pub fn lowpass_buffer(&self, input: &AudioBuffer, cutoff: f32) -> Result<AudioBuffer> {
+        input.like(self.lowpass_each(&input.channels().collect::<Vec<_>>(), 1, cutoff)?)
+    }
+
+    /// [`highpass`](Self::highpass) of each channel of a planar buffer.
+    pub fn highpass_buffer(&self, input: &AudioBuffer, cutoff: f32) -> Result<AudioBuffer> {
+        input.like(self.highpass_each(&input.channels().collect::<Vec<_>>(), 1, cutoff)?)
+    }
+
+    /// Low-passes each of `signals`, which interleave `channels` channels,
+    /// designing and reporting the filter once for all of them.
+    fn lowpass_each(&self, signals: &[&[f32]], channels: usize, cutoff: f32) -> Result<Vec<Vec<f32>>> {
+        if cutoff <= 0.0 {
+            return Ok(signals.iter().map(|signal| vec![0.0; signal.len()]).collect());
+        }
+        if cutoff >= self.nyquist() {
+            return Ok(signals.iter().map(|signal| signal.to_vec()).collect());
+        }
+
+        let cascade = self.design.lowpass(cutoff, self.sample_rate)?;
+        if 2.0 * cutoff < self.nyquist() {
+            // Both passes count towards the attenuation
+            let attenuation = 2.0 * stopband_attenuation_db(&cascade, 2.0 * cutoff, self.nyquist(), self.sample_rate);
+            info!(
+                "Low-pass at {} Hz ({:?}, order {}): {:.1} dB stopband attenuation above {} Hz",
+                cutoff, self.design.family, self.design.order, attenuation, 2.0 * cutoff
+            );
+        }
+        Ok(signals
+            .iter()
+            .map(|signal| filter_twice_at(&cascade, signal, channels, self.mode, self.precision))
+            .collect())
+    }
+
+    /// High-pass counterpart of [`lowpass_each`](Self::lowpass_each).
+    fn highpass_each(&self, signals: &[&[f32]], channels: usize, cutoff: f32) -> Result<Vec<Vec<f32>>> {
+        if cutoff <= 0.0 {
+            return Ok(signals.iter().map(|signal| signal.to_vec()).collect());
+        }
+        if cutoff >= self.nyquist() {
+            return Ok(signals.iter().map(|signal| vec![0.0; signal.len()]).collect());
+        }
+
+        if self.mode == FilterMode::ZeroPhase {
+            let lows = self.lowpass_each(signals, channels, cutoff)?;
+            return Ok(signals
+                .iter()
+                .zip(lows)
+                .map(|(signal, low)| signal.iter().zip(&low).map(|(x, l)| x - l).collect())
+                .collect());
+        }
+
+        let cascade = self.design.highpass(cutoff, self.sample_rate)?;
+        let attenuation = 2.0 * stopband_attenuation_db(&cascade, 1.0, cutoff / 2.0, self.sample_rate);
+        info!(
+            "High-pass at {} Hz ({:?}, order {}): {:.1} dB stopband attenuation below {} Hz",
+            cutoff, self.design.family, self.design.order, attenuation, cutoff / 2.0
+        );
+        Ok(signals
+            .iter()
+            .map(|signal| filter_twice_at(&cascade, signal, channels, self.mode, self.precision))
+            .collect())
+    }
+
+    /// Latency in samples of a band that went through a high-pass at each
+    /// of `highpasses` and then a low-pass at `lowpass`: the chain's group
+    /// delay at the geometric centre of the band's passband (from 20 Hz).
+    /// Zero in zero-phase mode.
+    pub fn latency(&self, highpasses: &[f32], lowpass: Option<f32>) -> Result<f64> {
+        if self.mode == FilterMode::ZeroPhase {
+            return Ok(0.0);
+        }
+        let mut chain = Vec::new();
+        for &cutoff in highpasses.iter().filter(|&&cutoff| cutoff > 0.0 && cutoff < self.nyquist()) {
+            chain.extend(self.design.highpass(cutoff, self.sample_rate)?);
+        }
+        if let Some(cutoff) = lowpass.filter(|&cutoff| cutoff > 0.0 && cutoff < self.nyquist()) {
+            chain.extend(self.design.lowpass(cutoff, self.sample_rate)?);
+        }
+        let bottom = highpasses.iter().copied().fold(20.0f32, f32::max);
+        let top = lowpass.unwrap_or(self.nyquist()).min(self.nyquist()).max(bottom);
+        // Each cascade runs twice, doubling its delay
+        Ok(2.0 * group_delay_samples(&chain, (bottom * top).sqrt(), self.sample_rate))
+    }
+
+    /// [`latency`](Self::latency) of each band from
+    /// [`split_bands`](Self::split_bands).
+    pub fn split_latencies(&self, cutoffs: &[f32]) -> Result<Vec<f64>> {
+        (0..=cutoffs.len())
+            .map(|band| self.latency(&cutoffs[..band], cutoffs.get(band).copied()))
+            .collect()
+    }
+
+    /// Splits `samples` into `cutoffs.len() + 1` bands by peeling off the
+    /// lowest band at each ascending cutoff in turn.
+    pub fn split_bands(&self, samples: &[f32], cutoffs: &[f32]) -> Result<Vec<Vec<f32>>> {
+        Ok(self.split_each(&[samples], self.channels, cutoffs)?.into_iter().map(|mut band| band.remove(0)).collect())
+    }
+
+    /// [`split_bands`](Self::split_bands) on each channel of a planar buffer.
+    pub fn split_buffer(&self, input: &AudioBuffer, cutoffs: &[f32]) -> Result<Vec<AudioBuffer>> {
+        self.split_each(&input.channels().collect::<Vec<_>>(), 1, cutoffs)?
+            .into_iter()
+            .map(|band| input.like(band))
+            .collect()
+    }
+
+    /// Splits each of `signals`, which interleave `channels` channels,
+    /// returning each band's outputs in the order of `signals`.
+    fn split_each(&self, signals: &[&[f32]], channels: usize, cutoffs: &[f32]) -> Result<Vec<Vec<Vec<f32>>>> {
+        let mut bands = Vec::with_capacity(cutoffs.len() + 1);
+        let mut remainder: Vec<Vec<f32>> = signals.iter().map(|signal| signal.to_vec()).collect();
+        for &cutoff in cutoffs {
+            let current: Vec<&[f32]> = remainder.iter().map(Vec::as_slice).collect();
+            bands.push(self.lowpass_each(&current, channels, cutoff)?);
+            remainder = self.highpass_each(&current, channels, cutoff)?;
+        }
+        bands.push(remainder);
+        Ok(bands)
+    }
+}
@@ -0,0 +1,89 @@
+//! Lossless intermediate format for passing audio between chained runs:
+//! an 8-byte magic, a little-endian u64 header length, a JSON header
+//! padded to 8 bytes, then interleaved little-endian f64 samples.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use super::buffer::interleave;
+
+/// File extension that selects the intermediate format.
+pub const EXTENSION: &str = "saunds";
+
+const MAGIC: &[u8; 8] = b"SAUNDS\x00\x01";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub frames: u64,
+    /// Always `f64le` in this version
+    pub encoding: String,
+}
+
+/// Writes `channels`, which must all be the same length, as f64 without
+/// quantization.
+pub fn write(path: &Path, sample_rate: u32, channels: &[&[f32]]) -> Result<()> {
+    let header = Header {
+        sample_rate,
+        channels: channels.len() as u32,
+        frames: channels.first().map_or(0, |channel| channel.len()) as u64,
+        encoding: "f64le".to_string(),
+    };
+    let mut json = serde_json::to_vec(&header)?;
+    json.resize(json.len().next_multiple_of(8), b' ');
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(json.len() as u64).to_le_bytes())?;
+    writer.write_all(&json)?;
+    for sample in interleave(channels) {
+        writer.write_all(&(sample as f64).to_le_bytes())?;
+    }
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Reads a header and its f64 samples, one vector per channel.
+pub fn read<R: Read>(mut reader: R) -> Result<(Header, Vec<Vec<f64>>)> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).context("Truncated intermediate file")?;
+    if &magic != MAGIC {
+        bail!("Not a saunds intermediate file");
+    }
+    let mut len = [0u8; 8];
+    reader.read_exact(&mut len).context("Truncated intermediate file")?;
+    let len = u64::from_le_bytes(len);
+    if len > 1 << 20 {
+        bail!("Intermediate header of {} bytes is implausibly large", len);
+    }
+    let mut json = vec![0u8; len as usize];
+    reader.read_exact(&mut json).context("Truncated intermediate header")?;
+    let header: Header = serde_json::from_slice(&json).context("Invalid intermediate header")?;
+    if header.encoding != "f64le" {
+        bail!("Unsupported intermediate encoding '{}'", header.encoding);
+    }
+    if header.channels == 0 {
+        bail!("Intermediate header declares no channels");
+    }
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).context("Failed to read intermediate samples")?;
+    let expected = header.frames * header.channels as u64 * 8;
+    if data.len() as u64 != expected {
+        bail!("Intermediate data holds {} bytes; the header declares {}", data.len(), expected);
+    }
+    let channels = header.channels as usize;
+    let planes = (0..channels)
+        .map(|channel| {
+            data.chunks_exact(8)
+                .skip(channel)
+                .step_by(channels)
+                .map(|bytes| f64::from_le_bytes(bytes.try_into().expect("chunks are 8 bytes")))
+                .collect()
+        })
+        .collect();
+    Ok((header, planes))
+}
@@ -0,0 +1,63 @@
+//! Lookahead peak limiter for offline processing.
+
+use std::collections::VecDeque;
+
+use super::AudioBuffer;
+
+/// Limits `buffer` in place so no sample exceeds `ceiling_db` dBFS. All
+/// channels share one gain, keeping the image stable. Gain reduction
+/// starts `lookahead_ms` ahead of each peak and recovers over
+/// `release_ms`.
+pub fn limit(buffer: &mut AudioBuffer, ceiling_db: f32, lookahead_ms: f32, release_ms: f32) {
+    let sample_rate = buffer.sample_rate();
+    let frames = buffer.frames();
+    let ceiling = 10f32.powf(ceiling_db / 20.0);
+    let lookahead = ((lookahead_ms / 1000.0 * sample_rate as f32).round() as usize).max(1);
+    let release = (-1.0 / (release_ms.max(0.1) / 1000.0 * sample_rate as f32)).exp();
+
+    let mut required = vec![0.0f32; frames];
+    for channel in buffer.channels() {
+        required.iter_mut().zip(channel).for_each(|(peak, x)| *peak = peak.max(x.abs()));
+    }
+    required.iter_mut().for_each(|peak| *peak = if *peak > ceiling { ceiling / *peak } else { 1.0 });
+
+    // Smallest required gain over the next `lookahead` frames, via a
+    // monotonic queue of candidate indices
+    let mut minimum = vec![1.0f32; frames];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for index in (0..frames).rev() {
+        while queue.back().is_some_and(|&back| required[back] >= required[index]) {
+            queue.pop_back();
+        }
+        queue.push_back(index);
+        while queue.front().is_some_and(|&front| front >= index + lookahead) {
+            queue.pop_front();
+        }
+        minimum[index] = required[queue[0]];
+    }
+
+    // Release only ever raises the gain slowly, so it stays at or below
+    // the minimum
+    let mut gain = 1.0f32;
+    for value in &mut minimum {
+        gain = value.min(release * gain + (1.0 - release) * *value);
+        *value = gain;
+    }
+
+    // A moving average over the lookahead turns steps into ramps. Every
+    // window it averages covers the frame, so the ceiling still holds
+    let mut sum = 0.0f64;
+    let gains: Vec<f32> = (0..frames)
+        .map(|index| {
+            sum += minimum[index] as f64;
+            if index >= lookahead {
+                sum -= minimum[index - lookahead] as f64;
+            }
+            let count = (index + 1).min(lookahead);
+            (sum / count as f64) as f32
+        })
+        .collect();
+    for channel in buffer.channels_mut() {
+        channel.iter_mut().zip(&gains).for_each(|(sample, gain)| *sample *= gain);
+    }
+}
@@ -0,0 +1,235 @@
+//! Programme loudness, loudness range and true peak per ITU-R BS.1770 and
+//! EBU Tech 3342.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::biquad::{filter_interleaved, Biquad};
+use super::{peaks, AudioBuffer};
+
+/// Gating block for integrated and momentary loudness.
+const MOMENTARY_SECONDS: f32 = 0.4;
+/// Window for short-term loudness and the loudness range.
+const SHORT_TERM_SECONDS: f32 = 3.0;
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+/// Level below which silence or a fully gated signal reads.
+pub const SILENCE_LUFS: f32 = -120.0;
+/// Loudness ReplayGain 2.0 gains are relative to.
+pub const REPLAYGAIN_REFERENCE_LUFS: f32 = -18.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Loudness {
+    /// Gated programme loudness
+    pub integrated_lufs: f32,
+    /// Spread between the 10th and 95th percentile of short-term loudness
+    pub loudness_range_lu: f32,
+    pub max_momentary_lufs: f32,
+    pub max_short_term_lufs: f32,
+    pub sample_peak_dbfs: f32,
+    /// Peak of the 4x oversampled signal
+    pub true_peak_dbtp: f32,
+}
+
+/// The two K-weighting sections: a high shelf modelling the head and a
+/// high-pass, designed for any sample rate so they match the standard's
+/// 48 kHz coefficients.
+pub fn k_weighting(sample_rate: u32) -> Vec<Biquad> {
+    let fs = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b0: (vh + vb * k / q + k * k) / a0,
+        b1: 2.0 * (k * k - vh) / a0,
+        b2: (vh - vb * k / q + k * k) / a0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: 2.0 * (k * k - 1.0) / a0,
+        a2: (1.0 - k / q + k * k) / a0,
+    };
+
+    vec![shelf, highpass]
+}
+
+/// BS.1770 channel weights, assuming 5.1 in L R C LFE Ls Rs order: the
+/// LFE is ignored and the surrounds count +1.5 dB.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        _ => vec![1.0; channels],
+    }
+}
+
+fn lufs(weighted_power: f64) -> f32 {
+    if weighted_power > 0.0 {
+        (-0.691 + 10.0 * weighted_power.log10()) as f32
+    } else {
+        SILENCE_LUFS
+    }
+}
+
+/// The K-weighted channels of `buffer`.
+fn k_weighted(buffer: &AudioBuffer) -> Vec<Vec<f32>> {
+    let cascade = k_weighting(buffer.sample_rate());
+    buffer
+        .channels()
+        .map(|channel| {
+            let mut weighted = channel.to_vec();
+            filter_interleaved(&cascade, &mut weighted, 1);
+            weighted
+        })
+        .collect()
+}
+
+/// Weighted mean square of each `block_seconds` block of the K-weighted
+/// channels, with blocks starting every `step_seconds`.
+fn block_powers(weighted: &[Vec<f32>], sample_rate: u32, block_seconds: f32, step_seconds: f32) -> Vec<f64> {
+    let frames = weighted[0].len();
+    let block = ((block_seconds * sample_rate as f32).round() as usize).max(1);
+    let step = ((step_seconds * sample_rate as f32).round() as usize).max(1);
+    let weights = channel_weights(weighted.len());
+
+    // Running sums of weighted squares make every block O(1)
+    let mut powers = vec![0.0f64; frames];
+    for (channel, weight) in weighted.iter().zip(&weights) {
+        powers.iter_mut().zip(channel).for_each(|(power, &x)| *power += weight * (x as f64).powi(2));
+    }
+    let mut cumulative = Vec::with_capacity(frames + 1);
+    cumulative.push(0.0f64);
+    let mut total = 0.0;
+    for power in powers {
+        total += power;
+        cumulative.push(total);
+    }
+
+    (0..)
+        .map(|i| i * step)
+        .take_while(|&start| start + block <= frames)
+        .map(|start| (cumulative[start + block] - cumulative[start]) / block as f64)
+        .collect()
+}
+
+/// Loudness of each `block_seconds` block, starting every `step_seconds`:
+/// 0.4 s blocks give momentary loudness and 3 s blocks short-term
+/// loudness.
+pub fn loudness_history(buffer: &AudioBuffer, block_seconds: f32, step_seconds: f32) -> Vec<f32> {
+    block_powers(&k_weighted(buffer), buffer.sample_rate(), block_seconds, step_seconds)
+        .into_iter()
+        .map(lufs)
+        .collect()
+}
+
+/// Momentary blocks above the absolute gate, counted by loudness in
+/// tenths of a LU. Pooling the histograms of several tracks gates them as
+/// one programme, which is how album loudness is measured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockHistogram {
+    pub counts: BTreeMap<i32, u64>,
+}
+
+impl BlockHistogram {
+    fn from_powers(powers: &[f64]) -> Self {
+        let mut counts = BTreeMap::new();
+        for &power in powers.iter().filter(|&&power| lufs(power) > ABSOLUTE_GATE_LUFS) {
+            *counts.entry((lufs(power) * 10.0).round() as i32).or_insert(0) += 1;
+        }
+        Self { counts }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (&bin, &count) in &other.counts {
+            *self.counts.entry(bin).or_insert(0) += count;
+        }
+    }
+
+    /// Integrated loudness of the pooled blocks, to within the 0.1 LU bins.
+    pub fn integrated_lufs(&self) -> f32 {
+        let power = |bin: i32| 10f64.powf((bin as f64 / 10.0 + 0.691) / 10.0);
+        let mean = |bins: &mut dyn Iterator<Item = (&i32, &u64)>| {
+            let (sum, count) = bins.fold((0.0, 0), |(sum, total), (&bin, &count)| (sum + power(bin) * count as f64, total + count));
+            if count > 0 { sum / count as f64 } else { 0.0 }
+        };
+        let relative_gate = lufs(mean(&mut self.counts.iter())) - 10.0;
+        let gated = mean(&mut self.counts.iter().filter(|(&bin, _)| bin as f32 / 10.0 > relative_gate));
+        if gated > 0.0 { lufs(gated) } else { SILENCE_LUFS }
+    }
+}
+
+/// ReplayGain 2.0 values of a track within an album: gains to
+/// [`REPLAYGAIN_REFERENCE_LUFS`] and true peaks as linear amplitudes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub track_gain_db: f32,
+    pub track_peak: f32,
+    pub album_gain_db: f32,
+    pub album_peak: f32,
+}
+
+/// Measures `buffer`. Signals shorter than one gating block are measured
+/// as a single block.
+pub fn measure(buffer: &AudioBuffer) -> Result<Loudness> {
+    measure_with_blocks(buffer).map(|(loudness, _)| loudness)
+}
+
+/// [`measure`], also returning the gating blocks for album loudness.
+pub fn measure_with_blocks(buffer: &AudioBuffer) -> Result<(Loudness, BlockHistogram)> {
+    if buffer.frames() == 0 {
+        bail!("Cannot measure the loudness of an empty signal");
+    }
+
+    let sample_rate = buffer.sample_rate();
+    let weighted = k_weighted(buffer);
+    let seconds = buffer.frames() as f32 / sample_rate as f32;
+
+    let momentary = block_powers(&weighted, sample_rate, MOMENTARY_SECONDS.min(seconds), MOMENTARY_SECONDS / 4.0);
+    let short_term = block_powers(&weighted, sample_rate, SHORT_TERM_SECONDS.min(seconds), SHORT_TERM_SECONDS / 30.0);
+
+    // Integrated: absolute gate, then a relative gate 10 LU below the
+    // loudness of the blocks that passed it
+    let mean = |powers: &[f64]| powers.iter().sum::<f64>() / powers.len().max(1) as f64;
+    let above_absolute: Vec<f64> = momentary.iter().copied().filter(|&power| lufs(power) > ABSOLUTE_GATE_LUFS).collect();
+    let relative_gate = lufs(mean(&above_absolute)) - 10.0;
+    let gated: Vec<f64> = above_absolute.iter().copied().filter(|&power| lufs(power) > relative_gate).collect();
+    let integrated_lufs = if gated.is_empty() { SILENCE_LUFS } else { lufs(mean(&gated)) };
+
+    // Range: short-term loudness gated 20 LU below its own mean
+    let short_term_levels: Vec<f32> = short_term.iter().map(|&power| lufs(power)).collect();
+    let short_term_above: Vec<f64> = short_term.iter().copied().filter(|&power| lufs(power) > ABSOLUTE_GATE_LUFS).collect();
+    let range_gate = lufs(mean(&short_term_above)) - 20.0;
+    let mut ranged: Vec<f32> = short_term_levels
+        .iter()
+        .copied()
+        .filter(|&level| level > ABSOLUTE_GATE_LUFS && level > range_gate)
+        .collect();
+    ranged.sort_by(f32::total_cmp);
+    let percentile = |p: f32| ranged[((ranged.len() - 1) as f32 * p).round() as usize];
+    let loudness_range_lu = if ranged.is_empty() { 0.0 } else { percentile(0.95) - percentile(0.10) };
+
+    let db = |amplitude: f32| 20.0 * amplitude.max(1e-6).log10();
+    let sample_peak = peaks::sample_peak(buffer);
+    let true_peak = peaks::true_peak(buffer);
+
+    let blocks = BlockHistogram::from_powers(&momentary);
+    let loudness = Loudness {
+        integrated_lufs,
+        loudness_range_lu,
+        max_momentary_lufs: momentary.into_iter().map(lufs).fold(SILENCE_LUFS, f32::max),
+        max_short_term_lufs: short_term_levels.into_iter().fold(SILENCE_LUFS, f32::max),
+        sample_peak_dbfs: db(sample_peak),
+        true_peak_dbtp: db(true_peak),
+    };
+    Ok((loudness, blocks))
+}
@@ -0,0 +1,295 @@
+//! Linear timecode (SMPTE 12M LTC) recorded as audio: biphase-mark
+//! decoding of its 80-bit frames, and mapping the timecode onto the
+//! recording's samples to find where a timecode falls and how far the
+//! recorder's clock drifts from the timecode source.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Bits per LTC frame.
+const FRAME_BITS: usize = 80;
+/// Sync word ending every frame, in transmission order.
+const SYNC: [u8; 16] = [0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1];
+/// Level around zero that a transition has to cross, relative to the
+/// peak.
+const HYSTERESIS: f32 = 0.1;
+/// Frame rates LTC is run at, for the bit lengths to look for.
+const MIN_FPS: f64 = 23.0;
+const MAX_FPS: f64 = 31.0;
+
+/// A SMPTE timecode, shown as `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop
+/// frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timecode {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// Frames since midnight at `base` frames per timecode second (24, 25
+    /// or 30), leaving out the frame numbers drop frame skips.
+    pub fn count(&self, base: u32) -> i64 {
+        let minutes = (self.hours * 60 + self.minutes) as i64;
+        let count = (minutes * 60 + self.seconds as i64) * base as i64 + self.frames as i64;
+        if self.drop_frame {
+            count - 2 * (minutes - minutes / 10)
+        } else {
+            count
+        }
+    }
+
+    /// The timecode `count` frames after midnight, the inverse of
+    /// [`Timecode::count`].
+    pub fn from_count(count: i64, base: u32, drop_frame: bool) -> Self {
+        let mut count = count.rem_euclid(24 * 3600 * base as i64);
+        if drop_frame {
+            // Frame numbers 0 and 1 are skipped every minute but every tenth
+            let (tens, rest) = (count / 17982, count % 17982);
+            count += 18 * tens + if rest > 2 { 2 * ((rest - 2) / 1798) } else { 0 };
+        }
+        let base = base as i64;
+        Self {
+            hours: (count / (3600 * base)) as u32,
+            minutes: (count / (60 * base) % 60) as u32,
+            seconds: (count / base % 60) as u32,
+            frames: (count % base) as u32,
+            drop_frame,
+        }
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(f, "{:02}:{:02}:{:02}{}{:02}", self.hours, self.minutes, self.seconds, separator, self.frames)
+    }
+}
+
+impl FromStr for Timecode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let drop_frame = value.contains(';');
+        let fields = value
+            .split([':', ';'])
+            .map(|field| field.trim().parse::<u32>().with_context(|| format!("Invalid timecode '{}'", value)))
+            .collect::<Result<Vec<_>>>()?;
+        let [hours, minutes, seconds, frames] = fields[..] else {
+            bail!("Expected a timecode as HH:MM:SS:FF, got '{}'", value);
+        };
+        if hours > 23 || minutes > 59 || seconds > 59 || frames > 29 {
+            bail!("Timecode '{}' is out of range", value);
+        }
+        Ok(Self { hours, minutes, seconds, frames, drop_frame })
+    }
+}
+
+impl Serialize for Timecode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A decoded LTC frame and the sample its first bit starts at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LtcFrame {
+    pub timecode: Timecode,
+    pub position: f64,
+}
+
+/// Decodes the forward-running LTC frames in `mono`.
+pub fn decode(mono: &[f32], sample_rate: u32) -> Vec<LtcFrame> {
+    let transitions = transitions(mono);
+    let intervals: Vec<f64> = transitions.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let Some(mut period) = bit_period(&intervals, sample_rate) else {
+        return Vec::new();
+    };
+
+    let mut frames = Vec::new();
+    let mut bits: Vec<(u8, f64)> = Vec::new();
+    let mut half: Option<f64> = None;
+    for (&interval, &start) in intervals.iter().zip(&transitions) {
+        if !(0.25 * period..1.5 * period).contains(&interval) {
+            // A dropout or glitch; start over
+            bits.clear();
+            half = None;
+            continue;
+        }
+        if interval > 0.75 * period {
+            period += 0.1 * (interval - period);
+            half = None;
+            bits.push((0, start));
+        } else {
+            period += 0.1 * (2.0 * interval - period);
+            match half.take() {
+                Some(first) => bits.push((1, first)),
+                None => half = Some(start),
+            }
+        }
+        if bits.len() >= FRAME_BITS && bits[bits.len() - SYNC.len()..].iter().map(|&(bit, _)| bit).eq(SYNC) {
+            let frame = &bits[bits.len() - FRAME_BITS..];
+            if let Some(timecode) = parse_frame(frame) {
+                frames.push(LtcFrame { timecode, position: frame[0].1 });
+            }
+            bits.clear();
+        }
+    }
+    frames
+}
+
+/// Sign changes of `mono` past a hysteresis band, at the fractional
+/// sample where each crosses zero.
+fn transitions(mono: &[f32]) -> Vec<f64> {
+    let mean = mono.iter().map(|&x| x as f64).sum::<f64>() / mono.len().max(1) as f64;
+    let peak = mono.iter().map(|&x| (x as f64 - mean).abs()).fold(0.0, f64::max);
+    let threshold = HYSTERESIS as f64 * peak;
+    let mut transitions = Vec::new();
+    let mut high: Option<bool> = None;
+    let mut crossing = 0.0;
+    for (i, pair) in mono.windows(2).enumerate() {
+        let (a, b) = (pair[0] as f64 - mean, pair[1] as f64 - mean);
+        if (a < 0.0) != (b < 0.0) {
+            crossing = i as f64 + a / (a - b);
+        }
+        let state = if b > threshold {
+            Some(true)
+        } else if b < -threshold {
+            Some(false)
+        } else {
+            continue;
+        };
+        if high.is_some() && high != state {
+            transitions.push(crossing);
+        }
+        high = state;
+    }
+    transitions
+}
+
+/// Length of one bit in samples, from the two clusters of transition
+/// intervals: half bits within ones and whole bits for zeros.
+fn bit_period(intervals: &[f64], sample_rate: u32) -> Option<f64> {
+    let shortest = sample_rate as f64 / (FRAME_BITS as f64 * MAX_FPS) / 2.0 * 0.8;
+    let longest = sample_rate as f64 / (FRAME_BITS as f64 * MIN_FPS) * 1.2;
+    let plausible: Vec<f64> = intervals.iter().copied().filter(|interval| (shortest..longest).contains(interval)).collect();
+    let (mut short, mut long) = plausible.iter().fold((f64::MAX, 0.0f64), |(low, high), &x| (low.min(x), high.max(x)));
+    if plausible.len() < FRAME_BITS {
+        return None;
+    }
+    for _ in 0..10 {
+        let split = (short + long) / 2.0;
+        let mean = |values: Vec<f64>| values.iter().sum::<f64>() / values.len().max(1) as f64;
+        short = mean(plausible.iter().copied().filter(|&x| x < split).collect());
+        long = mean(plausible.iter().copied().filter(|&x| x >= split).collect());
+    }
+    (1.6..2.4).contains(&(long / short)).then_some(long)
+}
+
+/// Reads the timecode of a frame's bits, if its digits are valid BCD.
+fn parse_frame(frame: &[(u8, f64)]) -> Option<Timecode> {
+    let field = |start: usize, len: usize| -> u32 { (0..len).map(|i| (frame[start + i].0 as u32) << i).sum() };
+    let digits = |units: u32, tens: u32| (units <= 9).then_some(tens * 10 + units);
+    let timecode = Timecode {
+        frames: digits(field(0, 4), field(8, 2))?,
+        seconds: digits(field(16, 4), field(24, 3))?,
+        minutes: digits(field(32, 4), field(40, 3))?,
+        hours: digits(field(48, 4), field(56, 2))?,
+        drop_frame: frame[10].0 == 1,
+    };
+    (timecode.hours < 24 && timecode.minutes < 60 && timecode.seconds < 60 && timecode.frames < 30).then_some(timecode)
+}
+
+/// Timecode mapped onto a recording's samples by a line fitted through
+/// its decoded frames.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimecodeTrack {
+    /// Frames per second the timecode runs at
+    pub fps: f64,
+    pub drop_frame: bool,
+    /// First decoded frame and the second it starts at
+    pub first: Timecode,
+    pub first_seconds: f64,
+    /// Timecode at the first sample, and how many frames past it the
+    /// sample lies
+    pub start: Timecode,
+    pub start_subframe: f64,
+    /// Timecode frames per second of audio, as measured
+    pub measured_fps: f64,
+    /// How much faster the timecode runs than the recording's clock, in
+    /// parts per million
+    pub drift_ppm: f64,
+    pub frames_decoded: usize,
+    /// Places where the timecode jumps instead of counting on
+    pub discontinuities: usize,
+    #[serde(skip)]
+    start_count: f64,
+    #[serde(skip)]
+    frames_per_sample: f64,
+}
+
+impl TimecodeTrack {
+    /// Fits the decoded `frames` of a `sample_rate` recording. The frame
+    /// rate is taken from `fps` if given, otherwise 29.97 for drop frame or
+    /// the highest frame number plus one.
+    pub fn fit(frames: &[LtcFrame], sample_rate: u32, fps: Option<f64>) -> Result<Self> {
+        let Some(first) = frames.first() else {
+            bail!("No LTC timecode found");
+        };
+        let drop_frame = first.timecode.drop_frame;
+        let fps = match fps {
+            Some(fps) => fps,
+            None if drop_frame => 30000.0 / 1001.0,
+            None => (frames.iter().map(|frame| frame.timecode.frames).max().unwrap_or(0) + 1) as f64,
+        };
+        let base = fps.round() as u32;
+        let rate = sample_rate as f64;
+
+        // Fit the run of frames up to the first jump
+        let counts: Vec<(f64, f64)> = frames.iter().map(|frame| (frame.position, frame.timecode.count(base) as f64)).collect();
+        let jumps: Vec<usize> = counts
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| ((pair[1].1 - pair[0].1) - (pair[1].0 - pair[0].0) * fps / rate).abs() > 0.5)
+            .map(|(i, _)| i + 1)
+            .collect();
+        let run = &counts[..jumps.first().copied().unwrap_or(counts.len())];
+        let frames_per_sample = if run.len() > 1 {
+            let n = run.len() as f64;
+            let (mean_x, mean_y) = (run.iter().map(|p| p.0).sum::<f64>() / n, run.iter().map(|p| p.1).sum::<f64>() / n);
+            let covariance: f64 = run.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+            let variance: f64 = run.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+            covariance / variance
+        } else {
+            fps / rate
+        };
+        let start_count = run.iter().map(|(x, y)| y - frames_per_sample * x).sum::<f64>() / run.len() as f64;
+        let measured_fps = frames_per_sample * rate;
+
+        Ok(Self {
+            fps,
+            drop_frame,
+            first: first.timecode,
+            first_seconds: first.position / rate,
+            start: Timecode::from_count(start_count.floor() as i64, base, drop_frame),
+            start_subframe: start_count - start_count.floor(),
+            measured_fps,
+            drift_ppm: (measured_fps / fps - 1.0) * 1e6,
+            frames_decoded: frames.len(),
+            discontinuities: jumps.len(),
+            start_count,
+            frames_per_sample,
+        })
+    }
+
+    /// Sample at which `timecode` starts, which may lie outside the
+    /// recording.
+    pub fn sample_at(&self, timecode: &Timecode) -> f64 {
+        let count = Timecode { drop_frame: self.drop_frame, ..*timecode }.count(self.fps.round() as u32) as f64;
+        (count - self.start_count) / self.frames_per_sample
+    }
+}
@@ -0,0 +1,147 @@
+//! Matching the long-term spectrum of one recording to another's, e.g.
+//! stems recorded on different days: by its overall tilt, or in full
+//! through a corrective FIR.
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
+
+use super::analysis::{long_term_spectrum, smoothed_spectrum, LONG_TERM_FFT_SIZE};
+use super::spectral::process_frames;
+use super::stft::hann_window;
+
+/// Tilt of pink noise's long-term spectrum, which falls by half its power
+/// per octave.
+pub const PINK_TILT_DB_PER_OCTAVE: f32 = -3.0;
+/// Frequency a tilt pivots around, keeping its level.
+pub const PIVOT_HZ: f32 = 1000.0;
+/// Range the tilt is fitted over, leaving out rumble and the top octave.
+const TILT_RANGE_HZ: (f32, f32) = (50.0, 16_000.0);
+/// Smoothing of the spectrum the tilt is fitted to, in octaves.
+const TILT_SMOOTHING_OCTAVES: f32 = 1.0 / 3.0;
+/// Points further below the spectrum's peak are noise and left out.
+const TILT_FLOOR_DB: f32 = 80.0;
+/// STFT frame length the matching EQ is applied with.
+const EQ_WINDOW_SIZE: usize = 4096;
+/// Deepest cut the matching EQ makes, where the reference has next to
+/// nothing the source has.
+const MAX_CUT_DB: f32 = 60.0;
+
+/// Slope of the least-squares line through the mono signal's smoothed
+/// long-term spectrum, in dB per octave.
+pub fn spectral_tilt(samples: &[f32], sample_rate: u32) -> Result<f32> {
+    let power = long_term_spectrum(samples, LONG_TERM_FFT_SIZE)?;
+    let grid = smoothed_spectrum(&power, sample_rate, TILT_SMOOTHING_OCTAVES);
+    let peak = grid.iter().map(|&(_, db)| db).fold(f32::MIN, f32::max);
+    let points: Vec<(f32, f32)> = grid
+        .into_iter()
+        .filter(|&(f, db)| f >= TILT_RANGE_HZ.0 && f <= TILT_RANGE_HZ.1 && db > peak - TILT_FLOOR_DB)
+        .map(|(f, db)| (f.log2(), db))
+        .collect();
+    if points.len() < 2 {
+        bail!("Not enough signal between {} Hz and {} Hz to measure a spectral tilt", TILT_RANGE_HZ.0, TILT_RANGE_HZ.1);
+    }
+
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let covariance: f32 = points.iter().map(|&(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f32 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+    Ok(covariance / variance)
+}
+
+/// Tilts interleaved `samples` by `db_per_octave` around [`PIVOT_HZ`],
+/// limiting the gain at either end of the spectrum to `max_db`.
+pub fn apply_tilt(samples: &mut [f32], channels: usize, sample_rate: u32, db_per_octave: f32, max_db: f32) -> Result<()> {
+    let df = sample_rate as f32 / EQ_WINDOW_SIZE as f32;
+    let gains: Vec<f32> = (0..=EQ_WINDOW_SIZE / 2)
+        .map(|bin| {
+            let octaves = (bin.max(1) as f32 * df / PIVOT_HZ).log2();
+            10f32.powf((db_per_octave * octaves).clamp(-max_db, max_db) / 20.0)
+        })
+        .collect();
+    process_frames(samples, channels, EQ_WINDOW_SIZE, |_, _, spectrum| {
+        spectrum.iter_mut().zip(&gains).for_each(|(bin, gain)| *bin *= gain);
+        Ok(())
+    })
+}
+
+/// Gain in dB per bin of a [`LONG_TERM_FFT_SIZE`] transform that brings
+/// the mono `source`'s long-term spectrum to `reference`'s, both smoothed
+/// over `smoothing_octaves` around each bin. Boosts stop at
+/// `max_boost_db`, so bands the source barely has aren't raised into
+/// noise.
+pub fn matching_curve(source: &[f32], reference: &[f32], smoothing_octaves: f32, max_boost_db: f32) -> Result<Vec<f32>> {
+    let smooth = |power: Vec<f32>| {
+        let half_window = 2f32.powf(smoothing_octaves / 2.0);
+        let mut prefix = vec![0.0f64; power.len() + 1];
+        for (i, &p) in power.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + p as f64;
+        }
+        (0..power.len())
+            .map(|bin| {
+                let first = ((bin as f32 / half_window).round() as usize).min(bin);
+                let last = ((bin as f32 * half_window).round() as usize).clamp(bin, power.len() - 1);
+                let mean = (prefix[last + 1] - prefix[first]) / (last + 1 - first) as f64;
+                10.0 * (mean as f32).max(1e-12).log10()
+            })
+            .collect::<Vec<f32>>()
+    };
+    let source = smooth(long_term_spectrum(source, LONG_TERM_FFT_SIZE)?);
+    let reference = smooth(long_term_spectrum(reference, LONG_TERM_FFT_SIZE)?);
+    Ok(source
+        .iter()
+        .zip(&reference)
+        .map(|(source, reference)| (reference - source).clamp(-MAX_CUT_DB, max_boost_db))
+        .collect())
+}
+
+/// Linear-phase FIR with the magnitude response `gains_db`, given per bin
+/// from DC to Nyquist. It's twice as long as the bins are apart, and
+/// delays by half its length.
+pub fn design_fir(gains_db: &[f32]) -> Result<Vec<f32>> {
+    if gains_db.len() < 2 {
+        bail!("A FIR needs a response at two or more frequencies");
+    }
+    let size = 2 * (gains_db.len() - 1);
+    let ifft = RealFftPlanner::<f32>::new().plan_fft_inverse(size);
+    let mut spectrum: Vec<Complex<f32>> = gains_db.iter().map(|db| Complex::new(10f32.powf(db / 20.0), 0.0)).collect();
+    let mut impulse = ifft.make_output_vec();
+    ifft.process(&mut spectrum, &mut impulse).with_context(|| "Failed to design the FIR")?;
+
+    // Centre the zero-phase response and taper its ends
+    let window = hann_window(size);
+    Ok((0..size).map(|n| impulse[(n + size / 2) % size] / size as f32 * window[n]).collect())
+}
+
+/// Filters each channel of interleaved `samples` with a [`design_fir`]
+/// FIR, compensating its delay so the output stays aligned.
+pub fn apply_fir(samples: &mut [f32], channels: usize, fir: &[f32]) -> Result<()> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let size = (frames + fir.len()).next_power_of_two();
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(size);
+    let ifft = planner.plan_fft_inverse(size);
+
+    let mut padded = vec![0.0f32; size];
+    padded[..fir.len()].copy_from_slice(fir);
+    let mut response = fft.make_output_vec();
+    fft.process(&mut padded, &mut response).with_context(|| "Failed to transform the FIR")?;
+
+    let delay = fir.len() / 2;
+    let mut spectrum = fft.make_output_vec();
+    for channel in 0..channels {
+        let mut signal = vec![0.0f32; size];
+        for (slot, sample) in signal.iter_mut().zip(samples.iter().skip(channel).step_by(channels)) {
+            *slot = *sample;
+        }
+        fft.process(&mut signal, &mut spectrum).with_context(|| "Failed to transform the input")?;
+        spectrum.iter_mut().zip(&response).for_each(|(bin, gain)| *bin *= gain);
+        ifft.process(&mut spectrum, &mut signal).with_context(|| "Failed to filter the input")?;
+        for (sample, filtered) in samples.iter_mut().skip(channel).step_by(channels).zip(&signal[delay..]) {
+            *sample = filtered / size as f32;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Separation quality against reference stems, in the style of BSS Eval
+//! with time-invariant gains: each estimate is projected onto the
+//! references to split it into target, interference and artifacts.
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// Regularization added to the reference Gram matrix diagonal.
+const GRAM_EPSILON: f64 = 1e-9;
+
+/// Quality of one separated band against its reference, in dB.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeparationMetrics {
+    /// Reference energy over the energy of the plain difference
+    pub sdr_db: f32,
+    /// Like SDR, with the reference optimally scaled first
+    pub si_sdr_db: f32,
+    /// Target energy over the energy explained by the other references
+    pub sir_db: f32,
+    /// Energy explained by the references over the unexplained rest
+    pub sar_db: f32,
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter().zip(b).map(|(&x, &y)| x as f64 * y as f64).sum()
+}
+
+fn ratio_db(numerator: f64, denominator: f64) -> f32 {
+    (10.0 * (numerator.max(1e-20) / denominator.max(1e-20)).log10()) as f32
+}
+
+/// Solves the symmetric positive definite system `a x = b` by Gaussian
+/// elimination.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs())).unwrap();
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (x, &p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+            b[col + 1 + offset] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let rest: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - rest) / a[row][row];
+    }
+    x
+}
+
+/// Scores each estimate against the reference at the same index. Signals
+/// are compared sample by sample over the shortest length, so
+/// interleaved channels must share a layout.
+pub fn evaluate(estimates: &[&[f32]], references: &[&[f32]]) -> Result<Vec<SeparationMetrics>> {
+    if estimates.len() != references.len() {
+        bail!("Got {} estimates but {} references", estimates.len(), references.len());
+    }
+    let len = estimates.iter().chain(references).map(|s| s.len()).min().unwrap_or(0);
+    if len == 0 {
+        bail!("Cannot evaluate empty signals");
+    }
+    let references: Vec<&[f32]> = references.iter().map(|r| &r[..len]).collect();
+
+    let mut gram: Vec<Vec<f64>> = references
+        .iter()
+        .map(|a| references.iter().map(|b| dot(a, b)).collect())
+        .collect();
+    let scale = gram.iter().enumerate().map(|(i, row)| row[i]).fold(0.0, f64::max).max(1e-20);
+    for (i, row) in gram.iter_mut().enumerate() {
+        row[i] += GRAM_EPSILON * scale;
+    }
+
+    Ok(estimates
+        .iter()
+        .zip(&references)
+        .map(|(estimate, reference)| {
+            let estimate = &estimate[..len];
+            let reference_energy = dot(reference, reference);
+
+            let difference: f64 = estimate.iter().zip(reference.iter()).map(|(&e, &r)| (e as f64 - r as f64).powi(2)).sum();
+
+            // Projection onto this reference alone
+            let gain = dot(reference, estimate) / reference_energy.max(1e-20);
+            let target: Vec<f64> = reference.iter().map(|&r| gain * r as f64).collect();
+            let target_energy: f64 = target.iter().map(|t| t * t).sum();
+
+            // Projection onto all references
+            let weights = solve(gram.clone(), references.iter().map(|r| dot(r, estimate)).collect());
+            let mut explained = vec![0.0f64; len];
+            for (weight, r) in weights.iter().zip(&references) {
+                for (e, &x) in explained.iter_mut().zip(r.iter()) {
+                    *e += weight * x as f64;
+                }
+            }
+            let interference: f64 = explained.iter().zip(&target).map(|(e, t)| (e - t).powi(2)).sum();
+            let artifacts: f64 = estimate.iter().zip(&explained).map(|(&s, e)| (s as f64 - e).powi(2)).sum();
+            let explained_energy: f64 = explained.iter().map(|e| e * e).sum();
+            let residual: f64 = estimate.iter().zip(&target).map(|(&s, t)| (s as f64 - t).powi(2)).sum();
+
+            SeparationMetrics {
+                sdr_db: ratio_db(reference_energy, difference),
+                si_sdr_db: ratio_db(target_energy, residual),
+                sir_db: ratio_db(target_energy, interference),
+                sar_db: ratio_db(explained_energy, artifacts),
+            }
+        })
+        .collect())
+}
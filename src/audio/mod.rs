@@ -1,151 +1,1012 @@
-use anyhow::{Result, Context};
-use dasp::Signal;
-use minimp3::{Decoder, Frame};
+use anyhow::{bail, Result, Context};
+use clap::ValueEnum;
+use realfft::num_traits::{AsPrimitive, Float, FloatConst};
 use num_complex::Complex;
-use realfft::RealFftPlanner;
-use std::{fs::File, io::BufReader, path::Path};
+use realfft::{FftNum, RealFftPlanner};
+use std::{fs::File, io::{BufReader, Read}, ops::Range, path::Path, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Instant};
 use tracing::{info, warn};
-use hound;
 
+pub mod aiff;
+pub mod align;
+pub mod analysis;
+pub mod biquad;
+pub mod breath;
+pub mod buffer;
+pub mod caf;
+pub mod channels;
+pub mod codec;
+pub mod chapters;
+pub mod cqt;
+pub mod design;
+pub mod dewow;
+pub mod dither;
+pub mod effects;
+pub mod excerpt;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod id3;
+pub mod iir;
+pub mod intermediate;
+pub mod limiter;
+pub mod loudness;
+pub mod ltc;
+pub mod matching;
+pub mod metrics;
+pub mod mp3;
+pub mod npy;
+#[cfg(feature = "opus")]
+pub mod opus;
+pub mod oversample;
+pub mod peaks;
+#[cfg(feature = "playback")]
+pub mod playback;
+pub mod resample;
+pub mod room_tone;
+pub mod scale;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod spectral;
+pub mod stft;
+pub mod stream;
+pub mod timings;
+pub mod vocoder;
+pub mod wav;
+pub mod weighting;
+pub mod wow_flutter;
+
+pub use buffer::{AudioBuffer, Layout};
+pub use design::{FilterDesign, FilterFamily};
+pub use dither::{BitDepth, Dither};
+pub use iir::FilterMode;
+pub use mp3::{DecodeErrorPolicy, DecodeStats, GaplessInfo};
+pub use stft::Window;
+pub use stream::StftProcessor;
+
+use stft::{apply_band_mask, apply_window, frame_offsets, is_silent, overlap_add};
+use timings::{Stage, Timings};
+
+/// Default FFT size used for the STFT band split.
+pub const WINDOW_SIZE: usize = 2048;
+
+/// Floating-point width of the DSP path: FFTs, filter state and
+/// overlap-add accumulation. Input and output stay 32-bit either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Precision {
+    #[default]
+    Single,
+    /// Measurement grade: no f32 rounding accumulates over long runs
+    Double,
+}
+
+/// Error returned when processing stops because the flag given to
+/// [`AudioProcessor::with_interrupt`] was set.
+#[derive(Debug, thiserror::Error)]
+#[error("Interrupted")]
+pub struct Interrupted;
+
+/// Averages interleaved channels into a mono signal.
+pub fn mixdown(samples: &[f32], channels: usize) -> Vec<f32> {
+    let channels = channels.max(1);
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Interleaves a band split from [`AudioProcessor::frame`]'s buffer, cut
+/// back to the `len` samples it was framed from.
+fn unframe(band: &AudioBuffer, len: usize) -> Vec<f32> {
+    let mut samples = band.to_interleaved();
+    samples.truncate(len);
+    samples
+}
+
+/// Configures an [`AudioProcessor`]'s DSP settings up front; start from
+/// [`AudioProcessor::builder`].
+#[derive(Debug, Clone)]
+pub struct AudioProcessorBuilder {
+    sample_rate: u32,
+    channels: u32,
+    fft_size: usize,
+    window: Window,
+    filter_mode: FilterMode,
+    filter_design: FilterDesign,
+    precision: Precision,
+    threads: usize,
+}
+
+impl Default for AudioProcessorBuilder {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            channels: 2,
+            fft_size: WINDOW_SIZE,
+            window: Window::default(),
+            filter_mode: FilterMode::default(),
+            filter_design: FilterDesign::default(),
+            precision: Precision::default(),
+            threads: 1,
+        }
+    }
+}
+
+impl AudioProcessorBuilder {
+    /// Sample rate assumed until a file is loaded (default 44100 Hz).
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Channel count assumed until a file is loaded (default 2).
+    pub fn channels(mut self, channels: u32) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// STFT frame length (default [`WINDOW_SIZE`]).
+    pub fn fft_size(mut self, fft_size: usize) -> Self {
+        self.fft_size = fft_size;
+        self
+    }
+
+    pub fn window(mut self, window: Window) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// STFT masks or IIR filters for band splits.
+    pub fn filter_mode(mut self, filter_mode: FilterMode) -> Self {
+        self.filter_mode = filter_mode;
+        self
+    }
+
+    pub fn filter_design(mut self, filter_design: FilterDesign) -> Self {
+        self.filter_design = filter_design;
+        self
+    }
+
+    pub fn precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Channels processed in parallel by the STFT split (default 1).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn build(self) -> Result<AudioProcessor> {
+        if self.sample_rate == 0 {
+            bail!("The sample rate must be positive");
+        }
+        if self.channels == 0 {
+            bail!("The channel count must be positive");
+        }
+        if self.fft_size < 2 || !self.fft_size.is_multiple_of(2) {
+            bail!("The FFT size must be even and at least 2, got {}", self.fft_size);
+        }
+        if self.threads == 0 {
+            bail!("At least one thread is required");
+        }
+        self.filter_design.validate()?;
+
+        Ok(AudioProcessor {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            window_size: self.fft_size,
+            window: self.window,
+            filter_mode: self.filter_mode,
+            filter_design: self.filter_design,
+            decode_error_policy: DecodeErrorPolicy::default(),
+            decode_stats: DecodeStats::default(),
+            bit_depth: BitDepth::default(),
+            dither: Dither::default(),
+            seed: 0,
+            bext: None,
+            force_rf64: false,
+            opus_bitrate_kbps: 96,
+            ambisonics: None,
+            precision: self.precision,
+            threads: self.threads,
+            silence_floor: 0.0,
+            timings: None,
+            interrupt: None,
+            codecs: codec::Registry::default(),
+        })
+    }
+}
+
+/// Loads, splits and saves audio. Loading a file adopts its sample rate
+/// and channel count, so one processor can be reused across files.
+#[derive(Clone)]
 pub struct AudioProcessor {
     sample_rate: u32,
     channels: u32,
+    window_size: usize,
+    window: Window,
+    filter_mode: FilterMode,
+    filter_design: FilterDesign,
+    decode_error_policy: DecodeErrorPolicy,
+    decode_stats: DecodeStats,
+    bit_depth: BitDepth,
+    dither: Dither,
+    seed: u64,
+    bext: Option<wav::Bext>,
+    force_rf64: bool,
+    opus_bitrate_kbps: u32,
+    ambisonics: Option<channels::Ambisonics>,
+    precision: Precision,
+    threads: usize,
+    silence_floor: f32,
+    timings: Option<Arc<Timings>>,
+    interrupt: Option<Arc<AtomicBool>>,
+    codecs: codec::Registry,
 }
 
 impl AudioProcessor {
+    pub fn builder() -> AudioProcessorBuilder {
+        AudioProcessorBuilder::default()
+    }
+
+    /// A processor with every default, the same as `builder().build()`.
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            sample_rate: 44100,  // Default sample rate
-            channels: 2,         // Default stereo
-        })
+        Self::builder().build()
+    }
+
+    /// Sets the sample rate used to map cutoff frequencies to FFT bins and
+    /// written to output files.
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Sets the channel count written to output files.
+    pub fn with_channels(mut self, channels: u32) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Selects between STFT masks and IIR filters for band splits.
+    pub fn with_filter_mode(mut self, filter_mode: FilterMode) -> Self {
+        self.filter_mode = filter_mode;
+        self
+    }
+
+    /// Sets the filter family and order used by the IIR filter modes.
+    pub fn with_filter_design(mut self, filter_design: FilterDesign) -> Self {
+        self.filter_design = filter_design;
+        self
+    }
+
+    /// Sets the FFT size used by [`separate_frequencies`](Self::separate_frequencies).
+    pub fn with_window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// Sets the STFT analysis and synthesis window.
+    pub fn with_window(mut self, window: Window) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Sets how many channels the STFT split processes in parallel.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
     }
 
-    pub fn load_audio<P: AsRef<Path>>(&self, path: P) -> Result<Vec<f32>> {
+    /// Sets how undecodable MP3 data is handled.
+    pub fn with_decode_error_policy(mut self, policy: DecodeErrorPolicy) -> Self {
+        self.decode_error_policy = policy;
+        self
+    }
+
+    /// Sets the sample format written by [`save_audio`](Self::save_audio).
+    pub fn with_bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    /// Sets the dither for integer outputs and the seed its noise is drawn
+    /// from. Each file gets its own stream derived from the seed and its
+    /// file name, so a render is bit-identical whenever it is repeated.
+    pub fn with_dither(mut self, dither: Dither, seed: u64) -> Self {
+        self.dither = dither;
+        self.seed = seed;
+        self
+    }
+
+    /// Writes Broadcast Wave metadata into every saved file, appending a
+    /// coding history line that describes the file's format.
+    pub fn with_bext(mut self, bext: wav::Bext) -> Self {
+        self.bext = Some(bext);
+        self
+    }
+
+    /// Writes RF64 even when a file would fit a RIFF header; larger files
+    /// always use RF64.
+    pub fn with_rf64(mut self, force_rf64: bool) -> Self {
+        self.force_rf64 = force_rf64;
+        self
+    }
+
+    /// Sets the bitrate of files saved with an `.opus` extension.
+    pub fn with_opus_bitrate(mut self, kbps: u32) -> Self {
+        self.opus_bitrate_kbps = kbps;
+        self
+    }
+
+    /// Marks the channels as ambisonic components, so saved files carry no
+    /// speaker positions.
+    pub fn with_ambisonics(mut self, ambisonics: channels::Ambisonics) -> Self {
+        self.ambisonics = Some(ambisonics);
+        self
+    }
+
+    /// Sets the floating-point width of the FFT and IIR band split.
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Skips the FFT work for STFT windows whose peak stays below `dbfs`,
+    /// leaving them silent. By default only digitally silent windows are
+    /// skipped, which doesn't change the output.
+    pub fn with_silence_threshold(mut self, dbfs: f32) -> Self {
+        self.silence_floor = 10f32.powf(dbfs / 20.0);
+        self
+    }
+
+    /// Records the time spent in each stage into `timings`.
+    pub fn with_timings(mut self, timings: Arc<Timings>) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    pub fn timings(&self) -> Option<&Timings> {
+        self.timings.as_deref()
+    }
+
+    /// Stops the STFT split with [`Interrupted`] at the next window once
+    /// `flag` is set.
+    pub fn with_interrupt(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /// Reads and writes files through `codecs` instead of the built-in
+    /// formats alone.
+    pub fn with_codecs(mut self, codecs: codec::Registry) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Runs `f`, timing it against `stage` if timings are recorded.
+    fn timed<T>(&self, stage: Stage, samples: usize, f: impl FnOnce() -> T) -> T {
+        match &self.timings {
+            Some(timings) => timings.time(stage, samples, f),
+            None => f(),
+        }
+    }
+
+    /// Statistics from the most recent MP3 decode.
+    pub fn decode_stats(&self) -> &DecodeStats {
+        &self.decode_stats
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Loads a file into a planar buffer through the decoder the codec
+    /// registry picks by extension: the built-in WAV, MP3 and intermediate
+    /// decoders or any added with [`with_codecs`](Self::with_codecs), with
+    /// unknown extensions read as MP3. Adopts the file's sample rate and
+    /// channel count for output, and the buffer carries the ambisonic
+    /// layout if one was set.
+    pub fn load_buffer<P: AsRef<Path>>(&mut self, path: P) -> Result<AudioBuffer> {
+        let start = Instant::now();
+        let decoded = self.decode_file(path)?;
+        let buffer = self.adopt(decoded);
+        if let Some(timings) = &self.timings {
+            timings.record(Stage::Decode, start.elapsed(), buffer.frames() * buffer.num_channels());
+        }
+        Ok(buffer)
+    }
+
+    /// [`load_buffer`](Self::load_buffer) into interleaved samples, for
+    /// processing that works on interleaved frames.
+    pub fn load_audio<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<f32>> {
+        Ok(self.load_buffer(path)?.to_interleaved())
+    }
+
+    /// Decodes a file as [`load_buffer`](Self::load_buffer) does without
+    /// adopting its format, so it can be decoded ahead on another thread
+    /// and handed to [`adopt`](Self::adopt) later.
+    pub fn decode_file<P: AsRef<Path>>(&self, path: P) -> Result<codec::Decoded> {
         info!("Loading audio file: {:?}", path.as_ref());
         
-        let mut decoder = Decoder::new(BufReader::new(File::open(&path)?));
-        let mut samples = Vec::new();
-        
-        let mut frame_count = 0;
-        while let Ok(Frame { data, .. }) = decoder.next_frame() {
-            frame_count += 1;
-            info!("Processing frame {}", frame_count);
-            
-            // Convert i16 samples to f32 and normalize to [-1.0, 1.0]
-            samples.extend(data.iter().map(|&s| s as f32 / 32768.0));
+        let mut reader = BufReader::new(File::open(&path)?);
+        self.decode_with(self.codecs.decoder(path.as_ref())?, &mut reader)
+    }
+
+    /// Decodes `reader` with `decoder` under the decode error policy.
+    fn decode_with(&self, decoder: &dyn codec::Decoder, reader: &mut dyn Read) -> Result<codec::Decoded> {
+        let decoded = decoder.decode(reader, &codec::DecodeOptions { error_policy: self.decode_error_policy })?;
+        if decoded.buffer.sample_rate() == 0 {
+            bail!("{} decoder reported {} channels at 0 Hz", decoder.name(), decoded.buffer.num_channels());
         }
+        Ok(decoded)
+    }
+
+    /// Adopts the format `decoded` reports, returning its samples with the
+    /// ambisonic layout if one was set.
+    pub fn adopt(&mut self, decoded: codec::Decoded) -> AudioBuffer {
+        self.sample_rate = decoded.buffer.sample_rate();
+        self.channels = decoded.buffer.num_channels() as u32;
+        self.decode_stats = decoded.stats;
+        match self.ambisonics {
+            Some(convention) => decoded.buffer.with_layout(Layout::Ambisonic(convention)),
+            None => decoded.buffer,
+        }
+    }
+
+    /// Decodes an MP3 stream, handling corrupt data according to the
+    /// decode error policy.
+    pub fn decode_mp3<R: Read>(&mut self, mut reader: R) -> Result<AudioBuffer> {
+        let decoded = self.decode_with(&codec::Mp3, &mut reader)?;
+        Ok(self.adopt(decoded))
+    }
+
+    /// Decodes a saunds intermediate stream, narrowing its f64 samples.
+    pub fn decode_intermediate<R: Read>(&mut self, mut reader: R) -> Result<AudioBuffer> {
+        let decoded = self.decode_with(&codec::Intermediate, &mut reader)?;
+        Ok(self.adopt(decoded))
+    }
 
-        info!("Loaded {} samples from {} frames", samples.len(), frame_count);
-        Ok(samples)
+    /// Decodes a WAV stream (RIFF, RF64 or BW64).
+    pub fn decode_wav<R: Read>(&mut self, mut reader: R) -> Result<AudioBuffer> {
+        let decoded = self.decode_with(&codec::Wav, &mut reader)?;
+        Ok(self.adopt(decoded))
     }
 
+    /// Saves a planar buffer, which must match this processor's sample rate
+    /// and channels, as WAV, or by extension as AIFF (`.aif`, `.aiff`), CAF
+    /// (`.caf`) or Ogg Opus (`.opus`). Broadcast Wave and RF64 settings only
+    /// apply to WAV.
+    pub fn save_buffer<P: AsRef<Path>>(&self, path: P, buffer: &AudioBuffer) -> Result<()> {
+        self.check_format(buffer)?;
+        self.timed(Stage::Encode, buffer.frames() * buffer.num_channels(), || self.write_audio(path.as_ref(), buffer))
+    }
+
+    /// [`save_buffer`](Self::save_buffer) for interleaved samples in this
+    /// processor's format.
     pub fn save_audio<P: AsRef<Path>>(&self, path: P, samples: &[f32]) -> Result<()> {
-        info!("Saving audio file: {:?}", path.as_ref());
+        self.save_buffer(path, &AudioBuffer::from_interleaved(samples, self.channels as usize, self.sample_rate))
+    }
+
+    fn check_format(&self, buffer: &AudioBuffer) -> Result<()> {
+        if (buffer.sample_rate(), buffer.num_channels()) != (self.sample_rate, self.channels as usize) {
+            bail!(
+                "Buffer is {} Hz with {} channels but the processor is set up for {} Hz with {}",
+                buffer.sample_rate(),
+                buffer.num_channels(),
+                self.sample_rate,
+                self.channels
+            );
+        }
+        Ok(())
+    }
+
+    fn write_audio(&self, path: &Path, buffer: &AudioBuffer) -> Result<()> {
+        info!("Saving audio file: {:?}", path);
         
-        let spec = hound::WavSpec {
+        let spec = self.output_spec();
+        let options = codec::EncodeOptions {
+            spec,
+            dither: self.dither,
+            seed: self.seed,
+            bext: self.output_bext(spec),
+            force_rf64: self.force_rf64,
+            opus_bitrate_kbps: self.opus_bitrate_kbps,
+        };
+        self.codecs.encoder(path)?.encode(path, buffer, &options)?;
+        info!("Successfully wrote {} frames", buffer.frames());
+        Ok(())
+    }
+
+    /// Format of saved files at the configured bit depth.
+    fn output_spec(&self) -> wav::Spec {
+        let bits = self.bit_depth.integer_bits();
+        wav::Spec {
             channels: self.channels as u16,
             sample_rate: self.sample_rate,
-            bits_per_sample: 32,
-            sample_format: hound::SampleFormat::Float,
-        };
+            bits_per_sample: bits.unwrap_or(32),
+            float: bits.is_none(),
+            channel_mask: match self.ambisonics {
+                Some(_) => 0,
+                None => channels::channel_mask(self.channels as usize),
+            },
+        }
+    }
+
+    /// The Broadcast Wave template, if any, with a coding history line for
+    /// a file in `spec`'s format.
+    fn output_bext(&self, spec: wav::Spec) -> Option<wav::Bext> {
+        self.bext.as_ref().map(|template| {
+            let mode = match self.channels {
+                1 => "mono",
+                2 => "stereo",
+                _ => "multitrack",
+            };
+            let mut bext = template.clone();
+            bext.coding_history.push_str(&format!(
+                "A=PCM,F={},W={},M={},T=saunds {}\r\n",
+                self.sample_rate,
+                spec.bits_per_sample,
+                mode,
+                env!("CARGO_PKG_VERSION")
+            ));
+            bext
+        })
+    }
+
+    /// Opens a WAV file for reading in pieces, adopting its sample rate and
+    /// channel count.
+    pub fn open_wav<P: AsRef<Path>>(&mut self, path: P) -> Result<wav::Reader<BufReader<File>>> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let reader = wav::Reader::new(BufReader::new(file))?;
+        self.sample_rate = reader.spec().sample_rate;
+        self.channels = reader.spec().channels as u32;
+        self.decode_stats = DecodeStats::default();
+        Ok(reader)
+    }
+
+    /// Starts a WAV file of `len` samples over all channels to be written in
+    /// pieces. The result is bit-identical to [`save_buffer`](Self::save_buffer)
+    /// writing the same samples at once.
+    pub fn create_wav<P: AsRef<Path>>(&self, path: P, len: u64) -> Result<WavStream> {
+        let path = path.as_ref();
+        info!("Saving audio file: {:?}", path);
+        let spec = self.output_spec();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(WavStream {
+            writer: wav::Writer::create(path, spec, len, self.output_bext(spec).as_ref(), self.force_rf64)?,
+            bits: self.bit_depth.integer_bits(),
+            dither: self.dither,
+            rng: dither::Rng::new(dither::stream_seed(self.seed, &name)),
+            timings: self.timings.clone(),
+            name: name.into_owned(),
+            clipped: 0,
+        })
+    }
+
+    /// Filters each channel of `buffer` in place through the weighting curve.
+    pub fn apply_weighting(&self, weighting: weighting::Weighting, buffer: &mut AudioBuffer) {
+        if weighting == weighting::Weighting::Z {
+            return;
+        }
+        info!("Applying {:?}-weighting", weighting);
+        let cascade = weighting.filter(buffer.sample_rate());
+        for channel in buffer.channels_mut() {
+            biquad::filter_interleaved(&cascade, channel, 1);
+        }
+    }
+
+    /// Checks that a pair of cutoffs describes a usable two-band split for
+    /// the current sample rate: both non-negative, below Nyquist and in
+    /// ascending order. Warns when a cutoff is finer than one FFT bin.
+    pub fn validate_cutoffs(&self, low_cutoff: f32, high_cutoff: f32) -> Result<()> {
+        let nyquist = self.sample_rate as f32 / 2.0;
         
-        let mut writer = hound::WavWriter::create(path, spec)
-            .with_context(|| "Failed to create WAV writer")?;
+        for (name, cutoff) in [("low", low_cutoff), ("high", high_cutoff)] {
+            if !cutoff.is_finite() || cutoff < 0.0 {
+                bail!("The {} cutoff must be a non-negative frequency, got {} Hz", name, cutoff);
+            }
+            if cutoff >= nyquist {
+                bail!(
+                    "The {} cutoff ({} Hz) must be below the Nyquist frequency ({} Hz) for {} Hz audio",
+                    name, cutoff, nyquist, self.sample_rate
+                );
+            }
+        }
         
-        for &sample in samples {
-            writer.write_sample(sample)
-                .with_context(|| "Failed to write sample")?;
+        if low_cutoff >= high_cutoff {
+            bail!(
+                "The low cutoff ({} Hz) must be below the high cutoff ({} Hz)",
+                low_cutoff, high_cutoff
+            );
+        }
+        
+        let freq_per_bin = self.sample_rate as f32 / self.window_size as f32;
+        for (name, cutoff) in [("Low", low_cutoff), ("High", high_cutoff)] {
+            if cutoff > 0.0 && cutoff < freq_per_bin {
+                warn!(
+                    "{} cutoff {} Hz is below the FFT frequency resolution of {:.1} Hz and cannot be placed accurately",
+                    name, cutoff, freq_per_bin
+                );
+            }
         }
         
-        writer.finalize()
-            .with_context(|| "Failed to finalize WAV file")?;
-            
-        info!("Successfully wrote {} samples", samples.len());
         Ok(())
     }
 
+    /// Splits `samples` into a low band containing everything below
+    /// `high_cutoff` and a high band containing everything from `low_cutoff`
+    /// upwards. The bands overlap between the two cutoffs.
     pub fn separate_frequencies(&self, samples: &[f32], low_cutoff: f32, high_cutoff: f32) -> Result<(Vec<f32>, Vec<f32>)> {
+        let (low_freq, high_freq) = self.separate_buffer(&self.frame(samples), low_cutoff, high_cutoff)?;
+        Ok((unframe(&low_freq, samples.len()), unframe(&high_freq, samples.len())))
+    }
+
+    /// [`separate_frequencies`](Self::separate_frequencies) on a planar
+    /// buffer, which must match this processor's sample rate and channels.
+    pub fn separate_buffer(&self, input: &AudioBuffer, low_cutoff: f32, high_cutoff: f32) -> Result<(AudioBuffer, AudioBuffer)> {
+        self.check_format(input)?;
         info!("Separating frequencies with cutoffs: low={}, high={}", low_cutoff, high_cutoff);
         
+        if self.filter_mode != FilterMode::Fft {
+            info!("Using {:?} IIR filters", self.filter_mode);
+            let crossover = self.crossover();
+            return Ok((crossover.lowpass_buffer(input, high_cutoff)?, crossover.highpass_buffer(input, low_cutoff)?));
+        }
+        
         // Convert cutoff frequencies to FFT bin indices
-        let window_size = 2048;
+        let (low_bin, high_bin) = self.cutoff_bins(self.window_size, low_cutoff, high_cutoff);
+        let bins = self.window_size / 2 + 1;
+        
+        let mut bands = self.process_bands(input, &[0..high_bin, low_bin..bins])?;
+        let high_freq = bands.pop().expect("two bands");
+        let low_freq = bands.pop().expect("two bands");
+        Ok((low_freq, high_freq))
+    }
+
+    /// Splits `samples` into `cutoffs.len() + 1` complementary bands at the
+    /// given ascending cutoff frequencies. The bands sum back to the input.
+    pub fn split_bands(&self, samples: &[f32], cutoffs: &[f32]) -> Result<Vec<Vec<f32>>> {
+        Ok(self.split_buffer(&self.frame(samples), cutoffs)?.iter().map(|band| unframe(band, samples.len())).collect())
+    }
+
+    /// [`split_bands`](Self::split_bands) on a planar buffer, which must
+    /// match this processor's sample rate and channels.
+    pub fn split_buffer(&self, input: &AudioBuffer, cutoffs: &[f32]) -> Result<Vec<AudioBuffer>> {
+        self.check_format(input)?;
+        info!("Splitting into {} bands at {:?} Hz", cutoffs.len() + 1, cutoffs);
+        
+        if cutoffs.windows(2).any(|pair| pair[0] > pair[1]) {
+            bail!("Band cutoffs must be in ascending order, got {:?}", cutoffs);
+        }
+        
+        if self.filter_mode != FilterMode::Fft {
+            info!("Using {:?} IIR filters", self.filter_mode);
+            return self.crossover().split_buffer(input, cutoffs);
+        }
+        
+        self.process_bands(input, &self.band_ranges(cutoffs))
+    }
+
+    /// Interleaved `samples` as a buffer in this processor's format. A
+    /// trailing partial frame is padded with silence rather than dropped.
+    fn frame(&self, samples: &[f32]) -> AudioBuffer {
+        let channels = self.channels.max(1) as usize;
+        if samples.len().is_multiple_of(channels) {
+            return AudioBuffer::from_interleaved(samples, channels, self.sample_rate);
+        }
+        let mut padded = samples.to_vec();
+        padded.resize(samples.len().next_multiple_of(channels), 0.0);
+        AudioBuffer::from_interleaved(&padded, channels, self.sample_rate)
+    }
+
+    /// FFT bins of each band between ascending `cutoffs`.
+    fn band_ranges(&self, cutoffs: &[f32]) -> Vec<Range<usize>> {
+        let nyquist = self.sample_rate as f32 / 2.0;
+        for &cutoff in cutoffs.iter().filter(|&&cutoff| cutoff >= nyquist) {
+            warn!("Cutoff {} Hz is at or above Nyquist ({} Hz); the bands above it are empty", cutoff, nyquist);
+        }
+        
+        let bins = self.window_size / 2 + 1;
+        let mut edges = vec![0];
+        edges.extend(cutoffs.iter().map(|&cutoff| self.frequency_bin(self.window_size, cutoff)));
+        edges.push(bins);
+        edges.windows(2).map(|pair| pair[0]..pair[1]).collect()
+    }
+
+    /// Streaming STFT keeping the bins from `low_cutoff` up to, but not
+    /// including, `high_cutoff`, at this processor's window size and
+    /// sample rate. Pushing a channel through it yields the same band as
+    /// [`split_bands`](Self::split_bands) with those cutoffs.
+    pub fn band_stream(&self, low_cutoff: f32, high_cutoff: f32) -> Result<StftProcessor> {
+        let bins = self.frequency_bin(self.window_size, low_cutoff)..self.frequency_bin(self.window_size, high_cutoff);
+        StftProcessor::with_window(self.window_size, self.window, move |spectrum: &mut [Complex<f32>]| {
+            for (bin, value) in spectrum.iter_mut().enumerate() {
+                if !bins.contains(&bin) {
+                    *value = Complex::new(0.0, 0.0);
+                }
+            }
+        })
+    }
+
+    /// Latency in samples that [`separate_frequencies`](Self::separate_frequencies)
+    /// introduces into its low and high bands. The STFT masks and
+    /// zero-phase filters have none.
+    pub fn separation_latency(&self, low_cutoff: f32, high_cutoff: f32) -> Result<(f64, f64)> {
+        if self.filter_mode == FilterMode::Fft {
+            return Ok((0.0, 0.0));
+        }
+        let crossover = self.crossover();
+        Ok((crossover.latency(&[], Some(high_cutoff))?, crossover.latency(&[low_cutoff], None)?))
+    }
+
+    /// Latency in samples of each band from [`split_bands`](Self::split_bands).
+    pub fn split_latencies(&self, cutoffs: &[f32]) -> Result<Vec<f64>> {
+        if self.filter_mode == FilterMode::Fft {
+            return Ok(vec![0.0; cutoffs.len() + 1]);
+        }
+        self.crossover().split_latencies(cutoffs)
+    }
+
+    fn crossover(&self) -> iir::Crossover {
+        iir::Crossover {
+            mode: self.filter_mode,
+            design: self.filter_design,
+            sample_rate: self.sample_rate,
+            channels: self.channels as usize,
+            precision: self.precision,
+        }
+    }
+
+    /// Runs the STFT over each channel of `input` and resynthesizes one
+    /// output per entry in `bands`, each keeping only the FFT bins in its
+    /// range. Up to `threads` channels are split at once, each thread
+    /// borrowing its channel from the input.
+    fn process_bands(&self, input: &AudioBuffer, bands: &[Range<usize>]) -> Result<Vec<AudioBuffer>> {
+        let channels = input.num_channels();
+        let mut outputs: Vec<Vec<Vec<f32>>> = vec![Vec::with_capacity(channels); bands.len()];
+        let all: Vec<usize> = (0..channels).collect();
+        for group in all.chunks(self.threads.max(1)) {
+            let rendered = std::thread::scope(|scope| {
+                let handles: Vec<_> = group
+                    .iter()
+                    .map(|&index| {
+                        scope.spawn(move || {
+                            info!("Processing channel {}/{}", index + 1, channels);
+                            self.process_channel(input.channel(index), bands)
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("channel thread panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?;
+            // Joined in channel order, so the output doesn't depend on the
+            // thread count
+            for channel_bands in rendered {
+                for (output, band) in outputs.iter_mut().zip(channel_bands) {
+                    output.push(band);
+                }
+            }
+        }
+        outputs.into_iter().map(|band| input.like(band)).collect()
+    }
+
+    /// [`process_bands`](Self::process_bands) for a single channel, at the
+    /// configured precision.
+    fn process_channel(&self, samples: &[f32], bands: &[Range<usize>]) -> Result<Vec<Vec<f32>>> {
+        match self.precision {
+            Precision::Single => self.process_channel_as::<f32>(samples, bands),
+            Precision::Double => self.process_channel_as::<f64>(samples, bands),
+        }
+    }
+
+    fn process_channel_as<T>(&self, samples: &[f32], bands: &[Range<usize>]) -> Result<Vec<Vec<f32>>>
+    where
+        T: FftNum + Float + FloatConst,
+        f32: AsPrimitive<T>,
+        T: AsPrimitive<f32>,
+    {
+        let samples: Vec<T> = samples.iter().map(|sample| sample.as_()).collect();
+        let window_size = self.window_size;
         let overlap = window_size / 2;
-        let freq_per_bin = self.sample_rate as f32 / window_size as f32;
-        let low_bin = (low_cutoff / freq_per_bin) as usize;
-        let high_bin = (high_cutoff / freq_per_bin) as usize;
         
-        info!("FFT parameters: window_size={}, overlap={}, bins: low={}, high={}", 
-             window_size, overlap, low_bin, high_bin);
+        info!("FFT parameters: window_size={}, overlap={}, bands: {:?}", window_size, overlap, bands);
         
         // Create FFT planner
         let mut planner = RealFftPlanner::new();
         let fft = planner.plan_fft_forward(window_size);
         let ifft = planner.plan_fft_inverse(window_size);
         
-        // Process audio in overlapping windows
-        let mut low_freq = vec![0.0; samples.len()];
-        let mut high_freq = vec![0.0; samples.len()];
-        let mut window = vec![0.0; window_size];
-        let mut spectrum = vec![Complex::new(0.0, 0.0); window_size / 2 + 1];
+        // Process audio in overlapping windows. All per-window buffers are
+        // allocated once up front and reused for every window.
+        let mut outputs = vec![vec![T::zero(); samples.len()]; bands.len()];
+        let mut window = fft.make_input_vec();
+        let mut spectrum = fft.make_output_vec();
+        let mut band_spectrum = fft.make_output_vec();
+        let mut band_window = ifft.make_output_vec();
+        let mut fft_scratch = fft.make_scratch_vec();
+        let mut ifft_scratch = ifft.make_scratch_vec();
         
-        // Hann window function for smooth transitions
-        let window_func: Vec<f32> = (0..window_size)
-            .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / window_size as f32).cos()))
-            .collect();
+        // Square-root Hann analysis and synthesis windows overlap-add to
+        // unity at 50% overlap
+        let window_func = self.window.coefficients::<T>(window_size);
+        let scale = T::one() / (window_size as f32).as_();
         
-        let total_windows = (samples.len() as f32 / overlap as f32).ceil() as usize;
+        let total_windows = frame_offsets(samples.len(), overlap).count();
         let mut processed_windows = 0;
+        let mut skipped_windows = 0;
+        let silence_floor: T = self.silence_floor.as_();
         
-        for chunk_start in (0..samples.len()).step_by(overlap) {
+        for offset in frame_offsets(samples.len(), overlap) {
             processed_windows += 1;
             if processed_windows % 100 == 0 {
                 info!("Processing window {}/{}", processed_windows, total_windows);
             }
             
-            // Fill window with samples
-            window.fill(0.0);
-            for i in 0..window_size {
-                if chunk_start + i < samples.len() {
-                    window[i] = samples[chunk_start + i] * window_func[i];
-                }
+            if self.interrupt.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                return Err(Interrupted.into());
             }
             
-            // Forward FFT
-            fft.process(&mut window, &mut spectrum)
-                .with_context(|| format!("Failed to perform forward FFT on window {}", processed_windows))?;
-            
-            // Separate frequencies
-            let mut low_spectrum = spectrum.clone();
-            let mut high_spectrum = spectrum.clone();
-            
-            // Apply frequency masks
-            for i in 0..spectrum.len() {
-                if i < low_bin {
-                    high_spectrum[i] = Complex::new(0.0, 0.0);
-                } else if i > high_bin {
-                    low_spectrum[i] = Complex::new(0.0, 0.0);
-                }
+            // Silent windows would only add (near) zeros to every band
+            if is_silent(&samples, offset, window_size, silence_floor) {
+                skipped_windows += 1;
+                continue;
             }
             
-            // Inverse FFT for both frequency ranges
-            let mut low_window = vec![0.0; window_size];
-            let mut high_window = vec![0.0; window_size];
+            // Fill window with samples
+            self.timed(Stage::Window, window_size, || apply_window(&samples, offset, &window_func, &mut window));
             
-            ifft.process(&mut low_spectrum, &mut low_window)
-                .with_context(|| format!("Failed to perform inverse FFT (low) on window {}", processed_windows))?;
-            ifft.process(&mut high_spectrum, &mut high_window)
-                .with_context(|| format!("Failed to perform inverse FFT (high) on window {}", processed_windows))?;
+            // Forward FFT
+            self.timed(Stage::Fft, window_size, || fft.process_with_scratch(&mut window, &mut spectrum, &mut fft_scratch))
+                .with_context(|| format!("Failed to perform forward FFT on window {}", processed_windows))?;
             
-            // Overlap-add to output
-            for i in 0..window_size {
-                if chunk_start + i < samples.len() {
-                    low_freq[chunk_start + i] += low_window[i] * window_func[i] / window_size as f32;
-                    high_freq[chunk_start + i] += high_window[i] * window_func[i] / window_size as f32;
-                }
+            for (bins, output) in bands.iter().zip(outputs.iter_mut()) {
+                // Separate frequencies
+                self.timed(Stage::Mask, window_size, || apply_band_mask(&spectrum, bins.clone(), &mut band_spectrum));
+                
+                // Inverse FFT for this frequency range
+                self.timed(Stage::Ifft, window_size, || {
+                    ifft.process_with_scratch(&mut band_spectrum, &mut band_window, &mut ifft_scratch)
+                })
+                .with_context(|| format!("Failed to perform inverse FFT (bins {:?}) on window {}", bins, processed_windows))?;
+                
+                // Overlap-add to output
+                self.timed(Stage::OverlapAdd, window_size, || overlap_add(&band_window, offset, &window_func, scale, output));
             }
         }
         
         info!("Frequency separation complete. Processed {} windows", processed_windows);
-        Ok((low_freq, high_freq))
+        if skipped_windows > 0 {
+            info!(
+                "Skipped {} of {} windows ({:.1}%) as silent",
+                skipped_windows,
+                total_windows,
+                100.0 * skipped_windows as f64 / total_windows as f64
+            );
+        }
+        Ok(outputs
+            .into_iter()
+            .map(|output| output.into_iter().map(|sample| sample.as_()).collect())
+            .collect())
     }
-} 
\ No newline at end of file
+
+    /// Same band split as [`separate_buffer`](Self::separate_buffer),
+    /// with the FFT work offloaded to the GPU in batches of windows.
+    #[cfg(feature = "gpu")]
+    pub fn separate_buffer_gpu(&self, stft: &gpu::GpuStft, input: &AudioBuffer, low_cutoff: f32, high_cutoff: f32) -> Result<(AudioBuffer, AudioBuffer)> {
+        self.check_format(input)?;
+        info!("Separating frequencies on GPU with cutoffs: low={}, high={}", low_cutoff, high_cutoff);
+        if self.window != Window::SqrtHann {
+            bail!("The GPU STFT only supports the square-root Hann window");
+        }
+
+        let (low_bin, high_bin) = self.cutoff_bins(stft.window_size(), low_cutoff, high_cutoff);
+        info!("GPU FFT parameters: window_size={}, batch_size={}, bins: low={}, high={}",
+             stft.window_size(), stft.batch_size(), low_bin, high_bin);
+
+        let mut low_freq = Vec::with_capacity(input.num_channels());
+        let mut high_freq = Vec::with_capacity(input.num_channels());
+        for channel in input.channels() {
+            let (low, high) = stft.separate(channel, high_bin, low_bin)?;
+            low_freq.push(low);
+            high_freq.push(high);
+        }
+        Ok((input.like(low_freq)?, input.like(high_freq)?))
+    }
+
+    /// Same split as [`split_buffer`](Self::split_buffer) in FFT mode, with
+    /// the FFT work offloaded to the GPU.
+    #[cfg(feature = "gpu")]
+    pub fn split_buffer_gpu(&self, stft: &gpu::GpuStft, input: &AudioBuffer, cutoffs: &[f32]) -> Result<Vec<AudioBuffer>> {
+        self.check_format(input)?;
+        info!("Splitting into {} bands on GPU at {:?} Hz", cutoffs.len() + 1, cutoffs);
+        if self.window != Window::SqrtHann {
+            bail!("The GPU STFT only supports the square-root Hann window");
+        }
+        if cutoffs.windows(2).any(|pair| pair[0] > pair[1]) {
+            bail!("Band cutoffs must be in ascending order, got {:?}", cutoffs);
+        }
+
+        let bins: Vec<usize> = cutoffs.iter().map(|&cutoff| self.frequency_bin(stft.window_size(), cutoff)).collect();
+        let mut outputs = vec![Vec::with_capacity(input.num_channels()); bins.len() + 1];
+        for channel in input.channels() {
+            for (output, band) in outputs.iter_mut().zip(stft.split_bands(channel, &bins)?) {
+                output.push(band);
+            }
+        }
+        outputs.into_iter().map(|band| input.like(band)).collect()
+    }
+
+    /// FFT bin ranges for the two-band split: the low band ends at the
+    /// first bin at or above `high_cutoff`, the high band starts at the
+    /// first bin at or above `low_cutoff`.
+    fn cutoff_bins(&self, window_size: usize, low_cutoff: f32, high_cutoff: f32) -> (usize, usize) {
+        (self.frequency_bin(window_size, low_cutoff), self.frequency_bin(window_size, high_cutoff))
+    }
+
+    /// Index of the first FFT bin whose center frequency is at or above
+    /// `frequency`. Cutoffs at or above Nyquist map past the last bin so
+    /// the band above them is empty.
+    fn frequency_bin(&self, window_size: usize, frequency: f32) -> usize {
+        let bins = window_size / 2 + 1;
+        if frequency >= self.sample_rate as f32 / 2.0 {
+            return bins;
+        }
+        let freq_per_bin = self.sample_rate as f32 / window_size as f32;
+        ((frequency / freq_per_bin).ceil() as usize).min(bins)
+    }
+}
+
+/// Warns that `clipped` samples of the file `name` were beyond full scale
+/// and clamped to it when quantized.
+pub(crate) fn warn_clipped(name: &str, clipped: usize) {
+    if clipped > 0 {
+        warn!("{} clipped: {} samples beyond full scale were clamped", name, clipped);
+    }
+}
+
+/// A WAV file being written in pieces, from [`AudioProcessor::create_wav`].
+/// Dither noise continues across pieces.
+pub struct WavStream {
+    writer: wav::Writer,
+    bits: Option<u16>,
+    dither: Dither,
+    rng: dither::Rng,
+    timings: Option<Arc<Timings>>,
+    name: String,
+    clipped: usize,
+}
+
+impl WavStream {
+    /// Appends the next frames of each channel, which must all be the same
+    /// length.
+    pub fn write(&mut self, channels: &[&[f32]]) -> Result<()> {
+        let start = Instant::now();
+        match self.bits {
+            Some(bits) => {
+                self.clipped += channels.iter().map(|channel| channel.iter().filter(|sample| sample.abs() > 1.0).count()).sum::<usize>();
+                self.writer.write(wav::Data::Int(&dither::quantize(channels, bits, self.dither, &mut self.rng)))?
+            }
+            None => self.writer.write(wav::Data::Float(channels))?,
+        }
+        if let Some(timings) = &self.timings {
+            timings.record(Stage::Encode, start.elapsed(), channels.iter().map(|channel| channel.len()).sum());
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        warn_clipped(&self.name, self.clipped);
+        self.writer.finish()
+    }
+}
@@ -0,0 +1,340 @@
+//! MP3 decoding on top of the raw minimp3 frame decoder, so corrupt or
+//! truncated streams can be detected and reported instead of silently
+//! resynchronized.
+
+use anyhow::{bail, Result};
+use minimp3::ffi;
+use std::mem;
+use tracing::{info, warn};
+
+/// What to do when part of an MP3 stream cannot be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DecodeErrorPolicy {
+    /// Abort decoding with an error
+    Fail,
+    /// Drop the undecodable frames and keep going
+    #[default]
+    Skip,
+    /// Replace the undecodable frames with silence to preserve timing
+    Pad,
+}
+
+/// Samples of delay introduced by the MP3 synthesis filterbank, which
+/// encoders account for in the LAME tag's delay and padding fields.
+const DECODER_DELAY: usize = 529;
+
+/// Encoder delay and padding read from a LAME tag, in samples per channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GaplessInfo {
+    pub encoder_delay: usize,
+    pub padding: usize,
+}
+
+/// Summary of a decode, including any data that had to be skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    pub frames: usize,
+    /// Gapless info from the stream's LAME tag, if it had one
+    pub gapless: Option<GaplessInfo>,
+    /// Estimated number of frames lost to corrupt data or truncation
+    pub skipped_frames: usize,
+    pub skipped_bytes: usize,
+    /// Frames of silence inserted under [`DecodeErrorPolicy::Pad`]
+    pub padded_frames: usize,
+}
+
+pub struct DecodedMp3 {
+    /// Samples normalized to [-1.0, 1.0], one vector per channel
+    pub channels: Vec<Vec<f32>>,
+    pub sample_rate: u32,
+    pub stats: DecodeStats,
+}
+
+/// Undecodable bytes found before the frame that follows them.
+struct Gap {
+    offset: usize,
+    bytes: usize,
+}
+
+pub fn decode(data: &[u8], policy: DecodeErrorPolicy) -> Result<DecodedMp3> {
+    let mut pos = id3v2_len(data);
+    let end = data.len() - trailer_len(&data[pos..]);
+
+    let mut decoder: Box<ffi::mp3dec_t> = Box::new(unsafe { mem::zeroed() });
+    unsafe { ffi::mp3dec_init(&mut *decoder) };
+    let mut pcm = vec![0i16; minimp3::MAX_SAMPLES_PER_FRAME];
+
+    let mut decoded = DecodedMp3 {
+        channels: Vec::new(),
+        sample_rate: 0,
+        stats: DecodeStats::default(),
+    };
+    let mut gap: Option<Gap> = None;
+    let mut frame_len = 0;
+
+    while pos < end {
+        let remaining = &data[pos..end];
+        let mut frame_info: ffi::mp3dec_frame_info_t = unsafe { mem::zeroed() };
+        let samples = unsafe {
+            ffi::mp3dec_decode_frame(
+                &mut *decoder,
+                remaining.as_ptr(),
+                remaining.len().min(i32::MAX as usize) as _,
+                pcm.as_mut_ptr(),
+                &mut frame_info,
+            )
+        } as usize;
+
+        let consumed = frame_info.frame_bytes as usize;
+        if consumed == 0 {
+            // No further frame header could be found
+            break;
+        }
+
+        // Bytes skipped before this frame, or the whole chunk if no frame
+        // could be decoded from it
+        let junk = if samples > 0 { frame_info.frame_offset as usize } else { consumed };
+        if junk > 0 {
+            let entry = gap.get_or_insert(Gap { offset: pos, bytes: 0 });
+            entry.bytes += junk;
+        }
+
+        if samples > 0 {
+            let channels = frame_info.channels as usize;
+            let frame_start = frame_info.frame_offset as usize;
+            frame_len = consumed - frame_start;
+            decoded.sample_rate = frame_info.hz as u32;
+            if decoded.channels.is_empty() {
+                decoded.channels = vec![Vec::new(); channels];
+            } else if decoded.channels.len() != channels {
+                bail!("MP3 switches from {} to {} channels at offset {}", decoded.channels.len(), channels, pos + frame_start);
+            }
+
+            // A Xing/Info frame at the start carries metadata, not audio
+            if decoded.stats.frames == 0 && gap.is_none() {
+                if let Some(gapless) = parse_info_frame(&remaining[frame_start..consumed]) {
+                    if let Some(info) = gapless {
+                        info!(
+                            "Found LAME tag: encoder delay {} samples, padding {} samples",
+                            info.encoder_delay, info.padding
+                        );
+                    }
+                    decoded.stats.gapless = gapless;
+                    pos += consumed;
+                    continue;
+                }
+            }
+
+            if let Some(gap) = gap.take() {
+                resolve_gap(&mut decoded, gap, frame_len, samples, policy)?;
+            }
+
+            decoded.stats.frames += 1;
+            // Convert i16 samples to f32, normalize to [-1.0, 1.0] and
+            // deinterleave
+            for (channel, plane) in decoded.channels.iter_mut().enumerate() {
+                plane.extend(pcm[..samples * channels].iter().skip(channel).step_by(channels).map(|&s| s as f32 / 32768.0));
+            }
+        }
+
+        pos += consumed;
+    }
+
+    // Anything left over is a truncated final frame
+    let trailing = end - pos.min(end);
+    if trailing > 0 || gap.is_some() {
+        let mut gap = gap.unwrap_or(Gap { offset: pos, bytes: 0 });
+        gap.bytes += trailing;
+        resolve_gap(&mut decoded, gap, frame_len.max(1), 1152, policy)?;
+    }
+
+    if let Some(gapless) = decoded.stats.gapless {
+        trim_gapless(&mut decoded, gapless);
+    }
+
+    if decoded.stats.skipped_frames > 0 {
+        warn!(
+            "Skipped {} undecodable MP3 frames ({} bytes){}",
+            decoded.stats.skipped_frames,
+            decoded.stats.skipped_bytes,
+            if decoded.stats.padded_frames > 0 { ", replaced with silence" } else { "" }
+        );
+    }
+
+    let samples: usize = decoded.channels.iter().map(Vec::len).sum();
+    info!("Loaded {} samples from {} frames", samples, decoded.stats.frames);
+    Ok(decoded)
+}
+
+fn resolve_gap(
+    decoded: &mut DecodedMp3,
+    gap: Gap,
+    frame_len: usize,
+    frame_samples: usize,
+    policy: DecodeErrorPolicy,
+) -> Result<()> {
+    if policy == DecodeErrorPolicy::Fail {
+        bail!(
+            "Corrupt MP3 data: {} bytes at offset {} could not be decoded",
+            gap.bytes, gap.offset
+        );
+    }
+
+    let lost_frames = gap.bytes.div_ceil(frame_len).max(1);
+    warn!("{} undecodable bytes at offset {} (~{} frames)", gap.bytes, gap.offset, lost_frames);
+
+    decoded.stats.skipped_frames += lost_frames;
+    decoded.stats.skipped_bytes += gap.bytes;
+
+    // Silence can only be inserted once the frame length is known, which
+    // is not the case for data before the first decodable frame
+    if policy == DecodeErrorPolicy::Pad && decoded.stats.frames > 0 {
+        decoded.stats.padded_frames += lost_frames;
+        for plane in &mut decoded.channels {
+            plane.resize(plane.len() + lost_frames * frame_samples, 0.0);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the encoder delay and padding so the output lines up sample for
+/// sample with the encoder's input.
+fn trim_gapless(decoded: &mut DecodedMp3, gapless: GaplessInfo) {
+    let frames = decoded.channels.first().map_or(0, Vec::len);
+    let start = (gapless.encoder_delay + DECODER_DELAY).min(frames);
+    let end = gapless.padding.saturating_sub(DECODER_DELAY).min(frames - start);
+
+    for plane in &mut decoded.channels {
+        plane.truncate(frames - end);
+        plane.drain(..start);
+    }
+    info!("Trimmed {} leading and {} trailing samples per channel for gapless playback", start, end);
+}
+
+/// Checks whether `frame` is a Xing/Info metadata frame. Returns `None` for
+/// ordinary audio frames, and `Some` with the gapless info from the LAME
+/// extension (if present) for metadata frames.
+fn parse_info_frame(frame: &[u8]) -> Option<Option<GaplessInfo>> {
+    if frame.len() < 4 {
+        return None;
+    }
+
+    let mpeg1 = (frame[1] >> 3) & 0x03 == 0x03;
+    let mono = frame[3] >> 6 == 0x03;
+    let crc = if frame[1] & 0x01 == 0 { 2 } else { 0 };
+    let side_info = match (mpeg1, mono) {
+        (true, true) => 17,
+        (true, false) => 32,
+        (false, true) => 9,
+        (false, false) => 17,
+    };
+
+    let tag = 4 + crc + side_info;
+    let id = frame.get(tag..tag + 4)?;
+    if id != b"Xing" && id != b"Info" {
+        return None;
+    }
+
+    // Optional fields: frame count, byte count, seek table, quality
+    let flags = u32::from_be_bytes(frame.get(tag + 4..tag + 8)?.try_into().ok()?);
+    let mut lame = tag + 8;
+    for (bit, len) in [(0x1, 4), (0x2, 4), (0x4, 100), (0x8, 4)] {
+        if flags & bit != 0 {
+            lame += len;
+        }
+    }
+
+    // The LAME extension starts with the encoder name ("LAME", "Lavc", ...)
+    // and stores delay and padding as two 12-bit values
+    let has_lame = frame
+        .get(lame..lame + 4)
+        .is_some_and(|name| name.iter().all(u8::is_ascii_alphanumeric));
+    let gapless = frame.get(lame + 21..lame + 24).filter(|_| has_lame).map(|bytes| GaplessInfo {
+        encoder_delay: ((bytes[0] as usize) << 4) | (bytes[1] as usize >> 4),
+        padding: ((bytes[1] as usize & 0x0f) << 8) | bytes[2] as usize,
+    });
+
+    Some(gapless)
+}
+
+/// Length of a leading ID3v2 tag, which is expected and not an error.
+pub(crate) fn id3v2_len(data: &[u8]) -> usize {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return 0;
+    }
+    // Syncsafe size excluding the 10 byte header, plus an optional footer
+    let size = data[6..10].iter().fold(0usize, |acc, &b| (acc << 7) | (b & 0x7f) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    (10 + size + footer).min(data.len())
+}
+
+/// Length of the tags trailing the audio: ID3v1, APEv2 and Lyrics3, which
+/// taggers stack in any order before a final ID3v1 tag.
+fn trailer_len(data: &[u8]) -> usize {
+    let mut end = data.len();
+    loop {
+        let tail = &data[..end];
+        let len = id3v1_len(tail).max(ape_len(tail)).max(lyrics3_len(tail));
+        if len == 0 {
+            return data.len() - end;
+        }
+        end -= len;
+    }
+}
+
+/// Length of a trailing ID3v1 tag.
+fn id3v1_len(data: &[u8]) -> usize {
+    if data.len() >= 128 && &data[data.len() - 128..data.len() - 125] == b"TAG" {
+        128
+    } else {
+        0
+    }
+}
+
+/// Length of a trailing APEv2 (or APEv1) tag, found by its 32 byte footer.
+fn ape_len(data: &[u8]) -> usize {
+    let Some(footer) = data.len().checked_sub(32).map(|start| &data[start..]) else {
+        return 0;
+    };
+    if &footer[..8] != b"APETAGEX" {
+        return 0;
+    }
+    // The size covers the items and the footer; a flag marks a header too
+    let size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as usize;
+    let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+    let header = if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+    let len = size.saturating_add(header);
+    if size < 32 || len > data.len() {
+        return 0;
+    }
+    len
+}
+
+/// Length of a trailing Lyrics3 v1 or v2 tag.
+fn lyrics3_len(data: &[u8]) -> usize {
+    const BEGIN: &[u8] = b"LYRICSBEGIN";
+    let len = if data.ends_with(b"LYRICS200") && data.len() >= 15 {
+        // v2 records the size before its end marker, in six ASCII digits
+        let digits = &data[data.len() - 15..data.len() - 9];
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return 0;
+        }
+        let size = digits.iter().fold(0usize, |acc, &b| acc * 10 + (b - b'0') as usize);
+        size + 15
+    } else if data.ends_with(b"LYRICSEND") {
+        // v1 holds at most 5100 bytes of lyrics between its markers
+        let from = data.len().saturating_sub(5100 + BEGIN.len() + 9);
+        match data[from..].windows(BEGIN.len()).position(|window| window == BEGIN) {
+            Some(start) => data.len() - from - start,
+            None => return 0,
+        }
+    } else {
+        return 0;
+    };
+    if len <= data.len() && data[data.len() - len..].starts_with(BEGIN) {
+        len
+    } else {
+        0
+    }
+}
@@ -0,0 +1,110 @@
+//! Minimal NumPy `.npy` reader and writer for spectra.
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+/// Magic string shared by all format versions.
+const MAGIC_PREFIX: &[u8] = b"\x93NUMPY";
+/// Header alignment required by NumPy.
+const ALIGNMENT: usize = 64;
+
+/// Writes `data` as a C-order little-endian `complex64` array of `shape`.
+pub fn write_complex64(path: &Path, shape: &[usize], data: &[Complex<f32>]) -> Result<()> {
+    let bytes = data.iter().flat_map(|value| [value.re, value.im]).flat_map(f32::to_le_bytes);
+    write(path, "<c8", shape, data.len(), bytes)
+}
+
+/// Writes `data` as a C-order little-endian `float32` array of `shape`.
+pub fn write_float32(path: &Path, shape: &[usize], data: &[f32]) -> Result<()> {
+    write(path, "<f4", shape, data.len(), data.iter().flat_map(|value| value.to_le_bytes()))
+}
+
+fn write(path: &Path, descr: &str, shape: &[usize], len: usize, bytes: impl Iterator<Item = u8>) -> Result<()> {
+    assert_eq!(shape.iter().product::<usize>(), len, "shape does not match data length");
+
+    let dims: Vec<String> = shape.iter().map(usize::to_string).collect();
+    // One-element tuples need a trailing comma
+    let shape = if dims.len() == 1 { format!("({},)", dims[0]) } else { format!("({})", dims.join(", ")) };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(ALIGNMENT) - unpadded));
+    header.push('\n');
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(&bytes.collect::<Vec<u8>>())?;
+    writer.flush().with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Reads a little-endian C-order `complex64` or `complex128` array,
+/// returning its shape and values (narrowed to `f32`).
+pub fn read_complex(path: &Path) -> Result<(Vec<usize>, Vec<Complex<f32>>)> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !bytes.starts_with(MAGIC_PREFIX) || bytes.len() < 10 {
+        bail!("{} is not a .npy file", path.display());
+    }
+    // Version 1 has a 2-byte header length, versions 2 and 3 a 4-byte one
+    let (header_start, header_len) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        2 | 3 if bytes.len() >= 12 => (12, u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize),
+        version => bail!("Unsupported .npy version {}", version),
+    };
+    let data_start = header_start + header_len;
+    let header = bytes
+        .get(header_start..data_start)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .context("Truncated or non-UTF-8 .npy header")?;
+
+    let field = |key: &str| -> Result<&str> {
+        let start = header.find(&format!("'{}':", key)).with_context(|| format!(".npy header lacks '{}'", key))?;
+        Ok(header[start + key.len() + 3..].trim_start())
+    };
+    if field("fortran_order")?.starts_with("True") {
+        bail!("Fortran-ordered arrays are not supported; save with np.ascontiguousarray");
+    }
+    let descr = field("descr")?;
+    let width = if descr.starts_with("'<c8'") {
+        4
+    } else if descr.starts_with("'<c16'") {
+        8
+    } else {
+        bail!("Expected a complex64 or complex128 array, got dtype {}", descr.split(',').next().unwrap_or(descr));
+    };
+    let shape_field = field("shape")?;
+    let shape: Vec<usize> = shape_field
+        .strip_prefix('(')
+        .and_then(|rest| rest.split(')').next())
+        .context("Malformed .npy shape")?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().with_context(|| format!("Malformed .npy shape dimension '{}'", dim)))
+        .collect::<Result<_>>()?;
+
+    let count: usize = shape.iter().product();
+    let data = &bytes[data_start..];
+    if data.len() != count * 2 * width {
+        bail!("{} holds {} bytes of data, expected {} for shape {:?}", path.display(), data.len(), count * 2 * width, shape);
+    }
+    let values = data
+        .chunks_exact(2 * width)
+        .map(|pair| {
+            let (re, im) = pair.split_at(width);
+            if width == 4 {
+                Complex::new(f32::from_le_bytes(re.try_into().unwrap()), f32::from_le_bytes(im.try_into().unwrap()))
+            } else {
+                Complex::new(
+                    f64::from_le_bytes(re.try_into().unwrap()) as f32,
+                    f64::from_le_bytes(im.try_into().unwrap()) as f32,
+                )
+            }
+        })
+        .collect();
+    Ok((shape, values))
+}
@@ -0,0 +1,75 @@
+//! Ogg Opus encoding of rendered bands.
+
+use anyhow::{anyhow, bail, Context, Result};
+use audiopus::{coder::Encoder, Application, Bitrate, Channels, SampleRate};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::buffer::interleave;
+use super::resample::resample;
+
+/// Opus always runs at 48 kHz; other rates are converted first.
+const OPUS_RATE: u32 = 48000;
+/// 20 ms frames.
+const FRAME: usize = 960;
+/// Largest packet the encoder may produce, per the Opus recommendation.
+const MAX_PACKET: usize = 4000;
+
+/// Encodes mono or stereo `channels` to an Ogg Opus file at
+/// `bitrate_kbps`. `serial` identifies the logical stream.
+pub fn write(path: &Path, channels: &[&[f32]], sample_rate: u32, bitrate_kbps: u32, serial: u32) -> Result<()> {
+    let opus_channels = match channels.len() {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        count => bail!("Opus output supports mono and stereo, got {} channels", count),
+    };
+    let failed = |err: audiopus::Error| anyhow!("Opus encoder error: {}", err);
+
+    let mut encoder = Encoder::new(SampleRate::Hz48000, opus_channels, Application::Audio).map_err(failed)?;
+    encoder.set_bitrate(Bitrate::BitsPerSecond(bitrate_kbps as i32 * 1000)).map_err(failed)?;
+    let pre_skip = encoder.lookahead().map_err(failed)? as usize;
+
+    let mut planes: Vec<Vec<f32>> = channels.iter().map(|channel| resample(channel, 1, sample_rate, OPUS_RATE)).collect();
+    let frames = planes[0].len();
+    // Flush the encoder's lookahead, then fill the last frame
+    let padded = (frames + pre_skip).next_multiple_of(FRAME);
+    planes.iter_mut().for_each(|plane| plane.resize(padded, 0.0));
+
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = PacketWriter::new(BufWriter::new(file));
+
+    let mut head = b"OpusHead".to_vec();
+    head.push(1);
+    head.push(planes.len() as u8);
+    head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    writer.write_packet(head.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+    let vendor = concat!("saunds ", env!("CARGO_PKG_VERSION"));
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    writer.write_packet(tags.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+    let mut packet = vec![0u8; MAX_PACKET];
+    let mut frame = Vec::with_capacity(FRAME * planes.len());
+    let count = padded / FRAME;
+    for index in 0..count {
+        // The encoder takes interleaved frames
+        let chunks: Vec<&[f32]> = planes.iter().map(|plane| &plane[index * FRAME..(index + 1) * FRAME]).collect();
+        frame.clear();
+        frame.extend(interleave(&chunks));
+        let len = encoder.encode_float(&frame, &mut packet).map_err(failed)?;
+        let last = index + 1 == count;
+        // The final granule position trims the padding on decode
+        let granule = if last { (pre_skip + frames) as u64 } else { ((index + 1) * FRAME) as u64 };
+        let info = if last { PacketWriteEndInfo::EndStream } else { PacketWriteEndInfo::NormalPacket };
+        writer.write_packet(packet[..len].into(), serial, info, granule)?;
+    }
+
+    writer.inner_mut().flush().with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,88 @@
+//! Sample and true peak levels and clipped sample counts, for the split's
+//! clipping and headroom report and `--headroom` scaling.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::oversample::Oversampler;
+use super::AudioBuffer;
+
+/// Level from which a sample counts as clipped: within a 16-bit step of
+/// full scale, so the largest positive integer code counts too.
+pub const CLIP_LEVEL: f32 = 1.0 - 1.0 / 32768.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Peaks {
+    pub sample_peak_dbfs: f32,
+    /// Peak of the 4x oversampled signal, catching peaks between samples
+    pub true_peak_dbtp: f32,
+    /// Samples at or beyond [`CLIP_LEVEL`]
+    pub clipped_samples: usize,
+}
+
+impl Peaks {
+    /// Room left below 0 dBTP; negative if inter-sample peaks exceed it.
+    pub fn headroom_db(&self) -> f32 {
+        -self.true_peak_dbtp
+    }
+}
+
+fn db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-6).log10()
+}
+
+/// Largest absolute value over `buffer`'s channels.
+pub fn sample_peak(buffer: &AudioBuffer) -> f32 {
+    buffer.channels().flatten().fold(0.0f32, |peak, x| peak.max(x.abs()))
+}
+
+/// Largest absolute value of `buffer` once each channel is oversampled
+/// 4x, never below the sample peak.
+pub fn true_peak(buffer: &AudioBuffer) -> f32 {
+    let oversampler = Oversampler::new(4);
+    buffer
+        .channels()
+        .map(|channel| oversampler.upsample(channel).into_iter().fold(0.0f32, |peak, x| peak.max(x.abs())))
+        .fold(sample_peak(buffer), f32::max)
+}
+
+/// Peaks of `buffer` over all its channels.
+pub fn measure(buffer: &AudioBuffer) -> Peaks {
+    Peaks {
+        sample_peak_dbfs: db(sample_peak(buffer)),
+        true_peak_dbtp: db(true_peak(buffer)),
+        clipped_samples: buffer.channels().flatten().filter(|sample| sample.abs() >= CLIP_LEVEL).count(),
+    }
+}
+
+/// How `--headroom` scales bands whose true peak is above the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scaling {
+    /// Each band is turned down only as far as it needs
+    #[default]
+    PerBand,
+    /// Every band gets the gain of the one needing the most, so they still
+    /// sum to a scaled version of the input
+    Linked,
+}
+
+/// Linear gains bringing each of the `bands` to a true peak of at most
+/// `-headroom_db` dBTP. Bands already below it keep a gain of 1.
+pub fn headroom_gains(bands: &[&AudioBuffer], headroom_db: f32, scaling: Scaling) -> Vec<f32> {
+    let target = 10f32.powf(-headroom_db / 20.0);
+    let gains: Vec<f32> = bands
+        .iter()
+        .map(|band| {
+            let peak = true_peak(band);
+            if peak > target { target / peak } else { 1.0 }
+        })
+        .collect();
+    match scaling {
+        Scaling::PerBand => gains,
+        Scaling::Linked => {
+            let gain = gains.iter().copied().fold(1.0, f32::min);
+            vec![gain; gains.len()]
+        }
+    }
+}
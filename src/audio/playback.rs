@@ -0,0 +1,42 @@
+//! Audio output through the default device.
+
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use tracing::{info, warn};
+
+/// A running output stream; playback stops when it is dropped.
+pub struct Player {
+    _stream: cpal::Stream,
+}
+
+impl Player {
+    /// Opens the default output device at the given rate and channel count
+    /// and starts calling `render` to fill each buffer of interleaved
+    /// samples.
+    pub fn start<F>(sample_rate: u32, channels: usize, mut render: F) -> Result<Self>
+    where
+        F: FnMut(&mut [f32]) + Send + 'static,
+    {
+        let device = cpal::default_host()
+            .default_output_device()
+            .context("No audio output device available")?;
+        info!("Playing through {}", device.name().unwrap_or_else(|_| "the default device".into()));
+
+        let config = cpal::StreamConfig {
+            channels: channels as u16,
+            sample_rate: cpal::SampleRate(sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| render(data),
+                |err| warn!("Playback error: {}", err),
+                None,
+            )
+            .with_context(|| format!("Output device does not support {} Hz with {} channels", sample_rate, channels))?;
+        stream.play().context("Failed to start playback")?;
+
+        Ok(Self { _stream: stream })
+    }
+}
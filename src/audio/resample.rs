@@ -0,0 +1,79 @@
+//! Arbitrary-ratio sample rate conversion with a windowed-sinc kernel.
+
+use std::f64::consts::PI;
+
+/// Kernel taps on each side of the interpolation point.
+const HALF_TAPS: i64 = 32;
+
+/// Windowed-sinc weight of a sample `x` samples from the interpolation
+/// point, for a kernel spanning `half` samples each side.
+fn kernel(x: f64, cutoff: f64, half: i64) -> f64 {
+    let sinc = if x == 0.0 { 1.0 } else { (PI * cutoff * x).sin() / (PI * cutoff * x) };
+    // Blackman window over the kernel span
+    let phase = (x / half as f64 + 1.0) * PI;
+    let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+    cutoff * sinc * window
+}
+
+/// Converts interleaved `samples` from `from` Hz to `to` Hz. The output is
+/// time-aligned with the input and has the same duration.
+pub fn resample(samples: &[f32], channels: usize, from: u32, to: u32) -> Vec<f32> {
+    let channels = channels.max(1);
+    if from == to {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let out_frames = ((samples.len() / channels) as f64 / ratio).round() as usize;
+    let positions: Vec<f64> = (0..out_frames).map(|frame| frame as f64 * ratio).collect();
+    warp(samples, channels, &positions)
+}
+
+/// Reads interleaved `samples` at fractional frame `positions`, for
+/// resampling at a varying rate. Where positions advance by more than a
+/// frame at a time the kernel band-limits to the lower Nyquist.
+pub fn warp(samples: &[f32], channels: usize, positions: &[f64]) -> Vec<f32> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let fastest = positions.windows(2).map(|pair| pair[1] - pair[0]).fold(1.0, f64::max);
+    let cutoff = 1.0 / fastest;
+    let half = (HALF_TAPS as f64 / cutoff).ceil() as i64;
+
+    let mut output = vec![0.0f32; positions.len() * channels];
+    for (&t, out) in positions.iter().zip(output.chunks_exact_mut(channels)) {
+        let center = t.floor() as i64;
+        for k in (center - half + 1).max(0)..=(center + half).min(frames as i64 - 1) {
+            let weight = kernel(t - k as f64, cutoff, half) as f32;
+            for (channel, value) in out.iter_mut().enumerate() {
+                *value += weight * samples[k as usize * channels + channel];
+            }
+        }
+    }
+    output
+}
+
+/// Delays one channel of interleaved `samples` by `frames`, which may be
+/// fractional or negative, keeping the length. Audio shifted past either
+/// end is dropped and the gap left is silent.
+pub fn delay(samples: &mut [f32], channels: usize, channel: usize, frames: f64) {
+    let channels = channels.max(1);
+    if frames == 0.0 || channel >= channels {
+        return;
+    }
+    let source: Vec<f32> = samples.iter().skip(channel).step_by(channels).copied().collect();
+    let len = source.len() as i64;
+    let whole = frames.floor();
+    let fraction = frames - whole;
+    for (frame, value) in samples.iter_mut().skip(channel).step_by(channels).enumerate() {
+        let center = frame as i64 - whole as i64;
+        *value = if fraction == 0.0 {
+            if (0..len).contains(&center) { source[center as usize] } else { 0.0 }
+        } else {
+            // Interpolate at center - fraction from the taps around it
+            let t = center as f64 - fraction;
+            ((center - HALF_TAPS)..(center + HALF_TAPS))
+                .filter(|k| (0..len).contains(k))
+                .map(|k| kernel(t - k as f64, 1.0, HALF_TAPS) * source[k as usize] as f64)
+                .sum::<f64>() as f32
+        };
+    }
+}
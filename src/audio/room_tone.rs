@@ -0,0 +1,104 @@
+//! Room tone: the noise profile of a quiet stretch of a recording, and
+//! matching noise synthesized from it to fill digital-black gaps.
+
+use anyhow::{bail, Result};
+use num_complex::Complex;
+use std::ops::Range;
+
+use super::dither::Rng;
+use super::spectral::process_frames;
+use super::stft::sqrt_hann_window;
+
+/// Long-term magnitude spectrum of each channel, in the framing of
+/// [`process_frames`].
+#[derive(Debug, Clone)]
+pub struct NoiseProfile {
+    window_size: usize,
+    magnitudes: Vec<Vec<f32>>,
+}
+
+impl NoiseProfile {
+    /// Profiles interleaved `samples`, which should hold only room tone.
+    pub fn measure(samples: &[f32], channels: usize, window_size: usize) -> Result<Self> {
+        let channels = channels.max(1);
+        if samples.len() < channels * window_size {
+            bail!("A noise profile needs at least {} samples per channel, got {}", window_size, samples.len() / channels);
+        }
+        let mut power = vec![vec![0.0f64; window_size / 2 + 1]; channels];
+        let mut frames = vec![0usize; channels];
+        process_frames(&mut samples.to_vec(), channels, window_size, |channel, _, spectrum| {
+            power[channel].iter_mut().zip(spectrum.iter()).for_each(|(p, bin)| *p += bin.norm_sqr() as f64);
+            frames[channel] += 1;
+            Ok(())
+        })?;
+        let magnitudes = power
+            .into_iter()
+            .zip(frames)
+            .map(|(power, frames)| power.iter().map(|p| (p / frames as f64).sqrt() as f32).collect())
+            .collect();
+        Ok(Self { window_size, magnitudes })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.magnitudes.len()
+    }
+
+    /// `frames` frames of interleaved noise with the profiled spectrum and
+    /// random phases drawn from `rng`.
+    pub fn synthesize(&self, frames: usize, rng: &mut Rng) -> Result<Vec<f32>> {
+        // Random phases spread each frame's power evenly over it instead of
+        // under the analysis window, so make up for the window's mean square
+        let window: Vec<f32> = sqrt_hann_window(self.window_size);
+        let gain = (self.window_size as f32 / window.iter().map(|w| w * w).sum::<f32>()).sqrt();
+        let mut samples = vec![0.0f32; frames * self.channels()];
+        process_frames(&mut samples, self.channels(), self.window_size, |channel, _, spectrum| {
+            for (bin, &magnitude) in spectrum.iter_mut().zip(&self.magnitudes[channel]) {
+                let phase = std::f32::consts::TAU * rng.uniform();
+                *bin = Complex::from_polar(gain * magnitude, phase);
+            }
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+}
+
+/// Runs of at least `min_frames` frames of interleaved `samples` in which
+/// every channel stays at or below `threshold`.
+pub fn find_gaps(samples: &[f32], channels: usize, threshold: f32, min_frames: usize) -> Vec<Range<usize>> {
+    let channels = channels.max(1);
+    let mut gaps = Vec::new();
+    let mut start = None;
+    let frames = samples.len() / channels;
+    for (index, frame) in samples.chunks_exact(channels).map(Some).chain([None]).enumerate() {
+        let silent = frame.is_some_and(|frame| frame.iter().all(|sample| sample.abs() <= threshold));
+        match (silent, start) {
+            (true, None) => start = Some(index),
+            (false, Some(first)) => {
+                if index - first >= min_frames.max(1) {
+                    gaps.push(first..index.min(frames));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    gaps
+}
+
+/// The `len` frames of interleaved `samples` with the lowest RMS level
+/// that don't overlap any of the `gaps`, stepping by a quarter of `len`.
+pub fn quietest_stretch(samples: &[f32], channels: usize, len: usize, gaps: &[Range<usize>]) -> Option<Range<usize>> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let step = (len / 4).max(1);
+    (0..=frames.checked_sub(len)?)
+        .step_by(step)
+        .map(|start| start..start + len)
+        .filter(|stretch| !gaps.iter().any(|gap| gap.start < stretch.end && stretch.start < gap.end))
+        .map(|stretch| {
+            let power: f64 = samples[stretch.start * channels..stretch.end * channels].iter().map(|&x| x as f64 * x as f64).sum();
+            (stretch, power)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(stretch, _)| stretch)
+}
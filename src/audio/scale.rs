@@ -0,0 +1,62 @@
+//! Frequency scales for distributing band edges.
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Lower end of the range band edges are spread over (Hz).
+pub const MIN_EDGE_FREQUENCY: f32 = 20.0;
+/// Upper end of the range band edges are spread over (Hz), clamped to
+/// Nyquist for low sample rates.
+pub const MAX_EDGE_FREQUENCY: f32 = 20_000.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BandScale {
+    /// Equal width in Hz
+    Linear,
+    /// Equal width in octaves
+    #[default]
+    Log,
+    /// Equal width in mels
+    Mel,
+    /// Equal width in Bark critical bands
+    Bark,
+}
+
+impl BandScale {
+    /// Maps a frequency in Hz onto the scale.
+    pub fn to_scale(self, frequency: f32) -> f32 {
+        match self {
+            BandScale::Linear => frequency,
+            BandScale::Log => frequency.log2(),
+            BandScale::Mel => 2595.0 * (1.0 + frequency / 700.0).log10(),
+            // Traunmüller's approximation
+            BandScale::Bark => 26.81 * frequency / (1960.0 + frequency) - 0.53,
+        }
+    }
+
+    /// Maps a value on the scale back to Hz.
+    pub fn to_frequency(self, value: f32) -> f32 {
+        match self {
+            BandScale::Linear => value,
+            BandScale::Log => value.exp2(),
+            BandScale::Mel => 700.0 * (10f32.powf(value / 2595.0) - 1.0),
+            BandScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+        }
+    }
+
+    /// Cutoffs dividing the audible range into `bands` bands of equal width
+    /// on this scale.
+    pub fn cutoffs(self, bands: usize, sample_rate: u32) -> Result<Vec<f32>> {
+        if bands < 2 {
+            bail!("Need at least 2 bands, got {}", bands);
+        }
+
+        let max = MAX_EDGE_FREQUENCY.min(sample_rate as f32 / 2.0);
+        let (low, high) = (self.to_scale(MIN_EDGE_FREQUENCY), self.to_scale(max));
+        Ok((1..bands)
+            .map(|i| self.to_frequency(low + (high - low) * i as f32 / bands as f32))
+            .collect())
+    }
+}
@@ -0,0 +1,81 @@
+//! Rhai scripting hook for prototyping spectral processing.
+//!
+//! A script runs once per STFT frame and channel with these variables in
+//! scope, and may rewrite `mags` in place; phases are kept:
+//!
+//! - `mags`: bin magnitudes (array of floats)
+//! - `freqs`: center frequency of each bin in Hz
+//! - `frame`, `channel`: frame and channel index
+//! - `time`: start of the frame in seconds
+//! - `sample_rate`: sample rate in Hz
+
+use anyhow::{anyhow, bail, Context, Result};
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+use std::path::Path;
+
+use super::spectral::process_frames;
+
+/// A compiled per-frame script.
+pub struct SpectralScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl SpectralScript {
+    pub fn compile(source: &str) -> Result<Self> {
+        let engine = Engine::new();
+        let ast = engine.compile(source).map_err(|e| anyhow!("Failed to compile script: {}", e))?;
+        Ok(Self { engine, ast })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read script {}", path.display()))?;
+        Self::compile(&source).with_context(|| format!("Invalid script {}", path.display()))
+    }
+
+    /// Runs the script over every frame of interleaved `samples` in place.
+    pub fn apply(&self, samples: &mut [f32], channels: usize, sample_rate: u32, window_size: usize) -> Result<()> {
+        let bins = window_size / 2 + 1;
+        let freqs: Array = (0..bins)
+            .map(|bin| Dynamic::from_float(bin as f64 * sample_rate as f64 / window_size as f64))
+            .collect();
+        let hop = (window_size / 2) as f64;
+
+        process_frames(samples, channels, window_size, |channel, frame, spectrum| {
+            let mags: Array = spectrum.iter().map(|bin| Dynamic::from_float(bin.norm() as f64)).collect();
+            let mut scope = Scope::new();
+            scope
+                .push("mags", mags)
+                .push_constant("freqs", freqs.clone())
+                .push_constant("frame", frame as i64)
+                .push_constant("channel", channel as i64)
+                .push_constant("time", (frame as f64 - 1.0) * hop / sample_rate as f64)
+                .push_constant("sample_rate", sample_rate as i64);
+
+            self.engine
+                .run_ast_with_scope(&mut scope, &self.ast)
+                .map_err(|e| anyhow!("Script failed on frame {} of channel {}: {}", frame, channel, e))?;
+
+            let mags = scope
+                .get_value::<Array>("mags")
+                .context("Script must leave `mags` as an array")?;
+            if mags.len() != spectrum.len() {
+                bail!("Script changed `mags` from {} to {} bins", spectrum.len(), mags.len());
+            }
+            for (bin, magnitude) in spectrum.iter_mut().zip(mags) {
+                let magnitude = magnitude
+                    .as_float()
+                    .or_else(|_| magnitude.as_int().map(|x| x as f64))
+                    .map_err(|kind| anyhow!("Script set a magnitude to a {}", kind))?;
+                let norm = bin.norm();
+                *bin = if norm > 0.0 {
+                    *bin * (magnitude as f32 / norm)
+                } else {
+                    num_complex::Complex::new(magnitude as f32, 0.0)
+                };
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,71 @@
+// Batched radix-2 Stockham FFT and band masking for the GPU STFT backend.
+//
+// Every buffer holds `count` transforms of length `n` laid out back to back,
+// with each complex value stored as vec2<f32>(re, im).
+
+struct FftParams {
+    n: u32,
+    // Size of the sub-transforms being combined in this stage.
+    ns: u32,
+    count: u32,
+    // -1.0 for the forward transform, 1.0 for the (unscaled) inverse.
+    sign: f32,
+}
+
+@group(0) @binding(0) var<uniform> fft_params: FftParams;
+@group(0) @binding(1) var<storage, read> fft_src: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read_write> fft_dst: array<vec2<f32>>;
+
+@compute @workgroup_size(64)
+fn fft_stage(@builtin(global_invocation_id) id: vec3<u32>) {
+    let half = fft_params.n / 2u;
+    if (id.x >= half * fft_params.count) {
+        return;
+    }
+
+    let base = (id.x / half) * fft_params.n;
+    let j = id.x % half;
+    let k = j % fft_params.ns;
+
+    let angle = fft_params.sign * 6.283185307179586 * f32(k) / f32(fft_params.ns * 2u);
+    let w = vec2<f32>(cos(angle), sin(angle));
+    let a = fft_src[base + j];
+    let b = fft_src[base + j + half];
+    let bw = vec2<f32>(b.x * w.x - b.y * w.y, b.x * w.y + b.y * w.x);
+
+    let out = base + (j / fft_params.ns) * fft_params.ns * 2u + k;
+    fft_dst[out] = a + bw;
+    fft_dst[out + fft_params.ns] = a - bw;
+}
+
+struct MaskParams {
+    n: u32,
+    count: u32,
+    // The low band keeps bins below low_end, the high band keeps bins
+    // from high_start upwards.
+    low_end: u32,
+    high_start: u32,
+}
+
+@group(0) @binding(0) var<uniform> mask_params: MaskParams;
+@group(0) @binding(1) var<storage, read> spectra: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read_write> bands: array<vec2<f32>>;
+
+// Writes the low band spectra followed by the high band spectra. The masks
+// are applied symmetrically to the negative frequencies so the inverse
+// transform stays real.
+@compute @workgroup_size(64)
+fn split_bands(@builtin(global_invocation_id) id: vec3<u32>) {
+    let total = mask_params.n * mask_params.count;
+    if (id.x >= total) {
+        return;
+    }
+
+    let i = id.x % mask_params.n;
+    let bin = min(i, mask_params.n - i);
+    let value = spectra[id.x];
+    let zero = vec2<f32>(0.0, 0.0);
+
+    bands[id.x] = select(zero, value, bin < mask_params.low_end);
+    bands[total + id.x] = select(zero, value, bin >= mask_params.high_start);
+}
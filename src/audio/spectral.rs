@@ -0,0 +1,224 @@
+//! Per-frame spectral processing on the STFT framing of the band split,
+//! and a spectral ducker built on it.
+
+use anyhow::{bail, Context, Result};
+use num_complex::Complex;
+use realfft::RealFftPlanner;
+use serde::{Deserialize, Serialize};
+
+use super::stft::{apply_window, frame_offsets, overlap_add, sqrt_hann_window};
+
+/// Runs an STFT over each channel of interleaved `samples`, hands every
+/// frame's spectrum to `process` along with its channel and frame index,
+/// and resynthesizes the result in place. Frames are `window_size` long
+/// at 50% overlap, so frame `i` of different signals of the same window
+/// size covers the same samples.
+pub fn process_frames<F>(samples: &mut [f32], channels: usize, window_size: usize, mut process: F) -> Result<()>
+where
+    F: FnMut(usize, usize, &mut [Complex<f32>]) -> Result<()>,
+{
+    if window_size < 2 || !window_size.is_multiple_of(2) {
+        bail!("Window size must be even and at least 2, got {}", window_size);
+    }
+    let channels = channels.max(1);
+    let hop = window_size / 2;
+    let mut planner = RealFftPlanner::<f32>::new();
+    let fft = planner.plan_fft_forward(window_size);
+    let ifft = planner.plan_fft_inverse(window_size);
+    let mut frame = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    let window_func = sqrt_hann_window(window_size);
+    let scale = 1.0 / window_size as f32;
+
+    for channel in 0..channels {
+        let input: Vec<f32> = samples.iter().skip(channel).step_by(channels).copied().collect();
+        let mut output = vec![0.0f32; input.len()];
+        for (index, offset) in frame_offsets(input.len(), hop).enumerate() {
+            apply_window(&input, offset, &window_func, &mut frame);
+            fft.process(&mut frame, &mut spectrum)
+                .with_context(|| format!("Failed to perform forward FFT on frame {}", index))?;
+            process(channel, index, &mut spectrum)?;
+            // The inverse transform needs real DC and Nyquist bins
+            spectrum[0].im = 0.0;
+            if let Some(last) = spectrum.last_mut() {
+                last.im = 0.0;
+            }
+            ifft.process(&mut spectrum, &mut frame)
+                .with_context(|| format!("Failed to perform inverse FFT on frame {}", index))?;
+            overlap_add(&frame, offset, &window_func, scale, &mut output);
+        }
+        for (sample, out) in samples.iter_mut().skip(channel).step_by(channels).zip(output) {
+            *sample = out;
+        }
+    }
+    Ok(())
+}
+
+/// Name of the window in [`StftLayout`].
+const SQRT_HANN: &str = "sqrt-hann";
+
+/// Framing of an exported STFT, enough to resynthesize it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StftLayout {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub window_size: usize,
+    pub hop: usize,
+    /// Analysis and synthesis window
+    pub window: String,
+    /// Start of the first frame relative to the first sample
+    pub first_offset: isize,
+    pub samples_per_channel: usize,
+    pub frames: usize,
+    pub bins: usize,
+}
+
+impl StftLayout {
+    pub fn new(sample_rate: u32, channels: usize, window_size: usize, samples_per_channel: usize) -> Self {
+        let hop = window_size / 2;
+        Self {
+            sample_rate,
+            channels,
+            window_size,
+            hop,
+            window: SQRT_HANN.to_string(),
+            first_offset: -(hop as isize),
+            samples_per_channel,
+            frames: frame_offsets(samples_per_channel, hop).count(),
+            bins: window_size / 2 + 1,
+        }
+    }
+}
+
+/// Complex STFT of each channel of interleaved `samples` in the framing of
+/// [`process_frames`], unnormalized, as `[channel][frame][bin]` flattened.
+pub fn stft(samples: &[f32], channels: usize, window_size: usize) -> Result<Vec<Complex<f32>>> {
+    let mut frames = Vec::new();
+    process_frames(&mut samples.to_vec(), channels, window_size, |_, _, spectrum| {
+        frames.extend_from_slice(spectrum);
+        Ok(())
+    })?;
+    Ok(frames)
+}
+
+/// Inverse of [`stft`]: overlap-adds `[channel][frame][bin]` spectra laid
+/// out as `layout` describes back into interleaved samples.
+pub fn istft(spectra: &[Complex<f32>], layout: &StftLayout) -> Result<Vec<f32>> {
+    if layout.window != SQRT_HANN {
+        bail!("Unsupported window '{}', expected '{}'", layout.window, SQRT_HANN);
+    }
+    if layout.window_size < 2 || layout.hop != layout.window_size / 2 || layout.bins != layout.window_size / 2 + 1 {
+        bail!(
+            "Inconsistent STFT layout: window {}, hop {}, {} bins",
+            layout.window_size, layout.hop, layout.bins
+        );
+    }
+    if spectra.len() != layout.channels * layout.frames * layout.bins {
+        bail!(
+            "Expected {} x {} x {} bins, got {}",
+            layout.channels, layout.frames, layout.bins, spectra.len()
+        );
+    }
+
+    let channels = layout.channels.max(1);
+    let mut planner = RealFftPlanner::<f32>::new();
+    let ifft = planner.plan_fft_inverse(layout.window_size);
+    let mut spectrum = ifft.make_input_vec();
+    let mut frame = ifft.make_output_vec();
+    let window_func = sqrt_hann_window(layout.window_size);
+    let scale = 1.0 / layout.window_size as f32;
+
+    let mut output = vec![0.0f32; layout.samples_per_channel * channels];
+    let mut channel_output = vec![0.0f32; layout.samples_per_channel];
+    for (channel, frames) in spectra.chunks(layout.frames * layout.bins).enumerate() {
+        channel_output.fill(0.0);
+        for (index, bins) in frames.chunks(layout.bins).enumerate() {
+            spectrum.copy_from_slice(bins);
+            spectrum[0].im = 0.0;
+            if let Some(last) = spectrum.last_mut() {
+                last.im = 0.0;
+            }
+            ifft.process(&mut spectrum, &mut frame)
+                .with_context(|| format!("Failed to perform inverse FFT on frame {}", index))?;
+            let offset = layout.first_offset + (index * layout.hop) as isize;
+            overlap_add(&frame, offset, &window_func, scale, &mut channel_output);
+        }
+        for (sample, &value) in output.iter_mut().skip(channel).step_by(channels).zip(&channel_output) {
+            *sample = value;
+        }
+    }
+    Ok(output)
+}
+
+/// Per-bin amplitude of each STFT frame of a mono signal, in the framing
+/// of [`process_frames`], scaled so a full-scale sine peaks near 1.
+pub fn frame_magnitudes(samples: &[f32], window_size: usize) -> Result<Vec<Vec<f32>>> {
+    let mut magnitudes = Vec::new();
+    let window_sum: f32 = sqrt_hann_window(window_size).iter().sum();
+    process_frames(&mut samples.to_vec(), 1, window_size, |_, _, spectrum| {
+        magnitudes.push(spectrum.iter().map(|bin| 2.0 * bin.norm() / window_sum).collect());
+        Ok(())
+    })?;
+    Ok(magnitudes)
+}
+
+/// Level in dBFS of each band of a [`frame_magnitudes`] frame, with bands
+/// split at the ascending `cutoffs` (Hz). Bin energies are divided by the
+/// window's equivalent noise bandwidth, so a full-scale sine reads 0 dB
+/// in its band; empty bands read -120 dB.
+pub fn band_levels(magnitudes: &[f32], window_size: usize, sample_rate: u32, cutoffs: &[f32]) -> Vec<f32> {
+    let window = sqrt_hann_window(window_size);
+    let sum: f32 = window.iter().sum();
+    let enbw = window_size as f32 * window.iter().map(|w| w * w).sum::<f32>() / (sum * sum);
+
+    let bin_hz = sample_rate as f32 / window_size as f32;
+    let mut energies = vec![0.0f32; cutoffs.len() + 1];
+    for (bin, &magnitude) in magnitudes.iter().enumerate() {
+        let band = cutoffs.partition_point(|&cutoff| cutoff <= bin as f32 * bin_hz);
+        energies[band] += magnitude * magnitude;
+    }
+    energies.iter().map(|&energy| 10.0 * (energy / enbw).max(1e-12).log10()).collect()
+}
+
+/// Settings for [`duck`].
+#[derive(Debug, Clone, Copy)]
+pub struct DuckSettings {
+    /// Key level per bin above which the input is attenuated (dBFS)
+    pub threshold_db: f32,
+    /// Largest attenuation (dB)
+    pub depth_db: f32,
+    /// Time for the attenuation to recover once the key falls (ms)
+    pub release_ms: f32,
+    /// Bins on either side of each bin whose key level also counts
+    pub spread: usize,
+    pub window_size: usize,
+}
+
+/// Spectral ducking: attenuates each bin of interleaved `samples` by as
+/// many dB as the matching bin of the mono `key` exceeds the threshold, up
+/// to the depth. The attenuation follows rises in the key immediately and
+/// recovers over the release time.
+pub fn duck(samples: &mut [f32], channels: usize, key: &[f32], sample_rate: u32, settings: DuckSettings) -> Result<()> {
+    let key_frames = frame_magnitudes(key, settings.window_size)?;
+    let hop_ms = 1000.0 * (settings.window_size / 2) as f32 / sample_rate as f32;
+    // One-pole smoothing per frame
+    let release = if settings.release_ms > 0.0 { (-hop_ms / settings.release_ms).exp() } else { 0.0 };
+    let bins = settings.window_size / 2 + 1;
+    let mut reduction = vec![vec![0.0f32; bins]; channels.max(1)];
+
+    process_frames(samples, channels, settings.window_size, |channel, index, spectrum| {
+        let reduction = &mut reduction[channel];
+        let key = key_frames.get(index);
+        for (bin, value) in spectrum.iter_mut().enumerate() {
+            let target = key.map_or(0.0, |key| {
+                let neighbours = &key[bin.saturating_sub(settings.spread)..(bin + settings.spread + 1).min(bins)];
+                let level = neighbours.iter().fold(0.0f32, |peak, &x| peak.max(x));
+                let over = 20.0 * level.max(1e-12).log10() - settings.threshold_db;
+                over.clamp(0.0, settings.depth_db)
+            });
+            reduction[bin] = if target > reduction[bin] { target } else { target + release * (reduction[bin] - target) };
+            *value *= 10f32.powf(-reduction[bin] / 20.0);
+        }
+        Ok(())
+    })
+}
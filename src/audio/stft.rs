@@ -0,0 +1,120 @@
+//! Building blocks of the STFT band split. Each stage works on
+//! caller-provided buffers so the processing loop never allocates and the
+//! stages can be benchmarked in isolation.
+//!
+//! Frames advance by half a window and may start before the first sample
+//! (negative offsets), so every input sample is covered by exactly two
+//! frames and the analysis/synthesis windows overlap-add to unity.
+
+use num_complex::Complex;
+use realfft::num_traits::{Float, FloatConst};
+use std::ops::Range;
+
+/// Periodic Hann window of the given length.
+pub fn hann_window(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos()))
+        .collect()
+}
+
+/// Square root of the periodic Hann window, computed at the precision of
+/// `T`. Used for both analysis and synthesis, its square sums to one at
+/// 50% overlap.
+pub fn sqrt_hann_window<T: Float + FloatConst>(size: usize) -> Vec<T> {
+    let half = T::from(0.5).expect("0.5 is representable");
+    let size_t = T::from(size).expect("window sizes are representable");
+    (0..size)
+        .map(|i| {
+            let phase = (T::TAU() * T::from(i).expect("indices are representable") / size_t).cos();
+            (half * (T::one() - phase)).sqrt()
+        })
+        .collect()
+}
+
+/// Analysis and synthesis window of the band split. Both are
+/// power-complementary, so applied twice they overlap-add to unity at 50%
+/// overlap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Window {
+    /// Square-root Hann, i.e. the sine window
+    #[default]
+    SqrtHann,
+    /// Vorbis power-complementary window: flatter top, lower sidelobes
+    Vorbis,
+}
+
+impl Window {
+    /// Coefficients of a periodic window of the given length.
+    pub fn coefficients<T: Float + FloatConst>(self, size: usize) -> Vec<T> {
+        match self {
+            Window::SqrtHann => sqrt_hann_window(size),
+            Window::Vorbis => {
+                let size_t = T::from(size).expect("window sizes are representable");
+                (0..size)
+                    .map(|i| {
+                        let sine = (T::PI() * T::from(i).expect("indices are representable") / size_t).sin();
+                        (T::FRAC_PI_2() * sine * sine).sin()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Start offsets of the frames covering `len` samples with the given hop.
+pub fn frame_offsets(len: usize, hop: usize) -> impl Iterator<Item = isize> {
+    (-(hop as isize)..len as isize).step_by(hop)
+}
+
+/// Part of a frame starting at `offset` that overlaps `len` samples, as
+/// (range in the frame, range in the signal).
+fn overlap(offset: isize, frame_len: usize, len: usize) -> (Range<usize>, Range<usize>) {
+    let start = offset.max(0) as usize;
+    let end = (offset + frame_len as isize).clamp(0, len as isize) as usize;
+    if start >= end {
+        return (0..0, 0..0);
+    }
+    let frame_start = (start as isize - offset) as usize;
+    (frame_start..frame_start + end - start, start..end)
+}
+
+/// Multiplies the samples covered by a frame starting at `offset` by the
+/// analysis window into `frame`, zero-padding past either end of `samples`.
+pub fn apply_window<T: Float>(samples: &[T], offset: isize, window_func: &[T], frame: &mut [T]) {
+    let (frame_range, sample_range) = overlap(offset, frame.len(), samples.len());
+    frame.fill(T::zero());
+    for ((f, &s), &w) in frame[frame_range.clone()]
+        .iter_mut()
+        .zip(&samples[sample_range])
+        .zip(&window_func[frame_range])
+    {
+        *f = s * w;
+    }
+}
+
+/// Whether every sample covered by a frame starting at `offset` is within
+/// `floor` of zero, so the frame can be skipped and left silent.
+pub fn is_silent<T: Float>(samples: &[T], offset: isize, frame_len: usize, floor: T) -> bool {
+    let (_, sample_range) = overlap(offset, frame_len, samples.len());
+    samples[sample_range].iter().all(|sample| sample.abs() <= floor)
+}
+
+/// Copies the bins of `spectrum` within `bins` into `band`, zeroing the rest.
+pub fn apply_band_mask<T: Float>(spectrum: &[Complex<T>], bins: Range<usize>, band: &mut [Complex<T>]) {
+    band.fill(Complex::new(T::zero(), T::zero()));
+    let bins = bins.start.min(spectrum.len())..bins.end.min(spectrum.len());
+    band[bins.clone()].copy_from_slice(&spectrum[bins]);
+}
+
+/// Applies the synthesis window and `scale` to a frame starting at
+/// `offset` and accumulates it into `output`.
+pub fn overlap_add<T: Float>(frame: &[T], offset: isize, window_func: &[T], scale: T, output: &mut [T]) {
+    let (frame_range, sample_range) = overlap(offset, frame.len(), output.len());
+    for ((o, &f), &w) in output[sample_range]
+        .iter_mut()
+        .zip(&frame[frame_range.clone()])
+        .zip(&window_func[frame_range])
+    {
+        *o = *o + f * w * scale;
+    }
+}
@@ -0,0 +1,148 @@
+//! Streaming STFT for real-time or incremental use: samples are pushed in
+//! arbitrary block sizes and processed samples pulled out as soon as every
+//! frame covering them has been overlap-added.
+
+use anyhow::{bail, Result};
+use num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+use super::stft::Window;
+
+/// Spectral stage run on every frame's bins, DC first.
+pub type SpectralFn = Box<dyn FnMut(&mut [Complex<f32>]) + Send>;
+
+/// Single-channel STFT with push/pull semantics. The output matches the
+/// offline band split sample for sample, delayed by
+/// [`latency`](Self::latency) until [`flush`](Self::flush).
+pub struct StftProcessor {
+    hop: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    window_func: Vec<f32>,
+    process: SpectralFn,
+    /// Samples not yet consumed by a frame, starting half a window before
+    /// the stream so the first frame matches the offline frame offsets
+    input: Vec<f32>,
+    accumulator: Vec<f32>,
+    output: Vec<f32>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    primed: bool,
+    pushed: usize,
+    emitted: usize,
+}
+
+impl StftProcessor {
+    /// Square-root Hann STFT at 50% overlap, running `process` on each
+    /// frame's `window_size / 2 + 1` bins.
+    pub fn new(window_size: usize, process: impl FnMut(&mut [Complex<f32>]) + Send + 'static) -> Result<Self> {
+        Self::with_window(window_size, Window::SqrtHann, process)
+    }
+
+    /// [`new`](Self::new) with another analysis and synthesis window.
+    pub fn with_window(
+        window_size: usize,
+        window: Window,
+        process: impl FnMut(&mut [Complex<f32>]) + Send + 'static,
+    ) -> Result<Self> {
+        if window_size < 2 || !window_size.is_multiple_of(2) {
+            bail!("The STFT window size must be even and at least 2, got {}", window_size);
+        }
+        let mut planner = RealFftPlanner::new();
+        let fft = planner.plan_fft_forward(window_size);
+        let ifft = planner.plan_fft_inverse(window_size);
+        let scratch_len = fft.get_scratch_len().max(ifft.get_scratch_len());
+        let hop = window_size / 2;
+        Ok(Self {
+            hop,
+            frame: fft.make_input_vec(),
+            spectrum: fft.make_output_vec(),
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            fft,
+            ifft,
+            window_func: window.coefficients(window_size),
+            process: Box::new(process),
+            input: vec![0.0; hop],
+            accumulator: vec![0.0; window_size],
+            output: Vec::new(),
+            primed: false,
+            pushed: 0,
+            emitted: 0,
+        })
+    }
+
+    /// Samples by which the output trails the input.
+    pub fn latency(&self) -> usize {
+        self.hop
+    }
+
+    /// Feeds input samples, processing every frame they complete.
+    pub fn push(&mut self, samples: &[f32]) {
+        self.pushed += samples.len();
+        self.input.extend_from_slice(samples);
+        self.process_frames();
+    }
+
+    /// Takes all processed samples available so far.
+    pub fn pull(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Ends the stream: zero-pads the last frames and returns the
+    /// remaining output, so the total pulled equals the total pushed. The
+    /// processor is then ready for a new stream.
+    pub fn flush(&mut self) -> Vec<f32> {
+        while self.emitted < self.pushed {
+            self.input.resize(self.input.len() + self.hop, 0.0);
+            self.process_frames();
+        }
+        let excess = self.emitted - self.pushed;
+        self.output.truncate(self.output.len() - excess);
+
+        self.input = vec![0.0; self.hop];
+        self.accumulator.fill(0.0);
+        self.primed = false;
+        self.pushed = 0;
+        self.emitted = 0;
+        std::mem::take(&mut self.output)
+    }
+
+    fn process_frames(&mut self) {
+        let window_size = self.frame.len();
+        let scale = 1.0 / window_size as f32;
+        let mut consumed = 0;
+        while self.input.len() - consumed >= window_size {
+            let block = &self.input[consumed..consumed + window_size];
+            for ((frame, &sample), &window) in self.frame.iter_mut().zip(block).zip(&self.window_func) {
+                *frame = sample * window;
+            }
+            self.fft
+                .process_with_scratch(&mut self.frame, &mut self.spectrum, &mut self.scratch)
+                .expect("buffers are sized by the planner");
+            (self.process)(&mut self.spectrum);
+            // A real signal's DC and Nyquist bins have no imaginary part
+            let last = self.spectrum.len() - 1;
+            self.spectrum[0].im = 0.0;
+            self.spectrum[last].im = 0.0;
+            self.ifft
+                .process_with_scratch(&mut self.spectrum, &mut self.frame, &mut self.scratch)
+                .expect("buffers are sized by the planner");
+
+            for ((acc, &value), &window) in self.accumulator.iter_mut().zip(&self.frame).zip(&self.window_func) {
+                *acc += value * window * scale;
+            }
+            // The first frame's leading half lies before the stream
+            if self.primed {
+                self.output.extend_from_slice(&self.accumulator[..self.hop]);
+                self.emitted += self.hop;
+            }
+            self.primed = true;
+            self.accumulator.copy_within(self.hop.., 0);
+            self.accumulator[window_size - self.hop..].fill(0.0);
+            consumed += self.hop;
+        }
+        self.input.drain(..consumed);
+    }
+}
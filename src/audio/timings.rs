@@ -0,0 +1,90 @@
+//! Wall time and throughput per processing stage, for `--timings`. A
+//! [`Timings`] is shared by every clone of an [`AudioProcessor`](super::AudioProcessor)
+//! it's attached to; stages run on several threads add up their time.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A stage of the split, in processing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Decode,
+    Window,
+    Fft,
+    Mask,
+    Ifft,
+    OverlapAdd,
+    Encode,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Decode,
+        Stage::Window,
+        Stage::Fft,
+        Stage::Mask,
+        Stage::Ifft,
+        Stage::OverlapAdd,
+        Stage::Encode,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Decode => "decode",
+            Stage::Window => "window",
+            Stage::Fft => "fft",
+            Stage::Mask => "mask",
+            Stage::Ifft => "ifft",
+            Stage::OverlapAdd => "overlap-add",
+            Stage::Encode => "encode",
+        }
+    }
+}
+
+/// Time spent in a stage and the samples it handled.
+#[derive(Debug, Clone, Copy)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub time: Duration,
+    pub samples: u64,
+}
+
+impl StageTiming {
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.time.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Timings {
+    nanos: [AtomicU64; 7],
+    samples: [AtomicU64; 7],
+}
+
+impl Timings {
+    pub fn record(&self, stage: Stage, time: Duration, samples: usize) {
+        self.nanos[stage as usize].fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+        self.samples[stage as usize].fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    /// Runs `f`, recording its time against `stage`.
+    pub fn time<T>(&self, stage: Stage, samples: usize, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed(), samples);
+        result
+    }
+
+    /// Stages that ran, in processing order.
+    pub fn stages(&self) -> Vec<StageTiming> {
+        Stage::ALL
+            .iter()
+            .map(|&stage| StageTiming {
+                stage,
+                time: Duration::from_nanos(self.nanos[stage as usize].load(Ordering::Relaxed)),
+                samples: self.samples[stage as usize].load(Ordering::Relaxed),
+            })
+            .filter(|timing| timing.samples > 0)
+            .collect()
+    }
+}
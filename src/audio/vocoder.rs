@@ -0,0 +1,70 @@
+//! Channel vocoder imposing one signal's band envelopes on another.
+
+use anyhow::{bail, Result};
+
+use super::effects::time_constant;
+use super::iir::{Crossover, FilterMode};
+use super::{FilterDesign, Precision};
+
+/// Attack of the band envelope followers (ms).
+const ENVELOPE_ATTACK_MS: f32 = 2.0;
+
+/// Splits the interleaved `carrier` and mono `modulator` into bands at
+/// `cutoffs` with the zero-phase crossover, follows the level of each
+/// modulator band with a `release_ms` peak envelope and scales the matching
+/// carrier band by it. The sum of the bands is normalized to the
+/// modulator's peak level. The output has the carrier's channel count and
+/// the length of the shorter input.
+pub fn vocode(
+    carrier: &[f32],
+    channels: usize,
+    modulator: &[f32],
+    cutoffs: &[f32],
+    sample_rate: u32,
+    release_ms: f32,
+) -> Result<Vec<f32>> {
+    let channels = channels.max(1);
+    let frames = (carrier.len() / channels).min(modulator.len());
+    if frames == 0 {
+        bail!("Cannot vocode an empty signal");
+    }
+    let carrier = &carrier[..frames * channels];
+    let modulator = &modulator[..frames];
+
+    let crossover = |channels| Crossover {
+        mode: FilterMode::ZeroPhase,
+        design: FilterDesign::default(),
+        sample_rate,
+        channels,
+        precision: Precision::default(),
+    };
+    let carrier_bands = crossover(channels).split_bands(carrier, cutoffs)?;
+    let modulator_bands = crossover(1).split_bands(modulator, cutoffs)?;
+
+    let attack = time_constant(ENVELOPE_ATTACK_MS, sample_rate);
+    let release = time_constant(release_ms, sample_rate);
+    let mut output = vec![0.0f32; carrier.len()];
+    for (carrier_band, modulator_band) in carrier_bands.iter().zip(&modulator_bands) {
+        let mut envelope = 0.0f32;
+        for ((out, band), &level) in output
+            .chunks_mut(channels)
+            .zip(carrier_band.chunks(channels))
+            .zip(modulator_band)
+        {
+            let level = level.abs();
+            let coefficient = if level > envelope { attack } else { release };
+            envelope = level + coefficient * (envelope - level);
+            for (out, &x) in out.iter_mut().zip(band) {
+                *out += x * envelope;
+            }
+        }
+    }
+
+    let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+    let output_peak = peak(&output);
+    if output_peak > 0.0 {
+        let gain = peak(modulator) / output_peak;
+        output.iter_mut().for_each(|x| *x *= gain);
+    }
+    Ok(output)
+}
@@ -0,0 +1,422 @@
+//! WAV reader and writer with Broadcast Wave (`bext`) metadata and RF64
+//! for files past the 4 GiB RIFF limit.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::buffer::interleave;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+/// Size field value meaning "see the ds64 chunk".
+const RF64_SIZE: u32 = u32::MAX;
+/// Tail shared by the sub-format GUIDs of the plain format tags.
+const GUID_TAIL: [u8; 14] = [0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71];
+/// Fixed part of a `bext` chunk, before the coding history.
+const BEXT_FIXED_LEN: usize = 602;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spec {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub float: bool,
+    /// WAVE_FORMAT_EXTENSIBLE speaker positions, 0 for none
+    pub channel_mask: u32,
+}
+
+impl Spec {
+    fn block_align(&self) -> u16 {
+        self.channels * self.bits_per_sample.div_ceil(8)
+    }
+}
+
+/// Samples to write, either as floats, one slice per channel, or as
+/// interleaved integer codes already quantized to the spec's word length.
+pub enum Data<'a> {
+    Float(&'a [&'a [f32]]),
+    Int(&'a [i32]),
+}
+
+impl Data<'_> {
+    /// Samples over all channels.
+    pub(super) fn len(&self) -> usize {
+        match self {
+            Data::Float(channels) => channels.iter().map(|channel| channel.len()).sum(),
+            Data::Int(codes) => codes.len(),
+        }
+    }
+}
+
+/// Broadcast Wave Format metadata per EBU Tech 3285.
+#[derive(Debug, Clone, Default)]
+pub struct Bext {
+    /// Free text, truncated to 256 bytes
+    pub description: String,
+    /// Truncated to 32 bytes
+    pub originator: String,
+    /// Truncated to 32 bytes
+    pub originator_reference: String,
+    /// Creation time in seconds since the Unix epoch, written as UTC
+    pub origination: u64,
+    /// Samples since midnight of the first sample, for timeline placement
+    pub time_reference: u64,
+    /// Lines of the form `A=PCM,F=48000,W=24,M=stereo,T=...`, each ending
+    /// in CR LF
+    pub coding_history: String,
+}
+
+impl Bext {
+    fn chunk(&self) -> Vec<u8> {
+        let mut chunk = Vec::with_capacity(BEXT_FIXED_LEN + self.coding_history.len());
+        let mut text = |value: &str, len: usize| {
+            let bytes = value.as_bytes();
+            let used = bytes.len().min(len);
+            chunk.extend_from_slice(&bytes[..used]);
+            chunk.resize(chunk.len() + len - used, 0);
+        };
+        let (date, time) = utc_date_time(self.origination);
+        text(&self.description, 256);
+        text(&self.originator, 32);
+        text(&self.originator_reference, 32);
+        text(&date, 10);
+        text(&time, 8);
+        chunk.extend_from_slice(&self.time_reference.to_le_bytes());
+        // Version 1: UMID present but zero, loudness fields reserved
+        chunk.extend_from_slice(&1u16.to_le_bytes());
+        chunk.resize(BEXT_FIXED_LEN, 0);
+        chunk.extend_from_slice(self.coding_history.as_bytes());
+        chunk
+    }
+}
+
+/// `yyyy-mm-dd` and `hh:mm:ss` of a Unix time in UTC.
+fn utc_date_time(seconds: u64) -> (String, String) {
+    let (days, second) = (seconds / 86400, seconds % 86400);
+    // Civil-from-days, counting eras of 400 years from 0000-03-01
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!("{:02}:{:02}:{:02}", second / 3600, second / 60 % 60, second % 60),
+    )
+}
+
+/// Incremental WAV writer for a file whose length is known up front. The
+/// headers are written on creation and corrected on [`finish`](Self::finish)
+/// if fewer samples arrived. Until then the file is written beside its
+/// path with a `.partial` suffix, and removed if the writer is dropped, so
+/// an interrupted write never leaves a truncated file behind.
+pub struct Writer {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    partial: PathBuf,
+    finished: bool,
+    spec: Spec,
+    bext: Option<Vec<u8>>,
+    rf64: bool,
+    /// Samples the headers announce
+    len: u64,
+    written: u64,
+}
+
+impl Writer {
+    /// Creates `path` for `len` samples, as RF64 if `force_rf64` is set or
+    /// the file would not fit a 32-bit RIFF size.
+    pub fn create(path: &Path, spec: Spec, len: u64, bext: Option<&Bext>, force_rf64: bool) -> Result<Self> {
+        let bext = bext.map(Bext::chunk);
+        let rf64 = force_rf64 || riff_len(spec, len, bext.as_deref(), false) > u32::MAX as u64;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = File::create(&partial).with_context(|| format!("Failed to create {}", partial.display()))?;
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+            path: path.to_path_buf(),
+            partial,
+            finished: false,
+            spec,
+            bext,
+            rf64,
+            len,
+            written: 0,
+        };
+        writer.writer.write_all(&header(spec, len, writer.bext.as_deref(), rf64))?;
+        Ok(writer)
+    }
+
+    /// Appends samples, which must be quantized to the spec's word length
+    /// if they are integer codes.
+    pub fn write(&mut self, data: Data) -> Result<()> {
+        self.written += data.len() as u64;
+        if self.written > self.len {
+            bail!("{} is announced with {} samples, got more", self.path.display(), self.len);
+        }
+        let bytes_per_sample = self.spec.bits_per_sample.div_ceil(8) as usize;
+        let result = match data {
+            Data::Float(channels) => interleave(channels).try_for_each(|sample| self.writer.write_all(&sample.to_le_bytes())),
+            Data::Int(codes) => codes
+                .iter()
+                .try_for_each(|code| self.writer.write_all(&code.to_le_bytes()[..bytes_per_sample])),
+        };
+        result.with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Pads the data chunk and, if fewer samples were written than
+    /// announced, rewrites the headers to match.
+    pub fn finish(mut self) -> Result<()> {
+        let data_len = self.written * self.spec.bits_per_sample.div_ceil(8) as u64;
+        if data_len % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+        if self.written != self.len {
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.writer.write_all(&header(self.spec, self.written, self.bext.as_deref(), self.rf64))?;
+        }
+        self.writer.flush().with_context(|| format!("Failed to write {}", self.path.display()))?;
+        std::fs::rename(&self.partial, &self.path).with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = std::fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Size of a chunk with an 8-byte header and padding to an even length.
+fn chunk_len(len: u64) -> u64 {
+    8 + len + len % 2
+}
+
+/// More than two channels need WAVE_FORMAT_EXTENSIBLE to carry the
+/// speaker layout.
+fn fmt_len(spec: Spec) -> u64 {
+    if spec.channels > 2 { 40 } else { 16 }
+}
+
+/// RIFF size of a file of `len` samples.
+fn riff_len(spec: Spec, len: u64, bext: Option<&[u8]>, rf64: bool) -> u64 {
+    let data_len = len * spec.bits_per_sample.div_ceil(8) as u64;
+    let mut riff_len = 4 + chunk_len(fmt_len(spec)) + chunk_len(data_len);
+    if let Some(bext) = bext {
+        riff_len += chunk_len(bext.len() as u64);
+    }
+    if rf64 {
+        riff_len += chunk_len(28);
+    }
+    riff_len
+}
+
+/// Everything before the samples of a file of `len` samples.
+fn header(spec: Spec, len: u64, bext: Option<&[u8]>, rf64: bool) -> Vec<u8> {
+    let data_len = len * spec.bits_per_sample.div_ceil(8) as u64;
+    let riff_len = riff_len(spec, len, bext, rf64);
+    let mut header = Vec::new();
+    let chunk = |header: &mut Vec<u8>, id: &[u8; 4], body: &[u8]| {
+        header.extend_from_slice(id);
+        header.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        header.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            header.push(0);
+        }
+    };
+
+    if rf64 {
+        header.extend_from_slice(b"RF64");
+        header.extend_from_slice(&RF64_SIZE.to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+        let frames = len / spec.channels.max(1) as u64;
+        let ds64: Vec<u8> = [riff_len, data_len, frames]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .chain(0u32.to_le_bytes())
+            .collect();
+        chunk(&mut header, b"ds64", &ds64);
+    } else {
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(riff_len as u32).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+    }
+
+    if let Some(bext) = bext {
+        chunk(&mut header, b"bext", bext);
+    }
+
+    let extensible = fmt_len(spec) == 40;
+    let mut fmt = Vec::with_capacity(fmt_len(spec) as usize);
+    let format = if spec.float { FORMAT_FLOAT } else { FORMAT_PCM };
+    fmt.extend_from_slice(&(if extensible { FORMAT_EXTENSIBLE } else { format }).to_le_bytes());
+    fmt.extend_from_slice(&spec.channels.to_le_bytes());
+    fmt.extend_from_slice(&spec.sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(spec.sample_rate * spec.block_align() as u32).to_le_bytes());
+    fmt.extend_from_slice(&spec.block_align().to_le_bytes());
+    fmt.extend_from_slice(&spec.bits_per_sample.to_le_bytes());
+    if extensible {
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&spec.bits_per_sample.to_le_bytes());
+        fmt.extend_from_slice(&spec.channel_mask.to_le_bytes());
+        fmt.extend_from_slice(&format.to_le_bytes());
+        fmt.extend_from_slice(&GUID_TAIL);
+    }
+    chunk(&mut header, b"fmt ", &fmt);
+
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&(if rf64 { RF64_SIZE } else { data_len as u32 }).to_le_bytes());
+    header
+}
+
+/// Writes `data` to `path`, as RF64 if `force_rf64` is set or the file
+/// would not fit a 32-bit RIFF size.
+pub fn write(path: &Path, spec: Spec, data: Data, bext: Option<&Bext>, force_rf64: bool) -> Result<()> {
+    let mut writer = Writer::create(path, spec, data.len() as u64, bext, force_rf64)?;
+    writer.write(data)?;
+    writer.finish()
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+/// Incremental WAV reader, positioned at the start of the samples.
+pub struct Reader<R> {
+    reader: R,
+    spec: Spec,
+    /// Bytes of sample data not yet read
+    remaining: u64,
+}
+
+impl<R: Read> Reader<R> {
+    /// Reads a RIFF, RF64 or BW64 stream's chunks up to its samples.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header).context("Failed to read WAV header")?;
+        if !matches!(&header[..4], b"RIFF" | b"RF64" | b"BW64") || &header[8..] != b"WAVE" {
+            bail!("Not a WAV file");
+        }
+
+        let mut spec = None;
+        let mut ds64_data_len = None;
+        loop {
+            let mut chunk_header = [0u8; 8];
+            reader.read_exact(&mut chunk_header).context("WAV file has no data chunk")?;
+            let id = &chunk_header[..4];
+            let len = read_u32(&chunk_header, 4);
+
+            if id == b"data" {
+                let spec: Spec = spec.context("WAV data chunk precedes its fmt chunk")?;
+                let remaining = match (len, ds64_data_len) {
+                    (RF64_SIZE, Some(len)) => len,
+                    _ => len as u64,
+                };
+                return Ok(Self { reader, spec, remaining });
+            }
+
+            let mut body = vec![0u8; len as usize + len as usize % 2];
+            reader.read_exact(&mut body).context("Truncated WAV chunk")?;
+            match id {
+                b"ds64" if len >= 16 => ds64_data_len = Some(read_u64(&body, 8)),
+                b"fmt " if len >= 16 => {
+                    let mut format = read_u16(&body, 0);
+                    let mut channel_mask = 0;
+                    if format == FORMAT_EXTENSIBLE && len >= 26 {
+                        channel_mask = read_u32(&body, 20);
+                        // The sub-format GUID starts with the plain format tag
+                        format = read_u16(&body, 24);
+                    }
+                    let bits_per_sample = read_u16(&body, 14);
+                    let float = match (format, bits_per_sample) {
+                        (FORMAT_PCM, 8 | 16 | 24 | 32) => false,
+                        (FORMAT_FLOAT, 32 | 64) => true,
+                        _ => bail!("Unsupported WAV format {} with {} bits per sample", format, bits_per_sample),
+                    };
+                    spec = Some(Spec {
+                        channels: read_u16(&body, 2),
+                        sample_rate: read_u32(&body, 4),
+                        bits_per_sample,
+                        float,
+                        channel_mask,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn spec(&self) -> Spec {
+        self.spec
+    }
+
+    /// Samples left according to the header; a truncated file has fewer.
+    pub fn remaining_samples(&self) -> u64 {
+        self.remaining / self.spec.bits_per_sample.div_ceil(8) as u64
+    }
+
+    /// Reads up to `max` more frames normalized to [-1.0, 1.0], one vector
+    /// per channel, or empty channels at the end of the data.
+    pub fn read_frames(&mut self, max: usize) -> Result<Vec<Vec<f32>>> {
+        let spec = self.spec;
+        let width = spec.bits_per_sample.div_ceil(8) as usize;
+        let channels = spec.channels.max(1) as usize;
+        let len = self.remaining.min((max as u64).saturating_mul((width * channels) as u64));
+        let mut bytes = Vec::with_capacity(len as usize);
+        (&mut self.reader).take(len).read_to_end(&mut bytes).context("Failed to read WAV samples")?;
+        // Tolerate a data size that overstates a truncated file
+        self.remaining = if (bytes.len() as u64) < len { 0 } else { self.remaining - len };
+        let frames = bytes.len() / (width * channels);
+
+        let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+        let decode = |sample: &[u8]| match (spec.float, width) {
+            (true, 4) => f32::from_le_bytes(sample.try_into().unwrap()),
+            (true, _) => f64::from_le_bytes(sample.try_into().unwrap()) as f32,
+            // 8-bit PCM is unsigned
+            (false, 1) => (sample[0] as i32 - 128) as f32 * scale,
+            (false, _) => {
+                let mut word = [0u8; 4];
+                word[4 - width..].copy_from_slice(sample);
+                (i32::from_le_bytes(word) >> (8 * (4 - width))) as f32 * scale
+            }
+        };
+        let planes = (0..channels)
+            .map(|channel| {
+                (0..frames)
+                    .map(|frame| {
+                        let at = (frame * channels + channel) * width;
+                        decode(&bytes[at..at + width])
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(planes)
+    }
+}
+
+/// Reads a RIFF, RF64 or BW64 WAV stream into one vector of samples per
+/// channel, normalized to [-1.0, 1.0].
+pub fn read<R: Read>(reader: R) -> Result<(Spec, Vec<Vec<f32>>)> {
+    let mut reader = Reader::new(reader)?;
+    let channels = reader.read_frames(usize::MAX)?;
+    Ok((reader.spec(), channels))
+}
@@ -0,0 +1,68 @@
+//! Frequency weighting curves from IEC 61672-1.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use super::biquad::{cascade_response_db, prewarp, Biquad};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    /// A-weighting, approximating loudness perception at low levels
+    A,
+    /// C-weighting, nearly flat across the audible range
+    C,
+    /// No weighting
+    #[default]
+    Z,
+}
+
+// Pole frequencies of the analog weighting networks (Hz)
+const F1: f64 = 20.598_997;
+const F2: f64 = 107.652_65;
+const F3: f64 = 737.862_23;
+const F4: f64 = 12_194.217;
+
+impl Weighting {
+    /// Gain of the weighting curve at `frequency` in dB, normalized to 0 dB
+    /// at 1 kHz.
+    pub fn gain_db(self, frequency: f32) -> f32 {
+        let f2 = (frequency as f64).powi(2);
+        let gain = match self {
+            Weighting::Z => return 0.0,
+            Weighting::A => {
+                F4 * F4 * f2 * f2
+                    / ((f2 + F1 * F1) * ((f2 + F2 * F2) * (f2 + F3 * F3)).sqrt() * (f2 + F4 * F4))
+            }
+            Weighting::C => F4 * F4 * f2 / ((f2 + F1 * F1) * (f2 + F4 * F4)),
+        };
+        (20.0 * gain.log10() - self.offset_db()) as f32
+    }
+
+    /// Biquad cascade realizing the curve at `sample_rate`, normalized to
+    /// 0 dB at 1 kHz. Empty for Z-weighting.
+    pub fn filter(self, sample_rate: u32) -> Vec<Biquad> {
+        let [w1, w2, w3, w4] = [F1, F2, F3, F4].map(|f| prewarp(f, sample_rate));
+        // s² / ((s + a)(s + b)) and ab / ((s + a)(s + b))
+        let high_pass = |a: f64, b: f64| Biquad::from_analog([0.0, 0.0, 1.0], [a * b, a + b, 1.0], sample_rate);
+        let low_pass = |a: f64, b: f64| Biquad::from_analog([a * b, 0.0, 0.0], [a * b, a + b, 1.0], sample_rate);
+
+        let mut cascade = match self {
+            Weighting::Z => return Vec::new(),
+            Weighting::A => vec![high_pass(w1, w1), high_pass(w2, w3), low_pass(w4, w4)],
+            Weighting::C => vec![high_pass(w1, w1), low_pass(w4, w4)],
+        };
+        let gain_at_1k = cascade_response_db(&cascade, 1000.0, sample_rate);
+        cascade[0] = cascade[0].scaled(10f64.powf(-gain_at_1k as f64 / 20.0));
+        cascade
+    }
+
+    /// Response of the unnormalized curve at 1 kHz.
+    fn offset_db(self) -> f64 {
+        match self {
+            Weighting::A => -2.000_0,
+            Weighting::C => -0.061_9,
+            Weighting::Z => 0.0,
+        }
+    }
+}
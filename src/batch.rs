@@ -0,0 +1,303 @@
+//! Batch mode: a directory given as `--input` has each of its audio files
+//! split into an output subdirectory of the same name. A file can carry a
+//! sidecar config, e.g. `track01.mp3.saunds.toml`, whose keys are long
+//! option names overriding the command line for that file:
+//!
+//! ```toml
+//! low-cutoff = 120
+//! filter = "zero-phase"
+//! fx = ["saturate:drive=4"]
+//! ```
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use saunds_v2::audio::{codec, intermediate, AudioProcessor, DecodeErrorPolicy};
+
+use crate::{Cli, SplitArgs};
+
+/// Appended to an input's file name to find its sidecar config.
+pub const SIDECAR_SUFFIX: &str = ".saunds.toml";
+
+/// Whether `name` matches a shell-style `pattern`, where `*` matches any
+/// run of characters and `?` any single character.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and how much of the name it has taken
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `file` passes the `--include`/`--exclude` patterns and the
+/// duration limits.
+fn selected(file: &Path, cli: &SplitArgs) -> Result<bool> {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    if !cli.include.is_empty() && !cli.include.iter().any(|pattern| glob_match(pattern, &name)) {
+        info!("Skipping {}: not included", name);
+        return Ok(false);
+    }
+    if let Some(pattern) = cli.exclude.iter().find(|pattern| glob_match(pattern, &name)) {
+        info!("Skipping {}: excluded by '{}'", name, pattern);
+        return Ok(false);
+    }
+    if cli.min_duration.is_none() && cli.max_duration.is_none() {
+        return Ok(true);
+    }
+
+    let mut processor = AudioProcessor::new()?.with_decode_error_policy(cli.on_decode_error);
+    let samples = processor.load_audio(file)?;
+    let seconds = samples.len() as f64 / processor.channels().max(1) as f64 / processor.sample_rate() as f64;
+    if cli.min_duration.is_some_and(|min| seconds < min) || cli.max_duration.is_some_and(|max| seconds > max) {
+        info!("Skipping {}: {:.1} s is outside the duration limits", name, seconds);
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Audio files directly inside `dir`, in file name order.
+fn inputs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut inputs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    inputs.retain(|path| {
+        path.is_file()
+            && path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| {
+                ["wav", "mp3", intermediate::EXTENSION].iter().any(|known| ext.eq_ignore_ascii_case(known))
+            })
+    });
+    inputs.sort();
+    Ok(inputs)
+}
+
+/// The split options as clap sees them, for checking config keys.
+pub fn split_command() -> clap::Command {
+    SplitArgs::augment_args(clap::Command::new("split"))
+}
+
+/// Options from `input`'s sidecar, as long option names and the
+/// command-line tokens that set them.
+fn sidecar_options(input: &Path) -> Result<Vec<(String, Vec<OsString>)>> {
+    let mut path = input.as_os_str().to_owned();
+    path.push(SIDECAR_SUFFIX);
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    info!("Applying sidecar {}", path.display());
+    let options = config_options(&path)?;
+    if let Some((key, _)) = options.iter().find(|(key, _)| matches!(key.as_str(), "input" | "output")) {
+        bail!("{}: '{}' can't be set per file", path.display(), key);
+    }
+    Ok(options)
+}
+
+/// Options from a TOML config whose keys are long split option names, as
+/// those names and the command-line tokens that set them.
+pub fn config_options(path: &Path) -> Result<Vec<(String, Vec<OsString>)>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let document = toml_edit::Document::parse(text).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let command = split_command();
+    let mut options = Vec::new();
+    for (key, item) in document.iter() {
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(key)) else {
+            bail!("{}: unknown option '{}'", path.display(), key);
+        };
+        let value = item
+            .as_value()
+            .with_context(|| format!("{}: '{}' must be a value, not a table", path.display(), key))?;
+
+        let scalar = |value: &toml_edit::Value| -> Result<String> {
+            Ok(match value {
+                toml_edit::Value::String(text) => text.value().clone(),
+                toml_edit::Value::Integer(number) => number.value().to_string(),
+                toml_edit::Value::Float(number) => number.value().to_string(),
+                other => bail!("{}: unsupported {} value for '{}'", path.display(), other.type_name(), key),
+            })
+        };
+        let tokens = if !arg.get_action().takes_values() {
+            match value.as_bool() {
+                Some(true) => vec![OsString::from(format!("--{}", key))],
+                Some(false) => Vec::new(),
+                None => bail!("{}: '{}' is a switch and takes true or false", path.display(), key),
+            }
+        } else if let Some(array) = value.as_array() {
+            if !matches!(arg.get_action(), clap::ArgAction::Append) {
+                bail!("{}: '{}' takes a single value", path.display(), key);
+            }
+            array
+                .iter()
+                .map(|value| Ok(OsString::from(format!("--{}={}", key, scalar(value)?))))
+                .collect::<Result<_>>()?
+        } else {
+            vec![OsString::from(format!("--{}={}", key, scalar(value)?))]
+        };
+        options.push((key.to_string(), tokens));
+    }
+    Ok(options)
+}
+
+/// `argv` without any occurrence of the options in `names`.
+pub fn without_options(argv: &[OsString], names: &HashSet<&str>) -> Vec<OsString> {
+    let command = split_command();
+    let removed: Vec<&clap::Arg> = command
+        .get_arguments()
+        .filter(|arg| arg.get_long().is_some_and(|long| names.contains(long)))
+        .collect();
+
+    let mut kept = Vec::new();
+    let mut tokens = argv.iter();
+    while let Some(token) = tokens.next() {
+        let text = token.to_string_lossy();
+        let matched = removed.iter().find_map(|arg| {
+            let long = format!("--{}", arg.get_long()?);
+            let short = arg.get_short().map(|short| format!("-{}", short));
+            if text == long || short.as_deref() == Some(text.as_ref()) {
+                Some(arg.get_action().takes_values())
+            } else if text.starts_with(&format!("{}=", long)) || short.is_some_and(|short| text.starts_with(&short)) {
+                // The value is part of this token
+                Some(false)
+            } else {
+                None
+            }
+        });
+        match matched {
+            Some(true) => {
+                tokens.next();
+            }
+            Some(false) => {}
+            None => kept.push(token.clone()),
+        }
+    }
+    kept
+}
+
+/// The split options for `file`: the batch's command line with its input
+/// and output replaced and the file's sidecar applied.
+fn file_options(cli: &SplitArgs, file: &Path) -> Result<SplitArgs> {
+    let output = cli.output.join(file.file_stem().unwrap_or_default());
+    let options = sidecar_options(file)?;
+    let mut names: HashSet<&str> = options.iter().map(|(name, _)| name.as_str()).collect();
+    names.extend(["input", "output"]);
+    let mut args = without_options(&cli.argv, &names);
+    args.push(OsString::from("--input"));
+    args.push(file.as_os_str().to_owned());
+    args.push(OsString::from("--output"));
+    args.push(output.as_os_str().to_owned());
+    args.extend(options.into_iter().flat_map(|(_, tokens)| tokens));
+
+    let parsed = Cli::try_parse_from(&args).with_context(|| format!("Invalid options for {}", file.display()))?;
+    let mut split = parsed.split.context("Batch mode runs the band split")?;
+    split.argv = args;
+    Ok(split)
+}
+
+/// An input a batch run decoded while the one before it was split.
+pub struct Prefetched {
+    pub decoded: codec::Decoded,
+    pub decode_time: Duration,
+}
+
+fn prefetch(path: &Path, policy: DecodeErrorPolicy) -> Result<Prefetched> {
+    let start = Instant::now();
+    let decoded = AudioProcessor::new()?.with_decode_error_policy(policy).decode_file(path)?;
+    Ok(Prefetched { decoded, decode_time: start.elapsed() })
+}
+
+/// Splits every audio file in `cli.input`, continuing past failures and
+/// reporting them at the end.
+pub fn run(cli: &SplitArgs) -> Result<()> {
+    let mut files = Vec::new();
+    for file in inputs(&cli.input)? {
+        if selected(&file, cli)? {
+            files.push(file);
+        }
+    }
+    if files.is_empty() {
+        bail!("No matching WAV, MP3 or intermediate files in {}", cli.input.display());
+    }
+    let mut stems = HashSet::new();
+    for file in &files {
+        let stem = file.file_stem().unwrap_or_default();
+        if !stems.insert(stem.to_owned()) {
+            bail!("Several inputs would write to {}; rename one", cli.output.join(stem).display());
+        }
+    }
+    info!("Batch of {} files", files.len());
+
+    if cli.save_project.is_some() {
+        bail!("--save-project needs a single input file, not a directory");
+    }
+    let splits: Vec<Result<SplitArgs>> = files.iter().map(|file| file_options(cli, file)).collect();
+    // Inputs streamed under --max-memory aren't decoded whole
+    let decodes: Vec<Option<(PathBuf, DecodeErrorPolicy)>> = splits
+        .iter()
+        .map(|split| split.as_ref().ok().filter(|split| split.max_memory.is_none()).map(|split| (split.input.clone(), split.on_decode_error)))
+        .collect();
+
+    let mut failures = 0;
+    std::thread::scope(|scope| {
+        // The decoder thread decodes the next input while the current one
+        // is split, then waits for the split to take it, so at most one
+        // input is held ahead
+        let (sender, prefetched) = mpsc::sync_channel(0);
+        scope.spawn(move || {
+            for decode in decodes {
+                if crate::interrupt::check().is_err() {
+                    break;
+                }
+                if sender.send(decode.map(|(path, policy)| prefetch(&path, policy))).is_err() {
+                    break;
+                }
+            }
+        });
+
+        for (done, (file, split)) in files.iter().zip(splits).enumerate() {
+            crate::interrupt::check().with_context(|| format!("Stopped after {} of {} files", done, files.len()))?;
+            let input = prefetched.recv().ok().flatten();
+            if let Err(e) = split.and_then(|split| crate::split_input(split, input)) {
+                if crate::interrupt::is_interrupted(&e) {
+                    return Err(e.context(format!("Stopped in {} after {} of {} files", file.display(), done, files.len())));
+                }
+                error!("{}: {:#}", file.display(), e);
+                failures += 1;
+            }
+        }
+        Ok(())
+    })?;
+
+    if failures > 0 {
+        bail!("{} of {} files failed", failures, files.len());
+    }
+    info!("Batch complete: {} files", files.len());
+    Ok(())
+}
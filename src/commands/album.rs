@@ -0,0 +1,115 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use saunds_v2::audio::{loudness, AudioBuffer, AudioProcessor, FilterDesign, FilterMode};
+
+use super::{DecodeArgs, DesignArgs};
+
+#[derive(Args, Debug)]
+pub struct AlbumArgs {
+    /// Directory of WAV and MP3 tracks, played in file name order
+    input: PathBuf,
+
+    /// Output directory; each track's bands go into a subdirectory named
+    /// after it
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Low frequency cutoff (Hz)
+    #[arg(long, default_value = "200")]
+    low_cutoff: f32,
+
+    /// High frequency cutoff (Hz)
+    #[arg(long, default_value = "2000")]
+    high_cutoff: f32,
+
+    /// Filter used for the band split
+    #[arg(long, value_enum, default_value_t = FilterMode::Fft)]
+    filter: FilterMode,
+
+    #[command(flatten)]
+    design: DesignArgs,
+
+    /// Apply one gain to the whole album so its integrated loudness hits
+    /// this target (LUFS)
+    #[arg(long, allow_hyphen_values = true)]
+    target_lufs: Option<f32>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: AlbumArgs) -> Result<()> {
+    let mut tracks: Vec<PathBuf> = std::fs::read_dir(&args.input)
+        .with_context(|| format!("Failed to read directory {}", args.input.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    tracks.retain(|path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav") || ext.eq_ignore_ascii_case("mp3"))
+    });
+    tracks.sort();
+    if tracks.is_empty() {
+        bail!("No WAV or MP3 tracks in {}", args.input.display());
+    }
+
+    let design = args.design.design();
+    if args.filter == FilterMode::Fft && design != FilterDesign::default() {
+        bail!("--design, --order, --ripple and --attenuation require --filter iir or zero-phase");
+    }
+    design.validate()?;
+
+    // Decode every track into one stream, remembering where each begins
+    let mut processor = AudioProcessor::new()?
+        .with_decode_error_policy(args.decode.on_decode_error)
+        .with_filter_mode(args.filter)
+        .with_filter_design(design);
+    let mut stream: Vec<Vec<f32>> = Vec::new();
+    let mut boundaries = vec![0];
+    let mut format = None;
+    for track in &tracks {
+        let buffer = processor.load_buffer(track)?;
+        let track_format = (buffer.sample_rate(), buffer.num_channels());
+        if *format.get_or_insert(track_format) != track_format {
+            bail!("{} does not match the sample rate and channels of the first track", track.display());
+        }
+        boundaries.push(boundaries.last().unwrap() + buffer.frames());
+        stream.resize_with(buffer.num_channels(), Vec::new);
+        for (channel, samples) in stream.iter_mut().zip(buffer.into_planar()) {
+            channel.extend(samples);
+        }
+    }
+    let mut stream = AudioBuffer::from_planar(stream, processor.sample_rate())?;
+    info!("Album of {} tracks, {:.1} s", tracks.len(), stream.duration_seconds());
+
+    if let Some(target) = args.target_lufs {
+        let measured = loudness::measure(&stream)?;
+        let gain_db = target - measured.integrated_lufs;
+        info!("Album loudness {:.1} LUFS; applying {:+.1} dB", measured.integrated_lufs, gain_db);
+        let gain = 10f32.powf(gain_db / 20.0);
+        stream.channels_mut().flatten().for_each(|sample| *sample *= gain);
+        let peak_db = measured.sample_peak_dbfs + gain_db;
+        if peak_db > 0.0 {
+            warn!("Album sample peak reaches {:+.1} dBFS after normalization", peak_db);
+        }
+    }
+
+    processor.validate_cutoffs(args.low_cutoff, args.high_cutoff)?;
+    let (low, high) = processor.separate_buffer(&stream, args.low_cutoff, args.high_cutoff)?;
+    let part = |band: &AudioBuffer, range: &[usize]| {
+        band.like(band.channels().map(|channel| channel[range[0]..range[1]].to_vec()).collect())
+    };
+
+    for (track, range) in tracks.iter().zip(boundaries.windows(2)) {
+        let name = track.file_stem().unwrap_or_default();
+        let dir = args.output.join(name);
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        info!("Writing {}", dir.display());
+        processor.save_buffer(dir.join("low_freq.wav"), &part(&low, range)?)?;
+        processor.save_buffer(dir.join("high_freq.wav"), &part(&high, range)?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use saunds_v2::audio::{align, mixdown, resample};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct AlignArgs {
+    /// Reference recording, or a stereo recording to check right against
+    /// left when no target is given
+    reference: PathBuf,
+
+    /// Recording to align to the reference
+    target: Option<PathBuf>,
+
+    /// Output directory for the aligned files, named `<stem>.aligned.wav`,
+    /// or `<stem>.reference.aligned.wav` and `<stem>.target.aligned.wav`
+    /// when the inputs share a name. Without it the delay and polarity are
+    /// only reported
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Largest delay to search for, in seconds
+    #[arg(long)]
+    max_delay: Option<f32>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: AlignArgs) -> Result<()> {
+    let Some(target_path) = &args.target else {
+        return run_channels(&args);
+    };
+    let mut reference_processor = args.decode.processor()?;
+    let mut target_processor = args.decode.processor()?;
+
+    let reference = reference_processor.load_audio(&args.reference)?;
+    let mut target = target_processor.load_audio(target_path)?;
+
+    let sample_rate = reference_processor.sample_rate();
+    if target_processor.sample_rate() != sample_rate {
+        bail!(
+            "Sample rates differ: {} is {} Hz, {} is {} Hz",
+            args.reference.display(), sample_rate,
+            target_path.display(), target_processor.sample_rate()
+        );
+    }
+
+    let reference_channels = reference_processor.channels() as usize;
+    let target_channels = target_processor.channels() as usize;
+
+    info!("Estimating delay with GCC-PHAT...");
+    let estimate = align::estimate_delay(
+        &mixdown(&reference, reference_channels),
+        &mixdown(&target, target_channels),
+        max_delay(&args, sample_rate),
+    )?;
+    report(&estimate, sample_rate, "Target", "reference");
+
+    let Some(output) = &args.output else {
+        return Ok(());
+    };
+    if !output.exists() {
+        std::fs::create_dir_all(output)?;
+    }
+
+    // A weak peak's sign says nothing about polarity
+    if estimate.inverted && estimate.peak_ratio >= align::MIN_PEAK_RATIO {
+        target.iter_mut().for_each(|sample| *sample = -*sample);
+    }
+    // Both outputs cover the reference's duration
+    let frames = reference.len() / reference_channels.max(1);
+    let aligned = align::apply_delay(&target, target_channels, estimate.frames, frames);
+
+    let mut reference_output = aligned_path(output, &args.reference, None);
+    let mut target_output = aligned_path(output, target_path, None);
+    if reference_output == target_output {
+        // Inputs of the same name from different directories mustn't
+        // overwrite each other
+        reference_output = aligned_path(output, &args.reference, Some("reference"));
+        target_output = aligned_path(output, target_path, Some("target"));
+    }
+    info!("Saving aligned reference to: {}", reference_output.display());
+    reference_processor.save_audio(&reference_output, &reference)?;
+    info!("Saving aligned target to: {}", target_output.display());
+    target_processor.save_audio(&target_output, &aligned)?;
+
+    Ok(())
+}
+
+/// Checks the right channel of a stereo recording against the left, and
+/// with an output realigns and flips the right channel to match.
+fn run_channels(args: &AlignArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.reference)?;
+    let channels = processor.channels() as usize;
+    if channels != 2 {
+        bail!(
+            "Aligning the channels of one recording needs a stereo input, but {} has {} channels; give a target recording instead",
+            args.reference.display(),
+            channels
+        );
+    }
+    let sample_rate = processor.sample_rate();
+    let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+    let right: Vec<f32> = samples.iter().skip(1).step_by(2).copied().collect();
+
+    info!("Estimating delay with GCC-PHAT...");
+    let estimate = align::estimate_delay(&left, &right, max_delay(args, sample_rate))?;
+    report(&estimate, sample_rate, "R", "L");
+
+    let Some(output) = &args.output else {
+        return Ok(());
+    };
+    if !output.exists() {
+        std::fs::create_dir_all(output)?;
+    }
+    if estimate.inverted && estimate.peak_ratio >= align::MIN_PEAK_RATIO {
+        samples.iter_mut().skip(1).step_by(2).for_each(|sample| *sample = -*sample);
+    }
+    resample::delay(&mut samples, 2, 1, -(estimate.frames as f64));
+
+    let path = aligned_path(output, &args.reference, None);
+    info!("Saving aligned channels to: {}", path.display());
+    processor.save_audio(&path, &samples)
+}
+
+fn max_delay(args: &AlignArgs, sample_rate: u32) -> Option<usize> {
+    args.max_delay.map(|seconds| (seconds * sample_rate as f32) as usize)
+}
+
+fn report(estimate: &align::DelayEstimate, sample_rate: u32, target: &str, reference: &str) {
+    info!(
+        "{} lags {} by {} samples ({:.3} ms), peak ratio {:.1}",
+        target,
+        reference,
+        estimate.frames,
+        estimate.frames as f64 * 1000.0 / sample_rate as f64,
+        estimate.peak_ratio
+    );
+    if estimate.peak_ratio < align::MIN_PEAK_RATIO {
+        warn!("Weak correlation peak; the recordings may not contain the same material");
+    } else if estimate.inverted {
+        warn!("{} has inverted polarity against {}", target, reference);
+    }
+}
+
+/// `<stem>.aligned.wav` in `output`, or `<stem>.<role>.aligned.wav` to tell
+/// apart inputs of the same name.
+fn aligned_path(output: &Path, input: &Path, role: Option<&str>) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    match role {
+        Some(role) => output.join(format!("{}.{}.aligned.wav", stem, role)),
+        None => output.join(format!("{}.aligned.wav", stem)),
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{analysis, weighting::Weighting, wow_flutter};
+#[cfg(feature = "plots")]
+use saunds_v2::audio::AudioBuffer;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Report levels in 1/1 or 1/3 octave bands
+    #[arg(long, value_name = "FRACTION", value_parser = ["1", "3"], required_unless_present = "wow_flutter")]
+    octave_bands: Option<String>,
+
+    /// Report wow and flutter instead, tracking a test tone of this
+    /// frequency (3150 Hz if not given) through the recording
+    #[arg(long, value_name = "HZ", num_args = 0..=1, default_missing_value = "3150", conflicts_with = "octave_bands")]
+    wow_flutter: Option<f32>,
+
+    /// Frequency weighting applied before measuring
+    #[arg(long, value_enum, default_value_t = Weighting::Z)]
+    weighting: Weighting,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Also plot the long-term average spectrum to this .svg or .png file
+    #[cfg(feature = "plots")]
+    #[arg(long, value_name = "FILE")]
+    plot_spectrum: Option<PathBuf>,
+
+    /// Also plot the momentary and short-term loudness history to this
+    /// .svg or .png file
+    #[cfg(feature = "plots")]
+    #[arg(long, value_name = "FILE")]
+    plot_loudness: Option<PathBuf>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+pub fn run(args: AnalyzeArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let buffer = processor.load_buffer(&args.input)?;
+    let mono = buffer.mixdown();
+    if let Some(reference_hz) = args.wow_flutter {
+        return report_wow_flutter(&args, &mono, processor.sample_rate(), reference_hz);
+    }
+
+    let fraction: u32 = args.octave_bands.as_deref().unwrap_or("1").parse()?;
+    info!("Measuring 1/{} octave band levels ({:?}-weighted)", fraction, args.weighting);
+    let bands = analysis::octave_band_levels(&mono, processor.sample_rate(), fraction, args.weighting)?;
+    let tempo = analysis::estimate_tempo(&mono, processor.sample_rate())?;
+    match tempo {
+        Some(bpm) => info!("Estimated tempo: {:.1} BPM", bpm),
+        None => info!("No tempo detected"),
+    }
+
+    let noise_floor = analysis::noise_floor(&mono, processor.sample_rate())?;
+    let broadband = 10.0 * noise_floor.iter().map(|band| 10f32.powf(band.level_db / 10.0)).sum::<f32>().log10();
+    info!("Estimated noise floor: {:.1} dBFS broadband", broadband);
+
+    #[cfg(feature = "plots")]
+    plot(&args, &buffer, &mono)?;
+
+    match args.format {
+        Format::Table => {
+            println!("{:>10}  {:>10}  {:>10}  {:>8}", "center Hz", "lower Hz", "upper Hz", "dB");
+            for band in &bands {
+                println!(
+                    "{:>10.1}  {:>10.1}  {:>10.1}  {:>8.1}",
+                    band.center, band.lower, band.upper, band.level_db
+                );
+            }
+            println!();
+            println!("{:>10}  {:>16}", "octave Hz", "noise floor dBFS");
+            for band in &noise_floor {
+                println!("{:>10.1}  {:>16.1}", band.center, band.level_db);
+            }
+        }
+        Format::Json => {
+            let report = serde_json::json!({
+                "input": args.input,
+                "sample_rate": processor.sample_rate(),
+                "fraction": fraction,
+                "weighting": args.weighting,
+                "tempo_bpm": tempo,
+                "bands": bands,
+                "noise_floor_dbfs": broadband,
+                "noise_floor": noise_floor,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn report_wow_flutter(args: &AnalyzeArgs, mono: &[f32], sample_rate: u32, reference_hz: f32) -> Result<()> {
+    info!("Tracking the {} Hz test tone", reference_hz);
+    let measured = wow_flutter::measure(mono, sample_rate, reference_hz)?;
+    info!(
+        "Wow and flutter {:.3}% weighted peak, speed {:+.2}%",
+        measured.weighted_peak_percent, measured.speed_error_percent
+    );
+    match args.format {
+        Format::Table => {
+            let rows = [
+                ("reference Hz", measured.reference_hz),
+                ("mean Hz", measured.mean_hz),
+                ("speed error %", measured.speed_error_percent),
+                ("weighted peak (2 sigma) %", measured.weighted_peak_percent),
+                ("weighted RMS %", measured.weighted_rms_percent),
+                ("wow RMS %", measured.wow_rms_percent),
+                ("flutter RMS %", measured.flutter_rms_percent),
+            ];
+            for (name, value) in rows {
+                println!("{:<26}  {:>9.3}", name, value);
+            }
+        }
+        Format::Json => {
+            let report = serde_json::json!({
+                "input": args.input,
+                "sample_rate": sample_rate,
+                "wow_flutter": measured,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "plots")]
+fn plot(args: &AnalyzeArgs, buffer: &AudioBuffer, mono: &[f32]) -> Result<()> {
+    use crate::plot;
+    use saunds_v2::audio::loudness;
+
+    let sample_rate = buffer.sample_rate();
+    let name = args.input.file_name().unwrap_or_default().to_string_lossy();
+
+    if let Some(path) = &args.plot_spectrum {
+        let power = analysis::long_term_spectrum(mono, analysis::LONG_TERM_FFT_SIZE)?;
+        let df = sample_rate as f32 / analysis::LONG_TERM_FFT_SIZE as f32;
+        let points: Vec<(f32, f32)> = power
+            .iter()
+            .enumerate()
+            .map(|(bin, &p)| (bin as f32 * df, 10.0 * p.max(1e-12).log10()))
+            .collect();
+        let spectrum = plot::Spectrum { title: format!("Long-term average spectrum: {}", name), points: &points, sample_rate };
+        plot::save(&spectrum, path)?;
+    }
+
+    if let Some(path) = &args.plot_loudness {
+        // Each block is plotted at its end, when a meter would show it
+        let timed = |levels: Vec<f32>, block: f32, step: f32| -> Vec<(f32, f32)> {
+            levels.into_iter().enumerate().map(|(i, lufs)| (i as f32 * step + block, lufs)).collect()
+        };
+        let momentary = timed(loudness::loudness_history(buffer, 0.4, 0.1), 0.4, 0.1);
+        let short_term = timed(loudness::loudness_history(buffer, 3.0, 0.5), 3.0, 0.5);
+        let history = plot::LoudnessHistory {
+            title: format!("Loudness: {}", name),
+            momentary: &momentary,
+            short_term: &short_term,
+            integrated_lufs: loudness::measure(buffer)?.integrated_lufs,
+            duration: buffer.duration_seconds() as f32,
+        };
+        plot::save(&history, path)?;
+    }
+
+    Ok(())
+}
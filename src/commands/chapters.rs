@@ -0,0 +1,114 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::chapters::{self, ChapterSettings, Content};
+use saunds_v2::audio::{id3, mixdown};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct ChaptersArgs {
+    /// Podcast or audiobook to find chapters in
+    input: PathBuf,
+
+    /// How far below the programme level audio counts as silence (dB)
+    #[arg(long, default_value_t = 40.0)]
+    silence_threshold: f32,
+
+    /// Shortest silence that starts a new chapter (s)
+    #[arg(long, default_value_t = 2.0)]
+    min_silence: f64,
+
+    /// Shortest chapter (s)
+    #[arg(long, default_value_t = 30.0)]
+    min_length: f64,
+
+    /// Share of low-energy frames from which a second counts as speech
+    /// rather than music
+    #[arg(long, default_value_t = 0.25)]
+    speech_ratio: f32,
+
+    /// Only start chapters after silences, not where speech turns to music
+    /// and back
+    #[arg(long)]
+    silences_only: bool,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Write a copy of the MP3 input with the chapters in its ID3 tag
+    #[arg(long, value_name = "FILE")]
+    mp3: Option<PathBuf>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+pub fn run(args: ChaptersArgs) -> Result<()> {
+    let is_mp3 = args.input.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("mp3"));
+    if args.mp3.is_some() && !is_mp3 {
+        bail!("--mp3 tags a copy of the input, which must be an MP3 file; {} isn't one", args.input.display());
+    }
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let settings = ChapterSettings {
+        silence_db: args.silence_threshold,
+        min_silence: args.min_silence,
+        min_length: args.min_length,
+        low_energy_ratio: args.speech_ratio,
+        transitions: !args.silences_only,
+    };
+    let chapters = chapters::detect(&mixdown(&samples, processor.channels() as usize), processor.sample_rate(), settings)?;
+    info!("Found {} chapters", chapters.len());
+
+    match args.format {
+        Format::Table => {
+            for chapter in &chapters {
+                let content = match chapter.content {
+                    Content::Speech => "speech",
+                    Content::Music => "music",
+                    Content::Silence => "silence",
+                };
+                println!("{:>10}  {:>10}  {:<7}  {}", clock(chapter.start), clock(chapter.end), content, chapter.title);
+            }
+        }
+        Format::Json => {
+            let report = serde_json::json!({
+                "input": args.input,
+                "chapters": chapters,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    if let Some(output) = &args.mp3 {
+        let data = std::fs::read(&args.input).with_context(|| format!("Failed to read {}", args.input.display()))?;
+        let tagged = id3::write_chapters(&data, &chapters)?;
+        if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        info!("Saving chapter-tagged MP3 to: {}", output.display());
+        std::fs::write(output, tagged).with_context(|| format!("Failed to write {}", output.display()))?;
+    }
+    Ok(())
+}
+
+/// `H:MM:SS.mmm`, or `M:SS.mmm` under an hour.
+fn clock(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    let (hours, minutes, rest) = (millis / 3_600_000, millis / 60_000 % 60, millis % 60_000);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}.{:03}", hours, minutes, rest / 1000, rest % 1000)
+    } else {
+        format!("{}:{:02}.{:03}", minutes, rest / 1000, rest % 1000)
+    }
+}
@@ -0,0 +1,16 @@
+use anyhow::Result;
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+
+use crate::Cli;
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    shell: Shell,
+}
+
+pub fn run(args: CompletionsArgs) -> Result<()> {
+    clap_complete::generate(args.shell, &mut Cli::command(), "saunds", &mut std::io::stdout());
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct ConformArgs {
+    /// Edit decision list: CSV rows of `source,in,out[,gain_db[,fade_in[,fade_out]]]`
+    /// or a JSON array of objects with the same keys. Times are seconds,
+    /// or sample counts with an `smp` suffix (e.g. `44100smp`); sources are
+    /// relative to the list
+    edl: PathBuf,
+
+    /// Output file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// A point in a source, resolved to frames once its rate is known.
+#[derive(Debug, Clone, Copy)]
+enum Time {
+    Seconds(f64),
+    Frames(usize),
+}
+
+impl Time {
+    fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        match text.strip_suffix("smp") {
+            Some(frames) => Ok(Time::Frames(frames.trim().parse().with_context(|| format!("Invalid sample count '{}'", text))?)),
+            None => {
+                let seconds: f64 = text.parse().with_context(|| format!("Invalid time '{}'", text))?;
+                if seconds < 0.0 {
+                    bail!("Times cannot be negative, got {}", text);
+                }
+                Ok(Time::Seconds(seconds))
+            }
+        }
+    }
+
+    fn frames(self, sample_rate: u32) -> usize {
+        match self {
+            Time::Seconds(seconds) => (seconds * sample_rate as f64).round() as usize,
+            Time::Frames(frames) => frames,
+        }
+    }
+}
+
+/// One region of a source placed after the previous one.
+#[derive(Debug)]
+struct Event {
+    source: PathBuf,
+    start: Time,
+    end: Time,
+    gain_db: f32,
+    fade_in: Time,
+    fade_out: Time,
+}
+
+fn parse_csv(text: &str, dir: &Path) -> Result<Vec<Event>> {
+    let mut events = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line.trim().is_empty() || line.trim_start().starts_with('#') || (number == 0 && fields[0] == "source") {
+            continue;
+        }
+        events.push(csv_event(&fields, dir).with_context(|| format!("EDL line {}", number + 1))?);
+    }
+    Ok(events)
+}
+
+fn csv_event(fields: &[&str], dir: &Path) -> Result<Event> {
+    if !(3..=6).contains(&fields.len()) {
+        bail!("Expected source,in,out[,gain_db[,fade_in[,fade_out]]]");
+    }
+    let optional = |index: usize| fields.get(index).copied().filter(|field| !field.is_empty());
+    Ok(Event {
+        source: dir.join(fields[0]),
+        start: Time::parse(fields[1])?,
+        end: Time::parse(fields[2])?,
+        gain_db: optional(3).map(str::parse).transpose().context("Invalid gain")?.unwrap_or(0.0),
+        fade_in: optional(4).map(Time::parse).transpose()?.unwrap_or(Time::Frames(0)),
+        fade_out: optional(5).map(Time::parse).transpose()?.unwrap_or(Time::Frames(0)),
+    })
+}
+
+fn parse_json(text: &str, dir: &Path) -> Result<Vec<Event>> {
+    let value: serde_json::Value = serde_json::from_str(text).context("Invalid JSON EDL")?;
+    let Some(rows) = value.as_array() else {
+        bail!("JSON EDL must be an array of events");
+    };
+    rows.iter()
+        .enumerate()
+        .map(|(index, row)| json_event(row, dir).with_context(|| format!("EDL event {}", index + 1)))
+        .collect()
+}
+
+fn json_event(row: &serde_json::Value, dir: &Path) -> Result<Event> {
+    let time = |key: &str| -> Result<Option<Time>> {
+        match &row[key] {
+            serde_json::Value::Null => Ok(None),
+            serde_json::Value::Number(number) => Time::parse(&number.to_string()).map(Some),
+            serde_json::Value::String(text) => Time::parse(text).map(Some),
+            other => bail!("Invalid {} {}", key, other),
+        }
+    };
+    Ok(Event {
+        source: dir.join(row["source"].as_str().context("Missing source")?),
+        start: time("in")?.context("Missing in")?,
+        end: time("out")?.context("Missing out")?,
+        gain_db: row["gain_db"].as_f64().unwrap_or(0.0) as f32,
+        fade_in: time("fade_in")?.unwrap_or(Time::Frames(0)),
+        fade_out: time("fade_out")?.unwrap_or(Time::Frames(0)),
+    })
+}
+
+pub fn run(args: ConformArgs) -> Result<()> {
+    let text = std::fs::read_to_string(&args.edl).with_context(|| format!("Failed to read EDL: {}", args.edl.display()))?;
+    let dir = args.edl.parent().unwrap_or(Path::new(""));
+    let is_json = args.edl.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let events = if is_json { parse_json(&text, dir)? } else { parse_csv(&text, dir)? };
+    if events.is_empty() {
+        bail!("EDL {} has no events", args.edl.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let mut sources: Vec<(PathBuf, Vec<f32>)> = Vec::new();
+    let mut format = None;
+    let mut program = Vec::new();
+
+    for (index, event) in events.iter().enumerate() {
+        if !sources.iter().any(|(path, _)| *path == event.source) {
+            let samples = processor.load_audio(&event.source)?;
+            let source_format = (processor.sample_rate(), processor.channels());
+            if *format.get_or_insert(source_format) != source_format {
+                bail!("{} does not match the sample rate and channels of the first source", event.source.display());
+            }
+            sources.push((event.source.clone(), samples));
+        }
+        let (sample_rate, channels) = format.unwrap_or_default();
+        let channels = channels as usize;
+        let samples = &sources.iter().find(|(path, _)| *path == event.source).unwrap().1;
+
+        let (start, end) = (event.start.frames(sample_rate), event.end.frames(sample_rate));
+        let length = samples.len() / channels;
+        if start >= end || end > length {
+            bail!(
+                "Event {} runs from frame {} to {} but {} has {} frames",
+                index + 1, start, end, event.source.display(), length
+            );
+        }
+        let frames = end - start;
+        let fade_in = event.fade_in.frames(sample_rate).min(frames);
+        let fade_out = event.fade_out.frames(sample_rate).min(frames);
+        let gain = 10f32.powf(event.gain_db / 20.0);
+        info!("Event {}: {} frames {}..{}", index + 1, event.source.display(), start, end);
+
+        for (frame, values) in samples[start * channels..end * channels].chunks_exact(channels).enumerate() {
+            let mut envelope = gain;
+            if frame < fade_in {
+                envelope *= frame as f32 / fade_in as f32;
+            }
+            if frames - frame <= fade_out {
+                envelope *= (frames - frame - 1) as f32 / fade_out as f32;
+            }
+            program.extend(values.iter().map(|&value| value * envelope));
+        }
+    }
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let (sample_rate, channels) = format.unwrap_or_default();
+    info!("Saving {} events ({} frames) to: {}", events.len(), program.len() / channels as usize, args.output.display());
+    processor.with_sample_rate(sample_rate).with_channels(channels).save_audio(&args.output, &program)
+}
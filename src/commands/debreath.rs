@@ -0,0 +1,88 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::breath::{self, BreathSettings};
+use saunds_v2::audio::mixdown;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct DebreathArgs {
+    /// Voiceover to clean up
+    input: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// How far below the speech level a breath stays at most (dB)
+    #[arg(long, default_value_t = 18.0)]
+    threshold: f32,
+
+    /// How far breaths are turned down (dB)
+    #[arg(long, default_value_t = 12.0)]
+    attenuation: f32,
+
+    /// Spectral flatness from which a quiet stretch counts as breath
+    /// rather than a voiced sound, between 0 and about 0.56 for white noise
+    #[arg(long, default_value_t = 0.25)]
+    flatness: f32,
+
+    /// Shortest breath (ms)
+    #[arg(long, default_value_t = 100.0)]
+    min_length: f32,
+
+    /// Longest breath (ms); longer noisy stretches are left alone
+    #[arg(long, default_value_t = 1000.0)]
+    max_length: f32,
+
+    /// Fade in and out of the attenuation (ms)
+    #[arg(long, default_value_t = 10.0)]
+    fade: f32,
+
+    /// Also write the detected breaths as a label track, to review them
+    #[arg(long, value_name = "FILE")]
+    labels: Option<PathBuf>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: DebreathArgs) -> Result<()> {
+    if args.attenuation < 0.0 {
+        bail!("Attenuation must not be negative, got {} dB", args.attenuation);
+    }
+    if args.min_length > args.max_length {
+        bail!("--min-length of {} ms is above --max-length of {} ms", args.min_length, args.max_length);
+    }
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let channels = processor.channels() as usize;
+    let rate = processor.sample_rate();
+
+    let settings = BreathSettings {
+        threshold_db: args.threshold,
+        flatness: args.flatness,
+        min_ms: args.min_length,
+        max_ms: args.max_length,
+    };
+    let breaths = breath::detect(&mixdown(&samples, channels), rate, settings)?;
+    let seconds: f64 = breaths.iter().map(|breath| breath.len() as f64 / rate as f64).sum();
+    info!("Found {} breaths, {:.1} s in total; turning them down by {} dB", breaths.len(), seconds, args.attenuation);
+    breath::attenuate(&mut samples, channels, rate, &breaths, args.attenuation, args.fade);
+
+    if let Some(path) = &args.labels {
+        let labels: String = breaths
+            .iter()
+            .map(|breath| format!("{:.6}\t{:.6}\tBreath\n", breath.start as f64 / rate as f64, breath.end as f64 / rate as f64))
+            .collect();
+        std::fs::write(path, labels).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving debreathed audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{dewow, mixdown};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct DewowArgs {
+    /// Transfer with slow pitch drift
+    input: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Follow a pilot or test tone recorded at this frequency, also
+    /// correcting the overall speed. Without it the speed is tracked from
+    /// the shift of the whole spectrum, around the average speed
+    #[arg(long, value_name = "HZ")]
+    pilot: Option<f32>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: DewowArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let channels = processor.channels() as usize;
+    let mono = mixdown(&samples, channels);
+
+    let curve = match args.pilot {
+        Some(pilot_hz) => {
+            info!("Tracking the speed from the {} Hz pilot tone", pilot_hz);
+            dewow::from_pilot(&mono, processor.sample_rate(), pilot_hz)?
+        }
+        None => {
+            info!("Tracking the speed from the spectrum");
+            dewow::from_spectrum(&mono, processor.sample_rate())?
+        }
+    };
+    let (slowest, fastest) = curve.range();
+    info!("Speed varied from {:+.2}% to {:+.2}%", 100.0 * (slowest - 1.0), 100.0 * (fastest - 1.0));
+    let corrected = dewow::correct(&samples, channels, processor.sample_rate(), &curve);
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving de-wowed audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &corrected)
+}
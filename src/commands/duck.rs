@@ -0,0 +1,83 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{mixdown, spectral, WINDOW_SIZE};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct DuckArgs {
+    /// Audio to carve, e.g. a music bed
+    input: PathBuf,
+
+    /// Key signal whose spectrum drives the attenuation, e.g. a voice
+    #[arg(long)]
+    key: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Key level per frequency above which the input is attenuated (dBFS)
+    #[arg(long, default_value_t = -50.0, allow_hyphen_values = true)]
+    threshold: f32,
+
+    /// Largest attenuation (dB)
+    #[arg(long, default_value_t = 12.0)]
+    depth: f32,
+
+    /// Time for the attenuation to recover once the key falls (ms)
+    #[arg(long, default_value_t = 150.0)]
+    release: f32,
+
+    /// Neighbouring FFT bins on either side whose key level also counts
+    #[arg(long, default_value_t = 2)]
+    spread: usize,
+
+    /// FFT window size
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: DuckArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let mut key_processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let key = key_processor.load_audio(&args.key)?;
+
+    if key_processor.sample_rate() != processor.sample_rate() {
+        bail!(
+            "Sample rates differ: {} is {} Hz, {} is {} Hz",
+            args.input.display(), processor.sample_rate(),
+            args.key.display(), key_processor.sample_rate()
+        );
+    }
+    if args.depth < 0.0 {
+        bail!("Depth must not be negative, got {} dB", args.depth);
+    }
+    if args.release < 0.0 {
+        bail!("Release must not be negative, got {} ms", args.release);
+    }
+
+    info!("Ducking {} by up to {} dB where {} exceeds {} dBFS", args.input.display(), args.depth, args.key.display(), args.threshold);
+    let key = mixdown(&key, key_processor.channels() as usize);
+    let settings = spectral::DuckSettings {
+        threshold_db: args.threshold,
+        depth_db: args.depth,
+        release_ms: args.release,
+        spread: args.spread,
+        window_size: args.window_size,
+    };
+    spectral::duck(&mut samples, processor.channels() as usize, &key, processor.sample_rate(), settings)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving ducked audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
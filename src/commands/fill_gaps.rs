@@ -0,0 +1,108 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::ops::Range;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use saunds_v2::audio::room_tone::{self, NoiseProfile};
+use saunds_v2::audio::{dither, WINDOW_SIZE};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct FillGapsArgs {
+    /// Recording with silent gaps, e.g. after silence trimming
+    input: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Stretch of room tone to profile, as `START-END`, e.g. `1:02-1:04.5`.
+    /// By default the quietest stretch outside the gaps is used
+    #[arg(long, value_name = "START-END", value_parser = parse_range)]
+    profile: Option<Range<f64>>,
+
+    /// Length of the automatically chosen profile stretch (s)
+    #[arg(long, default_value_t = 1.0, conflicts_with = "profile")]
+    profile_length: f64,
+
+    /// Level at or below which samples count as silent (dBFS)
+    #[arg(long, default_value_t = -90.0, allow_hyphen_values = true)]
+    threshold: f32,
+
+    /// Shortest silence to fill (ms)
+    #[arg(long, default_value_t = 50.0)]
+    min_gap: f64,
+
+    /// Fade in and out of the filled room tone (ms)
+    #[arg(long, default_value_t = 5.0)]
+    fade: f64,
+
+    /// Seed for the synthesized noise
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// Seconds as `START-END`, each in any form `parse_seconds` accepts.
+fn parse_range(text: &str) -> Result<Range<f64>, String> {
+    let (start, end) = text.split_once('-').ok_or_else(|| format!("invalid range '{}'; expected START-END, e.g. 1:02-1:04.5", text))?;
+    let (start, end) = (crate::parse_seconds(start)?, crate::parse_seconds(end)?);
+    if end <= start {
+        return Err(format!("invalid range '{}'; the end must be after the start", text));
+    }
+    Ok(start..end)
+}
+
+pub fn run(args: FillGapsArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let channels = processor.channels() as usize;
+    let rate = processor.sample_rate() as f64;
+    let frames = samples.len() / channels.max(1);
+
+    let threshold = 10f32.powf(args.threshold / 20.0);
+    let gaps = room_tone::find_gaps(&samples, channels, threshold, (args.min_gap / 1000.0 * rate) as usize);
+    if gaps.is_empty() {
+        warn!("No silences of {} ms or longer below {} dBFS; nothing to fill", args.min_gap, args.threshold);
+    }
+
+    let stretch = match &args.profile {
+        Some(range) => ((range.start * rate) as usize).min(frames)..((range.end * rate) as usize).min(frames),
+        None => {
+            let Some(stretch) = room_tone::quietest_stretch(&samples, channels, (args.profile_length * rate) as usize, &gaps) else {
+                bail!("No {} s stretch of room tone outside the gaps to profile; pass a shorter --profile-length", args.profile_length);
+            };
+            stretch
+        }
+    };
+    info!("Profiling room tone from {:.2} s to {:.2} s", stretch.start as f64 / rate, stretch.end as f64 / rate);
+    let profile = NoiseProfile::measure(&samples[stretch.start * channels..stretch.end * channels], channels, WINDOW_SIZE)?;
+
+    let mut rng = dither::Rng::new(dither::stream_seed(args.seed, "room-tone"));
+    let fade = ((args.fade / 1000.0 * rate) as usize).max(1);
+    for gap in &gaps {
+        let tone = profile.synthesize(gap.len(), &mut rng)?;
+        let fade = fade.min(gap.len() / 2).max(1);
+        for (offset, (frame, tone)) in samples[gap.start * channels..gap.end * channels]
+            .chunks_exact_mut(channels)
+            .zip(tone.chunks_exact(channels))
+            .enumerate()
+        {
+            let edge = offset.min(gap.len() - 1 - offset);
+            let gain = (edge as f32 / fade as f32).min(1.0);
+            frame.iter_mut().zip(tone).for_each(|(sample, tone)| *sample += gain * tone);
+        }
+    }
+    let seconds: f64 = gaps.iter().map(|gap| gap.len() as f64 / rate).sum();
+    info!("Filled {} gaps, {:.1} s in total, with room tone", gaps.len(), seconds);
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving gap-filled audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
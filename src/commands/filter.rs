@@ -0,0 +1,133 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{biquad, design::stopband_attenuation_db, iir};
+
+use super::{DecodeArgs, DesignArgs};
+
+#[derive(Args, Debug)]
+pub struct FilterArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Filter response
+    #[arg(long = "type", value_enum)]
+    kind: FilterType,
+
+    /// Lower band edge (Hz), for highpass, bandpass and bandstop
+    #[arg(long)]
+    from: Option<f32>,
+
+    /// Upper band edge (Hz), for lowpass, bandpass and bandstop
+    #[arg(long)]
+    to: Option<f32>,
+
+    #[command(flatten)]
+    design: DesignArgs,
+
+    /// Filter forward and backward for zero phase shift, squaring the
+    /// magnitude response
+    #[arg(long)]
+    zero_phase: bool,
+
+    /// Also plot the magnitude and phase response to this .svg or .png file
+    #[cfg(feature = "plots")]
+    #[arg(long, value_name = "FILE")]
+    plot: Option<PathBuf>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FilterType {
+    /// Keep everything below --to
+    Lowpass,
+    /// Keep everything above --from
+    Highpass,
+    /// Keep --from to --to
+    Bandpass,
+    /// Remove --from to --to
+    Bandstop,
+}
+
+pub fn run(args: FilterArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let sample_rate = processor.sample_rate();
+    let nyquist = sample_rate as f32 / 2.0;
+
+    let edge = |value: Option<f32>, flag: &str| -> Result<f32> {
+        match value {
+            Some(hz) if hz > 0.0 && hz < nyquist => Ok(hz),
+            Some(hz) => bail!("--{} ({} Hz) must be between 0 and the Nyquist frequency ({} Hz)", flag, hz, nyquist),
+            None => bail!("--type {:?} requires --{}", args.kind, flag),
+        }
+    };
+
+    let design = args.design.design();
+    let (cascade, stopband) = match args.kind {
+        FilterType::Lowpass => {
+            let to = edge(args.to, "to")?;
+            (design.lowpass(to, sample_rate)?, (2.0 * to < nyquist).then_some((2.0 * to, nyquist)))
+        }
+        FilterType::Highpass => {
+            let from = edge(args.from, "from")?;
+            (design.highpass(from, sample_rate)?, Some((1.0, from / 2.0)))
+        }
+        FilterType::Bandpass | FilterType::Bandstop => {
+            let (from, to) = (edge(args.from, "from")?, edge(args.to, "to")?);
+            if from >= to {
+                bail!("--from ({} Hz) must be below --to ({} Hz)", from, to);
+            }
+            if args.kind == FilterType::Bandpass {
+                (design.bandpass(from, to, sample_rate)?, None)
+            } else {
+                (design.bandstop(from, to, sample_rate)?, None)
+            }
+        }
+    };
+
+    info!("Applying {:?} filter ({:?}, order {})", args.kind, design.family, design.order);
+    if let Some((from, to)) = stopband {
+        let passes = if args.zero_phase { 2.0 } else { 1.0 };
+        info!(
+            "{:.1} dB stopband attenuation between {} Hz and {} Hz",
+            passes * stopband_attenuation_db(&cascade, from, to, sample_rate), from, to
+        );
+    }
+
+    #[cfg(feature = "plots")]
+    if let Some(path) = &args.plot {
+        let response = crate::plot::Response {
+            title: format!("{:?} {:?}, order {}", args.kind, design.family, design.order),
+            cascade: &cascade,
+            sample_rate,
+            zero_phase: args.zero_phase,
+        };
+        crate::plot::save(&response, path)?;
+    }
+
+    let channels = processor.channels() as usize;
+    if args.zero_phase {
+        samples = iir::filtfilt(&cascade, &samples, channels);
+    } else {
+        biquad::filter_interleaved(&cascade, &mut samples, channels);
+    }
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving filtered audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
@@ -0,0 +1,57 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::effects;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct FxArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Effect stage as `name:key=value,...` (repeatable, applied in order)
+    #[arg(long = "stage", value_name = "STAGE", required_unless_present = "preset")]
+    stages: Vec<effects::StageSpec>,
+
+    /// Built-in chain of stages run before any --stage, e.g. `telephone`
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(effects::preset_names()))]
+    preset: Option<String>,
+
+    /// Keep each stage's level change instead of matching its input RMS
+    #[arg(long)]
+    no_autogain: bool,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: FxArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let mut buffer = processor.load_buffer(&args.input)?;
+    let mut stages = match &args.preset {
+        Some(name) => {
+            info!("Using the {} preset", name);
+            effects::preset(name)?
+        }
+        None => Vec::new(),
+    };
+    stages.extend(args.stages);
+    effects::apply_chain(&stages, &mut buffer, !args.no_autogain)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving processed audio to: {}", args.output.display());
+    processor.save_buffer(&args.output, &buffer)
+}
@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use axum::{http::StatusCode, routing::get, Router};
+use clap::Args;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio_stream::wrappers::TcpListenerStream;
+use tracing::info;
+
+use saunds_v2::grpc::{separator_server::SeparatorServer, Limits, SeparatorService};
+
+#[derive(Args, Debug)]
+pub struct GrpcArgs {
+    /// Address to serve on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+
+    /// Address for plain HTTP /healthz and /readyz probes and Prometheus
+    /// /metrics; /readyz fails once shutdown has begun
+    #[arg(long, value_name = "ADDRESS")]
+    health_listen: Option<SocketAddr>,
+
+    /// Streams served at once across all clients; more are refused with
+    /// RESOURCE_EXHAUSTED
+    #[arg(long, default_value_t = Limits::default().max_streams)]
+    max_streams: usize,
+
+    /// Streams one client address may have open at once
+    #[arg(long, default_value_t = Limits::default().max_client_streams)]
+    max_client_streams: usize,
+
+    /// Streams one client address may start per minute
+    #[arg(long, default_value_t = Limits::default().client_streams_per_minute)]
+    client_streams_per_minute: usize,
+
+    /// Largest request message accepted (bytes)
+    #[arg(long, default_value_t = 4 << 20)]
+    max_message_bytes: usize,
+}
+
+/// Resolves on SIGTERM or Ctrl-C.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+pub fn run(args: GrpcArgs) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new().context("Failed to start the async runtime")?;
+    runtime.block_on(async {
+        let limits = Limits {
+            max_streams: args.max_streams,
+            max_client_streams: args.max_client_streams,
+            client_streams_per_minute: args.client_streams_per_minute,
+        };
+        let separator = SeparatorService::default().with_limits(limits);
+        let ready = Arc::new(AtomicBool::new(false));
+        if let Some(address) = args.health_listen {
+            let readiness = ready.clone();
+            let metrics = separator.metrics();
+            let probes = Router::new()
+                .route("/healthz", get(|| async { "ok" }))
+                .route(
+                    "/readyz",
+                    get(move || async move {
+                        if readiness.load(Ordering::SeqCst) {
+                            (StatusCode::OK, "ready")
+                        } else {
+                            (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+                        }
+                    }),
+                )
+                .route("/metrics", get(move || async move { metrics.render() }));
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .with_context(|| format!("Failed to listen on {}", address))?;
+            info!("Serving health probes and metrics on {}", address);
+            tokio::spawn(async move { axum::serve(listener, probes).await });
+        }
+
+        let listener = tokio::net::TcpListener::bind(args.listen)
+            .await
+            .with_context(|| format!("Failed to listen on {}", args.listen))?;
+        info!("Serving saunds.Separator on {}", args.listen);
+        ready.store(true, Ordering::SeqCst);
+        let stopping = ready.clone();
+        let service = SeparatorServer::new(separator)
+            .max_decoding_message_size(args.max_message_bytes);
+        tonic::transport::Server::builder()
+            .max_concurrent_streams(u32::try_from(args.max_client_streams).ok())
+            .add_service(service)
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+                shutdown_signal().await;
+                info!("Shutting down after in-flight streams finish");
+                stopping.store(false, Ordering::SeqCst);
+            })
+            .await
+            .with_context(|| format!("Failed to serve on {}", args.listen))?;
+        info!("Shut down");
+        Ok(())
+    })
+}
@@ -0,0 +1,101 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::analysis::LONG_TERM_FFT_SIZE;
+use saunds_v2::audio::{matching, mixdown};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct MatchArgs {
+    /// Recording to correct
+    input: PathBuf,
+
+    /// Recording whose long-term spectrum to match, e.g. a mastered version
+    #[arg(long)]
+    reference: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Width the spectra are smoothed over before comparing, e.g. `1/3oct`
+    /// or `1oct`. Narrower follows the reference more closely
+    #[arg(long, default_value = "1/3oct", value_parser = parse_octaves)]
+    smoothing: f32,
+
+    /// Largest boost the matching EQ may apply (dB)
+    #[arg(long, default_value_t = 12.0)]
+    max_boost: f32,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// Octaves as `1/3oct`, `1/6`, `1oct` or `0.5oct`.
+fn parse_octaves(text: &str) -> Result<f32, String> {
+    let invalid = || format!("invalid smoothing '{}'; expected e.g. 1/3oct or 1oct", text);
+    let number = text.trim().trim_end_matches("oct");
+    let value = match number.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f32 = numerator.trim().parse().map_err(|_| invalid())?;
+            let denominator: f32 = denominator.trim().parse().map_err(|_| invalid())?;
+            numerator / denominator
+        }
+        None => number.trim().parse().map_err(|_| invalid())?,
+    };
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid());
+    }
+    Ok(value)
+}
+
+pub fn run(args: MatchArgs) -> Result<()> {
+    if args.max_boost < 0.0 {
+        bail!("Max boost must not be negative, got {} dB", args.max_boost);
+    }
+    let mut processor = args.decode.processor()?;
+    let mut reference_processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let reference = reference_processor.load_audio(&args.reference)?;
+    if reference_processor.sample_rate() != processor.sample_rate() {
+        bail!(
+            "Sample rates differ: {} is {} Hz, {} is {} Hz",
+            args.input.display(), processor.sample_rate(),
+            args.reference.display(), reference_processor.sample_rate()
+        );
+    }
+
+    let channels = processor.channels() as usize;
+    let curve = matching::matching_curve(
+        &mixdown(&samples, channels),
+        &mixdown(&reference, reference_processor.channels() as usize),
+        args.smoothing,
+        args.max_boost,
+    )?;
+    let df = processor.sample_rate() as f32 / LONG_TERM_FFT_SIZE as f32;
+    let extreme = |better: fn(f32, f32) -> bool| {
+        curve.iter().enumerate().skip(1).fold((0, curve[0]), |best, (bin, &db)| if better(db, best.1) { (bin, db) } else { best })
+    };
+    let (boost_bin, boost) = extreme(|a, b| a > b);
+    let (cut_bin, cut) = extreme(|a, b| a < b);
+    info!(
+        "Matching EQ over {:.2} octaves: largest boost {:+.1} dB at {:.0} Hz, largest cut {:+.1} dB at {:.0} Hz",
+        args.smoothing, boost, boost_bin as f32 * df, cut, cut_bin as f32 * df
+    );
+    let limited = curve.iter().filter(|&&db| db >= args.max_boost).count();
+    if limited > 0 {
+        info!("Boost held at {} dB over {:.0}% of the spectrum", args.max_boost, 100.0 * limited as f32 / curve.len() as f32);
+    }
+
+    let fir = matching::design_fir(&curve)?;
+    matching::apply_fir(&mut samples, channels, &fir)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving matched audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
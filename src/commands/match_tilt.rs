@@ -0,0 +1,66 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::matching::{self, PINK_TILT_DB_PER_OCTAVE, PIVOT_HZ};
+use saunds_v2::audio::mixdown;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct MatchTiltArgs {
+    /// Recording to correct
+    input: PathBuf,
+
+    /// Recording whose spectral tilt to match
+    #[arg(long, required_unless_present = "pink")]
+    reference: Option<PathBuf>,
+
+    /// Match the tilt of pink noise instead of a reference recording
+    #[arg(long, conflicts_with = "reference")]
+    pink: bool,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Largest boost or cut at either end of the spectrum (dB)
+    #[arg(long, default_value_t = 12.0)]
+    max_gain: f32,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: MatchTiltArgs) -> Result<()> {
+    if args.max_gain < 0.0 {
+        bail!("Max gain must not be negative, got {} dB", args.max_gain);
+    }
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+    let channels = processor.channels() as usize;
+    let tilt = matching::spectral_tilt(&mixdown(&samples, channels), processor.sample_rate())?;
+
+    let target = match &args.reference {
+        Some(reference) => {
+            let mut reference_processor = args.decode.processor()?;
+            let reference_samples = reference_processor.load_audio(reference)?;
+            let mono = mixdown(&reference_samples, reference_processor.channels() as usize);
+            matching::spectral_tilt(&mono, reference_processor.sample_rate())?
+        }
+        None => PINK_TILT_DB_PER_OCTAVE,
+    };
+    let correction = target - tilt;
+    info!(
+        "Source tilt {:.2} dB/octave, target {:.2} dB/octave; applying {:+.2} dB/octave around {} Hz",
+        tilt, target, correction, PIVOT_HZ
+    );
+    matching::apply_tilt(&mut samples, channels, processor.sample_rate(), correction, args.max_gain)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving tilt-matched audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
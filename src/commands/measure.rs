@@ -0,0 +1,142 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use saunds_v2::audio::loudness::{self, BlockHistogram, Loudness, ReplayGain, REPLAYGAIN_REFERENCE_LUFS};
+use super::DecodeArgs;
+
+use crate::manifest;
+
+#[derive(Args, Debug)]
+pub struct MeasureArgs {
+    /// Files to measure
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Stats sidecar to write. Entries already in it are kept, so an
+    /// interrupted batch resumes where it stopped
+    #[arg(long)]
+    stats: PathBuf,
+
+    /// Measure files again even when the sidecar already has them
+    #[arg(long)]
+    force: bool,
+
+    /// Treat the inputs as an album: record ReplayGain track and album
+    /// gains and peaks for each, the album's measured as one programme
+    #[arg(long)]
+    album: bool,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// Loudness measured by the first pass, keyed by content hash so sidecars
+/// from different machines can be combined.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Stats {
+    pub files: Vec<FileStats>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FileStats {
+    pub file: String,
+    pub sha256: String,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub loudness: Loudness,
+    /// Gating blocks, for measuring the loudness of several files together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocks: Option<BlockHistogram>,
+    /// Gains and peaks from the last `--album` measurement including it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaygain: Option<ReplayGain>,
+}
+
+impl Stats {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse stats {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        // Write beside the target and rename, so an interrupted run never
+        // leaves a truncated sidecar
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn find(&self, sha256: &str) -> Option<&FileStats> {
+        self.files.iter().find(|entry| entry.sha256 == sha256)
+    }
+
+    /// Loudness and true peak (dBTP) of `entries` played as one programme.
+    pub fn album(entries: &[&FileStats]) -> Result<(f32, f32)> {
+        let mut blocks = BlockHistogram::default();
+        for entry in entries {
+            let Some(histogram) = &entry.blocks else {
+                bail!("{} was measured without its gating blocks; measure it again with --force", entry.file);
+            };
+            blocks.merge(histogram);
+        }
+        let peak = entries.iter().map(|entry| entry.loudness.true_peak_dbtp).fold(f32::MIN, f32::max);
+        Ok((blocks.integrated_lufs(), peak))
+    }
+}
+
+pub fn run(args: MeasureArgs) -> Result<()> {
+    let mut stats = if args.stats.exists() { Stats::read(&args.stats)? } else { Stats::default() };
+    let mut processor = args.decode.processor()?;
+
+    let mut hashes = Vec::new();
+    for input in &args.inputs {
+        let sha256 = manifest::sha256_file(input)?;
+        hashes.push(sha256.clone());
+        if !args.force && stats.find(&sha256).is_some() {
+            info!("{}: already measured", input.display());
+            continue;
+        }
+        let buffer = processor.load_buffer(input)?;
+        let (measured, blocks) = loudness::measure_with_blocks(&buffer)?;
+        info!(
+            "{}: {:.1} LUFS, {:.1} dBTP",
+            input.display(),
+            measured.integrated_lufs,
+            measured.true_peak_dbtp
+        );
+        stats.files.retain(|entry| entry.sha256 != sha256);
+        stats.files.push(FileStats {
+            file: input.display().to_string(),
+            sha256,
+            sample_rate: processor.sample_rate(),
+            channels: processor.channels(),
+            loudness: measured,
+            blocks: Some(blocks),
+            replaygain: None,
+        });
+        // Save after every file so a crash loses at most one measurement
+        stats.write(&args.stats)?;
+    }
+
+    if args.album {
+        let entries: Vec<&FileStats> = hashes.iter().filter_map(|sha256| stats.find(sha256)).collect();
+        let (album_lufs, album_peak_dbtp) = Stats::album(&entries)?;
+        let album_gain_db = REPLAYGAIN_REFERENCE_LUFS - album_lufs;
+        info!("Album: {:.1} LUFS, {:.1} dBTP; album gain {:+.2} dB", album_lufs, album_peak_dbtp, album_gain_db);
+        let amplitude = |db: f32| 10f32.powf(db / 20.0);
+        for entry in stats.files.iter_mut().filter(|entry| hashes.contains(&entry.sha256)) {
+            entry.replaygain = Some(ReplayGain {
+                track_gain_db: REPLAYGAIN_REFERENCE_LUFS - entry.loudness.integrated_lufs,
+                track_peak: amplitude(entry.loudness.true_peak_dbtp),
+                album_gain_db,
+                album_peak: amplitude(album_peak_dbtp),
+            });
+        }
+        stats.write(&args.stats)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+pub mod album;
+pub mod align;
+pub mod analyze;
+pub mod chapters;
+pub mod completions;
+pub mod conform;
+pub mod debreath;
+pub mod dewow;
+pub mod duck;
+pub mod fill_gaps;
+pub mod filter;
+pub mod fx;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod match_eq;
+pub mod match_tilt;
+pub mod measure;
+pub mod normalize;
+pub mod resynth;
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod spectrogram;
+pub mod regions;
+pub mod repl;
+pub mod report;
+pub mod suggest_cutoffs;
+pub mod sweep;
+pub mod sync;
+pub mod timecode;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod undo;
+pub mod verify;
+pub mod vocode;
+
+use anyhow::Result;
+use clap::Args;
+use saunds_v2::audio::{AudioProcessor, DecodeErrorPolicy, FilterDesign, FilterFamily};
+
+/// Decoding options of commands that load audio.
+#[derive(Args, Debug, Clone, Copy)]
+pub struct DecodeArgs {
+    /// How to handle corrupt or truncated MP3 data
+    #[arg(long, value_enum, default_value_t = DecodeErrorPolicy::Skip)]
+    pub on_decode_error: DecodeErrorPolicy,
+}
+
+impl DecodeArgs {
+    /// A processor that loads audio with these options.
+    pub fn processor(&self) -> Result<AudioProcessor> {
+        Ok(AudioProcessor::new()?.with_decode_error_policy(self.on_decode_error))
+    }
+}
+
+/// IIR filter design options.
+#[derive(Args, Debug)]
+pub struct DesignArgs {
+    /// Filter family
+    #[arg(long, value_enum, default_value_t = FilterFamily::Butterworth)]
+    pub design: FilterFamily,
+
+    /// Filter order
+    #[arg(long, default_value_t = 2)]
+    pub order: usize,
+
+    /// Passband ripple for Chebyshev I and elliptic designs (dB)
+    #[arg(long, default_value_t = 1.0)]
+    pub ripple: f64,
+
+    /// Stopband attenuation for Chebyshev II and elliptic designs (dB)
+    #[arg(long, default_value_t = 60.0)]
+    pub attenuation: f64,
+}
+
+impl DesignArgs {
+    pub fn design(&self) -> FilterDesign {
+        FilterDesign {
+            family: self.design,
+            order: self.order,
+            passband_ripple_db: self.ripple,
+            stopband_attenuation_db: self.attenuation,
+        }
+    }
+}
+
+/// Maps 0..1 onto a black–blue–red–yellow–white colour ramp for
+/// spectrogram displays.
+pub fn heat(value: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 5] = [[0.0, 0.0, 0.0], [0.1, 0.1, 0.6], [0.8, 0.1, 0.2], [1.0, 0.8, 0.0], [1.0, 1.0, 1.0]];
+    let position = value.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    std::array::from_fn(|i| ((a[i] + (b[i] - a[i]) * t) * 255.0).round() as u8)
+}
+
+/// Compact axis label for a frequency, e.g. `63`, `1k` or `2.5k`.
+pub fn frequency_label(hz: f32) -> String {
+    if hz >= 1000.0 {
+        format!("{:.1}k", hz / 1000.0).replace(".0k", "k")
+    } else {
+        format!("{:.0}", hz)
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use saunds_v2::audio::{intermediate, limiter, loudness, peaks, AudioBuffer, AudioProcessor};
+
+use super::DecodeArgs;
+
+use super::measure::Stats;
+use crate::{journal, manifest};
+
+#[derive(Args, Debug)]
+pub struct NormalizeArgs {
+    /// Files to normalize; each must appear in a stats sidecar
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Stats sidecars written by `saunds measure`; repeat to combine
+    /// batches measured separately
+    #[arg(long, required_unless_present = "linked")]
+    stats: Vec<PathBuf>,
+
+    /// Treat the inputs as bands of one split and apply the same gain to
+    /// all of them, bringing their sum to the target, so they still add
+    /// up to a scaled version of the original. Peaks over the ceiling
+    /// lower the shared gain instead of being limited
+    #[arg(long, conflicts_with = "stats")]
+    linked: bool,
+
+    /// Treat the inputs as an album and apply the same gain to every
+    /// track, bringing the album's loudness to the target so the tracks
+    /// keep their relative levels. Tracks peaking over the ceiling are
+    /// limited on their own
+    #[arg(long, conflicts_with = "linked")]
+    album: bool,
+
+    /// Output directory; files keep their names
+    #[arg(short, long, required_unless_present = "in_place")]
+    output: Option<PathBuf>,
+
+    /// Rewrite the inputs instead, journaling each edit so `saunds undo`
+    /// can restore the original
+    #[arg(long, conflicts_with = "output")]
+    in_place: bool,
+
+    /// Integrated loudness to normalize to (LUFS)
+    #[arg(long, default_value = "-16", allow_hyphen_values = true)]
+    target_lufs: f32,
+
+    /// Peak ceiling (dBFS); the limiter engages only when the gain would
+    /// push the measured true peak above it
+    #[arg(long, default_value = "-1", allow_hyphen_values = true)]
+    ceiling: f32,
+
+    /// Limiter lookahead (ms)
+    #[arg(long, default_value = "5")]
+    lookahead: f32,
+
+    /// Limiter release (ms)
+    #[arg(long, default_value = "100")]
+    release: f32,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: NormalizeArgs) -> Result<()> {
+    let mut stats = Stats::default();
+    for path in &args.stats {
+        stats.files.extend(Stats::read(path)?.files);
+    }
+    if args.in_place {
+        let writable = |input: &PathBuf| {
+            input.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| {
+                ["wav", intermediate::EXTENSION].iter().any(|known| ext.eq_ignore_ascii_case(known))
+            })
+        };
+        if let Some(input) = args.inputs.iter().find(|input| !writable(input)) {
+            bail!("{} can't be rewritten in place; only WAV and intermediate files can", input.display());
+        }
+    }
+    if let Some(output) = &args.output {
+        std::fs::create_dir_all(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    }
+
+    let mut processor = args.decode.processor()?;
+    if args.linked {
+        return run_linked(&args, &mut processor);
+    }
+    let mut entries = Vec::new();
+    for input in &args.inputs {
+        let sha256 = manifest::sha256_file(input)?;
+        let Some(entry) = stats.find(&sha256) else {
+            bail!("No stats for {}; run saunds measure on it first", input.display());
+        };
+        entries.push(entry);
+    }
+    let album_gain_db = if args.album {
+        let (album_lufs, _) = Stats::album(&entries)?;
+        let gain_db = args.target_lufs - album_lufs;
+        info!("Album loudness {:.1} LUFS; applying {:+.1} dB to every track", album_lufs, gain_db);
+        Some(gain_db)
+    } else {
+        None
+    };
+
+    for (input, entry) in args.inputs.iter().zip(entries) {
+        let mut buffer = processor.load_buffer(input)?;
+
+        let gain_db = album_gain_db.unwrap_or(args.target_lufs - entry.loudness.integrated_lufs);
+        let gain = 10f32.powf(gain_db / 20.0);
+        buffer.channels_mut().flatten().for_each(|sample| *sample *= gain);
+        let peak_db = entry.loudness.true_peak_dbtp + gain_db;
+        info!("{}: applying {:+.1} dB", input.display(), gain_db);
+        if peak_db > args.ceiling {
+            info!("{}: limiting {:.1} dB of peak overshoot", input.display(), peak_db - args.ceiling);
+            limiter::limit(&mut buffer, args.ceiling, args.lookahead, args.release);
+        }
+
+        save(&args, &processor, input, &buffer)?;
+    }
+    Ok(())
+}
+
+/// Normalizes the inputs by one gain measured on their sum.
+fn run_linked(args: &NormalizeArgs, processor: &mut AudioProcessor) -> Result<()> {
+    let mut bands = Vec::new();
+    for input in &args.inputs {
+        bands.push((input, processor.load_buffer(input)?));
+    }
+    let (sample_rate, channels) = (bands[0].1.sample_rate(), bands[0].1.num_channels());
+    if let Some((input, _)) =
+        bands.iter().find(|(_, band)| (band.sample_rate(), band.num_channels()) != (sample_rate, channels))
+    {
+        bail!(
+            "{} doesn't match the {} Hz, {}-channel format of {}; linked bands must share it",
+            input.display(),
+            sample_rate,
+            channels,
+            bands[0].0.display()
+        );
+    }
+
+    let frames = bands.iter().map(|(_, band)| band.frames()).max().unwrap_or(0);
+    let mut sum = vec![vec![0.0f32; frames]; channels];
+    for (_, band) in &bands {
+        for (total, channel) in sum.iter_mut().zip(band.channels()) {
+            total.iter_mut().zip(channel).for_each(|(total, sample)| *total += sample);
+        }
+    }
+    let measured = loudness::measure(&AudioBuffer::from_planar(sum, sample_rate)?)?;
+    let mut gain_db = args.target_lufs - measured.integrated_lufs;
+    info!("Bands sum to {:.1} LUFS; applying {:+.1} dB to each", measured.integrated_lufs, gain_db);
+
+    // Limiting bands one by one would change their balance, so the loudest
+    // peak lowers the shared gain instead
+    let (loudest, peak) = bands
+        .iter()
+        .map(|(input, band)| (*input, peaks::true_peak(band)))
+        .fold((bands[0].0, 0.0f32), |max, band| if band.1 > max.1 { band } else { max });
+    let peak_db = 20.0 * peak.max(1e-6).log10() + gain_db;
+    if peak_db > args.ceiling {
+        gain_db -= peak_db - args.ceiling;
+        warn!(
+            "{} would peak {:.1} dB over the ceiling; applying {:+.1} dB instead, leaving the sum at {:.1} LUFS",
+            loudest.display(),
+            peak_db - args.ceiling,
+            gain_db,
+            measured.integrated_lufs + gain_db
+        );
+    }
+
+    let gain = 10f32.powf(gain_db / 20.0);
+    for (input, mut band) in bands {
+        band.channels_mut().flatten().for_each(|sample| *sample *= gain);
+        save(args, processor, input, &band)?;
+    }
+    Ok(())
+}
+
+/// Writes a normalized input to the output directory, or over itself.
+fn save(args: &NormalizeArgs, processor: &AudioProcessor, input: &PathBuf, buffer: &AudioBuffer) -> Result<()> {
+    match &args.output {
+        Some(output) => {
+            let name = input.file_name().context("Input has no file name")?;
+            processor.save_buffer(output.join(name).with_extension("wav"), buffer)
+        }
+        None => journal::edit_in_place(input, "normalize", || processor.save_buffer(input, buffer)),
+    }
+}
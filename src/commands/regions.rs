@@ -0,0 +1,191 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{AudioProcessor, FilterDesign, FilterMode};
+
+use super::{DecodeArgs, DesignArgs};
+
+#[derive(Args, Debug)]
+pub struct RegionsArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Audacity label track (.txt) or CUE sheet (.cue). Point labels and
+    /// CUE indexes run to the next marker or the end of the file
+    markers: PathBuf,
+
+    /// Output directory; each region is written as `<label>.wav`
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Band-split each region into `<label>/low_freq.wav` and
+    /// `<label>/high_freq.wav` instead of writing it whole
+    #[arg(long)]
+    split: bool,
+
+    /// Low frequency cutoff for --split (Hz)
+    #[arg(long, default_value = "200", requires = "split")]
+    low_cutoff: f32,
+
+    /// High frequency cutoff for --split (Hz)
+    #[arg(long, default_value = "2000", requires = "split")]
+    high_cutoff: f32,
+
+    /// Filter used for --split
+    #[arg(long, value_enum, default_value_t = FilterMode::Fft)]
+    filter: FilterMode,
+
+    #[command(flatten)]
+    design: DesignArgs,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// A labeled region in seconds; `end` is `None` for point markers.
+#[derive(Debug)]
+struct Marker {
+    label: String,
+    start: f64,
+    end: Option<f64>,
+}
+
+/// Audacity labels: `start<TAB>end<TAB>label` per line, in seconds.
+/// Spectral label lines, which start with a backslash, are skipped.
+fn parse_labels(text: &str) -> Result<Vec<Marker>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('\\'))
+        .map(|(number, line)| {
+            let fields: Vec<&str> = line.splitn(3, '\t').collect();
+            if fields.len() < 2 {
+                bail!("Label line {}: expected start<TAB>end<TAB>label", number + 1);
+            }
+            let time = |text: &str| -> Result<f64> {
+                text.trim().parse().with_context(|| format!("Label line {}: invalid time '{}'", number + 1, text))
+            };
+            let (start, end) = (time(fields[0])?, time(fields[1])?);
+            Ok(Marker {
+                label: fields.get(2).map(|label| label.trim().to_string()).unwrap_or_default(),
+                start,
+                end: (end > start).then_some(end),
+            })
+        })
+        .collect()
+}
+
+/// CUE sheets: each TRACK's TITLE and INDEX 01 in `mm:ss:ff`, at 75
+/// frames per second.
+fn parse_cue(text: &str) -> Result<Vec<Marker>> {
+    let mut markers: Vec<Marker> = Vec::new();
+    let mut title = None;
+    let mut track = 0;
+    for line in text.lines() {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("TRACK") => {
+                track += 1;
+                title = None;
+            }
+            Some("TITLE") if track > 0 => {
+                title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string());
+            }
+            Some("INDEX") if words.next() == Some("01") => {
+                let stamp = words.next().context("CUE INDEX without a time")?;
+                let parts: Vec<u32> = stamp
+                    .split(':')
+                    .map(|part| part.parse().with_context(|| format!("Invalid CUE time '{}'", stamp)))
+                    .collect::<Result<_>>()?;
+                let [minutes, seconds, frames] = parts[..] else {
+                    bail!("Invalid CUE time '{}'; expected mm:ss:ff", stamp);
+                };
+                markers.push(Marker {
+                    label: title.clone().unwrap_or_else(|| format!("Track {:02}", track)),
+                    start: minutes as f64 * 60.0 + seconds as f64 + frames as f64 / 75.0,
+                    end: None,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(markers)
+}
+
+/// A label made safe as a file name.
+fn file_stem(label: &str, index: usize) -> String {
+    let stem: String = label
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    let stem = stem.trim().trim_matches('.');
+    if stem.is_empty() { format!("region_{:02}", index + 1) } else { stem.to_string() }
+}
+
+pub fn run(args: RegionsArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+    let text = std::fs::read_to_string(&args.markers)
+        .with_context(|| format!("Failed to read markers: {}", args.markers.display()))?;
+    let is_cue = args.markers.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("cue"));
+    let mut markers = if is_cue { parse_cue(&text)? } else { parse_labels(&text)? };
+    if markers.is_empty() {
+        bail!("No markers found in {}", args.markers.display());
+    }
+    markers.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    let design = args.design.design();
+    if args.filter == FilterMode::Fft && design != FilterDesign::default() {
+        bail!("--design, --order, --ripple and --attenuation require --filter iir or zero-phase");
+    }
+    design.validate()?;
+
+    let mut processor = AudioProcessor::new()?
+        .with_decode_error_policy(args.decode.on_decode_error)
+        .with_filter_mode(args.filter)
+        .with_filter_design(design);
+    let samples = processor.load_audio(&args.input)?;
+    let (sample_rate, channels) = (processor.sample_rate(), processor.channels() as usize);
+    let frames = samples.len() / channels;
+    if args.split {
+        processor.validate_cutoffs(args.low_cutoff, args.high_cutoff)?;
+    }
+    std::fs::create_dir_all(&args.output).with_context(|| format!("Failed to create {}", args.output.display()))?;
+
+    let mut used: Vec<String> = Vec::new();
+    for (index, marker) in markers.iter().enumerate() {
+        let end = marker.end.or_else(|| markers.get(index + 1).map(|next| next.start));
+        let frame = |seconds: f64| ((seconds * sample_rate as f64).round() as usize).min(frames);
+        let (start, end) = (frame(marker.start), end.map_or(frames, frame));
+        if start >= end {
+            info!("Skipping empty region '{}'", marker.label);
+            continue;
+        }
+
+        // Repeated labels get a numeric suffix instead of overwriting
+        let base = file_stem(&marker.label, index);
+        let mut stem = base.clone();
+        let mut copy = 1;
+        while used.contains(&stem) {
+            copy += 1;
+            stem = format!("{} ({})", base, copy);
+        }
+        used.push(stem.clone());
+
+        let region = &samples[start * channels..end * channels];
+        info!("Region '{}': {:.3} s - {:.3} s", marker.label, start as f64 / sample_rate as f64, end as f64 / sample_rate as f64);
+        if args.split {
+            let dir = args.output.join(&stem);
+            std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            let (low, high) = processor.separate_frequencies(region, args.low_cutoff, args.high_cutoff)?;
+            processor.save_audio(dir.join("low_freq.wav"), &low)?;
+            processor.save_audio(dir.join("high_freq.wav"), &high)?;
+        } else {
+            processor.save_audio(args.output.join(format!("{}.wav", stem)), region)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use tracing::{error, info};
+
+use saunds_v2::audio::effects::{self, StageSpec};
+use saunds_v2::audio::{analysis, loudness, weighting::Weighting, AudioBuffer, AudioProcessor};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct ReplArgs {
+    /// Input audio file path, decoded once for the whole session
+    input: PathBuf,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+const HELP: &str = "\
+info                   sample rate, channels and duration
+loudness               integrated loudness, range and peaks
+octaves [1|3]          octave or third-octave band levels
+tempo                  estimated tempo
+gain DB                scale the buffer
+trim START END         keep the part between two times in seconds
+band LOW HIGH          keep the frequencies between two cutoffs in Hz
+fx STAGE...            run effect stages, e.g. fx saturate:drive=4
+undo                   revert the last change
+reset                  go back to the file as loaded
+save PATH              write the buffer
+quit                   leave";
+
+/// The decoded file and the edits made to it so far.
+struct Session {
+    processor: AudioProcessor,
+    original: AudioBuffer,
+    buffer: AudioBuffer,
+    previous: Option<AudioBuffer>,
+}
+
+fn number(word: Option<&str>, what: &str) -> Result<f32> {
+    let word = word.with_context(|| format!("Missing {}", what))?;
+    word.parse().with_context(|| format!("Invalid {} '{}'", what, word))
+}
+
+impl Session {
+    /// Replaces the buffer, keeping the old one for `undo`.
+    fn change(&mut self, buffer: AudioBuffer) {
+        self.previous = Some(std::mem::replace(&mut self.buffer, buffer));
+    }
+
+    /// Runs one command line, returning false when the session should end.
+    fn execute(&mut self, line: &str) -> Result<bool> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(true);
+        };
+        let sample_rate = self.buffer.sample_rate();
+        match command {
+            "help" | "?" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(false),
+            "info" => {
+                println!(
+                    "{} Hz, {} channels, {} frames ({:.3} s)",
+                    sample_rate,
+                    self.buffer.num_channels(),
+                    self.buffer.frames(),
+                    self.buffer.duration_seconds()
+                );
+            }
+            "loudness" => {
+                let measured = loudness::measure(&self.buffer)?;
+                println!(
+                    "{:.1} LUFS, {:.1} LU range, {:.1} dBFS sample peak, {:.1} dBTP true peak",
+                    measured.integrated_lufs,
+                    measured.loudness_range_lu,
+                    measured.sample_peak_dbfs,
+                    measured.true_peak_dbtp
+                );
+            }
+            "octaves" => {
+                let fraction = match words.next().unwrap_or("1") {
+                    "1" => 1,
+                    "3" => 3,
+                    other => bail!("Octave fraction must be 1 or 3, got '{}'", other),
+                };
+                let mono = self.buffer.mixdown();
+                println!("{:>10}  {:>8}", "center Hz", "dB");
+                for band in analysis::octave_band_levels(&mono, sample_rate, fraction, Weighting::Z)? {
+                    println!("{:>10.1}  {:>8.1}", band.center, band.level_db);
+                }
+            }
+            "tempo" => match analysis::estimate_tempo(&self.buffer.mixdown(), sample_rate)? {
+                Some(bpm) => println!("{:.1} BPM", bpm),
+                None => println!("No tempo detected"),
+            },
+            "gain" => {
+                let gain = 10f32.powf(number(words.next(), "gain")? / 20.0);
+                let mut buffer = self.buffer.clone();
+                buffer.channels_mut().flatten().for_each(|sample| *sample *= gain);
+                self.change(buffer);
+            }
+            "trim" => {
+                let (start, end) = (number(words.next(), "start")?, number(words.next(), "end")?);
+                let frames = self.buffer.frames();
+                let frame = |seconds: f32| ((seconds.max(0.0) * sample_rate as f32).round() as usize).min(frames);
+                if frame(start) >= frame(end) {
+                    bail!("The trim end must come after its start");
+                }
+                let channels = self.buffer.channels().map(|channel| channel[frame(start)..frame(end)].to_vec()).collect();
+                self.change(self.buffer.like(channels)?);
+            }
+            "band" => {
+                let (low, high) = (number(words.next(), "low cutoff")?, number(words.next(), "high cutoff")?);
+                let mut bands = self.processor.split_buffer(&self.buffer, &[low, high])?;
+                self.change(bands.swap_remove(1));
+            }
+            "fx" => {
+                let stages: Vec<StageSpec> = words.map(str::parse).collect::<Result<_>>()?;
+                if stages.is_empty() {
+                    bail!("Give at least one effect stage");
+                }
+                let mut buffer = self.buffer.clone();
+                effects::apply_chain(&stages, &mut buffer, true)?;
+                self.change(buffer);
+            }
+            "undo" => {
+                let previous = self.previous.take().context("Nothing to undo")?;
+                self.buffer = previous;
+            }
+            "reset" => self.change(self.original.clone()),
+            "save" => {
+                let path = words.next().context("Missing path")?;
+                self.processor.save_buffer(path, &self.buffer)?;
+            }
+            other => bail!("Unknown command '{}'; try help", other),
+        }
+        Ok(true)
+    }
+}
+
+pub fn run(args: ReplArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let buffer = processor.load_buffer(&args.input)?;
+    info!("Loaded {}; type help for commands", args.input.display());
+    let mut session = Session { processor, original: buffer.clone(), buffer, previous: None };
+
+    // Prompt only for a person at a terminal, not for piped scripts
+    let interactive = std::io::stdin().is_terminal();
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        if interactive {
+            eprint!("saunds> ");
+            std::io::stderr().flush()?;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        match session.execute(&line?) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => error!("{:#}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,293 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::{fmt::Write, path::PathBuf};
+use tracing::info;
+
+use saunds_v2::audio::{
+    analysis, excerpt, loudness, spectral, weighting::Weighting, AudioBuffer, WINDOW_SIZE,
+};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Input audio files; each gets its own report
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output directory for the `<input stem>.html` reports
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// FFT window size for the spectrogram
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// Width of every plot in SVG units and of the spectrogram in pixels.
+const PLOT_WIDTH: usize = 1000;
+/// Spectrogram rows, log-spaced from [`MIN_FREQUENCY`] to Nyquist.
+const SPECTROGRAM_ROWS: usize = 256;
+const MIN_FREQUENCY: f32 = 30.0;
+/// Spectrogram and band plot floor (dBFS).
+const FLOOR_DB: f32 = -90.0;
+/// Lowest loudness on the history plot (LUFS).
+const LOUDNESS_FLOOR: f32 = -60.0;
+/// Length of the representative excerpt suggested for auditioning (s).
+const EXCERPT_SECONDS: f32 = 10.0;
+
+pub fn run(args: ReportArgs) -> Result<()> {
+    for input in &args.inputs {
+        if !input.exists() {
+            bail!("Input file does not exist: {}", input.display());
+        }
+    }
+    std::fs::create_dir_all(&args.output)
+        .with_context(|| format!("Failed to create {}", args.output.display()))?;
+
+    for input in &args.inputs {
+        let mut processor = args.decode.processor()?;
+        let buffer = processor.load_buffer(input)?;
+        let channels = buffer.num_channels();
+        let sample_rate = buffer.sample_rate();
+        let mono = buffer.mixdown();
+        let name = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        info!("Measuring {}", name);
+        let stats = loudness::measure(&buffer)?;
+        let momentary = loudness::loudness_history(&buffer, 0.4, 0.1);
+        let short_term = loudness::loudness_history(&buffer, 3.0, 0.5);
+        let bands = analysis::octave_band_levels(&mono, sample_rate, 3, Weighting::Z)?;
+        let magnitudes = spectral::frame_magnitudes(&mono, args.window_size)?;
+        let duration = mono.len() as f32 / sample_rate as f32;
+
+        let mut table = vec![
+            ("Duration", format!("{:.2} s", duration)),
+            ("Format", format!("{} Hz, {} channel{}", sample_rate, channels, if channels == 1 { "" } else { "s" })),
+            ("Integrated loudness", format!("{:.1} LUFS", stats.integrated_lufs)),
+            ("Loudness range", format!("{:.1} LU", stats.loudness_range_lu)),
+            ("Max momentary", format!("{:.1} LUFS", stats.max_momentary_lufs)),
+            ("Max short-term", format!("{:.1} LUFS", stats.max_short_term_lufs)),
+            ("Sample peak", format!("{:.1} dBFS", stats.sample_peak_dbfs)),
+            ("True peak", format!("{:.1} dBTP", stats.true_peak_dbtp)),
+        ];
+        if duration > EXCERPT_SECONDS {
+            let length = (EXCERPT_SECONDS * sample_rate as f32) as usize;
+            let excerpt = excerpt::most_interesting(&mono, 1, sample_rate, length)?;
+            let seconds = |frame: usize| frame as f32 / sample_rate as f32;
+            table.push(("Representative excerpt", format!("{:.1} s to {:.1} s", seconds(excerpt.start), seconds(excerpt.end))));
+        }
+        if channels == 2 {
+            table.push(("Stereo correlation", format!("{:.2}", analysis::stereo_correlation(&buffer))));
+        }
+
+        let mut html = String::new();
+        write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{name}</h1>\n<table>\n",
+            name = escape(&name)
+        )?;
+        for (label, value) in &table {
+            writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, value)?;
+        }
+        html.push_str("</table>\n");
+
+        let nyquist = sample_rate as f32 / 2.0;
+        html.push_str("<h2>Waveform</h2>\n");
+        html.push_str(&waveform_svg(&buffer));
+        writeln!(html, "<h2>Spectrogram</h2>\n<p>{:.0} Hz to {:.1} kHz on a log axis, {:.0} dBFS to 0 dBFS</p>", MIN_FREQUENCY, nyquist / 1000.0, FLOOR_DB)?;
+        writeln!(
+            html,
+            "<img class=\"spectrogram\" alt=\"Spectrogram\" src=\"data:image/bmp;base64,{}\">",
+            base64(&spectrogram_bmp(&magnitudes, args.window_size, sample_rate))
+        )?;
+        html.push_str("<h2>Loudness</h2>\n<p>Momentary (light) and short-term (dark) loudness; the dashed line is the integrated loudness</p>\n");
+        html.push_str(&loudness_svg(&momentary, &short_term, stats.integrated_lufs, duration));
+        html.push_str("<h2>Band energy</h2>\n<p>1/3-octave band levels (dBFS)</p>\n");
+        html.push_str(&bands_svg(&bands));
+        html.push_str("</body>\n</html>\n");
+
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let path = args.output.join(format!("{}.html", stem));
+        info!("Writing report to: {}", path.display());
+        std::fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:1040px;margin:2em auto;color:#222}\
+table{border-collapse:collapse}th,td{padding:2px 12px;text-align:left}th{font-weight:normal;color:#666}\
+svg,img.spectrogram{width:100%;display:block;background:#fafafa}img.spectrogram{height:300px;image-rendering:pixelated}\
+text{font-size:11px;fill:#666}";
+
+/// Min/max envelope of each channel, one lane per channel.
+fn waveform_svg(buffer: &AudioBuffer) -> String {
+    const LANE: f32 = 120.0;
+    let frames = buffer.frames();
+    let columns = PLOT_WIDTH.min(frames.max(1));
+    let mut svg = format!("<svg viewBox=\"0 0 {} {}\">\n", PLOT_WIDTH, LANE as usize * buffer.num_channels());
+
+    for (lane, samples) in buffer.channels().enumerate() {
+        let mid = LANE * (lane as f32 + 0.5);
+        let mut upper = Vec::with_capacity(columns);
+        let mut lower = Vec::with_capacity(columns);
+        for column in 0..columns {
+            let (start, end) = (column * frames / columns, (column + 1) * frames / columns);
+            let (min, max) = samples[start..end]
+                .iter()
+                .fold((0.0f32, 0.0f32), |(min, max), &x| (min.min(x), max.max(x)));
+            let x = column as f32 * PLOT_WIDTH as f32 / columns as f32;
+            upper.push(format!("{:.1},{:.1}", x, mid - max.clamp(-1.0, 1.0) * LANE * 0.45));
+            lower.push(format!("{:.1},{:.1}", x, mid - min.clamp(-1.0, 1.0) * LANE * 0.45));
+        }
+        lower.reverse();
+        let _ = writeln!(svg, "<line x1=\"0\" y1=\"{mid}\" x2=\"{PLOT_WIDTH}\" y2=\"{mid}\" stroke=\"#ccc\"/>");
+        let _ = writeln!(svg, "<polygon fill=\"#3a6ea5\" points=\"{} {}\"/>", upper.join(" "), lower.join(" "));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Spectrogram as a 24-bit BMP, time left to right and frequency on a
+/// log axis from the bottom. Frames are merged by their per-bin maximum
+/// when there are more than [`PLOT_WIDTH`].
+fn spectrogram_bmp(magnitudes: &[Vec<f32>], window_size: usize, sample_rate: u32) -> Vec<u8> {
+    let width = PLOT_WIDTH.min(magnitudes.len()).max(1);
+    let bins = window_size / 2 + 1;
+    let bin_hz = sample_rate as f32 / window_size as f32;
+    let nyquist = sample_rate as f32 / 2.0;
+    let frequency = |row: usize| MIN_FREQUENCY * (nyquist / MIN_FREQUENCY).powf(row as f32 / SPECTROGRAM_ROWS as f32);
+    let ranges: Vec<(usize, usize)> = (0..SPECTROGRAM_ROWS)
+        .map(|row| {
+            let low = ((frequency(row) / bin_hz).round() as usize).min(bins - 1);
+            (low, ((frequency(row + 1) / bin_hz).round() as usize).clamp(low + 1, bins))
+        })
+        .collect();
+
+    let mut columns = vec![vec![0.0f32; bins]; width];
+    for (i, frame) in magnitudes.iter().enumerate() {
+        let column = &mut columns[i * width / magnitudes.len()];
+        for (peak, &m) in column.iter_mut().zip(frame) {
+            *peak = peak.max(m);
+        }
+    }
+
+    // Rows are stored bottom-up, which puts low frequencies at the bottom
+    let stride = (3 * width).div_ceil(4) * 4;
+    let mut pixels = vec![0u8; stride * SPECTROGRAM_ROWS];
+    for (row, &(low, high)) in ranges.iter().enumerate() {
+        for (x, column) in columns.iter().enumerate() {
+            let level = 20.0 * column[low..high].iter().fold(1e-6f32, |peak, &m| peak.max(m)).log10();
+            let [r, g, b] = super::heat((level - FLOOR_DB) / -FLOOR_DB);
+            pixels[row * stride + 3 * x..row * stride + 3 * x + 3].copy_from_slice(&[b, g, r]);
+        }
+    }
+
+    let mut bmp = Vec::with_capacity(54 + pixels.len());
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&(54 + pixels.len() as u32).to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&54u32.to_le_bytes());
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&(width as i32).to_le_bytes());
+    bmp.extend_from_slice(&(SPECTROGRAM_ROWS as i32).to_le_bytes());
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&24u16.to_le_bytes());
+    bmp.extend_from_slice(&[0; 24]);
+    bmp.extend(pixels);
+    bmp
+}
+
+/// Momentary and short-term loudness over time with the integrated level.
+fn loudness_svg(momentary: &[f32], short_term: &[f32], integrated: f32, duration: f32) -> String {
+    const HEIGHT: f32 = 240.0;
+    let (left, right, top, bottom) = (40.0, PLOT_WIDTH as f32 - 10.0, 10.0, HEIGHT - 20.0);
+    let y = |lufs: f32| top + (bottom - top) * (lufs.clamp(LOUDNESS_FLOOR, 0.0) / LOUDNESS_FLOOR);
+    let polyline = |levels: &[f32], block: f32, step: f32| -> String {
+        levels
+            .iter()
+            .enumerate()
+            .map(|(i, &lufs)| {
+                // Each block is plotted at its end, when a meter would show it
+                let t = (i as f32 * step + block) / duration.max(block);
+                format!("{:.1},{:.1}", left + (right - left) * t, y(lufs))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let mut svg = format!("<svg viewBox=\"0 0 {} {}\">\n", PLOT_WIDTH, HEIGHT);
+    for lufs in (LOUDNESS_FLOOR as i32..=0).step_by(10) {
+        let level = y(lufs as f32);
+        let _ = writeln!(svg, "<line x1=\"{left}\" y1=\"{level}\" x2=\"{right}\" y2=\"{level}\" stroke=\"#ddd\"/>");
+        let _ = writeln!(svg, "<text x=\"4\" y=\"{}\">{}</text>", level + 4.0, lufs);
+    }
+    let _ = writeln!(svg, "<text x=\"{left}\" y=\"{}\">0 s</text>", HEIGHT - 4.0);
+    let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.1} s</text>", right, HEIGHT - 4.0, duration);
+    let _ = writeln!(svg, "<polyline fill=\"none\" stroke=\"#9bb8d6\" points=\"{}\"/>", polyline(momentary, 0.4, 0.1));
+    let _ = writeln!(svg, "<polyline fill=\"none\" stroke=\"#1f4e79\" stroke-width=\"2\" points=\"{}\"/>", polyline(short_term, 3.0, 0.5));
+    if integrated > LOUDNESS_FLOOR {
+        let level = y(integrated);
+        let _ = writeln!(svg, "<line x1=\"{left}\" y1=\"{level}\" x2=\"{right}\" y2=\"{level}\" stroke=\"#c0392b\" stroke-dasharray=\"6 4\"/>");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Bar chart of fractional-octave band levels, labelled every octave.
+fn bands_svg(bands: &[analysis::BandLevel]) -> String {
+    const HEIGHT: f32 = 260.0;
+    let (left, right, top, bottom) = (40.0, PLOT_WIDTH as f32 - 10.0, 10.0, HEIGHT - 30.0);
+    let y = |db: f32| top + (bottom - top) * (db.clamp(FLOOR_DB, 0.0) / FLOOR_DB);
+    let slot = (right - left) / bands.len().max(1) as f32;
+
+    let mut svg = format!("<svg viewBox=\"0 0 {} {}\">\n", PLOT_WIDTH, HEIGHT);
+    for db in (FLOOR_DB as i32..=0).step_by(15) {
+        let level = y(db as f32);
+        let _ = writeln!(svg, "<line x1=\"{left}\" y1=\"{level}\" x2=\"{right}\" y2=\"{level}\" stroke=\"#ddd\"/>");
+        let _ = writeln!(svg, "<text x=\"4\" y=\"{}\">{}</text>", level + 4.0, db);
+    }
+    for (i, band) in bands.iter().enumerate() {
+        let x = left + slot * i as f32;
+        let level = y(band.level_db);
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#3a6ea5\"><title>{:.0} Hz: {:.1} dB</title></rect>",
+            x + 1.0, level, slot - 2.0, bottom - level, band.center, band.level_db
+        );
+        if i % 3 == 0 {
+            let _ = writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x + slot / 2.0, HEIGHT - 12.0, super::frequency_label(band.center)
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
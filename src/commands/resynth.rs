@@ -0,0 +1,61 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use saunds_v2::audio::{npy, spectral::{self, StftLayout}, AudioProcessor};
+
+#[derive(Args, Debug)]
+pub struct ResynthArgs {
+    /// STFT frames as a complex .npy array of shape (channels, frames, bins)
+    /// or (frames, bins)
+    frames: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Framing description written by --export-stft [default: stft.json
+    /// next to FRAMES]
+    #[arg(long)]
+    layout: Option<PathBuf>,
+}
+
+pub fn run(args: ResynthArgs) -> Result<()> {
+    let layout_path = args
+        .layout
+        .clone()
+        .unwrap_or_else(|| args.frames.with_file_name("stft.json"));
+    let json = std::fs::read_to_string(&layout_path)
+        .with_context(|| format!("Failed to read STFT layout {}", layout_path.display()))?;
+    let mut layout: StftLayout = serde_json::from_str(&json)
+        .with_context(|| format!("Invalid STFT layout {}", layout_path.display()))?;
+
+    let (shape, spectra) = npy::read_complex(&args.frames)?;
+    let (channels, frames, bins) = match shape[..] {
+        [frames, bins] => (1, frames, bins),
+        [channels, frames, bins] => (channels, frames, bins),
+        _ => bail!("Expected a 2-D or 3-D array of frames, got shape {:?}", shape),
+    };
+    if bins != layout.bins {
+        bail!("Frames have {} bins but the layout expects {}", bins, layout.bins);
+    }
+    if frames != layout.frames {
+        warn!("Frame count changed from {} to {}; the output length follows the frames", layout.frames, frames);
+        layout.frames = frames;
+        layout.samples_per_channel = frames.saturating_sub(1) * layout.hop;
+    }
+    layout.channels = channels;
+
+    info!("Resynthesizing {} channel(s) of {} frames", channels, frames);
+    let samples = spectral::istft(&spectra, &layout)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let processor = AudioProcessor::new()?
+        .with_sample_rate(layout.sample_rate)
+        .with_channels(channels as u32);
+    info!("Saving resynthesized audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
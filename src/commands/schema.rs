@@ -0,0 +1,104 @@
+use anyhow::Result;
+use clap::{Args, CommandFactory};
+use serde::Serialize;
+
+use crate::Cli;
+
+#[derive(Args, Debug)]
+pub struct SchemaArgs {
+    /// Print JSON, currently the only format
+    #[arg(long)]
+    json: bool,
+}
+
+/// A command and its parameters, for generating forms and wrappers.
+#[derive(Serialize)]
+struct CommandSchema {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    about: Option<String>,
+    args: Vec<ArgSchema>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subcommands: Vec<CommandSchema>,
+}
+
+#[derive(Serialize)]
+struct ArgSchema {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    long: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short: Option<char>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+    /// Positional, rather than an option
+    positional: bool,
+    required: bool,
+    /// A switch taking no value
+    flag: bool,
+    /// May be given more than once, or takes several values
+    multiple: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    value_names: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    default: Vec<String>,
+    /// The allowed values, for enumerations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    conflicts_with: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Choice {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+}
+
+fn describe(command: &clap::Command) -> CommandSchema {
+    let args = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .map(|arg| {
+            let flag = !arg.get_action().takes_values();
+            ArgSchema {
+                id: arg.get_id().to_string(),
+                long: arg.get_long().map(str::to_string),
+                short: arg.get_short(),
+                help: arg.get_help().map(|help| help.to_string()),
+                positional: arg.is_positional(),
+                required: arg.is_required_set(),
+                flag,
+                multiple: matches!(arg.get_action(), clap::ArgAction::Append)
+                    || arg.get_num_args().is_some_and(|range| range.max_values() > 1),
+                value_names: arg.get_value_names().unwrap_or_default().iter().map(|name| name.to_string()).collect(),
+                default: if flag {
+                    Vec::new()
+                } else {
+                    arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect()
+                },
+                choices: arg
+                    .get_possible_values()
+                    .iter()
+                    .filter(|value| !value.is_hide_set())
+                    .map(|value| Choice { name: value.get_name().to_string(), help: value.get_help().map(|help| help.to_string()) })
+                    .collect(),
+                conflicts_with: command.get_arg_conflicts_with(arg).iter().map(|other| other.get_id().to_string()).collect(),
+            }
+        })
+        .collect();
+    CommandSchema {
+        name: command.get_name().to_string(),
+        about: command.get_about().map(|about| about.to_string()),
+        args,
+        subcommands: command.get_subcommands().filter(|sub| !sub.is_hide_set()).map(describe).collect(),
+    }
+}
+
+pub fn run(_args: SchemaArgs) -> Result<()> {
+    let mut command = Cli::command();
+    command.build();
+    println!("{}", serde_json::to_string_pretty(&describe(&command))?);
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{script::SpectralScript, WINDOW_SIZE};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct ScriptArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Rhai script run on every STFT frame
+    #[arg(long)]
+    script: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// FFT window size
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: ScriptArgs) -> Result<()> {
+    let script = SpectralScript::load(&args.script)?;
+    let mut processor = args.decode.processor()?;
+    let mut samples = processor.load_audio(&args.input)?;
+
+    info!("Running {} on each frame", args.script.display());
+    script.apply(&mut samples, processor.channels() as usize, processor.sample_rate(), args.window_size)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving processed audio to: {}", args.output.display());
+    processor.save_audio(&args.output, &samples)
+}
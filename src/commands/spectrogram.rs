@@ -0,0 +1,113 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{cqt, mixdown, npy, spectral, WINDOW_SIZE};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct SpectrogramArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Output .npy file of float32 frames; a .json file with the same stem
+    /// describes the axes
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Time-frequency transform
+    #[arg(long, value_enum, default_value_t = Transform::Cqt)]
+    transform: Transform,
+
+    /// Write 12-bin chroma frames instead of the spectrogram
+    #[arg(long)]
+    chroma: bool,
+
+    /// FFT window size for the STFT; frames advance by half a window
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    /// Lowest CQT bin (Hz); the default is C1
+    #[arg(long, default_value_t = 32.703)]
+    min_freq: f32,
+
+    /// CQT bins per octave
+    #[arg(long, default_value_t = 36)]
+    bins_per_octave: usize,
+
+    /// CQT octaves
+    #[arg(long, default_value_t = 7)]
+    octaves: usize,
+
+    /// CQT frame hop in samples
+    #[arg(long, default_value_t = 512)]
+    hop: usize,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Transform {
+    /// Log-spaced bins with constant resolution per octave
+    Cqt,
+    /// Linearly spaced FFT bins
+    Stft,
+}
+
+pub fn run(args: SpectrogramArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let mono = mixdown(&samples, processor.channels() as usize);
+    let sample_rate = processor.sample_rate();
+
+    info!("Computing {:?} spectrogram", args.transform);
+    let (frequencies, frames, hop) = match args.transform {
+        Transform::Cqt => {
+            let cqt = cqt::Cqt {
+                min_frequency: args.min_freq,
+                bins_per_octave: args.bins_per_octave,
+                octaves: args.octaves,
+            };
+            (cqt.frequencies(), cqt.transform(&mono, sample_rate, args.hop)?, args.hop)
+        }
+        Transform::Stft => {
+            let frequencies = (0..args.window_size / 2 + 1)
+                .map(|bin| bin as f32 * sample_rate as f32 / args.window_size as f32)
+                .collect();
+            (frequencies, spectral::frame_magnitudes(&mono, args.window_size)?, args.window_size / 2)
+        }
+    };
+
+    let (columns, values): (usize, Vec<f32>) = if args.chroma {
+        (12, frames.iter().flat_map(|frame| cqt::chroma(&frequencies, frame)).collect())
+    } else {
+        let db = |m: f32| 20.0 * m.max(1e-6).log10();
+        (frequencies.len(), frames.iter().flatten().map(|&m| db(m)).collect())
+    };
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving {} frames of {} bins to: {}", frames.len(), columns, args.output.display());
+    npy::write_float32(&args.output, &[frames.len(), columns], &values)?;
+
+    let axes = serde_json::json!({
+        "transform": args.transform,
+        "values": if args.chroma { "chroma" } else { "magnitude_db" },
+        "sample_rate": sample_rate,
+        "hop": hop,
+        "frequencies": if args.chroma { None } else { Some(&frequencies) },
+        "pitch_classes": args.chroma.then_some(["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"]),
+    });
+    let path = args.output.with_extension("json");
+    std::fs::write(&path, serde_json::to_string_pretty(&axes)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,61 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use saunds_v2::audio::{analysis, mixdown};
+
+use super::DecodeArgs;
+
+/// Where a boundary between bass and the vocal range is looked for (Hz).
+const LOW_CUTOFF_RANGE: RangeInclusive<f32> = 50.0..=800.0;
+/// Where a boundary between the vocal range and treble is looked for (Hz).
+const HIGH_CUTOFF_RANGE: RangeInclusive<f32> = 800.0..=12_000.0;
+/// Fallbacks matching the split defaults.
+const DEFAULT_LOW_CUTOFF: f32 = 200.0;
+const DEFAULT_HIGH_CUTOFF: f32 = 2000.0;
+
+#[derive(Args, Debug)]
+pub struct SuggestCutoffsArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: SuggestCutoffsArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let mono = mixdown(&samples, processor.channels() as usize);
+
+    info!("Analyzing long-term spectrum...");
+    let valleys = analysis::spectral_valleys(&mono, processor.sample_rate())?;
+    for valley in &valleys {
+        info!("Valley at {:.0} Hz: {:.1} dB, {:.1} dB deep", valley.frequency, valley.level_db, valley.depth_db);
+    }
+
+    // Valleys are sorted deepest first
+    let deepest_in = |range: &RangeInclusive<f32>, above: f32| {
+        valleys
+            .iter()
+            .find(|valley| range.contains(&valley.frequency) && valley.frequency > above)
+            .map(|valley| valley.frequency.round())
+    };
+    let low_cutoff = deepest_in(&LOW_CUTOFF_RANGE, 0.0).unwrap_or_else(|| {
+        warn!("No spectral valley between bass and mids; using the default low cutoff");
+        DEFAULT_LOW_CUTOFF
+    });
+    let high_cutoff = deepest_in(&HIGH_CUTOFF_RANGE, low_cutoff).unwrap_or_else(|| {
+        warn!("No spectral valley between mids and treble; using the default high cutoff");
+        DEFAULT_HIGH_CUTOFF.max(low_cutoff * 2.0)
+    });
+
+    println!("--low-cutoff {} --high-cutoff {}", low_cutoff, high_cutoff);
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use std::{path::PathBuf, str::FromStr};
+use tracing::info;
+
+use saunds_v2::audio::{metrics, AudioProcessor, FilterDesign, FilterMode, WINDOW_SIZE};
+
+use super::{DecodeArgs, DesignArgs};
+
+#[derive(Args, Debug)]
+pub struct SweepArgs {
+    /// Input audio file path
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Parameter to sweep, as NAME=START..END:STEP or NAME=V1,V2,... where
+    /// NAME is low_cutoff, high_cutoff, window_size or order. Repeat to
+    /// sweep every combination
+    #[arg(long = "param", value_name = "NAME=VALUES", required = true)]
+    params: Vec<Sweep>,
+
+    /// Also write each combination's bands into a subdirectory of this
+    /// directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Low frequency cutoff when not swept (Hz)
+    #[arg(long, default_value = "200")]
+    low_cutoff: f32,
+
+    /// High frequency cutoff when not swept (Hz)
+    #[arg(long, default_value = "2000")]
+    high_cutoff: f32,
+
+    /// FFT window size when not swept
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    /// Filter used for the band split
+    #[arg(long, value_enum, default_value_t = FilterMode::Fft)]
+    filter: FilterMode,
+
+    #[command(flatten)]
+    design: DesignArgs,
+
+    /// Reference stems for the low and high band, in that order; adds
+    /// each band's SDR to the summary
+    #[arg(long = "reference", value_name = "FILE")]
+    references: Vec<PathBuf>,
+
+    /// Summary format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+/// Largest number of combinations a sweep may render.
+const MAX_COMBINATIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Param {
+    LowCutoff,
+    HighCutoff,
+    WindowSize,
+    Order,
+}
+
+impl Param {
+    fn name(self) -> &'static str {
+        match self {
+            Param::LowCutoff => "low_cutoff",
+            Param::HighCutoff => "high_cutoff",
+            Param::WindowSize => "window_size",
+            Param::Order => "order",
+        }
+    }
+}
+
+/// One swept parameter and the values it takes.
+#[derive(Debug, Clone)]
+struct Sweep {
+    param: Param,
+    values: Vec<f32>,
+}
+
+impl FromStr for Sweep {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some((name, range)) = value.split_once('=') else {
+            bail!("Expected NAME=START..END:STEP or NAME=V1,V2,..., got '{}'", value);
+        };
+        let param = match name {
+            "low_cutoff" => Param::LowCutoff,
+            "high_cutoff" => Param::HighCutoff,
+            "window_size" => Param::WindowSize,
+            "order" => Param::Order,
+            _ => bail!("Unknown parameter '{}'; expected low_cutoff, high_cutoff, window_size or order", name),
+        };
+        let number = |text: &str| -> Result<f32> {
+            text.trim().parse().with_context(|| format!("Invalid number '{}' in '{}'", text, value))
+        };
+
+        let values = if let Some((start, rest)) = range.split_once("..") {
+            let Some((end, step)) = rest.split_once(':') else {
+                bail!("Range '{}' needs a step, as START..END:STEP", range);
+            };
+            let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+            if step <= 0.0 || end < start {
+                bail!("Range '{}' must ascend with a positive step", range);
+            }
+            let count = ((end - start) / step + 1e-3).floor() as usize + 1;
+            if count > MAX_COMBINATIONS {
+                bail!("Range '{}' has {} values; the limit is {}", range, count, MAX_COMBINATIONS);
+            }
+            (0..count).map(|i| start + i as f32 * step).collect()
+        } else {
+            range.split(',').map(number).collect::<Result<Vec<_>>>()?
+        };
+        Ok(Self { param, values })
+    }
+}
+
+/// Settings for one rendered combination.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    low_cutoff: f32,
+    high_cutoff: f32,
+    window_size: usize,
+    design: FilterDesign,
+}
+
+impl Settings {
+    fn set(&mut self, param: Param, value: f32) {
+        match param {
+            Param::LowCutoff => self.low_cutoff = value,
+            Param::HighCutoff => self.high_cutoff = value,
+            Param::WindowSize => self.window_size = value as usize,
+            Param::Order => self.design.order = value as usize,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Row {
+    /// Swept parameters in the order they were given
+    params: Vec<(&'static str, f32)>,
+    low_db: f32,
+    high_db: f32,
+    /// Input minus the sum of the complementary three-way split at the
+    /// same cutoffs, relative to the input
+    residual_db: f32,
+    sdr_db: Option<(f32, f32)>,
+}
+
+impl Row {
+    fn to_json(&self) -> serde_json::Value {
+        let params: serde_json::Map<_, _> =
+            self.params.iter().map(|&(name, value)| (name.to_string(), serde_json::json!(value))).collect();
+        let mut row = serde_json::json!({
+            "params": params,
+            "low_db": self.low_db,
+            "high_db": self.high_db,
+            "residual_db": self.residual_db,
+        });
+        if let Some((low, high)) = self.sdr_db {
+            row["low_sdr_db"] = serde_json::json!(low);
+            row["high_sdr_db"] = serde_json::json!(high);
+        }
+        row
+    }
+}
+
+fn rms_db(samples: &[f32]) -> f32 {
+    let mean_square = samples.iter().map(|&x| (x as f64).powi(2)).sum::<f64>() / samples.len().max(1) as f64;
+    (10.0 * mean_square.max(1e-20).log10()) as f32
+}
+
+pub fn run(args: SweepArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+    for (i, sweep) in args.params.iter().enumerate() {
+        if args.params[..i].iter().any(|other| other.param == sweep.param) {
+            bail!("Parameter {} is swept more than once", sweep.param.name());
+        }
+    }
+    let design = args.design.design();
+    let sweeps_order = args.params.iter().any(|sweep| sweep.param == Param::Order);
+    if args.filter == FilterMode::Fft && (design != FilterDesign::default() || sweeps_order) {
+        bail!("--design, --order, --ripple, --attenuation and sweeping order require --filter iir or zero-phase");
+    }
+
+    let combinations: usize = args.params.iter().map(|sweep| sweep.values.len()).product();
+    if combinations > MAX_COMBINATIONS {
+        bail!("The sweep has {} combinations; the limit is {}", combinations, MAX_COMBINATIONS);
+    }
+
+    let mut loader = args.decode.processor()?;
+    let samples = loader.load_audio(&args.input)?;
+    let (sample_rate, channels) = (loader.sample_rate(), loader.channels());
+
+    let references = match args.references.len() {
+        0 => None,
+        2 => Some(
+            args.references
+                .iter()
+                .map(|path| {
+                    let mut stem_loader = AudioProcessor::new()?;
+                    let stem = stem_loader.load_audio(path)?;
+                    if (stem_loader.sample_rate(), stem_loader.channels()) != (sample_rate, channels) {
+                        bail!("Reference {} does not match the input's sample rate and channels", path.display());
+                    }
+                    Ok(stem)
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        count => bail!("Got {} reference stems; give two, for the low and the high band", count),
+    };
+
+    info!("Sweeping {} combinations", combinations);
+    let mut rows = Vec::with_capacity(combinations);
+    for index in 0..combinations {
+        let mut settings = Settings {
+            low_cutoff: args.low_cutoff,
+            high_cutoff: args.high_cutoff,
+            window_size: args.window_size,
+            design,
+        };
+        // Mixed-radix counter over the sweeps, the last one varying fastest
+        let mut params = Vec::with_capacity(args.params.len());
+        let mut rest = index;
+        for sweep in args.params.iter().rev() {
+            let value = sweep.values[rest % sweep.values.len()];
+            rest /= sweep.values.len();
+            settings.set(sweep.param, value);
+            params.push((sweep.param.name(), value));
+        }
+        params.reverse();
+
+        settings.design.validate()?;
+        let processor = AudioProcessor::new()?
+            .with_sample_rate(sample_rate)
+            .with_channels(channels)
+            .with_window_size(settings.window_size)
+            .with_filter_mode(args.filter)
+            .with_filter_design(settings.design);
+        processor.validate_cutoffs(settings.low_cutoff, settings.high_cutoff)?;
+
+        let (low, high) = processor.separate_frequencies(&samples, settings.low_cutoff, settings.high_cutoff)?;
+        let parts = processor.split_bands(&samples, &[settings.low_cutoff, settings.high_cutoff])?;
+        let residual: Vec<f32> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| x - parts.iter().map(|part| part[i]).sum::<f32>())
+            .collect();
+
+        let sdr_db = match &references {
+            Some(stems) => {
+                let scores = metrics::evaluate(&[&low, &high], &[&stems[0], &stems[1]])?;
+                Some((scores[0].sdr_db, scores[1].sdr_db))
+            }
+            None => None,
+        };
+
+        if let Some(output) = &args.output {
+            let name: Vec<String> = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            let dir = output.join(name.join(","));
+            std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            processor.save_audio(dir.join("low_freq.wav"), &low)?;
+            processor.save_audio(dir.join("high_freq.wav"), &high)?;
+        }
+
+        rows.push(Row {
+            params,
+            low_db: rms_db(&low),
+            high_db: rms_db(&high),
+            residual_db: rms_db(&residual) - rms_db(&samples),
+            sdr_db,
+        });
+    }
+
+    match args.format {
+        Format::Table => {
+            let mut header: Vec<String> = args.params.iter().map(|sweep| format!("{:>12}", sweep.param.name())).collect();
+            header.extend(["low dB", "high dB", "residual dB"].map(|column| format!("{:>12}", column)));
+            if references.is_some() {
+                header.extend(["low SDR", "high SDR"].map(|column| format!("{:>12}", column)));
+            }
+            println!("{}", header.join("  "));
+            for row in &rows {
+                let mut cells: Vec<String> = row.params.iter().map(|(_, value)| format!("{:>12}", value)).collect();
+                cells.extend([row.low_db, row.high_db, row.residual_db].map(|db| format!("{:>12.1}", db)));
+                if let Some((low, high)) = row.sdr_db {
+                    cells.extend([low, high].map(|db| format!("{:>12.1}", db)));
+                }
+                println!("{}", cells.join("  "));
+            }
+        }
+        Format::Json => {
+            let rows: Vec<_> = rows.iter().map(Row::to_json).collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{align, mixdown, resample};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct SyncArgs {
+    /// Recording whose clock and timeline to follow, e.g. camera audio
+    #[arg(long)]
+    reference: PathBuf,
+
+    /// Recording of the same event to bring into sync, e.g. from a
+    /// separate recorder
+    #[arg(long)]
+    target: PathBuf,
+
+    /// Output WAV file path for the synced target
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Length of the stretches the delay is measured on (s)
+    #[arg(long, default_value_t = 10.0)]
+    segment: f64,
+
+    /// Largest offset between the recordings to search for (s)
+    #[arg(long)]
+    max_delay: Option<f64>,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: SyncArgs) -> Result<()> {
+    if args.segment <= 0.0 {
+        bail!("Segment length must be positive, got {} s", args.segment);
+    }
+    let mut reference_processor = args.decode.processor()?;
+    let mut target_processor = args.decode.processor()?;
+    let reference = reference_processor.load_audio(&args.reference)?;
+    let mut target = target_processor.load_audio(&args.target)?;
+    let sample_rate = reference_processor.sample_rate();
+    let channels = target_processor.channels() as usize;
+
+    // Nominal rate differences are resampled away before measuring what
+    // the clocks drift on top of them
+    if target_processor.sample_rate() != sample_rate {
+        info!("Resampling the target from {} Hz to {} Hz", target_processor.sample_rate(), sample_rate);
+        target = resample::resample(&target, channels, target_processor.sample_rate(), sample_rate);
+        target_processor = target_processor.with_sample_rate(sample_rate);
+    }
+
+    let rate = sample_rate as f64;
+    let drift = align::estimate_drift(
+        &mixdown(&reference, reference_processor.channels() as usize),
+        &mixdown(&target, channels),
+        (args.segment * rate) as usize,
+        args.max_delay.map(|seconds| (seconds * rate) as usize),
+    )?;
+    let frames = reference.len() / reference_processor.channels().max(1) as usize;
+    info!(
+        "Target lags reference by {:.1} ms and its clock runs {:+.1} ppm ({:+.1} ms over the recording), from {} segments",
+        1000.0 * drift.offset_frames / rate,
+        drift.drift_ppm,
+        drift.drift_ppm * 1e-3 * frames as f64 / rate,
+        drift.segments
+    );
+
+    // The synced target covers the reference's timeline
+    let positions: Vec<f64> = (0..frames).map(|frame| drift.target_position(frame as f64)).collect();
+    let synced = resample::warp(&target, channels, &positions);
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving synced target to: {}", args.output.display());
+    target_processor.save_audio(&args.output, &synced)
+}
@@ -0,0 +1,139 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use saunds_v2::audio::ltc::{self, Timecode, TimecodeTrack};
+use saunds_v2::audio::AudioProcessor;
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct TimecodeArgs {
+    /// Recording with LTC on one of its channels
+    input: PathBuf,
+
+    /// Channel carrying the LTC, from 0. By default every channel is tried
+    #[arg(long)]
+    channel: Option<usize>,
+
+    /// Frame rate of the timecode, e.g. 23.976 or 29.97 for non-drop
+    /// pulled-down rates. By default it's read from the frame numbers
+    #[arg(long)]
+    fps: Option<f64>,
+
+    /// Write the recording trimmed or padded to start at this timecode
+    #[arg(long, value_name = "TC", requires = "output")]
+    start: Option<Timecode>,
+
+    /// Write the recording trimmed or padded to end at this timecode
+    #[arg(long, value_name = "TC", requires = "output")]
+    end: Option<Timecode>,
+
+    /// Output WAV file path for the trimmed recording
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Report format
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+pub fn run(args: TimecodeArgs) -> Result<()> {
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let channels = processor.channels() as usize;
+    let sample_rate = processor.sample_rate();
+
+    let candidates: Vec<usize> = match args.channel {
+        Some(channel) if channel >= channels => bail!("No channel {} in a {}-channel input", channel, channels),
+        Some(channel) => vec![channel],
+        None => (0..channels).collect(),
+    };
+    let Some((channel, frames)) = candidates
+        .into_iter()
+        .map(|channel| {
+            let signal: Vec<f32> = samples.iter().skip(channel).step_by(channels).copied().collect();
+            (channel, ltc::decode(&signal, sample_rate))
+        })
+        .find(|(_, frames)| !frames.is_empty())
+    else {
+        bail!("No LTC timecode found in {}", args.input.display());
+    };
+    info!("Decoded {} LTC frames from channel {}", frames.len(), channel);
+    let track = TimecodeTrack::fit(&frames, sample_rate, args.fps)?;
+    if track.discontinuities > 0 {
+        warn!("The timecode jumps {} times; start and drift are measured up to the first jump", track.discontinuities);
+    }
+
+    match args.format {
+        Format::Table => {
+            let rows = [
+                ("timecode at start", format!("{} +{:.2} frames", track.start, track.start_subframe)),
+                ("first frame", format!("{} at {:.3} s", track.first, track.first_seconds)),
+                ("frame rate", format!("{:.3} fps{}", track.fps, if track.drop_frame { " drop frame" } else { "" })),
+                ("measured rate", format!("{:.4} fps", track.measured_fps)),
+                ("drift", format!("{:+.1} ppm", track.drift_ppm)),
+                ("frames decoded", track.frames_decoded.to_string()),
+                ("discontinuities", track.discontinuities.to_string()),
+            ];
+            for (name, value) in rows {
+                println!("{:<18}  {}", name, value);
+            }
+        }
+        Format::Json => {
+            let report = serde_json::json!({
+                "input": args.input,
+                "channel": channel,
+                "timecode": track,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    if let Some(output) = &args.output {
+        trim(&args, &processor, &samples, &track, output)?;
+    }
+    Ok(())
+}
+
+/// Writes `samples` from the `--start` to the `--end` timecode, padding
+/// with silence where those fall outside the recording.
+fn trim(args: &TimecodeArgs, processor: &AudioProcessor, samples: &[f32], track: &TimecodeTrack, output: &Path) -> Result<()> {
+    if args.start.is_none() && args.end.is_none() {
+        bail!("--output needs --start or --end to trim to");
+    }
+    let channels = processor.channels() as usize;
+    let frames = (samples.len() / channels) as i64;
+    let start = args.start.map_or(0, |timecode| track.sample_at(&timecode).round() as i64);
+    let end = args.end.map_or(frames, |timecode| track.sample_at(&timecode).round() as i64);
+    if end <= start {
+        bail!("The end timecode must come after the start");
+    }
+    let trimmed: Vec<f32> = (start..end)
+        .flat_map(|frame| {
+            let source = (0..frames).contains(&frame).then(|| frame as usize * channels);
+            (0..channels).map(move |channel| source.map_or(0.0, |offset| samples[offset + channel]))
+        })
+        .collect();
+    info!(
+        "Trimmed to {:.3} s - {:.3} s of the recording",
+        start as f64 / processor.sample_rate() as f64,
+        end as f64 / processor.sample_rate() as f64
+    );
+
+    if let Some(parent) = output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving trimmed audio to: {}", output.display());
+    processor.save_audio(output, &trimmed)
+}
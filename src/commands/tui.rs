@@ -0,0 +1,279 @@
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Paragraph, Widget},
+    DefaultTerminal, Frame,
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+use saunds_v2::audio::{mixdown, scale::BandScale, spectral, WINDOW_SIZE};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Input audio file path
+    input: PathBuf,
+
+    /// Number of level meters
+    #[arg(long, default_value_t = 8)]
+    bands: usize,
+
+    /// Frequency scale on which the meter bands are equally wide
+    #[arg(long, value_enum, default_value_t = BandScale::Log)]
+    band_scale: BandScale,
+
+    /// FFT window size; the display advances by half a window
+    #[arg(long, default_value_t = WINDOW_SIZE)]
+    window_size: usize,
+
+    /// Level shown as black in the spectrogram and as an empty meter (dBFS)
+    #[arg(long, default_value_t = -90.0, allow_hyphen_values = true)]
+    floor: f32,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+/// Lowest frequency on the spectrogram's log axis.
+const MIN_FREQUENCY: f32 = 30.0;
+/// Seek step for the arrow keys.
+const SEEK_SECONDS: f64 = 5.0;
+/// Redraw interval while playing.
+const TICK: Duration = Duration::from_millis(33);
+
+/// Spectra and band levels of every frame, precomputed so the display
+/// only has to look them up.
+struct Meters {
+    name: String,
+    sample_rate: u32,
+    window_size: usize,
+    floor: f32,
+    /// Per-bin level of each frame in dBFS
+    spectra: Vec<Vec<f32>>,
+    /// Per-band level of each frame in dBFS
+    levels: Vec<Vec<f32>>,
+    /// Lower edge of each band in Hz
+    edges: Vec<f32>,
+}
+
+impl Meters {
+    fn hop(&self) -> usize {
+        self.window_size / 2
+    }
+
+    fn duration(&self) -> f64 {
+        (self.spectra.len() * self.hop()) as f64 / self.sample_rate as f64
+    }
+
+    /// Frame playing at `seconds`.
+    fn frame_at(&self, seconds: f64) -> usize {
+        // The first frame is centred on sample 0
+        let frame = (seconds * self.sample_rate as f64 / self.hop() as f64).round() as usize;
+        frame.min(self.spectra.len().saturating_sub(1))
+    }
+}
+
+/// Where the display is in the file and whether it is advancing.
+struct Transport {
+    position: f64,
+    playing: bool,
+    last: Instant,
+}
+
+impl Transport {
+    fn tick(&mut self, duration: f64) {
+        let now = Instant::now();
+        if self.playing {
+            self.position += now.duration_since(self.last).as_secs_f64();
+            if self.position >= duration {
+                self.position = duration;
+                self.playing = false;
+            }
+        }
+        self.last = now;
+    }
+
+    fn seek(&mut self, seconds: f64, duration: f64) {
+        self.position = (self.position + seconds).clamp(0.0, duration);
+    }
+}
+
+pub fn run(args: TuiArgs) -> Result<()> {
+    if !args.input.exists() {
+        bail!("Input file does not exist: {}", args.input.display());
+    }
+    if args.floor >= 0.0 {
+        bail!("Floor must be below 0 dBFS, got {} dB", args.floor);
+    }
+
+    let mut processor = args.decode.processor()?;
+    let samples = processor.load_audio(&args.input)?;
+    let mono = mixdown(&samples, processor.channels() as usize);
+    let sample_rate = processor.sample_rate();
+    let cutoffs = args.band_scale.cutoffs(args.bands, sample_rate)?;
+
+    info!("Computing spectra for {} meters", args.bands);
+    let magnitudes = spectral::frame_magnitudes(&mono, args.window_size)?;
+    let meters = Meters {
+        name: args.input.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        sample_rate,
+        window_size: args.window_size,
+        floor: args.floor,
+        levels: magnitudes
+            .iter()
+            .map(|frame| spectral::band_levels(frame, args.window_size, sample_rate, &cutoffs))
+            .collect(),
+        spectra: magnitudes
+            .iter()
+            .map(|frame| frame.iter().map(|&m| 20.0 * m.max(1e-6).log10()).collect())
+            .collect(),
+        edges: std::iter::once(0.0).chain(cutoffs).collect(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &meters);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, meters: &Meters) -> Result<()> {
+    let duration = meters.duration();
+    let mut transport = Transport { position: 0.0, playing: true, last: Instant::now() };
+
+    loop {
+        transport.tick(duration);
+        terminal
+            .draw(|frame| draw(frame, meters, &transport))
+            .context("Failed to draw the terminal UI")?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char(' ') => {
+                if transport.position >= duration {
+                    transport.position = 0.0;
+                }
+                transport.playing = !transport.playing;
+            }
+            KeyCode::Left => transport.seek(-SEEK_SECONDS, duration),
+            KeyCode::Right => transport.seek(SEEK_SECONDS, duration),
+            KeyCode::Home => transport.position = 0.0,
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, meters: &Meters, transport: &Transport) {
+    let [header, body] = Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(body);
+
+    let status = if transport.playing { "playing" } else { "paused" };
+    let line = format!(
+        " {}  {} / {}  {}   [space] play/pause  [←/→] seek  [home] start  [q] quit",
+        meters.name,
+        timestamp(transport.position),
+        timestamp(meters.duration()),
+        status
+    );
+    frame.render_widget(Paragraph::new(line), header);
+
+    let current = meters.frame_at(transport.position);
+    let block = Block::bordered().title(format!(" Spectrogram {:.0} Hz – {:.1} kHz ", MIN_FREQUENCY, meters.sample_rate as f32 / 2000.0));
+    let inner = block.inner(left);
+    frame.render_widget(block, left);
+    frame.render_widget(Spectrogram { meters, current }, inner);
+
+    let block = Block::bordered().title(" Band levels (dBFS) ");
+    let inner = block.inner(right);
+    let count = meters.edges.len() as u16;
+    let bar_width = (inner.width.saturating_sub(count - 1) / count).max(1);
+    let bars: Vec<Bar> = meters.levels[current]
+        .iter()
+        .zip(&meters.edges)
+        .map(|(&level, &edge)| {
+            Bar::default()
+                .value((level - meters.floor).max(0.0).round() as u64)
+                .text_value(format!("{:.0}", level.max(meters.floor)))
+                .label(Line::from(super::frequency_label(edge)))
+                .style(Style::default().fg(colour((level - meters.floor) / -meters.floor)))
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(block)
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(bar_width)
+        .bar_gap(1)
+        .max(-meters.floor as u64);
+    frame.render_widget(chart, right);
+}
+
+/// Scrolling spectrogram with the current frame in the rightmost column
+/// and frequency on a log axis. Each cell shows two rows using a half
+/// block, the upper one in the foreground colour.
+struct Spectrogram<'a> {
+    meters: &'a Meters,
+    current: usize,
+}
+
+impl Widget for Spectrogram<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let meters = self.meters;
+        let rows = 2 * area.height as usize;
+        let nyquist = meters.sample_rate as f32 / 2.0;
+        let bin_hz = meters.sample_rate as f32 / meters.window_size as f32;
+        let bins = meters.window_size / 2 + 1;
+
+        // Bin range of each pixel row, bottom row first
+        let ranges: Vec<(usize, usize)> = (0..rows)
+            .map(|row| {
+                let frequency = |row: usize| MIN_FREQUENCY * (nyquist / MIN_FREQUENCY).powf(row as f32 / rows as f32);
+                let low = ((frequency(row) / bin_hz).round() as usize).min(bins - 1);
+                let high = ((frequency(row + 1) / bin_hz).round() as usize).clamp(low + 1, bins);
+                (low, high)
+            })
+            .collect();
+
+        for x in 0..area.width {
+            let age = (area.width - 1 - x) as usize;
+            let Some(spectrum) = self.current.checked_sub(age).map(|frame| &meters.spectra[frame]) else {
+                continue;
+            };
+            let colour = |row: usize| {
+                let (low, high) = ranges[row];
+                let level = spectrum[low..high].iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                colour((level - meters.floor) / -meters.floor)
+            };
+            for y in 0..area.height {
+                let lower = rows - 2 - 2 * y as usize;
+                buf[(area.x + x, area.y + y)].set_char('▀').set_fg(colour(lower + 1)).set_bg(colour(lower));
+            }
+        }
+    }
+}
+
+fn colour(value: f32) -> Color {
+    let [r, g, b] = super::heat(value);
+    Color::Rgb(r, g, b)
+}
+
+fn timestamp(seconds: f64) -> String {
+    format!("{}:{:04.1}", (seconds / 60.0) as u64, seconds % 60.0)
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+use crate::journal::{self, Journal};
+
+#[derive(Args, Debug)]
+pub struct UndoArgs {
+    /// File edited in place; its latest journaled edit is undone
+    file: PathBuf,
+
+    /// List the file's journaled edits, oldest first, instead of undoing
+    #[arg(long)]
+    list: bool,
+
+    /// Restore even if the file changed since the edit
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(args: UndoArgs) -> Result<()> {
+    if args.list {
+        let dir = args.file.parent().unwrap_or(std::path::Path::new("")).join(journal::DIR);
+        let journal = Journal::read(&dir)?;
+        let name = args.file.file_name().unwrap_or_default().to_string_lossy();
+        for edit in journal.edits_of(&name) {
+            println!("{}\t{}", edit.time, edit.command);
+        }
+        return Ok(());
+    }
+    journal::undo(&args.file, args.force)?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+use crate::manifest::{self, Checksums};
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Manifest written by a split; band files are looked up next to it
+    manifest: PathBuf,
+}
+
+pub fn run(args: VerifyArgs) -> Result<()> {
+    let checksums = Checksums::read(&args.manifest)?;
+    let dir = args.manifest.parent().unwrap_or(std::path::Path::new(""));
+
+    let mut failures = 0;
+    for band in &checksums.bands {
+        let path = dir.join(&band.file);
+        let status = match &band.sha256 {
+            None => "NO CHECKSUM",
+            Some(_) if !path.exists() => "MISSING",
+            Some(expected) if manifest::sha256_file(&path)?.eq_ignore_ascii_case(expected) => "OK",
+            Some(_) => "MISMATCH",
+        };
+        if status != "OK" {
+            warn!("{}: {}", band.file, status);
+            failures += 1;
+        }
+        println!("{}: {}", band.file, status);
+    }
+
+    if failures > 0 {
+        bail!("{} of {} files failed verification", failures, checksums.bands.len());
+    }
+    info!("All {} files verified", checksums.bands.len());
+    Ok(())
+}
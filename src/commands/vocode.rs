@@ -0,0 +1,69 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use std::path::PathBuf;
+use tracing::info;
+
+use saunds_v2::audio::{mixdown, scale::BandScale, vocoder};
+
+use super::DecodeArgs;
+
+#[derive(Args, Debug)]
+pub struct VocodeArgs {
+    /// Signal whose timbre is kept, typically a synth
+    #[arg(long)]
+    carrier: PathBuf,
+
+    /// Signal whose band envelopes are imposed, typically a voice
+    #[arg(long)]
+    modulator: PathBuf,
+
+    /// Output WAV file path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Number of vocoder bands
+    #[arg(long, default_value_t = 24)]
+    bands: usize,
+
+    /// Scale the band edges are evenly spaced on
+    #[arg(long, value_enum, default_value_t = BandScale::Log)]
+    band_scale: BandScale,
+
+    /// Release time of the band envelope followers (ms)
+    #[arg(long, default_value_t = 20.0)]
+    release: f32,
+
+    #[command(flatten)]
+    decode: DecodeArgs,
+}
+
+pub fn run(args: VocodeArgs) -> Result<()> {
+    let mut carrier_processor = args.decode.processor()?;
+    let mut modulator_processor = args.decode.processor()?;
+    let carrier = carrier_processor.load_audio(&args.carrier)?;
+    let modulator = modulator_processor.load_audio(&args.modulator)?;
+
+    let sample_rate = carrier_processor.sample_rate();
+    if modulator_processor.sample_rate() != sample_rate {
+        bail!(
+            "Sample rates differ: {} is {} Hz, {} is {} Hz",
+            args.carrier.display(), sample_rate,
+            args.modulator.display(), modulator_processor.sample_rate()
+        );
+    }
+    if args.release <= 0.0 {
+        bail!("Release must be positive, got {} ms", args.release);
+    }
+
+    let cutoffs = args.band_scale.cutoffs(args.bands, sample_rate)?;
+    info!("Vocoding with {} {:?}-spaced bands", args.bands, args.band_scale);
+    let modulator = mixdown(&modulator, modulator_processor.channels() as usize);
+    let channels = carrier_processor.channels() as usize;
+    let output = vocoder::vocode(&carrier, channels, &modulator, &cutoffs, sample_rate, args.release)?;
+
+    if let Some(parent) = args.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    info!("Saving vocoded audio to: {}", args.output.display());
+    carrier_processor.save_audio(&args.output, &output)
+}
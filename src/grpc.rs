@@ -0,0 +1,380 @@
+//! gRPC band separation with bidirectional streaming: clients stream PCM
+//! frames in and get each band's frames back as soon as the streaming STFT
+//! has them. The wire format is described in `proto/saunds.proto`.
+
+use anyhow::{bail, Result};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::audio::{AudioProcessor, StftProcessor, WINDOW_SIZE};
+
+include!(concat!(env!("OUT_DIR"), "/saunds.Separator.rs"));
+
+/// Largest STFT window a client may ask for.
+pub const MAX_WINDOW_SIZE: usize = 65536;
+/// Most channels in one stream.
+pub const MAX_CHANNELS: u32 = 64;
+/// Most bands, i.e. cutoffs plus one, in one stream.
+pub const MAX_BANDS: usize = 32;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamConfig {
+    #[prost(uint32, tag = "1")]
+    pub sample_rate: u32,
+    #[prost(uint32, tag = "2")]
+    pub channels: u32,
+    /// Ascending band edges in Hz
+    #[prost(float, repeated, tag = "3")]
+    pub cutoffs: Vec<f32>,
+    /// STFT window size, 0 for [`WINDOW_SIZE`]
+    #[prost(uint32, tag = "4")]
+    pub window_size: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SplitRequest {
+    /// Required on the first request of a stream and rejected after it
+    #[prost(message, optional, tag = "1")]
+    pub config: Option<StreamConfig>,
+    /// Interleaved frames
+    #[prost(float, repeated, tag = "2")]
+    pub samples: Vec<f32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BandFrames {
+    #[prost(float, repeated, tag = "1")]
+    pub samples: Vec<f32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SplitResponse {
+    /// Lowest band first, all with the same number of frames
+    #[prost(message, repeated, tag = "1")]
+    pub bands: Vec<BandFrames>,
+    /// Frames by which the output trails the input until the stream ends
+    #[prost(uint32, tag = "2")]
+    pub latency_frames: u32,
+}
+
+/// One streaming STFT per band and channel.
+struct BandSplitter {
+    channels: usize,
+    /// Indexed by band, then channel
+    streams: Vec<Vec<StftProcessor>>,
+    latency: u32,
+}
+
+impl BandSplitter {
+    fn new(config: &StreamConfig) -> Result<Self> {
+        // Each band and channel holds its own STFT, so the client mustn't
+        // be able to size them freely
+        if !(1..=MAX_CHANNELS).contains(&config.channels) {
+            bail!("Streams carry 1 to {} channels, got {}", MAX_CHANNELS, config.channels);
+        }
+        if config.cutoffs.len() >= MAX_BANDS {
+            bail!("Streams split into at most {} bands, got {} cutoffs", MAX_BANDS, config.cutoffs.len());
+        }
+        if config.window_size as usize > MAX_WINDOW_SIZE {
+            bail!("The window size must be at most {}, got {}", MAX_WINDOW_SIZE, config.window_size);
+        }
+        if config.cutoffs.windows(2).any(|pair| pair[0] >= pair[1]) {
+            bail!("Band cutoffs must be in ascending order, got {:?}", config.cutoffs);
+        }
+        let window_size = match config.window_size {
+            0 => WINDOW_SIZE,
+            size => size as usize,
+        };
+        let processor = AudioProcessor::builder()
+            .sample_rate(config.sample_rate)
+            .channels(config.channels)
+            .fft_size(window_size)
+            .build()?;
+
+        let mut edges = vec![0.0];
+        edges.extend(&config.cutoffs);
+        edges.push(config.sample_rate as f32 / 2.0);
+        let streams = edges
+            .windows(2)
+            .map(|pair| (0..config.channels).map(|_| processor.band_stream(pair[0], pair[1])).collect())
+            .collect::<Result<Vec<Vec<_>>>>()?;
+        Ok(Self { channels: config.channels as usize, streams, latency: (window_size / 2) as u32 })
+    }
+
+    fn push(&mut self, samples: &[f32]) -> Result<SplitResponse> {
+        if !samples.len().is_multiple_of(self.channels) {
+            bail!("{} samples is not a whole number of {}-channel frames", samples.len(), self.channels);
+        }
+        for channel in 0..self.channels {
+            let channel_samples: Vec<f32> = samples.iter().skip(channel).step_by(self.channels).copied().collect();
+            for band in &mut self.streams {
+                band[channel].push(&channel_samples);
+            }
+        }
+        Ok(self.collect(StftProcessor::pull))
+    }
+
+    fn flush(&mut self) -> SplitResponse {
+        self.collect(StftProcessor::flush)
+    }
+
+    /// Interleaves what `take` returns for each band's channels.
+    fn collect(&mut self, take: fn(&mut StftProcessor) -> Vec<f32>) -> SplitResponse {
+        let bands = self
+            .streams
+            .iter_mut()
+            .map(|band| {
+                let channels: Vec<Vec<f32>> = band.iter_mut().map(take).collect();
+                let frames = channels.iter().map(Vec::len).min().unwrap_or(0);
+                let samples = (0..frames).flat_map(|frame| channels.iter().map(move |channel| channel[frame])).collect();
+                BandFrames { samples }
+            })
+            .collect();
+        SplitResponse { bands, latency_frames: self.latency }
+    }
+}
+
+/// Caps on what clients may ask of the server at once, so one client can't
+/// exhaust it.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Streams served at once across all clients
+    pub max_streams: usize,
+    /// Streams one client address may have open at once
+    pub max_client_streams: usize,
+    /// Streams one client address may start per minute
+    pub client_streams_per_minute: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self { max_streams: 16, max_client_streams: 4, client_streams_per_minute: 60 }
+    }
+}
+
+/// Streams open in total and by client address.
+#[derive(Debug, Default)]
+struct Admissions {
+    open: usize,
+    clients: HashMap<IpAddr, Client>,
+}
+
+#[derive(Debug, Default)]
+struct Client {
+    open: usize,
+    /// When the client's streams of the last minute started
+    started: VecDeque<Instant>,
+}
+
+/// Upper bounds of the stream duration histogram (s).
+const DURATION_BUCKETS: [f64; 6] = [0.1, 1.0, 10.0, 60.0, 600.0, 3600.0];
+
+/// Counters of the service since it started, for Prometheus to scrape.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    started: AtomicU64,
+    refused: AtomicU64,
+    failed: AtomicU64,
+    active: AtomicU64,
+    input_frames: AtomicU64,
+    /// Streams by the first duration bucket they fit, the last for longer
+    durations: [AtomicU64; DURATION_BUCKETS.len() + 1],
+    duration_micros: AtomicU64,
+}
+
+impl Metrics {
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut text = String::new();
+        let counters = [
+            ("saunds_grpc_streams_started_total", "Streams admitted", &self.started),
+            ("saunds_grpc_streams_refused_total", "Streams refused by the limits", &self.refused),
+            ("saunds_grpc_streams_failed_total", "Streams ended by an error", &self.failed),
+            ("saunds_grpc_input_frames_total", "PCM frames received", &self.input_frames),
+        ];
+        for (name, help, counter) in counters {
+            let _ = writeln!(text, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, load(counter));
+        }
+        let name = "saunds_grpc_streams_active";
+        let _ = writeln!(text, "# HELP {} Streams being served\n# TYPE {} gauge\n{} {}", name, name, name, load(&self.active));
+
+        let name = "saunds_grpc_stream_duration_seconds";
+        let _ = writeln!(text, "# HELP {} Duration of finished streams\n# TYPE {} histogram", name, name);
+        let mut count = 0;
+        for (bound, streams) in DURATION_BUCKETS.iter().zip(&self.durations) {
+            count += load(streams);
+            let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        count += load(&self.durations[DURATION_BUCKETS.len()]);
+        let _ = writeln!(text, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(text, "{}_sum {}", name, load(&self.duration_micros) as f64 / 1e6);
+        let _ = writeln!(text, "{}_count {}", name, count);
+        text
+    }
+
+    fn fail(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A stream's place under the limits, given back when it is dropped.
+struct Admission {
+    admissions: Arc<Mutex<Admissions>>,
+    client: Option<IpAddr>,
+    metrics: Arc<Metrics>,
+    start: Instant,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        let seconds = self.start.elapsed().as_secs_f64();
+        let bucket = DURATION_BUCKETS.iter().position(|&bound| seconds <= bound).unwrap_or(DURATION_BUCKETS.len());
+        self.metrics.durations[bucket].fetch_add(1, Ordering::Relaxed);
+        self.metrics.duration_micros.fetch_add((seconds * 1e6) as u64, Ordering::Relaxed);
+        self.metrics.active.fetch_sub(1, Ordering::Relaxed);
+
+        let mut admissions = self.admissions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        admissions.open -= 1;
+        if let Some(address) = self.client {
+            if let Some(client) = admissions.clients.get_mut(&address) {
+                client.open -= 1;
+            }
+        }
+    }
+}
+
+/// The `saunds.Separator` service.
+#[derive(Debug, Default)]
+pub struct SeparatorService {
+    limits: Limits,
+    admissions: Arc<Mutex<Admissions>>,
+    metrics: Arc<Metrics>,
+}
+
+impl SeparatorService {
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The service's metrics, which stay shared with it.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Admits a stream from `client` if it stays within the limits.
+    fn admit(&self, client: Option<IpAddr>) -> Result<Admission> {
+        let admitted = self.try_admit(client);
+        let counter = if admitted.is_ok() { &self.metrics.started } else { &self.metrics.refused };
+        counter.fetch_add(1, Ordering::Relaxed);
+        admitted
+    }
+
+    fn try_admit(&self, client: Option<IpAddr>) -> Result<Admission> {
+        let mut admissions = self.admissions.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if admissions.open >= self.limits.max_streams {
+            bail!("The server is already serving {} streams", admissions.open);
+        }
+        if let Some(address) = client {
+            let now = Instant::now();
+            // Forget clients that have been idle for a minute
+            admissions.clients.retain(|_, client| {
+                while client.started.front().is_some_and(|&start| now - start >= Duration::from_secs(60)) {
+                    client.started.pop_front();
+                }
+                client.open > 0 || !client.started.is_empty()
+            });
+            let client = admissions.clients.entry(address).or_default();
+            if client.open >= self.limits.max_client_streams {
+                bail!("{} already has {} streams open", address, client.open);
+            }
+            if client.started.len() >= self.limits.client_streams_per_minute {
+                bail!("{} has started {} streams in the last minute", address, client.started.len());
+            }
+            client.open += 1;
+            client.started.push_back(now);
+        }
+        admissions.open += 1;
+        self.metrics.active.fetch_add(1, Ordering::Relaxed);
+        Ok(Admission { admissions: self.admissions.clone(), client, metrics: self.metrics.clone(), start: Instant::now() })
+    }
+}
+
+type SplitStream = Pin<Box<dyn Stream<Item = Result<SplitResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl separator_server::Separator for SeparatorService {
+    type SplitStream = SplitStream;
+
+    async fn split(&self, request: Request<Streaming<SplitRequest>>) -> Result<Response<SplitStream>, Status> {
+        let admission = self
+            .admit(request.remote_addr().map(|address| address.ip()))
+            .map_err(|e| Status::resource_exhausted(format!("{:#}", e)))?;
+        let metrics = self.metrics.clone();
+        let mut requests = request.into_inner();
+        let started = async {
+            let first = requests.message().await?.ok_or_else(|| Status::invalid_argument("The stream is empty"))?;
+            let config = first
+                .config
+                .as_ref()
+                .ok_or_else(|| Status::invalid_argument("The first request must carry the stream config"))?;
+            let splitter = BandSplitter::new(config).map_err(|e| Status::invalid_argument(format!("{:#}", e)))?;
+            Ok::<_, Status>((first, splitter))
+        };
+        let (first, mut splitter) = started.await.inspect_err(|_| metrics.fail())?;
+
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let _admission = admission;
+            let mut next = Some(first);
+            let mut first_request = true;
+            loop {
+                let request = match next.take() {
+                    Some(request) => request,
+                    None => match requests.message().await {
+                        Ok(Some(request)) => request,
+                        Ok(None) => break,
+                        Err(status) => {
+                            metrics.fail();
+                            let _ = sender.send(Err(status)).await;
+                            return;
+                        }
+                    },
+                };
+                if request.config.is_some() && !first_request {
+                    metrics.fail();
+                    let _ = sender.send(Err(Status::invalid_argument("Only the first request may carry a config"))).await;
+                    return;
+                }
+                first_request = false;
+                metrics.input_frames.fetch_add((request.samples.len() / splitter.channels) as u64, Ordering::Relaxed);
+
+                match splitter.push(&request.samples) {
+                    Err(e) => {
+                        metrics.fail();
+                        let _ = sender.send(Err(Status::invalid_argument(format!("{:#}", e)))).await;
+                        return;
+                    }
+                    // Nothing to send until a hop of input has built up
+                    Ok(response) if response.bands.iter().all(|band| band.samples.is_empty()) => {}
+                    Ok(response) => {
+                        if sender.send(Ok(response)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = sender.send(Ok(splitter.flush())).await;
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
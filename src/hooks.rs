@@ -0,0 +1,42 @@
+//! User commands run after a split, for slotting saunds into ops
+//! pipelines. The hook runs through the shell with the manifest path
+//! appended as its last argument, and sees `SAUNDS_INPUT`,
+//! `SAUNDS_MANIFEST`, `SAUNDS_STATUS` and, on failure, `SAUNDS_ERROR` in
+//! its environment.
+
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+use tracing::info;
+
+/// Runs `hook` for the split of `input` that wrote, or would have
+/// written, `manifest`. Fails if the hook can't start or exits non-zero.
+pub fn run(hook: &str, input: &Path, manifest: &Path, error: Option<&anyhow::Error>) -> Result<()> {
+    info!("Running hook: {}", hook);
+    #[cfg(unix)]
+    let mut command = {
+        let mut command = Command::new("sh");
+        // "$1" keeps the path a single argument whatever it contains
+        command.arg("-c").arg(format!("{} \"$1\"", hook)).arg("saunds-hook").arg(manifest);
+        command
+    };
+    #[cfg(not(unix))]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(format!("{} \"{}\"", hook, manifest.display()));
+        command
+    };
+    command
+        .env("SAUNDS_INPUT", input)
+        .env("SAUNDS_MANIFEST", manifest)
+        .env("SAUNDS_STATUS", if error.is_some() { "failure" } else { "success" });
+    if let Some(error) = error {
+        command.env("SAUNDS_ERROR", format!("{:#}", error));
+    }
+
+    let status = command.status().with_context(|| format!("Failed to run hook '{}'", hook))?;
+    if !status.success() {
+        bail!("Hook '{}' exited with {}", hook, status);
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Ctrl-C handling for splits. The first press stops at the next STFT
+//! window, chunk or file: outputs already finished are kept, the one being
+//! written is removed, and streamed bands are finalized at the length
+//! reached. A second press exits at once.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use tracing::{error, warn};
+
+use saunds_v2::audio::Interrupted;
+
+/// Exit status of a run stopped by Ctrl-C, as shells report SIGINT.
+pub const EXIT_CODE: i32 = 130;
+
+static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// The flag Ctrl-C sets, installing the handler on first use.
+pub fn flag() -> Arc<AtomicBool> {
+    FLAG.get_or_init(|| {
+        let flag = Arc::new(AtomicBool::new(false));
+        let handler_flag = flag.clone();
+        let installed = ctrlc::set_handler(move || {
+            if handler_flag.swap(true, Ordering::Relaxed) {
+                error!("Interrupted again; exiting at once");
+                std::process::exit(EXIT_CODE);
+            }
+            warn!("Interrupted; stopping cleanly (press Ctrl-C again to exit at once)");
+        });
+        if let Err(e) = installed {
+            warn!("Failed to install the Ctrl-C handler: {}", e);
+        }
+        flag
+    })
+    .clone()
+}
+
+/// Fails with [`Interrupted`] once Ctrl-C was pressed.
+pub fn check() -> Result<()> {
+    if flag().load(Ordering::Relaxed) {
+        return Err(Interrupted.into());
+    }
+    Ok(())
+}
+
+/// Whether `e` stems from Ctrl-C.
+pub fn is_interrupted(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<Interrupted>().is_some()
+}
@@ -0,0 +1,117 @@
+//! Edit journal for commands that rewrite files in place. Before an edit
+//! the original is copied into a `.saunds-journal` directory beside the
+//! file and the edit is recorded in its `journal.json`, so `saunds undo`
+//! can put the previous version back, one edit at a time.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::{lock, manifest};
+
+/// Journal directory created next to edited files.
+pub const DIR: &str = ".saunds-journal";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    /// Oldest first
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Edit {
+    /// File name of the edited file, within the journal's directory
+    pub file: String,
+    pub command: String,
+    /// File name of the original's copy, within the journal directory
+    pub backup: String,
+    /// Hash of the file as the edit left it, to catch later changes
+    pub sha256: String,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+}
+
+/// The journal directory for `file`, and `file`'s name.
+fn locate(file: &Path) -> Result<(PathBuf, String)> {
+    let name = file.file_name().with_context(|| format!("{} has no file name", file.display()))?;
+    let dir = file.parent().unwrap_or(Path::new("")).join(DIR);
+    Ok((dir, name.to_string_lossy().into_owned()))
+}
+
+impl Journal {
+    /// The journal in `dir`, empty if there is none yet.
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join("journal.json");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Failed to parse journal {}", path.display()))
+    }
+
+    fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join("journal.json");
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Edits of the file called `name`, oldest first.
+    pub fn edits_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Edit> + 'a {
+        self.edits.iter().filter(move |edit| edit.file == name)
+    }
+}
+
+/// Backs up `file`, runs `edit` to rewrite it and journals the change. If
+/// the edit fails the original is put back and nothing is journaled.
+pub fn edit_in_place(file: &Path, command: &str, edit: impl FnOnce() -> Result<()>) -> Result<()> {
+    let (dir, name) = locate(file)?;
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let _lock = lock::lock_dir(&dir)?;
+    let mut journal = Journal::read(&dir)?;
+
+    let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs();
+    let mut index = journal.edits.len();
+    let backup = loop {
+        let backup = format!("{}-{}", index, name);
+        if !dir.join(&backup).exists() {
+            break backup;
+        }
+        index += 1;
+    };
+    std::fs::copy(file, dir.join(&backup)).with_context(|| format!("Failed to back up {}", file.display()))?;
+
+    if let Err(e) = edit() {
+        std::fs::rename(dir.join(&backup), file)
+            .with_context(|| format!("{:#}; restoring {} from its backup also failed", e, file.display()))?;
+        return Err(e);
+    }
+    journal.edits.push(Edit { file: name, command: command.to_string(), backup, sha256: manifest::sha256_file(file)?, time });
+    journal.write(&dir)
+}
+
+/// Restores `file` to its state before its latest journaled edit. Refuses
+/// if the file changed since that edit, unless `force` is set.
+pub fn undo(file: &Path, force: bool) -> Result<Edit> {
+    let (dir, name) = locate(file)?;
+    let _lock = if dir.exists() { Some(lock::lock_dir(&dir)?) } else { None };
+    let mut journal = Journal::read(&dir)?;
+    let Some(index) = journal.edits.iter().rposition(|edit| edit.file == name) else {
+        bail!("No journaled edits of {}", file.display());
+    };
+    let edit = journal.edits[index].clone();
+
+    if file.exists() && manifest::sha256_file(file)? != edit.sha256 {
+        if !force {
+            bail!("{} changed since its last '{}' edit; use --force to restore anyway", file.display(), edit.command);
+        }
+        warn!("{} changed since its last '{}' edit; restoring anyway", file.display(), edit.command);
+    }
+    std::fs::rename(dir.join(&edit.backup), file).with_context(|| format!("Failed to restore {}", file.display()))?;
+    journal.edits.remove(index);
+    journal.write(&dir)?;
+    info!("Undid '{}' on {}", edit.command, file.display());
+    Ok(edit)
+}
@@ -0,0 +1,3 @@
+pub mod audio;
+#[cfg(feature = "grpc")]
+pub mod grpc;
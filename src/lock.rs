@@ -0,0 +1,37 @@
+//! Advisory locks that keep concurrent saunds processes, such as parallel
+//! batch jobs from a scheduler, from writing into the same directory at
+//! once. A run waits for the lock instead of failing, then writes its
+//! outputs as a whole.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::Path;
+use tracing::info;
+
+/// Lock file created in locked directories.
+pub const FILE: &str = ".saunds.lock";
+
+/// An exclusive lock, released when dropped.
+pub struct Lock {
+    _file: File,
+}
+
+/// Locks `dir` for writing, waiting while another process holds it.
+pub fn lock_dir(dir: &Path) -> Result<Lock> {
+    let path = dir.join(FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            info!("Waiting for another saunds run writing to {}", dir.display());
+            file.lock().with_context(|| format!("Failed to lock {}", path.display()))?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e).with_context(|| format!("Failed to lock {}", path.display())),
+    }
+    Ok(Lock { _file: file })
+}
@@ -1,14 +1,130 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
 use std::path::PathBuf;
-use tracing::{info, error, Level};
+use std::sync::mpsc;
+use tracing::{info, error, warn};
+use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
-mod audio;
+use saunds_v2::audio;
+
+mod batch;
+mod commands;
+mod hooks;
+mod interrupt;
+mod journal;
+mod lock;
+mod manifest;
+mod project;
+mod storage;
+mod streaming;
+#[cfg(feature = "plots")]
+mod plot;
+mod stft_export;
+#[cfg(feature = "playback")]
+mod tune;
+mod warnings;
+
+use manifest::{BandEntry, Manifest};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// Input audio file path
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    split: Option<SplitArgs>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Split a directory of tracks as one continuous stream and cut the
+    /// bands back at the track boundaries
+    Album(commands::album::AlbumArgs),
+    /// Estimate the delay and polarity between two recordings, or between
+    /// the channels of a stereo one, and write aligned copies
+    Align(commands::align::AlignArgs),
+    /// Report per-band levels of a recording
+    Analyze(commands::analyze::AnalyzeArgs),
+    /// Find chapters in a podcast or audiobook at long silences and
+    /// changes between speech and music, and tag them into an MP3
+    Chapters(commands::chapters::ChaptersArgs),
+    /// Print a shell completion script
+    Completions(commands::completions::CompletionsArgs),
+    /// Assemble a program from the regions listed in an edit decision list
+    Conform(commands::conform::ConformArgs),
+    /// Turn down breaths between phrases of a voiceover
+    Debreath(commands::debreath::DebreathArgs),
+    /// Flatten slow pitch drift (wow) by resampling at a varying rate
+    Dewow(commands::dewow::DewowArgs),
+    /// Apply a corrective FIR matching the long-term spectrum of a
+    /// reference recording
+    Match(commands::match_eq::MatchArgs),
+    /// Apply a smooth EQ matching the spectral tilt of a reference
+    /// recording or of pink noise
+    MatchTilt(commands::match_tilt::MatchTiltArgs),
+    /// Measure loudness and peaks into a stats sidecar, the first pass of
+    /// two-pass normalization
+    Measure(commands::measure::MeasureArgs),
+    /// Normalize and limit files using a stats sidecar from `measure`
+    Normalize(commands::normalize::NormalizeArgs),
+    /// Write an STFT or constant-Q spectrogram or chroma features as .npy
+    Spectrogram(commands::spectrogram::SpectrogramArgs),
+    /// Export each region of a label track or CUE sheet as its own file
+    Regions(commands::regions::RegionsArgs),
+    /// Load a file once and run analysis and processing commands on it
+    Repl(commands::repl::ReplArgs),
+    /// Write a self-contained HTML report with plots and loudness statistics
+    Report(commands::report::ReportArgs),
+    /// Propose band cutoffs at valleys in the long-term spectrum
+    SuggestCutoffs(commands::suggest_cutoffs::SuggestCutoffsArgs),
+    /// Render the two-band split over a grid of parameter values and
+    /// summarize each result
+    Sweep(commands::sweep::SweepArgs),
+    /// Measure the clock drift of a recording against another of the same
+    /// event and resample it into sync
+    Sync(commands::sync::SyncArgs),
+    /// Decode LTC timecode from a recording, report its start and drift,
+    /// and trim the recording to a timecode range
+    Timecode(commands::timecode::TimecodeArgs),
+    /// Fill silent gaps with room tone synthesized from a quiet stretch
+    FillGaps(commands::fill_gaps::FillGapsArgs),
+    /// Apply a single low-pass, high-pass, band-pass or band-stop filter
+    Filter(commands::filter::FilterArgs),
+    /// Attenuate a file wherever a key file has energy at the same frequencies
+    Duck(commands::duck::DuckArgs),
+    /// Run a chain of effect stages over a file
+    Fx(commands::fx::FxArgs),
+    /// Serve streaming band separation over gRPC
+    #[cfg(feature = "grpc")]
+    Grpc(commands::grpc::GrpcArgs),
+    /// Re-render or inspect a saved split project
+    Project(project::ProjectArgs),
+    /// Inverse STFT of frames exported with --export-stft
+    Resynth(commands::resynth::ResynthArgs),
+    /// Print every command's parameters as JSON, for generating forms
+    Schema(commands::schema::SchemaArgs),
+    /// Rewrite each STFT frame's magnitudes with a Rhai script
+    #[cfg(feature = "scripting")]
+    Script(commands::script::ScriptArgs),
+    /// Browse a scrolling spectrogram and band level meters in the terminal
+    #[cfg(feature = "tui")]
+    Tui(commands::tui::TuiArgs),
+    /// Restore a file edited in place to its state before the last edit
+    Undo(commands::undo::UndoArgs),
+    /// Recompute the checksums recorded in a manifest and compare them
+    Verify(commands::verify::VerifyArgs),
+    /// Impose a modulator's band envelopes onto a carrier
+    Vocode(commands::vocode::VocodeArgs),
+}
+
+/// Band split, run when no subcommand is given
+#[derive(Args, Debug)]
+struct SplitArgs {
+    /// Input audio file path, or a directory whose files are each split
+    /// into a subdirectory of the output
     #[arg(short, long)]
     input: PathBuf,
 
@@ -16,6 +132,34 @@ struct Args {
     #[arg(short, long)]
     output: PathBuf,
 
+    /// Shell command run after a successful split, with the manifest path
+    /// appended as an argument; a failing hook fails the run
+    #[arg(long, value_name = "COMMAND")]
+    on_success: Option<String>,
+
+    /// Shell command run when the split fails, with the manifest path
+    /// appended and the error in SAUNDS_ERROR
+    #[arg(long, value_name = "COMMAND")]
+    on_failure: Option<String>,
+
+    /// In batch mode, only split files whose name matches one of these
+    /// patterns (`*` and `?` wildcards; repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// In batch mode, skip files whose name matches this pattern
+    /// (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// In batch mode, skip files shorter than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    min_duration: Option<f64>,
+
+    /// In batch mode, skip files longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    max_duration: Option<f64>,
+
     /// Low frequency cutoff (Hz)
     #[arg(long, default_value = "200")]
     low_cutoff: f32,
@@ -23,20 +167,356 @@ struct Args {
     /// High frequency cutoff (Hz)
     #[arg(long, default_value = "2000")]
     high_cutoff: f32,
+
+    /// Split into this many complementary bands instead of the two cutoff bands
+    #[arg(long, conflicts_with_all = ["low_cutoff", "high_cutoff"])]
+    bands: Option<usize>,
+
+    /// Scale the multiband edges are evenly spaced on
+    #[arg(long, value_enum, default_value_t = audio::scale::BandScale::Log, requires = "bands")]
+    band_scale: audio::scale::BandScale,
+
+    /// How to handle corrupt or truncated MP3 data
+    // Inline rather than a flattened `commands::DecodeArgs`: clap leaves the
+    // group of an `Args` with flattened fields empty, and `Cli` finds the
+    // split's arguments by that group
+    #[arg(long, value_enum, default_value_t = audio::DecodeErrorPolicy::Skip)]
+    on_decode_error: audio::DecodeErrorPolicy,
+
+    /// Filter used for the band split
+    #[arg(long, value_enum, default_value_t = audio::FilterMode::Fft)]
+    filter: audio::FilterMode,
+
+    /// STFT analysis and synthesis window
+    #[arg(long, value_enum, default_value_t = audio::Window::SqrtHann)]
+    window: audio::Window,
+
+    /// Channels split in parallel by the STFT
+    #[arg(long, default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: usize,
+
+    /// Advance each band by its filter latency, rounded to whole samples,
+    /// so it stays time-aligned with the input
+    #[arg(long)]
+    compensate_latency: bool,
+
+    /// Floating-point width of the FFT, filters and overlap-add
+    #[arg(long, value_enum, default_value_t = audio::Precision::Single)]
+    precision: audio::Precision,
+
+    /// Leave STFT windows whose peak stays below this level (dBFS) silent
+    /// instead of transforming them; by default only digitally silent
+    /// windows are skipped
+    #[arg(long, allow_hyphen_values = true)]
+    silence_threshold: Option<f32>,
+
+    /// Filter family for the IIR filter modes
+    #[arg(long, value_enum, default_value_t = audio::FilterFamily::Butterworth)]
+    design: audio::FilterFamily,
+
+    /// Filter order for the IIR filter modes; each filter runs twice
+    #[arg(long, default_value_t = 2)]
+    order: usize,
+
+    /// Passband ripple for Chebyshev I and elliptic designs (dB)
+    #[arg(long, default_value_t = 1.0)]
+    ripple: f64,
+
+    /// Stopband attenuation for Chebyshev II and elliptic designs (dB)
+    #[arg(long, default_value_t = 60.0)]
+    attenuation: f64,
+
+    /// Effect stage applied to the input before splitting, as
+    /// `name:key=value,...` (repeatable, applied in order)
+    #[arg(long = "fx", value_name = "STAGE")]
+    effects: Vec<audio::effects::StageSpec>,
+
+    /// Effect stage applied to one output band, as `BAND:STAGE` where BAND
+    /// is `low`, `high` or a 1-based band number (repeatable)
+    #[arg(long = "band-fx", value_name = "BAND:STAGE")]
+    band_effects: Vec<BandEffect>,
+
+    /// Write one band at a lower sample rate, as `BAND=RATE` where BAND is
+    /// `low`, `high` or a 1-based band number (repeatable). The band is
+    /// low-pass filtered at the new Nyquist before decimation
+    #[arg(long = "band-rate", value_name = "BAND=RATE")]
+    band_rates: Vec<BandRate>,
+
+    /// Keep each effect stage's level change instead of matching its input RMS
+    #[arg(long)]
+    no_autogain: bool,
+
+    /// Reference stem to score the matching output band against, in band
+    /// order (repeatable; give one per band)
+    #[arg(long = "reference", value_name = "FILE")]
+    references: Vec<PathBuf>,
+
+    /// Also write the complex STFT of the input and of each band as .npy
+    /// files into this directory
+    #[arg(long, value_name = "DIR")]
+    export_stft: Option<PathBuf>,
+
+    /// Frequency weighting applied to the input before splitting
+    #[arg(long, value_enum, default_value_t = audio::weighting::Weighting::Z)]
+    weighting: audio::weighting::Weighting,
+
+    /// Sample format of the written bands
+    #[arg(long, value_enum, default_value_t = audio::BitDepth::Float32)]
+    bit_depth: audio::BitDepth,
+
+    /// Dither applied when writing 16- or 24-bit bands
+    #[arg(long, value_enum, default_value_t = audio::Dither::Tpdf)]
+    dither: audio::Dither,
+
+    /// Seed for every randomized stage. Renders with the same inputs,
+    /// parameters and seed are bit-identical, except on the GPU
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Route input channels to new channels before splitting, as
+    /// IN:OUT[:GAIN_DB] pairs, e.g. 0:0,0:1 for mono to stereo or
+    /// 0:0:-6,1:0:-6 for a mono fold-down
+    #[arg(long, conflicts_with = "map_file")]
+    map: Option<audio::channels::ChannelMatrix>,
+
+    /// Channel matrix file with one row of input gains per output channel
+    #[arg(long, value_name = "FILE")]
+    map_file: Option<PathBuf>,
+
+    /// Delay a channel by a fractional time before splitting, as
+    /// CHANNEL=TIME with TIME in s, ms, us or samples, e.g. R=+0.3ms to
+    /// correct tape azimuth or L=-2samples for a misaligned mic. Repeatable
+    #[arg(long = "delay", value_name = "CHANNEL=TIME")]
+    delays: Vec<audio::channels::ChannelDelay>,
+
+    /// Treat a four-channel input as first-order B-format in this channel
+    /// convention. All components go through identical filters, keeping
+    /// the sound field intact, and channel files are named by component
+    #[arg(long, value_enum)]
+    ambisonics: Option<audio::channels::Ambisonics>,
+
+    /// Write each channel of each band to its own mono file, named by
+    /// speaker position (e.g. low_freq.LFE.wav)
+    #[arg(long)]
+    channel_files: bool,
+
+    /// Container and codec of the written bands
+    #[arg(long, value_enum, default_value_t = OutputFormat::Wav)]
+    format: OutputFormat,
+
+    /// Opus bitrate in kbit/s
+    #[cfg(feature = "opus")]
+    #[arg(long, default_value_t = 96, value_parser = clap::value_parser!(u32).range(6..=510))]
+    bitrate: u32,
+
+    /// Write Broadcast Wave metadata: description, origination time and
+    /// coding history. SOURCE_DATE_EPOCH overrides the origination time
+    /// for reproducible renders
+    #[arg(long)]
+    bwf: bool,
+
+    /// Write RF64 headers even for files under 4 GiB; larger files always
+    /// use RF64
+    #[arg(long)]
+    rf64: bool,
+
+    /// Loop the input through the speakers and pick the cutoffs from the
+    /// keyboard before rendering
+    #[cfg(feature = "playback")]
+    #[arg(long, conflicts_with = "bands")]
+    interactive: bool,
+
+    /// Render only an excerpt this long, e.g. `10s`, to audition settings
+    /// before a full render
+    #[arg(long, value_name = "DURATION", value_parser = parse_seconds)]
+    preview: Option<f64>,
+
+    /// How the preview excerpt is chosen
+    #[arg(long, value_enum, default_value_t = audio::excerpt::Pick::default(), requires = "preview")]
+    preview_pick: audio::excerpt::Pick,
+
+    /// Start the preview excerpt here instead, e.g. `1:30` or `90s`
+    #[arg(long, value_name = "TIME", value_parser = parse_seconds, requires = "preview", conflicts_with = "preview_pick")]
+    preview_start: Option<f64>,
+
+    /// Marker position in seconds, written to markers.txt in the output as
+    /// a label track (repeatable)
+    #[arg(long = "marker", value_name = "SECONDS")]
+    markers: Vec<f64>,
+
+    /// Memory budget, e.g. `512M` or `2G`. A WAV input whose split would
+    /// need more in memory is streamed through the STFT instead, in
+    /// chunks sized to fit
+    #[arg(long, value_name = "SIZE", value_parser = parse_bytes)]
+    max_memory: Option<u64>,
+
+    /// Turn bands down so their true peak stays this far below full
+    /// scale, e.g. `1dB`. Bands already below it are left as they are
+    #[arg(long, value_name = "DB", value_parser = parse_db)]
+    headroom: Option<f32>,
+
+    /// Whether --headroom scales each band on its own or all bands alike
+    #[arg(long, value_enum, default_value_t = audio::peaks::Scaling::PerBand, requires = "headroom")]
+    headroom_scaling: audio::peaks::Scaling,
+
+    /// Print the time and throughput of each stage after the split. Stages
+    /// run on parallel threads add up their time
+    #[arg(long)]
+    timings: bool,
+
+    /// Save these options, with any tuned cutoffs and markers, to a
+    /// project file for `saunds project render`
+    #[arg(long, value_name = "FILE")]
+    save_project: Option<PathBuf>,
+
+    /// Run the STFT on the GPU
+    #[cfg(feature = "gpu")]
+    #[arg(long, conflicts_with_all = ["filter", "precision"])]
+    gpu: bool,
+
+    /// Number of windows per GPU dispatch
+    #[cfg(feature = "gpu")]
+    #[arg(long, default_value_t = audio::gpu::DEFAULT_BATCH_SIZE)]
+    gpu_batch: usize,
+
+    /// Command line these options were parsed from, for saving projects
+    #[arg(skip)]
+    argv: Vec<OsString>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Wav,
+    /// AIFF for integer bit depths, AIFF-C for float
+    Aiff,
+    /// Core Audio Format
+    Caf,
+    /// Lossless f64 intermediate for feeding another saunds run; bit
+    /// depth and BWF options don't apply
+    Saunds,
+    /// Ogg Opus; 16-bit and BWF options don't apply
+    #[cfg(feature = "opus")]
+    Opus,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Aiff => "aiff",
+            OutputFormat::Caf => "caf",
+            OutputFormat::Saunds => audio::intermediate::EXTENSION,
+            #[cfg(feature = "opus")]
+            OutputFormat::Opus => "opus",
+        }
+    }
 }
 
 fn main() -> Result<()> {
-    // Initialize basic logging
-    tracing_subscriber::fmt()
-        .with_max_level(Level::DEBUG)
+    // Initialize basic logging on stderr, keeping stdout for reports, and
+    // collect warnings for manifests and the closing summary
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr).with_filter(LevelFilter::DEBUG))
+        .with(warnings::Collector)
         .init();
 
-    let cli = Args::parse();
+    let cli = Cli::parse();
 
+    let result = match cli.command {
+        Some(Command::Album(args)) => commands::album::run(args),
+        Some(Command::Align(args)) => commands::align::run(args),
+        Some(Command::Analyze(args)) => commands::analyze::run(args),
+        Some(Command::Chapters(args)) => commands::chapters::run(args),
+        Some(Command::Completions(args)) => commands::completions::run(args),
+        Some(Command::Conform(args)) => commands::conform::run(args),
+        Some(Command::Match(args)) => commands::match_eq::run(args),
+        Some(Command::MatchTilt(args)) => commands::match_tilt::run(args),
+        Some(Command::Debreath(args)) => commands::debreath::run(args),
+        Some(Command::Dewow(args)) => commands::dewow::run(args),
+        Some(Command::Measure(args)) => commands::measure::run(args),
+        Some(Command::Normalize(args)) => commands::normalize::run(args),
+        Some(Command::Spectrogram(args)) => commands::spectrogram::run(args),
+        Some(Command::Regions(args)) => commands::regions::run(args),
+        Some(Command::Repl(args)) => commands::repl::run(args),
+        Some(Command::Report(args)) => commands::report::run(args),
+        Some(Command::SuggestCutoffs(args)) => commands::suggest_cutoffs::run(args),
+        Some(Command::Sweep(args)) => commands::sweep::run(args),
+        Some(Command::Sync(args)) => commands::sync::run(args),
+        Some(Command::Timecode(args)) => commands::timecode::run(args),
+        Some(Command::FillGaps(args)) => commands::fill_gaps::run(args),
+        Some(Command::Filter(args)) => commands::filter::run(args),
+        Some(Command::Duck(args)) => commands::duck::run(args),
+        Some(Command::Fx(args)) => commands::fx::run(args),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc(args)) => commands::grpc::run(args),
+        Some(Command::Resynth(args)) => commands::resynth::run(args),
+        Some(Command::Schema(args)) => commands::schema::run(args),
+        #[cfg(feature = "scripting")]
+        Some(Command::Script(args)) => commands::script::run(args),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(args)) => commands::tui::run(args),
+        Some(Command::Undo(args)) => commands::undo::run(args),
+        Some(Command::Verify(args)) => commands::verify::run(args),
+        Some(Command::Vocode(args)) => commands::vocode::run(args),
+        Some(Command::Project(args)) => project::run(args),
+        None => {
+            let mut split_args = cli.split.expect("clap requires the split arguments without a subcommand");
+            split_args.argv = std::env::args_os().collect();
+            split(split_args)
+        }
+    };
+    warnings::summarize();
+    if let Err(e) = &result {
+        if interrupt::is_interrupted(e) {
+            error!("{:#}", e);
+            std::process::exit(interrupt::EXIT_CODE);
+        }
+    }
+    result
+}
+
+fn split(cli: SplitArgs) -> Result<()> {
+    if storage::is_remote(&cli.input) || storage::is_remote(&cli.output) {
+        return storage::split(cli);
+    }
+    if cli.input.is_dir() {
+        return batch::run(&cli);
+    }
+    split_input(cli, None)
+}
+
+/// Splits the single file `cli.input` and runs the hooks. A batch run
+/// passes the input it decoded ahead as `prefetched`.
+fn split_input(cli: SplitArgs, prefetched: Option<Result<batch::Prefetched>>) -> Result<()> {
+    let input = cli.input.clone();
+    let manifest = cli.output.join("manifest.json");
+    let (on_success, on_failure) = (cli.on_success.clone(), cli.on_failure.clone());
+    match split_file(cli, prefetched) {
+        Ok(()) => match on_success {
+            Some(hook) => hooks::run(&hook, &input, &manifest, None),
+            None => Ok(()),
+        },
+        Err(e) => {
+            if let Some(hook) = on_failure {
+                if let Err(hook_error) = hooks::run(&hook, &input, &manifest, Some(&e)) {
+                    warn!("{:#}", hook_error);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+fn split_file(cli: SplitArgs, prefetched: Option<Result<batch::Prefetched>>) -> Result<()> {
+    let started = std::time::Instant::now();
+    let warnings_mark = warnings::mark();
     info!("Starting audio processing...");
     info!("Input file: {}", cli.input.display());
     info!("Output directory: {}", cli.output.display());
-    info!("Frequency cutoffs: {} Hz - {} Hz", cli.low_cutoff, cli.high_cutoff);
+    match cli.bands {
+        Some(count) => info!("Bands: {} ({:?} scale)", count, cli.band_scale),
+        None => info!("Frequency cutoffs: {} Hz - {} Hz", cli.low_cutoff, cli.high_cutoff),
+    }
 
     // Verify input file exists
     if !cli.input.exists() {
@@ -49,39 +529,656 @@ fn main() -> Result<()> {
         info!("Creating output directory: {}", cli.output.display());
         std::fs::create_dir_all(&cli.output)?;
     }
+    let _lock = lock::lock_dir(&cli.output)?;
+
+    let design = audio::FilterDesign {
+        family: cli.design,
+        order: cli.order,
+        passband_ripple_db: cli.ripple,
+        stopband_attenuation_db: cli.attenuation,
+    };
+    if cli.filter == audio::FilterMode::Fft && design != audio::FilterDesign::default() {
+        bail!("--design, --order, --ripple and --attenuation require --filter iir or zero-phase");
+    }
+    design.validate()?;
 
     // Initialize audio processor
-    let processor = audio::AudioProcessor::new()?;
+    let mut processor = audio::AudioProcessor::builder()
+        .filter_mode(cli.filter)
+        .filter_design(design)
+        .precision(cli.precision)
+        .window(cli.window)
+        .threads(cli.threads)
+        .build()?
+        .with_decode_error_policy(cli.on_decode_error)
+        .with_bit_depth(cli.bit_depth)
+        .with_dither(cli.dither, cli.seed)
+        .with_rf64(cli.rf64)
+        .with_interrupt(interrupt::flag());
+    if let Some(dbfs) = cli.silence_threshold {
+        processor = processor.with_silence_threshold(dbfs);
+    }
+    if cli.timings {
+        processor = processor.with_timings(Default::default());
+    }
+    if cli.bwf {
+        processor = processor.with_bext(broadcast_metadata(&cli.input)?);
+    }
+    #[cfg(feature = "opus")]
+    {
+        processor = processor.with_opus_bitrate(cli.bitrate);
+    }
+
+    if let Some(budget) = cli.max_memory {
+        if let Some(chunk_frames) = streaming::plan(&cli, &processor, budget)? {
+            if let Some(path) = &cli.save_project {
+                project::save(path, &cli)?;
+            }
+            let entries = streaming::split(&cli, &mut processor, chunk_frames)?;
+            write_manifest(&cli, &processor, entries, None, None, warnings_mark)?;
+            print_timings(&processor, started.elapsed());
+            return Ok(());
+        }
+    }
 
     // Load audio file
     info!("Loading audio file...");
-    let samples = processor.load_audio(&cli.input)?;
-    info!("Loaded {} samples", samples.len());
+    let mut buffer = match prefetched {
+        Some(prefetched) => {
+            let prefetched = prefetched?;
+            let buffer = processor.adopt(prefetched.decoded);
+            if let Some(timings) = processor.timings() {
+                timings.record(audio::timings::Stage::Decode, prefetched.decode_time, buffer.frames() * buffer.num_channels());
+            }
+            buffer
+        }
+        None => processor.load_buffer(&cli.input)?,
+    };
+    info!("Loaded {} samples", buffer.frames() * buffer.num_channels());
+    let preview = match cli.preview {
+        Some(seconds) => {
+            let rate = processor.sample_rate() as f64;
+            let length = (seconds * rate).round() as usize;
+            let frames = match cli.preview_start {
+                Some(start) => {
+                    let start = ((start * rate).round() as usize).min(buffer.frames());
+                    start..(start + length).min(buffer.frames())
+                }
+                None => cli.preview_pick.pick(&buffer, length)?,
+            };
+            if frames.is_empty() {
+                bail!("The preview starts after the end of the input");
+            }
+            info!("Previewing {:.1} s from {:.1} s", frames.len() as f64 / rate, frames.start as f64 / rate);
+            buffer = buffer.like(buffer.channels().map(|channel| channel[frames.clone()].to_vec()).collect())?;
+            Some(manifest::Preview { start_seconds: frames.start as f64 / rate, duration_seconds: frames.len() as f64 / rate })
+        }
+        None => None,
+    };
+    let input_peaks = audio::peaks::measure(&buffer);
+    info!(
+        "Input peak {:.1} dBFS, true peak {:.1} dBTP, {} clipped samples",
+        input_peaks.sample_peak_dbfs, input_peaks.true_peak_dbtp, input_peaks.clipped_samples
+    );
+    if input_peaks.clipped_samples > 0 {
+        warn!("The input has {} clipped samples at full scale", input_peaks.clipped_samples);
+    }
+    let matrix = match &cli.map_file {
+        Some(path) => Some(audio::channels::ChannelMatrix::parse_rows(
+            &std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+        )?),
+        None => cli.map.clone(),
+    };
+    if let Some(matrix) = matrix {
+        buffer = matrix.apply(&buffer)?;
+        info!("Mapped {} input channels to {}", processor.channels(), matrix.outputs());
+        processor = processor.with_channels(matrix.outputs() as u32);
+    }
+    for delay in &cli.delays {
+        let channels = processor.channels() as usize;
+        let channel = delay.index(channels)?;
+        let frames = delay.frames(processor.sample_rate());
+        audio::resample::delay(buffer.channel_mut(channel), 1, 0, frames);
+        info!(
+            "Delayed {} by {:.3} ms ({:.2} samples)",
+            audio::channels::speaker_names(channels)[channel],
+            1000.0 * frames / processor.sample_rate() as f64,
+            frames
+        );
+    }
+    if let Some(ambisonics) = cli.ambisonics {
+        if processor.channels() != 4 {
+            bail!("--ambisonics expects a four-channel first-order B-format input, got {} channels", processor.channels());
+        }
+        if !cli.band_effects.is_empty() {
+            bail!("--band-fx runs per channel and would distort an ambisonic sound field");
+        }
+        processor = processor.with_ambisonics(ambisonics);
+        buffer = buffer.with_layout(audio::Layout::Ambisonic(ambisonics));
+    }
+    processor.apply_weighting(cli.weighting, &mut buffer);
+    audio::effects::apply_chain(&cli.effects, &mut buffer, !cli.no_autogain)?;
+
+    #[cfg(feature = "playback")]
+    let mut cli = cli;
+    #[cfg(feature = "playback")]
+    if cli.interactive {
+        let channels = processor.channels() as usize;
+        let start = tune::Tuned { low: cli.low_cutoff, high: cli.high_cutoff, markers: cli.markers.clone() };
+        match tune::tune(&buffer.to_interleaved(), channels, processor.sample_rate(), &design, start)? {
+            Some(tuned) => {
+                info!("Rendering with tuned cutoffs: {:.1} Hz - {:.1} Hz", tuned.low, tuned.high);
+                cli.low_cutoff = tuned.low;
+                cli.high_cutoff = tuned.high;
+                cli.markers = tuned.markers;
+            }
+            None => {
+                info!("Tuning cancelled; nothing rendered");
+                return Ok(());
+            }
+        }
+    }
+
+    if let Some(path) = &cli.save_project {
+        project::save(path, &cli)?;
+    }
+
+    let mut bands = match cli.bands {
+        Some(count) => split_multiband(&processor, &buffer, count, &cli),
+        None => split_two_bands(&processor, &buffer, &cli),
+    }
+    .map_err(|e| {
+        if interrupt::is_interrupted(&e) {
+            e.context("Stopped during the band split; no bands were written")
+        } else {
+            e
+        }
+    })?;
+
+    let channels = processor.channels() as usize;
+    for band in &mut bands {
+        info!(
+            "{} latency: {:.2} samples ({:.3} ms)",
+            band.file, band.latency, band.latency * 1000.0 / processor.sample_rate() as f64
+        );
+        if cli.compensate_latency && band.latency >= 0.5 {
+            // Advance by whole frames, padding the end to keep the length
+            let shift = (band.latency.round() as usize).min(band.buffer.frames());
+            for channel in band.buffer.channels_mut() {
+                channel.copy_within(shift.., 0);
+                let len = channel.len();
+                channel[len - shift..].fill(0.0);
+            }
+        }
+    }
+
+    for effect in &cli.band_effects {
+        let index = effect.band.index(bands.len())?;
+        let band = &mut bands[index];
+        info!("Applying {} stage to {}", effect.stage.name, band.file);
+        audio::effects::apply_chain(std::slice::from_ref(&effect.stage), &mut band.buffer, !cli.no_autogain)?;
+    }
+
+    let metrics = if cli.references.is_empty() {
+        None
+    } else {
+        Some(score_bands(&processor, &bands, &cli.references)?)
+    };
+
+    if let Some(dir) = &cli.export_stft {
+        // The STFT export reads interleaved frames, as the .npy arrays lay
+        // them out
+        let interleaved: Vec<Vec<f32>> = bands.iter().map(|band| band.buffer.to_interleaved()).collect();
+        let stems: Vec<(&str, &[f32])> = bands
+            .iter()
+            .zip(&interleaved)
+            .map(|(band, samples)| (band.file.trim_end_matches(".wav"), samples.as_slice()))
+            .collect();
+        stft_export::export(
+            dir,
+            processor.sample_rate(),
+            processor.channels() as usize,
+            processor.window_size(),
+            &buffer.to_interleaved(),
+            &stems,
+        )?;
+    }
+
+    // Save separated audio files, one per band or one per band and channel
+    let speakers = match cli.ambisonics {
+        Some(ambisonics) => ambisonics.component_names().map(str::to_string).to_vec(),
+        None => audio::channels::speaker_names(channels),
+    };
+    let mut band_rates = vec![processor.sample_rate(); bands.len()];
+    for rate in &cli.band_rates {
+        let index = rate.band.index(bands.len())?;
+        if rate.sample_rate > processor.sample_rate() {
+            bail!("--band-rate {} Hz is above the input rate of {} Hz", rate.sample_rate, processor.sample_rate());
+        }
+        band_rates[index] = rate.sample_rate;
+    }
+    for (band, &band_rate) in bands.iter_mut().zip(&band_rates) {
+        if band_rate == processor.sample_rate() {
+            continue;
+        }
+        if band.high_hz > band_rate as f32 / 2.0 {
+            warn!(
+                "{} extends to {:.0} Hz; content above {:.0} Hz is filtered out at {} Hz",
+                band.file, band.high_hz, band_rate as f32 / 2.0, band_rate
+            );
+        }
+        info!("Resampling {} to {} Hz", band.file, band_rate);
+        let resampled = band
+            .buffer
+            .channels()
+            .map(|channel| audio::resample::resample(channel, 1, processor.sample_rate(), band_rate))
+            .collect();
+        band.buffer = audio::AudioBuffer::from_planar(resampled, band_rate)?.with_layout(band.buffer.layout());
+    }
+
+    let gains = match cli.headroom {
+        Some(headroom) => {
+            let buffers: Vec<&audio::AudioBuffer> = bands.iter().map(|band| &band.buffer).collect();
+            let gains = audio::peaks::headroom_gains(&buffers, headroom, cli.headroom_scaling);
+            for (band, &gain) in bands.iter_mut().zip(&gains) {
+                if gain < 1.0 {
+                    info!("Scaling {} by {:.1} dB for {:.1} dB of headroom", band.file, 20.0 * gain.log10(), headroom);
+                    band.buffer.channels_mut().flatten().for_each(|sample| *sample *= gain);
+                }
+            }
+            gains
+        }
+        None => vec![1.0; bands.len()],
+    };
+
+    // Bands are encoded and hashed on a writer thread, so each band's peaks
+    // are measured while the one before it is written
+    let entries = std::thread::scope(|scope| {
+        let (sender, files) = mpsc::sync_channel(streaming::PIPELINE_DEPTH);
+        let output = &cli.output;
+        let writer = scope.spawn(move || write_band_files(output, files));
+        for (i, band) in bands.into_iter().enumerate() {
+            let stem = band.file.trim_end_matches(".wav");
+            let band_rate = band_rates[i];
+            let peaks = audio::peaks::measure(&band.buffer);
+            info!("{} peak {:.1} dBFS, {:.1} dB headroom", band.file, peaks.sample_peak_dbfs, peaks.headroom_db());
+            if peaks.true_peak_dbtp > 0.0 {
+                warn!("{} has inter-sample peaks at {:+.1} dBTP, above full scale", band.file, peaks.true_peak_dbtp);
+            }
+            let writer = processor.clone().with_sample_rate(band_rate);
+            let entry = |file: String, channel: Option<&str>| BandEntry {
+                sha256: String::new(),
+                file,
+                channel: channel.map(str::to_string),
+                sample_rate: (band_rate != processor.sample_rate()).then_some(band_rate),
+                low_hz: band.low_hz,
+                high_hz: band.high_hz,
+                latency_samples: if cli.compensate_latency { band.latency - band.latency.round() } else { band.latency },
+                metrics: metrics.as_ref().map(|metrics| metrics[i]),
+                peaks: Some(peaks),
+                gain_db: (gains[i] < 1.0).then(|| 20.0 * gains[i].log10()),
+            };
+
+            // A closed channel means the writer failed; its error is
+            // returned below
+            let sent = if cli.channel_files && channels > 1 {
+                let mono_writer = writer.with_channels(1);
+                speakers.iter().zip(band.buffer.into_planar()).all(|(speaker, channel)| {
+                    sender
+                        .send(BandFile {
+                            writer: mono_writer.clone(),
+                            buffer: audio::AudioBuffer::from_planar(vec![channel], band_rate).expect("one channel"),
+                            entry: entry(format!("{}.{}.{}", stem, speaker, cli.format.extension()), Some(speaker)),
+                        })
+                        .is_ok()
+                })
+            } else {
+                let entry = entry(format!("{}.{}", stem, cli.format.extension()), None);
+                sender.send(BandFile { writer, buffer: band.buffer, entry }).is_ok()
+            };
+            if !sent {
+                break;
+            }
+        }
+        drop(sender);
+        writer.join().expect("band writer thread panicked")
+    })?;
+
+    write_manifest(&cli, &processor, entries, preview, Some(input_peaks), warnings_mark)?;
+    print_timings(&processor, started.elapsed());
+    Ok(())
+}
+
+/// A band file for the writer thread: the processor to write it with, its
+/// audio and its manifest entry, hashed once written.
+struct BandFile {
+    writer: audio::AudioProcessor,
+    buffer: audio::AudioBuffer,
+    entry: BandEntry,
+}
+
+/// Saves and hashes each of `files` into `output` as it arrives, returning
+/// their manifest entries in order.
+fn write_band_files(output: &std::path::Path, files: mpsc::Receiver<BandFile>) -> Result<Vec<BandEntry>> {
+    let mut entries = Vec::new();
+    for BandFile { writer, buffer, mut entry } in files {
+        interrupt::check()
+            .with_context(|| format!("Stopped after writing {} band files; no manifest was written", entries.len()))?;
+        let path = output.join(&entry.file);
+        info!("Saving {:.0} Hz - {:.0} Hz band to: {}", entry.low_hz, entry.high_hz, path.display());
+        writer.save_buffer(&path, &buffer)?;
+        entry.sha256 = manifest::sha256_file(&path)?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Prints the stage breakdown of a split that took `total`, if the
+/// processor recorded one.
+fn print_timings(processor: &audio::AudioProcessor, total: std::time::Duration) {
+    let Some(timings) = processor.timings() else {
+        return;
+    };
+    println!("{:<12} {:>9} {:>7} {:>14}", "stage", "seconds", "share", "samples/s");
+    for timing in timings.stages() {
+        let seconds = timing.time.as_secs_f64();
+        println!(
+            "{:<12} {:>9.3} {:>6.1}% {:>14.0}",
+            timing.stage.name(),
+            seconds,
+            100.0 * seconds / total.as_secs_f64(),
+            timing.samples_per_second()
+        );
+    }
+    println!("{:<12} {:>9.3}", "total", total.as_secs_f64());
+}
+
+/// Writes the manifest of a finished split, with the warnings raised
+/// since `warnings_mark`, and its markers.
+fn write_manifest(
+    cli: &SplitArgs,
+    processor: &audio::AudioProcessor,
+    entries: Vec<BandEntry>,
+    preview: Option<manifest::Preview>,
+    input_peaks: Option<audio::peaks::Peaks>,
+    warnings_mark: usize,
+) -> Result<()> {
+    let manifest = Manifest {
+        input: cli.input.clone(),
+        sample_rate: processor.sample_rate(),
+        channels: processor.channels(),
+        band_scale: cli.bands.map(|_| cli.band_scale),
+        ambisonics: cli.ambisonics,
+        preview,
+        input_peaks,
+        bands: entries,
+        warnings: warnings::since(warnings_mark),
+    };
+    manifest.write(&cli.output.join("manifest.json"))?;
+    if !cli.markers.is_empty() {
+        let labels: String = cli
+            .markers
+            .iter()
+            .enumerate()
+            .map(|(index, at)| format!("{:.6}\t{:.6}\tMarker {}\n", at, at, index + 1))
+            .collect();
+        std::fs::write(cli.output.join("markers.txt"), labels).context("Failed to write markers.txt")?;
+    }
+
+    info!("Audio processing completed successfully!");
+    Ok(())
+}
+
+/// Bytes as `1048576`, `64K`, `512M` or `2G`, in powers of 1024; a
+/// trailing `B` or `iB` is allowed.
+fn parse_bytes(text: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size '{}'; expected e.g. 512M or 2G", text);
+    let lower = text.trim().to_ascii_lowercase();
+    let number = lower.trim_end_matches('b').trim_end_matches('i');
+    let (number, shift) = match number.as_bytes().last() {
+        Some(b'k') => (&number[..number.len() - 1], 10),
+        Some(b'm') => (&number[..number.len() - 1], 20),
+        Some(b'g') => (&number[..number.len() - 1], 30),
+        Some(b't') => (&number[..number.len() - 1], 40),
+        _ => (number, 0),
+    };
+    let value: f64 = number.trim().parse().map_err(|_| invalid())?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err(invalid());
+    }
+    Ok((value * (1u64 << shift) as f64) as u64)
+}
+
+/// Decibels as `1` or `1dB`, not negative.
+fn parse_db(text: &str) -> Result<f32, String> {
+    let invalid = || format!("invalid level '{}'; expected e.g. 1 or 1dB", text);
+    let text = text.trim();
+    let number = text.strip_suffix("dB").or_else(|| text.strip_suffix("db")).unwrap_or(text);
+    let value: f32 = number.trim().parse().map_err(|_| invalid())?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid());
+    }
+    Ok(value)
+}
+
+/// Seconds as `90`, `90s`, `1:30` or `1:02:30.5`.
+fn parse_seconds(text: &str) -> Result<f64, String> {
+    let invalid = || format!("invalid time '{}'; expected e.g. 90, 90s or 1:30", text);
+    let mut seconds = 0.0;
+    for part in text.trim().trim_end_matches('s').split(':') {
+        let value: f64 = part.parse().map_err(|_| invalid())?;
+        if !value.is_finite() || value < 0.0 {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Ok(seconds)
+}
+
+/// Broadcast Wave metadata for the bands of `input`, originated now or at
+/// SOURCE_DATE_EPOCH if set.
+fn broadcast_metadata(input: &std::path::Path) -> Result<audio::wav::Bext> {
+    let origination = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.trim().parse().with_context(|| format!("Invalid SOURCE_DATE_EPOCH '{}'", epoch))?,
+        Err(_) => std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+    };
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    Ok(audio::wav::Bext {
+        description: format!("Frequency band split of {}", name),
+        originator: "saunds".to_string(),
+        origination,
+        ..Default::default()
+    })
+}
+
+/// Scores each band against its reference stem and prints a report.
+fn score_bands(
+    processor: &audio::AudioProcessor,
+    bands: &[Band],
+    references: &[PathBuf],
+) -> Result<Vec<audio::metrics::SeparationMetrics>> {
+    if references.len() != bands.len() {
+        bail!("Got {} reference stems for {} bands; give one per band", references.len(), bands.len());
+    }
+
+    let stems = references
+        .iter()
+        .map(|path| {
+            let mut stem_processor = audio::AudioProcessor::new()?;
+            let samples = stem_processor.load_buffer(path)?.to_interleaved();
+            if (stem_processor.sample_rate(), stem_processor.channels()) != (processor.sample_rate(), processor.channels()) {
+                bail!(
+                    "Reference {} is {} Hz with {} channels; the input is {} Hz with {}",
+                    path.display(), stem_processor.sample_rate(), stem_processor.channels(),
+                    processor.sample_rate(), processor.channels()
+                );
+            }
+            Ok(samples)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    info!("Scoring bands against reference stems");
+    // Scored on interleaved samples, so every channel lines up with the
+    // reference's whatever the two lengths
+    let estimates: Vec<Vec<f32>> = bands.iter().map(|band| band.buffer.to_interleaved()).collect();
+    let estimates: Vec<&[f32]> = estimates.iter().map(Vec::as_slice).collect();
+    let stems: Vec<&[f32]> = stems.iter().map(Vec::as_slice).collect();
+    let metrics = audio::metrics::evaluate(&estimates, &stems)?;
+
+    println!("{:<16}  {:>8}  {:>8}  {:>8}  {:>8}", "band", "SDR dB", "SI-SDR", "SIR dB", "SAR dB");
+    for (band, m) in bands.iter().zip(&metrics) {
+        println!(
+            "{:<16}  {:>8.1}  {:>8.1}  {:>8.1}  {:>8.1}",
+            band.file, m.sdr_db, m.si_sdr_db, m.sir_db, m.sar_db
+        );
+    }
+    Ok(metrics)
+}
+
+/// Which output band a `--band-fx` stage applies to.
+#[derive(Debug, Clone)]
+enum BandSelector {
+    Low,
+    High,
+    Number(usize),
+}
+
+impl BandSelector {
+    fn index(&self, bands: usize) -> Result<usize> {
+        match *self {
+            BandSelector::Low => Ok(0),
+            BandSelector::High => Ok(bands - 1),
+            BandSelector::Number(n) if (1..=bands).contains(&n) => Ok(n - 1),
+            BandSelector::Number(n) => bail!("Band {} does not exist; there are {} bands", n, bands),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BandEffect {
+    band: BandSelector,
+    stage: audio::effects::StageSpec,
+}
+
+impl std::str::FromStr for BandEffect {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some((band, stage)) = value.split_once(':') else {
+            bail!("Expected BAND:STAGE, got '{}'", value);
+        };
+        Ok(Self { band: band.parse()?, stage: stage.parse()? })
+    }
+}
+
+impl std::str::FromStr for BandSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Ok(match value {
+            "low" => BandSelector::Low,
+            "high" => BandSelector::High,
+            number => BandSelector::Number(
+                number.parse().map_err(|_| anyhow::anyhow!("Unknown band '{}'; use low, high or a band number", number))?,
+            ),
+        })
+    }
+}
+
+/// Sample rate one band is written at, from `--band-rate BAND=RATE`.
+#[derive(Debug, Clone)]
+struct BandRate {
+    band: BandSelector,
+    sample_rate: u32,
+}
+
+impl std::str::FromStr for BandRate {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some((band, rate)) = value.split_once('=') else {
+            bail!("Expected BAND=RATE, got '{}'", value);
+        };
+        let sample_rate = rate.parse().map_err(|_| anyhow::anyhow!("Invalid sample rate '{}'", rate))?;
+        if sample_rate == 0 {
+            bail!("Sample rate must be positive");
+        }
+        Ok(Self { band: band.parse()?, sample_rate })
+    }
+}
+
+/// One rendered band, the frequency range it covers and the delay its
+/// filters introduced, in samples.
+struct Band {
+    file: String,
+    low_hz: f32,
+    high_hz: f32,
+    latency: f64,
+    buffer: audio::AudioBuffer,
+}
+
+fn split_two_bands(processor: &audio::AudioProcessor, input: &audio::AudioBuffer, cli: &SplitArgs) -> Result<Vec<Band>> {
+    processor.validate_cutoffs(cli.low_cutoff, cli.high_cutoff)?;
 
     // Separate frequencies
     info!("Separating frequencies...");
-    let (low_freq, high_freq) = match processor.separate_frequencies(
-        &samples,
-        cli.low_cutoff,
-        cli.high_cutoff,
-    ) {
+    #[cfg(feature = "gpu")]
+    let separated = if cli.gpu {
+        audio::gpu::GpuStft::new(processor.window_size(), cli.gpu_batch).and_then(|stft| {
+            processor.separate_buffer_gpu(&stft, input, cli.low_cutoff, cli.high_cutoff)
+        })
+    } else {
+        processor.separate_buffer(input, cli.low_cutoff, cli.high_cutoff)
+    };
+    #[cfg(not(feature = "gpu"))]
+    let separated = processor.separate_buffer(input, cli.low_cutoff, cli.high_cutoff);
+
+    let (low_freq, high_freq) = match separated {
         Ok(result) => result,
         Err(e) => {
-            error!("Failed to separate frequencies: {}", e);
+            if !interrupt::is_interrupted(&e) {
+                error!("Failed to separate frequencies: {}", e);
+            }
             return Err(e);
         }
     };
 
-    // Save separated audio files
-    let low_freq_path = cli.output.join("low_freq.wav");
-    let high_freq_path = cli.output.join("high_freq.wav");
+    let nyquist = processor.sample_rate() as f32 / 2.0;
+    let (low_latency, high_latency) = processor.separation_latency(cli.low_cutoff, cli.high_cutoff)?;
+    Ok(vec![
+        Band { file: "low_freq.wav".into(), low_hz: 0.0, high_hz: cli.high_cutoff, latency: low_latency, buffer: low_freq },
+        Band { file: "high_freq.wav".into(), low_hz: cli.low_cutoff, high_hz: nyquist, latency: high_latency, buffer: high_freq },
+    ])
+}
 
-    info!("Saving low frequency audio to: {}", low_freq_path.display());
-    processor.save_audio(&low_freq_path, &low_freq)?;
+fn split_multiband(processor: &audio::AudioProcessor, input: &audio::AudioBuffer, count: usize, cli: &SplitArgs) -> Result<Vec<Band>> {
+    let cutoffs = cli.band_scale.cutoffs(count, processor.sample_rate())?;
+    info!("{:?}-spaced band edges: {:?} Hz", cli.band_scale, cutoffs);
 
-    info!("Saving high frequency audio to: {}", high_freq_path.display());
-    processor.save_audio(&high_freq_path, &high_freq)?;
+    let nyquist = processor.sample_rate() as f32 / 2.0;
+    let edges: Vec<f32> = std::iter::once(0.0).chain(cutoffs.iter().copied()).chain([nyquist]).collect();
+    #[cfg(feature = "gpu")]
+    let rendered = if cli.gpu {
+        let stft = audio::gpu::GpuStft::new(processor.window_size(), cli.gpu_batch)?;
+        processor.split_buffer_gpu(&stft, input, &cutoffs)?
+    } else {
+        processor.split_buffer(input, &cutoffs)?
+    };
+    #[cfg(not(feature = "gpu"))]
+    let rendered = processor.split_buffer(input, &cutoffs)?;
+    let latencies = processor.split_latencies(&cutoffs)?;
 
-    info!("Audio processing completed successfully!");
-    Ok(())
-}
\ No newline at end of file
+    Ok(rendered
+        .into_iter()
+        .zip(edges.windows(2))
+        .zip(latencies)
+        .enumerate()
+        .map(|(i, ((buffer, edge), latency))| Band {
+            file: format!("band_{:02}.wav", i + 1),
+            low_hz: edge[0],
+            high_hz: edge[1],
+            latency,
+            buffer,
+        })
+        .collect())
+}
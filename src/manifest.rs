@@ -0,0 +1,99 @@
+//! Machine-readable summary written next to the rendered bands.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub input: PathBuf,
+    pub sample_rate: u32,
+    pub channels: u32,
+    /// Scale the band edges were distributed on, if chosen automatically
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub band_scale: Option<saunds_v2::audio::scale::BandScale>,
+    /// Ambisonic channel convention, if the input was B-format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambisonics: Option<saunds_v2::audio::channels::Ambisonics>,
+    /// Part of the input rendered, if only a preview excerpt was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Preview>,
+    /// Levels of the input as loaded; not measured when streaming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_peaks: Option<saunds_v2::audio::peaks::Peaks>,
+    pub bands: Vec<BandEntry>,
+    /// Non-fatal issues raised during the split
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<crate::warnings::Warning>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Preview {
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BandEntry {
+    pub file: String,
+    /// Speaker position, if channels were written to separate files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Rate the band was written at, if `--band-rate` lowered it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    pub low_hz: f32,
+    pub high_hz: f32,
+    /// Group delay left in the band, in samples
+    pub latency_samples: f64,
+    /// Hex SHA-256 of the written file
+    pub sha256: String,
+    /// Quality against the matching `--reference` stem, if given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<saunds_v2::audio::metrics::SeparationMetrics>,
+    /// Levels of the band as written, for its headroom; not measured when
+    /// streaming
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peaks: Option<saunds_v2::audio::peaks::Peaks>,
+    /// Gain `--headroom` applied to the band, if it turned it down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gain_db: Option<f32>,
+}
+
+impl Manifest {
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))
+    }
+}
+
+/// The parts of a manifest needed to check its outputs.
+#[derive(Debug, Deserialize)]
+pub struct Checksums {
+    pub bands: Vec<ChecksumEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChecksumEntry {
+    pub file: String,
+    /// Absent in manifests written before checksums were recorded
+    pub sha256: Option<String>,
+}
+
+impl Checksums {
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid manifest: {}", path.display()))
+    }
+}
+
+/// Hex SHA-256 of the file at `path`.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
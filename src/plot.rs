@@ -0,0 +1,181 @@
+//! SVG and PNG rendering of filter responses, spectra and loudness
+//! histories.
+
+use anyhow::{anyhow, bail, Result};
+use plotters::{coord::Shift, prelude::*};
+use std::path::Path;
+use tracing::info;
+
+use saunds_v2::audio::biquad::Biquad;
+
+type DrawResult<DB> = Result<(), DrawingAreaErrorKind<<DB as DrawingBackend>::ErrorType>>;
+
+const SIZE: (u32, u32) = (1000, 600);
+/// Lowest frequency on the log axes.
+const MIN_FREQUENCY: f32 = 10.0;
+/// Points per plotted response.
+const RESPONSE_POINTS: usize = 512;
+
+/// Something that can draw itself onto any plotters backend.
+pub trait Plot {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult<DB>;
+}
+
+/// Renders `plot` to `path`, as SVG or PNG depending on its extension.
+pub fn save(plot: &impl Plot, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+    let failed = |err: &dyn std::fmt::Display| anyhow!("Failed to render {}: {}", path.display(), err);
+    match extension.as_deref() {
+        Some("svg") => {
+            let root = SVGBackend::new(path, SIZE).into_drawing_area();
+            plot.draw(&root).and_then(|_| root.present()).map_err(|err| failed(&err))?;
+        }
+        Some("png") => {
+            let root = BitMapBackend::new(path, SIZE).into_drawing_area();
+            plot.draw(&root).and_then(|_| root.present()).map_err(|err| failed(&err))?;
+        }
+        _ => bail!("Unsupported plot format for {}; use .svg or .png", path.display()),
+    }
+    info!("Saved plot to: {}", path.display());
+    Ok(())
+}
+
+fn log_frequencies(nyquist: f32, count: usize) -> impl Iterator<Item = f32> {
+    (0..count).map(move |i| MIN_FREQUENCY * (nyquist / MIN_FREQUENCY).powf(i as f32 / (count - 1) as f32))
+}
+
+/// Magnitude and phase response of a biquad cascade.
+pub struct Response<'a> {
+    pub title: String,
+    pub cascade: &'a [Biquad],
+    pub sample_rate: u32,
+    /// Forward-backward filtering: squared magnitude and no phase shift
+    pub zero_phase: bool,
+}
+
+impl Plot for Response<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult<DB> {
+        root.fill(&WHITE)?;
+        let nyquist = self.sample_rate as f32 / 2.0;
+        let passes = if self.zero_phase { 2.0 } else { 1.0 };
+
+        let mut magnitude = Vec::with_capacity(RESPONSE_POINTS);
+        let mut phase = Vec::with_capacity(RESPONSE_POINTS);
+        let mut unwrapped = 0.0f64;
+        let mut previous = 0.0f64;
+        for frequency in log_frequencies(nyquist * 0.999, RESPONSE_POINTS) {
+            let response = self
+                .cascade
+                .iter()
+                .map(|section| section.response(frequency, self.sample_rate))
+                .fold(num_complex::Complex::new(1.0, 0.0), |acc, h| acc * h);
+            magnitude.push((frequency, passes * 20.0 * (response.norm().max(1e-12).log10() as f32)));
+
+            // Unwrap so the phase plot doesn't jump at ±180 degrees
+            let angle = response.arg();
+            let mut delta = angle - previous;
+            delta -= (delta / std::f64::consts::TAU).round() * std::f64::consts::TAU;
+            unwrapped += delta;
+            previous = angle;
+            phase.push((frequency, if self.zero_phase { 0.0 } else { unwrapped.to_degrees() as f32 }));
+        }
+
+        let top = magnitude.iter().map(|&(_, db)| db).fold(0.0f32, f32::max) + 6.0;
+        let (low_phase, high_phase) = phase
+            .iter()
+            .fold((-90.0f32, 90.0f32), |(low, high), &(_, degrees)| (low.min(degrees), high.max(degrees)));
+
+        let (upper, lower) = root.split_vertically(SIZE.1 / 2);
+        let mut chart = ChartBuilder::on(&upper)
+            .caption(&self.title, ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(50)
+            .build_cartesian_2d((MIN_FREQUENCY..nyquist).log_scale(), -120f32..top)?;
+        chart.configure_mesh().y_desc("Magnitude (dB)").draw()?;
+        chart.draw_series(LineSeries::new(magnitude.into_iter().map(|(f, db)| (f, db.max(-120.0))), &BLUE))?;
+
+        let mut chart = ChartBuilder::on(&lower)
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(50)
+            .build_cartesian_2d((MIN_FREQUENCY..nyquist).log_scale(), low_phase..high_phase)?;
+        chart.configure_mesh().x_desc("Frequency (Hz)").y_desc("Phase (degrees)").draw()?;
+        chart.draw_series(LineSeries::new(phase, &RED))?;
+        Ok(())
+    }
+}
+
+/// Level per frequency on a log axis, e.g. a long-term average spectrum.
+pub struct Spectrum<'a> {
+    pub title: String,
+    /// Frequency and level in dB of each point
+    pub points: &'a [(f32, f32)],
+    pub sample_rate: u32,
+}
+
+impl Plot for Spectrum<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult<DB> {
+        root.fill(&WHITE)?;
+        let nyquist = self.sample_rate as f32 / 2.0;
+        let points = self.points.iter().copied().filter(|&(f, _)| (MIN_FREQUENCY..=nyquist).contains(&f));
+        let floor = -120.0f32;
+        let top = self.points.iter().map(|&(_, db)| db).fold(floor, f32::max).max(floor + 20.0) + 6.0;
+
+        let mut chart = ChartBuilder::on(root)
+            .caption(&self.title, ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d((MIN_FREQUENCY..nyquist).log_scale(), floor..top)?;
+        chart.configure_mesh().x_desc("Frequency (Hz)").y_desc("Level (dB)").draw()?;
+        chart.draw_series(LineSeries::new(points.map(|(f, db)| (f, db.max(floor))), &BLUE))?;
+        Ok(())
+    }
+}
+
+/// Momentary and short-term loudness over time with the integrated level.
+pub struct LoudnessHistory<'a> {
+    pub title: String,
+    /// Time in seconds and loudness in LUFS of each momentary block
+    pub momentary: &'a [(f32, f32)],
+    pub short_term: &'a [(f32, f32)],
+    pub integrated_lufs: f32,
+    pub duration: f32,
+}
+
+impl Plot for LoudnessHistory<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> DrawResult<DB> {
+        root.fill(&WHITE)?;
+        let floor = -60.0f32;
+
+        let mut chart = ChartBuilder::on(root)
+            .caption(&self.title, ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(40)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0f32..self.duration.max(0.1), floor..0f32)?;
+        chart.configure_mesh().x_desc("Time (s)").y_desc("Loudness (LUFS)").draw()?;
+
+        let clamp = |&(t, lufs): &(f32, f32)| (t, lufs.clamp(floor, 0.0));
+        chart
+            .draw_series(LineSeries::new(self.momentary.iter().map(clamp), &RGBColor(155, 184, 214)))?
+            .label("Momentary")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], RGBColor(155, 184, 214)));
+        chart
+            .draw_series(LineSeries::new(self.short_term.iter().map(clamp), BLUE.stroke_width(2)))?
+            .label("Short-term")
+            .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], BLUE.stroke_width(2)));
+        if self.integrated_lufs > floor {
+            chart
+                .draw_series(LineSeries::new([(0.0, self.integrated_lufs), (self.duration, self.integrated_lufs)], &RED))?
+                .label(format!("Integrated {:.1} LUFS", self.integrated_lufs))
+                .legend(|(x, y)| PathElement::new([(x, y), (x + 20, y)], RED));
+        }
+        chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+        Ok(())
+    }
+}
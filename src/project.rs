@@ -0,0 +1,165 @@
+//! Project files: a split's options saved by `--save-project`, with any
+//! cutoffs and markers picked while tuning, so the render can be repeated
+//! or retuned later without touching the input. They use the batch sidecar
+//! format, with input and output stored as absolute paths:
+//!
+//! ```toml
+//! input = "/music/song.wav"
+//! output = "/music/song"
+//! fx = ["saturate:drive=4"]
+//! low-cutoff = 180.5
+//! high-cutoff = 2400
+//! marker = [12.5, 61]
+//! ```
+
+use anyhow::{Context, Result};
+use clap::{parser::ValueSource, Args, Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::{batch, storage, Cli, SplitArgs};
+
+#[derive(Args, Debug)]
+pub struct ProjectArgs {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Render a project's split
+    Render {
+        project: PathBuf,
+
+        /// Output directory instead of the project's
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Retune the cutoffs and markers before rendering, saving them
+        /// back to the project
+        #[cfg(feature = "playback")]
+        #[arg(long)]
+        interactive: bool,
+    },
+    /// Print the split command line a project stands for
+    Show { project: PathBuf },
+}
+
+/// Options set on the command line that the saved session overrides, or
+/// that only make sense for the run that saved it.
+const UNSAVED: &[&str] = &["save-project", "interactive", "low-cutoff", "high-cutoff", "marker"];
+
+/// A value as TOML: plain numbers bare, anything else a string.
+fn toml_value(value: &str) -> String {
+    let number = value.strip_prefix('-').unwrap_or(value);
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, "0"));
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+    if digits(whole) && digits(fraction) && !(whole.len() > 1 && whole.starts_with('0')) {
+        value.to_string()
+    } else {
+        serde_json::to_string(value).expect("strings always serialize")
+    }
+}
+
+/// Writes the options `cli` was parsed from, with its current cutoffs and
+/// markers, to the project file at `path`.
+pub fn save(path: &Path, cli: &SplitArgs) -> Result<()> {
+    let command = batch::split_command();
+    let matches = command.clone().try_get_matches_from(&cli.argv).context("Failed to re-read the split options")?;
+
+    let mut text = String::from("# saunds project: split options by long name\n");
+    for arg in command.get_arguments() {
+        let (Some(long), id) = (arg.get_long(), arg.get_id().as_str()) else {
+            continue;
+        };
+        if UNSAVED.contains(&long) || matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        if !arg.get_action().takes_values() {
+            text.push_str(&format!("{} = true\n", long));
+            continue;
+        }
+        let values: Vec<String> = matches
+            .get_raw(id)
+            .into_iter()
+            .flatten()
+            .map(|value| {
+                let value = PathBuf::from(value);
+                // Paths stay valid wherever the project is rendered from
+                if matches!(long, "input" | "output") && !storage::is_remote(&value) {
+                    std::path::absolute(&value).unwrap_or(value)
+                } else {
+                    value
+                }
+            })
+            .map(|value| toml_value(&value.to_string_lossy()))
+            .collect();
+        if matches!(arg.get_action(), clap::ArgAction::Append) {
+            text.push_str(&format!("{} = [{}]\n", long, values.join(", ")));
+        } else if let Some(value) = values.first() {
+            text.push_str(&format!("{} = {}\n", long, value));
+        }
+    }
+    if cli.bands.is_none() {
+        text.push_str(&format!("low-cutoff = {}\nhigh-cutoff = {}\n", cli.low_cutoff, cli.high_cutoff));
+    }
+    if !cli.markers.is_empty() {
+        let markers: Vec<String> = cli.markers.iter().map(f64::to_string).collect();
+        text.push_str(&format!("marker = [{}]\n", markers.join(", ")));
+    }
+
+    std::fs::write(path, text).with_context(|| format!("Failed to write project {}", path.display()))?;
+    info!("Saved project {}", path.display());
+    Ok(())
+}
+
+/// The split command line stored in `project`.
+fn argv(project: &Path) -> Result<Vec<OsString>> {
+    let mut argv = vec![OsString::from("saunds")];
+    argv.extend(batch::config_options(project)?.into_iter().flat_map(|(_, tokens)| tokens));
+    Ok(argv)
+}
+
+/// Quotes `token` for a POSIX shell if it needs it.
+fn shell_quote(token: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_=./:,+@%".contains(c);
+    if !token.is_empty() && token.chars().all(plain) {
+        token.to_string()
+    } else {
+        format!("'{}'", token.replace('\'', r"'\''"))
+    }
+}
+
+pub fn run(args: ProjectArgs) -> Result<()> {
+    match args.action {
+        Action::Render {
+            project,
+            output,
+            #[cfg(feature = "playback")]
+            interactive,
+        } => {
+            let mut argv = argv(&project)?;
+            if let Some(output) = output {
+                argv = batch::without_options(&argv, &["output"].into());
+                argv.extend([OsString::from("--output"), output.into_os_string()]);
+            }
+            #[cfg(feature = "playback")]
+            if interactive {
+                argv.extend([OsString::from("--interactive"), OsString::from("--save-project"), project.clone().into_os_string()]);
+            }
+
+            info!("Rendering project {}", project.display());
+            let cli = Cli::try_parse_from(&argv).with_context(|| format!("Invalid options in {}", project.display()))?;
+            let mut split = cli.split.context("Projects hold band split options")?;
+            split.argv = argv;
+            crate::split(split)
+        }
+        Action::Show { project } => {
+            let argv = argv(&project)?;
+            let tokens: Vec<String> = argv.iter().map(|token| shell_quote(&token.to_string_lossy())).collect();
+            println!("{}", tokens.join(" "));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Dumps the STFT of the split input and of each rendered band as `.npy`.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::info;
+
+use saunds_v2::audio::{npy, spectral};
+
+/// Writes `input.npy`, one `<band>.npy` per band and `stft.json`
+/// describing the framing into `dir`. Arrays are `complex64` of shape
+/// `(channels, frames, bins)`.
+pub fn export(
+    dir: &Path,
+    sample_rate: u32,
+    channels: usize,
+    window_size: usize,
+    input: &[f32],
+    bands: &[(&str, &[f32])],
+) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let layout = spectral::StftLayout::new(sample_rate, channels, window_size, input.len() / channels.max(1));
+    let shape = [layout.channels, layout.frames, layout.bins];
+
+    for (stem, samples) in std::iter::once(("input", input)).chain(bands.iter().copied()) {
+        let path = dir.join(format!("{}.npy", stem));
+        info!("Exporting STFT frames to: {}", path.display());
+        npy::write_complex64(&path, &shape, &spectral::stft(samples, channels, window_size)?)?;
+    }
+
+    let path = dir.join("stft.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&layout)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
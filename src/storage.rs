@@ -0,0 +1,110 @@
+//! Object storage for `--input`/`--output`: `s3://` and `gs://` URIs are
+//! staged through a local directory, using the `aws` and `gcloud` command
+//! line tools so the usual credential chains (instance roles, workload
+//! identity, profiles) apply unchanged. A URI ending in `/` is a prefix and
+//! is copied as a whole, which with a prefix input runs batch mode. Hooks
+//! run against the staged copy, before the upload.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+use crate::SplitArgs;
+
+/// An object or prefix in a bucket.
+struct Remote {
+    uri: String,
+    gcs: bool,
+}
+
+impl Remote {
+    fn parse(path: &Path) -> Option<Remote> {
+        let uri = path.to_str()?;
+        let gcs = uri.starts_with("gs://");
+        (gcs || uri.starts_with("s3://")).then(|| Remote { uri: uri.to_string(), gcs })
+    }
+
+    fn is_prefix(&self) -> bool {
+        self.uri.ends_with('/')
+    }
+
+    /// The object's last path component.
+    fn name(&self) -> &str {
+        self.uri.trim_end_matches('/').rsplit('/').next().unwrap_or_default()
+    }
+
+    /// Runs the storage tool's copy (`sync` for whole directories).
+    fn copy(&self, from: &str, to: &str, recursive: bool) -> Result<()> {
+        let mut command = if self.gcs {
+            let mut command = Command::new("gcloud");
+            command.args(["storage", if recursive { "rsync" } else { "cp" }]);
+            if recursive {
+                command.arg("--recursive");
+            }
+            command
+        } else {
+            let mut command = Command::new("aws");
+            command.args(["s3", if recursive { "sync" } else { "cp" }, "--only-show-errors"]);
+            command
+        };
+        command.arg(from).arg(to);
+        let tool = if self.gcs { "gcloud" } else { "aws" };
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to run '{}'; it's needed for {} URIs", tool, &self.uri[..5]))?;
+        if !status.success() {
+            bail!("Copying {} to {} failed: '{}' exited with {}", from, to, tool, status);
+        }
+        Ok(())
+    }
+}
+
+/// Whether `path` names an object storage URI.
+pub fn is_remote(path: &Path) -> bool {
+    Remote::parse(path).is_some()
+}
+
+/// Splits with remote input and/or output: downloads the input into a
+/// staging directory, splits there, and uploads the output directory.
+/// Output is uploaded even when some files of a batch failed.
+pub fn split(cli: SplitArgs) -> Result<()> {
+    let staging = std::env::temp_dir().join(format!("saunds-{}", std::process::id()));
+    std::fs::create_dir_all(&staging).with_context(|| format!("Failed to create {}", staging.display()))?;
+    let result = split_staged(cli, &staging);
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        warn!("Failed to remove staging directory {}: {}", staging.display(), e);
+    }
+    result
+}
+
+fn split_staged(mut cli: SplitArgs, staging: &Path) -> Result<()> {
+    if let Some(input) = Remote::parse(&cli.input) {
+        let local = staging.join("input").join(input.name());
+        info!("Downloading {}", input.uri);
+        let target = local.to_string_lossy();
+        if input.is_prefix() {
+            input.copy(&input.uri, &target, true)?;
+        } else {
+            std::fs::create_dir_all(staging.join("input"))?;
+            input.copy(&input.uri, &target, false)?;
+        }
+        cli.input = local;
+    }
+
+    let output = Remote::parse(&cli.output);
+    if output.is_some() {
+        cli.output = staging.join("output");
+    }
+    let local_output: PathBuf = cli.output.clone();
+    let result = crate::split(cli);
+
+    if let Some(output) = output {
+        if local_output.exists() {
+            let target = format!("{}/", output.uri.trim_end_matches('/'));
+            info!("Uploading results to {}", target);
+            output.copy(&local_output.to_string_lossy(), &target, true)?;
+        }
+    }
+    result
+}
@@ -0,0 +1,262 @@
+//! `--max-memory`: before loading a WAV input, estimates what splitting it
+//! in memory would hold, and if that's over the budget streams the file
+//! through per-band STFTs instead, in chunks sized to fit. Decoding, the
+//! STFTs and encoding run as pipelined stages on their own threads, handing
+//! chunks on through bounded channels so reading and writing overlap the
+//! DSP. The in-memory split, whose effects, headroom and reference metrics
+//! need whole bands, still hands its finished bands to a writer thread the
+//! same way, and batch runs decode the next input during the current split.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::mpsc;
+use std::time::Instant;
+use tracing::{info, warn};
+
+use saunds_v2::audio::{self, timings::Stage, AudioProcessor, StftProcessor, WavStream};
+
+use crate::manifest::{self, BandEntry};
+use crate::SplitArgs;
+
+const MIB: f64 = 1024.0 * 1024.0;
+/// Chunks, or in the in-memory split band files, queued between pipeline
+/// stages.
+pub const PIPELINE_DEPTH: usize = 1;
+
+/// Options set in `cli` that only the in-memory split supports.
+fn unsupported(cli: &SplitArgs) -> Vec<&'static str> {
+    let mut options = Vec::new();
+    let mut check = |set: bool, option| {
+        if set {
+            options.push(option);
+        }
+    };
+    check(cli.filter != audio::FilterMode::Fft, "--filter");
+    check(cli.precision != audio::Precision::Single, "--precision");
+    check(cli.silence_threshold.is_some(), "--silence-threshold");
+    check(!cli.effects.is_empty(), "--fx");
+    check(!cli.band_effects.is_empty(), "--band-fx");
+    check(!cli.band_rates.is_empty(), "--band-rate");
+    check(!cli.references.is_empty(), "--reference");
+    check(cli.export_stft.is_some(), "--export-stft");
+    check(cli.weighting != audio::weighting::Weighting::Z, "--weighting");
+    check(cli.map.is_some() || cli.map_file.is_some(), "--map");
+    check(!cli.delays.is_empty(), "--delay");
+    check(cli.ambisonics.is_some(), "--ambisonics");
+    check(cli.channel_files, "--channel-files");
+    check(cli.format != crate::OutputFormat::Wav, "--format");
+    check(cli.preview.is_some(), "--preview");
+    check(cli.headroom.is_some(), "--headroom");
+    #[cfg(feature = "playback")]
+    check(cli.interactive, "--interactive");
+    #[cfg(feature = "gpu")]
+    check(cli.gpu, "--gpu");
+    options
+}
+
+/// Rough peak of an in-memory split: the input and every band as f32, one
+/// band's quantized copy while saving, and the STFT buffers of the
+/// channels split at once.
+fn in_memory_bytes(cli: &SplitArgs, samples: u64, channels: u64, bands: u64) -> u64 {
+    let width = match cli.precision {
+        audio::Precision::Single => 4,
+        audio::Precision::Double => 8,
+    };
+    let frames = samples / channels;
+    samples * 4 * (bands + 2) + frames * width * (bands + 1) * (cli.threads as u64).min(channels)
+}
+
+/// Frames per chunk to stream the input in within `budget` bytes, or None
+/// if splitting it in memory fits.
+pub fn plan(cli: &SplitArgs, processor: &AudioProcessor, budget: u64) -> Result<Option<usize>> {
+    if !cli.input.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")) {
+        warn!("--max-memory only applies to WAV input; loading {} whole", cli.input.display());
+        return Ok(None);
+    }
+    let file = File::open(&cli.input).with_context(|| format!("Failed to open {}", cli.input.display()))?;
+    let reader = audio::wav::Reader::new(BufReader::new(file))?;
+    let channels = reader.spec().channels.max(1) as u64;
+    let frames = reader.remaining_samples() / channels;
+    let bands = cli.bands.unwrap_or(2) as u64;
+
+    let needed = in_memory_bytes(cli, reader.remaining_samples(), channels, bands);
+    if needed <= budget {
+        info!("Splitting in memory needs about {:.0} MiB, within --max-memory", needed as f64 / MIB);
+        return Ok(None);
+    }
+    let unsupported = unsupported(cli);
+    if !unsupported.is_empty() {
+        bail!(
+            "Splitting {} in memory needs about {:.0} MiB, over --max-memory, and streaming doesn't support {}",
+            cli.input.display(),
+            needed as f64 / MIB,
+            unsupported.join(", ")
+        );
+    }
+
+    // Per frame: the chunks being read, queued and split, and each band's
+    // pulled and interleaved samples, those queued, and those being
+    // quantized and written. Each band and channel's STFT keeps a few
+    // windows.
+    let in_flight = PIPELINE_DEPTH as u64 + 2;
+    let frame_bytes = channels * 4 * (in_flight + bands * (2 + in_flight));
+    let window = processor.window_size() as u64;
+    let fixed = bands * channels * window * 4 * 8;
+    let minimum = fixed + window * frame_bytes;
+    if budget < minimum {
+        bail!(
+            "--max-memory of {:.1} MiB is too small to stream {}; it needs at least {:.1} MiB",
+            budget as f64 / MIB,
+            cli.input.display(),
+            minimum as f64 / MIB
+        );
+    }
+    let chunk_frames = ((budget - fixed) / frame_bytes).min(frames.max(window)) as usize;
+    info!(
+        "Splitting in memory needs about {:.0} MiB, over --max-memory; streaming in chunks of {} frames",
+        needed as f64 / MIB,
+        chunk_frames
+    );
+    Ok(Some(chunk_frames))
+}
+
+/// What `take` returns for each band's channels.
+fn pulled(streams: &mut [Vec<StftProcessor>], take: fn(&mut StftProcessor) -> Vec<f32>) -> Vec<Vec<Vec<f32>>> {
+    streams.iter_mut().map(|band| band.iter_mut().map(take).collect()).collect()
+}
+
+/// Finalizes the bands at the `done` frames written so far, for Ctrl-C.
+fn stop(writers: Vec<WavStream>, done: u64, frames: u64) -> anyhow::Error {
+    for writer in writers {
+        if let Err(e) = writer.finish() {
+            warn!("{:#}", e);
+        }
+    }
+    anyhow::Error::new(audio::Interrupted).context(format!(
+        "Stopped after streaming {} of {} frames ({:.0}%); the bands were finalized at about that length and no manifest was written",
+        done,
+        frames,
+        100.0 * done as f64 / frames.max(1) as f64
+    ))
+}
+
+/// Splits the WAV input `chunk_frames` at a time, writing every band as it
+/// goes. The bands match the in-memory STFT split's.
+pub fn split(cli: &SplitArgs, processor: &mut AudioProcessor, chunk_frames: usize) -> Result<Vec<BandEntry>> {
+    let mut reader = processor.open_wav(&cli.input)?;
+    let channels = processor.channels() as usize;
+    let len = reader.remaining_samples();
+    let nyquist = processor.sample_rate() as f32 / 2.0;
+
+    let bands: Vec<(String, f32, f32)> = match cli.bands {
+        Some(count) => {
+            let cutoffs = cli.band_scale.cutoffs(count, processor.sample_rate())?;
+            info!("{:?}-spaced band edges: {:?} Hz", cli.band_scale, cutoffs);
+            let edges: Vec<f32> = std::iter::once(0.0).chain(cutoffs).chain([nyquist]).collect();
+            edges
+                .windows(2)
+                .enumerate()
+                .map(|(i, edge)| (format!("band_{:02}.wav", i + 1), edge[0], edge[1]))
+                .collect()
+        }
+        None => {
+            processor.validate_cutoffs(cli.low_cutoff, cli.high_cutoff)?;
+            vec![
+                ("low_freq.wav".to_string(), 0.0, cli.high_cutoff),
+                ("high_freq.wav".to_string(), cli.low_cutoff, nyquist),
+            ]
+        }
+    };
+    let mut streams = bands
+        .iter()
+        .map(|&(_, low, high)| (0..channels).map(|_| processor.band_stream(low, high)).collect())
+        .collect::<Result<Vec<Vec<_>>>>()?;
+    let writers = bands
+        .iter()
+        .map(|(file, ..)| processor.create_wav(cli.output.join(file), len))
+        .collect::<Result<Vec<_>>>()?;
+    let timings = processor.timings();
+
+    let mut done = 0;
+    let (writers, result) = std::thread::scope(|scope| {
+        let (chunk_sender, chunks) = mpsc::sync_channel(PIPELINE_DEPTH);
+        scope.spawn(move || loop {
+            let start = Instant::now();
+            let chunk = reader.read_frames(chunk_frames);
+            if let (Some(timings), Ok(chunk)) = (timings, &chunk) {
+                timings.record(Stage::Decode, start.elapsed(), chunk[0].len() * channels);
+            }
+            let last = chunk.as_ref().map_or(true, |chunk| chunk[0].is_empty());
+            if chunk_sender.send(chunk).is_err() || last {
+                break;
+            }
+        });
+        let (band_sender, pulled_bands) = mpsc::sync_channel::<Vec<Vec<Vec<f32>>>>(PIPELINE_DEPTH);
+        let encoder = scope.spawn(move || {
+            let mut writers = writers;
+            for bands in pulled_bands {
+                for (band, writer) in bands.iter().zip(&mut writers) {
+                    let channels: Vec<&[f32]> = band.iter().map(Vec::as_slice).collect();
+                    if let Err(e) = writer.write(&channels) {
+                        return (writers, Err(e));
+                    }
+                }
+            }
+            (writers, Ok(()))
+        });
+
+        // Whether the input was split to the end rather than interrupted
+        let split = (|| -> Result<bool> {
+            for chunk in chunks {
+                let chunk = chunk?;
+                if chunk[0].is_empty() {
+                    break;
+                }
+                if crate::interrupt::check().is_err() {
+                    return Ok(false);
+                }
+                for (channel, signal) in chunk.iter().enumerate() {
+                    for band in &mut streams {
+                        band[channel].push(signal);
+                    }
+                }
+                // A closed channel means the encoder failed; its error is
+                // returned below
+                if band_sender.send(pulled(&mut streams, StftProcessor::pull)).is_err() {
+                    return Ok(true);
+                }
+                done += chunk[0].len() as u64;
+                info!("Streamed {}/{} frames", done, len / channels as u64);
+            }
+            let _ = band_sender.send(pulled(&mut streams, StftProcessor::flush));
+            Ok(true)
+        })();
+        drop(band_sender);
+        let (writers, written) = encoder.join().expect("encoder thread panicked");
+        (writers, written.and(split))
+    });
+    if !result? {
+        return Err(stop(writers, done, len / channels as u64));
+    }
+
+    let mut entries = Vec::new();
+    for ((file, low_hz, high_hz), writer) in bands.into_iter().zip(writers) {
+        writer.finish()?;
+        let path = cli.output.join(&file);
+        info!("Saved {:.0} Hz - {:.0} Hz band to: {}", low_hz, high_hz, path.display());
+        entries.push(BandEntry {
+            sha256: manifest::sha256_file(&path)?,
+            file,
+            channel: None,
+            sample_rate: None,
+            low_hz,
+            high_hz,
+            latency_samples: 0.0,
+            metrics: None,
+            peaks: None,
+            gain_db: None,
+        });
+    }
+    Ok(entries)
+}
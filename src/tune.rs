@@ -0,0 +1,241 @@
+//! Interactive cutoff tuning for the two-band split: loops the input
+//! through the output device with one band soloed while the cutoffs are
+//! nudged and markers dropped from the keyboard.
+
+use anyhow::{Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    DefaultTerminal,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use saunds_v2::audio::{
+    biquad::{Biquad, BiquadState},
+    playback::Player,
+    FilterDesign,
+};
+
+/// Cutoff change per arrow key press, in octaves.
+const STEP_OCTAVES: f32 = 1.0 / 12.0;
+/// Redraw interval.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Band heard while tuning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Solo {
+    /// Everything below the high cutoff
+    Low,
+    /// Everything above the low cutoff
+    High,
+    /// The unfiltered input
+    Off,
+}
+
+/// Looping source feeding the output device. The preview filters run
+/// twice like the IIR split, whatever filter the final render uses.
+struct Preview {
+    samples: Vec<f32>,
+    channels: usize,
+    position: usize,
+    cascade: Vec<Biquad>,
+    states: Vec<Vec<BiquadState>>,
+}
+
+impl Preview {
+    fn set_cascade(&mut self, cascade: Vec<Biquad>) {
+        self.states = vec![vec![BiquadState::default(); cascade.len()]; self.channels];
+        self.cascade = cascade;
+    }
+
+    fn render(&mut self, output: &mut [f32]) {
+        if self.samples.is_empty() {
+            output.fill(0.0);
+            return;
+        }
+        for frame in output.chunks_mut(self.channels) {
+            if self.position >= self.samples.len() {
+                self.position = 0;
+            }
+            for (channel, out) in frame.iter_mut().enumerate() {
+                let x = self.samples[self.position + channel] as f64;
+                *out = self
+                    .cascade
+                    .iter()
+                    .zip(self.states[channel].iter_mut())
+                    .fold(x, |x, (section, state)| state.process(section, x)) as f32;
+            }
+            self.position += self.channels;
+        }
+    }
+}
+
+/// Session state the tuner edits.
+#[derive(Debug, Clone)]
+pub struct Tuned {
+    pub low: f32,
+    pub high: f32,
+    /// Marker positions in seconds, in the order they were dropped
+    pub markers: Vec<f64>,
+}
+
+struct Tuner {
+    tuned: Tuned,
+    /// Whether the arrow keys move the high cutoff
+    editing_high: bool,
+    solo: Solo,
+    nyquist: f32,
+}
+
+impl Tuner {
+    fn nudge(&mut self, octaves: f32) {
+        let ratio = octaves.exp2();
+        // Keep the cutoffs at least a step apart and inside the audible range
+        let gap = STEP_OCTAVES.exp2();
+        if self.editing_high {
+            self.tuned.high = (self.tuned.high * ratio).clamp(self.tuned.low * gap, self.nyquist * 0.95);
+        } else {
+            self.tuned.low = (self.tuned.low * ratio).clamp(10.0, self.tuned.high / gap);
+        }
+    }
+
+    fn cascade(&self, design: &FilterDesign, sample_rate: u32) -> Result<Vec<Biquad>> {
+        let filter = match self.solo {
+            Solo::Low => design.lowpass(self.tuned.high, sample_rate)?,
+            Solo::High => design.highpass(self.tuned.low, sample_rate)?,
+            Solo::Off => Vec::new(),
+        };
+        Ok(filter.repeat(2))
+    }
+}
+
+/// Loops `samples` with the chosen band soloed, starting from `start`,
+/// until the user accepts the session with Enter, returning it, or quits,
+/// returning `None`.
+pub fn tune(
+    samples: &[f32],
+    channels: usize,
+    sample_rate: u32,
+    design: &FilterDesign,
+    start: Tuned,
+) -> Result<Option<Tuned>> {
+    let mut tuner = Tuner {
+        tuned: start,
+        editing_high: false,
+        solo: Solo::Low,
+        nyquist: sample_rate as f32 / 2.0,
+    };
+
+    let preview = Arc::new(Mutex::new(Preview {
+        samples: samples.to_vec(),
+        channels,
+        position: 0,
+        cascade: Vec::new(),
+        states: Vec::new(),
+    }));
+    lock(&preview).set_cascade(tuner.cascade(design, sample_rate)?);
+
+    let source = Arc::clone(&preview);
+    let _player = Player::start(sample_rate, channels, move |output| lock(&source).render(output))?;
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut tuner, &preview, design, sample_rate);
+    ratatui::restore();
+    result
+}
+
+fn lock(preview: &Mutex<Preview>) -> std::sync::MutexGuard<'_, Preview> {
+    // A panic mid-buffer leaves nothing worth protecting
+    preview.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    tuner: &mut Tuner,
+    preview: &Mutex<Preview>,
+    design: &FilterDesign,
+    sample_rate: u32,
+) -> Result<Option<Tuned>> {
+    let duration = {
+        let preview = lock(preview);
+        (preview.samples.len() / preview.channels) as f32 / sample_rate as f32
+    };
+
+    loop {
+        let position = {
+            let preview = lock(preview);
+            (preview.position / preview.channels) as f32 / sample_rate as f32
+        };
+        terminal
+            .draw(|frame| frame.render_widget(panel(tuner, position, duration), frame.area()))
+            .context("Failed to draw the terminal UI")?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(Some(tuner.tuned.clone())),
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Up | KeyCode::Down | KeyCode::Tab => tuner.editing_high = !tuner.editing_high,
+            KeyCode::Left => tuner.nudge(-STEP_OCTAVES),
+            KeyCode::Right => tuner.nudge(STEP_OCTAVES),
+            KeyCode::Char('l') => tuner.solo = Solo::Low,
+            KeyCode::Char('h') => tuner.solo = Solo::High,
+            KeyCode::Char('a') => tuner.solo = Solo::Off,
+            KeyCode::Char('m') => {
+                tuner.tuned.markers.push(position as f64);
+                continue;
+            }
+            KeyCode::Char('x') => {
+                tuner.tuned.markers.pop();
+                continue;
+            }
+            _ => continue,
+        }
+        lock(preview).set_cascade(tuner.cascade(design, sample_rate)?);
+    }
+}
+
+fn panel(tuner: &Tuner, position: f32, duration: f32) -> Paragraph<'static> {
+    let selected = Style::default().add_modifier(Modifier::REVERSED);
+    let cutoff = |label: &str, hz: f32, active: bool| {
+        Line::from(vec![
+            Span::raw(format!("  {:<12}", label)),
+            Span::styled(format!(" {:>7.1} Hz ", hz), if active { selected } else { Style::default() }),
+        ])
+    };
+    let solo = match tuner.solo {
+        Solo::Low => "low band (below the high cutoff)",
+        Solo::High => "high band (above the low cutoff)",
+        Solo::Off => "off (full input)",
+    };
+    let markers = match tuner.tuned.markers.last() {
+        Some(last) => format!("{} (last at {:.1} s)", tuner.tuned.markers.len(), last),
+        None => "none".to_string(),
+    };
+
+    Paragraph::new(vec![
+        Line::from(format!("  Looping {:.1} / {:.1} s", position, duration)),
+        Line::default(),
+        cutoff("Low cutoff", tuner.tuned.low, !tuner.editing_high),
+        cutoff("High cutoff", tuner.tuned.high, tuner.editing_high),
+        Line::from(format!("  {:<12} {}", "Solo", solo)),
+        Line::from(format!("  {:<12} {}", "Markers", markers)),
+        Line::default(),
+        Line::from("  [←/→] nudge a semitone  [↑/↓] pick cutoff  [l/h/a] solo low/high/off"),
+        Line::from("  [m] drop a marker here  [x] remove the last marker"),
+        Line::from("  [enter] render with these cutoffs  [q] quit without rendering"),
+    ])
+    .block(Block::bordered().title(" Cutoff tuning "))
+}
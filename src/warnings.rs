@@ -0,0 +1,69 @@
+//! Non-fatal issues such as clipped outputs, skipped MP3 frames or cutoffs
+//! past Nyquist. Every warning logged anywhere is also collected here, so a
+//! split can record its warnings in the manifest and a run can list them
+//! again at the end instead of leaving them scattered through the log.
+
+use serde::Serialize;
+use std::fmt::Debug;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{warn, Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    /// Module that raised it, e.g. `saunds_v2::audio::mp3`
+    pub source: String,
+    pub message: String,
+}
+
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+/// Tracing layer collecting warning events.
+pub struct Collector;
+
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Collector {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        let metadata = event.metadata();
+        // The end-of-run summary repeats warnings rather than raising new ones
+        if *metadata.level() != Level::WARN || metadata.target() == module_path!() {
+            return;
+        }
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        let warning = Warning { source: metadata.target().to_string(), message: message.0 };
+        WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).push(warning);
+    }
+}
+
+/// Position in the list, to collect the warnings raised after it.
+pub fn mark() -> usize {
+    WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).len()
+}
+
+/// Warnings raised since `mark`.
+pub fn since(mark: usize) -> Vec<Warning> {
+    WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).get(mark..).unwrap_or_default().to_vec()
+}
+
+/// Logs every warning of the run again, as a closing summary.
+pub fn summarize() {
+    let warnings = since(0);
+    if warnings.is_empty() {
+        return;
+    }
+    warn!("Finished with {} warning{}:", warnings.len(), if warnings.len() == 1 { "" } else { "s" });
+    for warning in warnings {
+        warn!("  {}", warning.message);
+    }
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::noise;
+use saunds_v2::audio::align::estimate_delay;
+
+/// `signal` delayed by `delay` frames, or advanced when negative, at the
+/// same length.
+fn shifted(signal: &[f32], delay: isize) -> Vec<f32> {
+    (0..signal.len() as isize)
+        .map(|i| signal.get((i - delay) as usize).copied().filter(|_| i >= delay).unwrap_or(0.0))
+        .collect()
+}
+
+#[test]
+fn blocked_search_within_a_limit_finds_the_delay() {
+    // Long enough that the limit brings the transform well below the
+    // recordings' length
+    let reference = noise(600_000, 0.5, 3);
+    for delay in [777, -1500, 0] {
+        let target = shifted(&reference, delay);
+        let estimate = estimate_delay(&reference, &target, Some(2000)).unwrap();
+        assert_eq!(estimate.frames, delay);
+        assert!(estimate.peak_ratio > 100.0, "peak ratio {}", estimate.peak_ratio);
+        assert!(!estimate.inverted);
+    }
+}
+
+#[test]
+fn blocked_search_matches_the_whole_recordings() {
+    let reference = noise(400_000, 0.5, 5);
+    let target: Vec<f32> = shifted(&reference, 321).iter().map(|x| -x).collect();
+    let limited = estimate_delay(&reference, &target, Some(1000)).unwrap();
+    let whole = estimate_delay(&reference, &target, None).unwrap();
+    assert_eq!((limited.frames, limited.inverted), (whole.frames, whole.inverted));
+    assert_eq!(limited.frames, 321);
+    assert!(limited.inverted);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{multitone, noise, write_wav};
+use saunds_v2::audio::channels::Ambisonics;
+use saunds_v2::audio::{mixdown, AudioBuffer, AudioProcessor, FilterMode, Layout};
+use tempfile::TempDir;
+
+#[test]
+fn converts_between_interleaved_and_planar() {
+    let interleaved: Vec<f32> = (0..12).map(|i| i as f32).collect();
+    let buffer = AudioBuffer::from_interleaved(&interleaved, 3, 8000);
+    assert_eq!((buffer.num_channels(), buffer.frames(), buffer.sample_rate()), (3, 4, 8000));
+    assert_eq!(buffer.channel(1), [1.0, 4.0, 7.0, 10.0]);
+    assert_eq!(buffer.to_interleaved(), interleaved);
+    assert_eq!(buffer.mixdown(), mixdown(&interleaved, 3));
+    assert_eq!(buffer.channel_names(), ["ch1", "ch2", "ch3"]);
+
+    assert!(AudioBuffer::from_planar(vec![vec![0.0; 4], vec![0.0; 3]], 8000).is_err());
+    assert!(AudioBuffer::from_planar(Vec::new(), 8000).is_err());
+    let b_format = AudioBuffer::from_planar(vec![vec![0.0; 4]; 4], 48000).unwrap().with_layout(Layout::Ambisonic(Ambisonics::Fuma));
+    assert_eq!(b_format.channel_names(), ["W", "X", "Y", "Z"]);
+}
+
+#[test]
+fn planar_split_matches_the_interleaved_one() {
+    let tones = multitone(&[200.0, 5000.0], 0.3, 0.25, 44100);
+    let samples: Vec<f32> = tones.iter().zip(noise(tones.len(), 0.1, 5)).flat_map(|(&a, b)| [a, b]).collect();
+    let input = AudioBuffer::from_interleaved(&samples, 2, 44100);
+    for mode in [FilterMode::Fft, FilterMode::Iir] {
+        let processor = AudioProcessor::builder().channels(2).filter_mode(mode).threads(2).build().unwrap();
+        let interleaved = processor.split_bands(&samples, &[500.0, 2000.0]).unwrap();
+        let planar = processor.split_buffer(&input, &[500.0, 2000.0]).unwrap();
+        assert_eq!(planar.len(), 3);
+        for (band, buffer) in interleaved.iter().zip(&planar) {
+            assert_eq!(&buffer.to_interleaved(), band);
+        }
+    }
+
+    let mono = AudioProcessor::builder().channels(1).build().unwrap();
+    assert!(mono.split_buffer(&input, &[1000.0]).is_err());
+}
+
+#[test]
+fn loads_and_saves_buffers() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("in.wav");
+    let samples: Vec<f32> = noise(800, 0.5, 2).chunks(2).flat_map(|pair| [pair[0], pair[1]]).collect();
+    write_wav(&path, &samples, 16000, 2);
+
+    let mut processor = AudioProcessor::new().unwrap();
+    let buffer = processor.load_buffer(&path).unwrap();
+    assert_eq!((buffer.num_channels(), buffer.frames(), buffer.sample_rate()), (2, 400, 16000));
+    let copy = dir.path().join("copy.wav");
+    processor.save_buffer(&copy, &buffer).unwrap();
+    assert_eq!(processor.load_buffer(&copy).unwrap(), buffer);
+}
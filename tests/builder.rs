@@ -0,0 +1,69 @@
+mod common;
+
+use common::{multitone, noise, silent_mp3, write_wav};
+use saunds_v2::audio::{AudioProcessor, FilterDesign, FilterFamily, FilterMode, Window};
+use tempfile::TempDir;
+
+#[test]
+fn builder_rejects_invalid_settings() {
+    assert!(AudioProcessor::builder().sample_rate(0).build().is_err());
+    assert!(AudioProcessor::builder().channels(0).build().is_err());
+    assert!(AudioProcessor::builder().fft_size(1023).build().is_err());
+    assert!(AudioProcessor::builder().threads(0).build().is_err());
+    let design = FilterDesign { family: FilterFamily::Elliptic, order: 0, ..FilterDesign::default() };
+    assert!(AudioProcessor::builder().filter_design(design).build().is_err());
+
+    let processor = AudioProcessor::builder().sample_rate(48000).channels(1).fft_size(512).build().unwrap();
+    assert_eq!((processor.sample_rate(), processor.channels(), processor.window_size()), (48000, 1, 512));
+}
+
+#[test]
+fn vorbis_window_reconstructs_the_input() {
+    let samples = noise(9000, 0.5, 11);
+    let processor = AudioProcessor::builder().channels(1).fft_size(1024).window(Window::Vorbis).build().unwrap();
+    let bands = processor.split_bands(&samples, &[300.0, 4000.0]).unwrap();
+    for (i, &sample) in samples.iter().enumerate() {
+        let sum: f32 = bands.iter().map(|band| band[i]).sum();
+        assert!((sum - sample).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn threads_do_not_change_the_output() {
+    let tones = multitone(&[200.0, 5000.0], 0.3, 0.25, 44100);
+    let samples: Vec<f32> = tones.iter().zip(noise(tones.len(), 0.1, 5)).flat_map(|(&a, b)| [a, b, a - b]).collect();
+    let render = |threads| {
+        AudioProcessor::builder()
+            .channels(3)
+            .threads(threads)
+            .build()
+            .unwrap()
+            .separate_frequencies(&samples, 500.0, 2000.0)
+            .unwrap()
+    };
+    assert_eq!(render(1), render(2));
+    assert_eq!(render(1), render(8));
+}
+
+#[test]
+fn one_processor_serves_several_files() {
+    let dir = TempDir::new().unwrap();
+    let mp3 = dir.path().join("a.mp3");
+    let wav = dir.path().join("b.wav");
+    std::fs::write(&mp3, silent_mp3(8)).unwrap();
+    write_wav(&wav, &multitone(&[440.0], 0.3, 0.1, 22050), 22050, 1);
+
+    let mut processor = AudioProcessor::builder().filter_mode(FilterMode::ZeroPhase).build().unwrap();
+    processor.load_audio(&mp3).unwrap();
+    assert_eq!((processor.sample_rate(), processor.channels()), (44100, 2));
+    assert!(processor.decode_stats().frames > 0);
+
+    let samples = processor.load_audio(&wav).unwrap();
+    assert_eq!((processor.sample_rate(), processor.channels()), (22050, 1));
+    assert_eq!(processor.decode_stats().frames, 0);
+    let fresh = AudioProcessor::builder().sample_rate(22050).channels(1).filter_mode(FilterMode::ZeroPhase).build().unwrap();
+    assert_eq!(
+        processor.separate_frequencies(&samples, 300.0, 1000.0).unwrap(),
+        fresh.separate_frequencies(&samples, 300.0, 1000.0).unwrap()
+    );
+}
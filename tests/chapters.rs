@@ -0,0 +1,87 @@
+mod common;
+
+use common::{multitone, silent_mp3, speech_like};
+use saunds_v2::audio::chapters::{detect, Chapter, ChapterSettings, Content};
+use saunds_v2::audio::id3::write_chapters;
+use saunds_v2::audio::mp3::{decode, DecodeErrorPolicy};
+
+const RATE: u32 = 8000;
+
+/// Speech, a pause, more speech, a music bed and speech again.
+fn programme() -> Vec<f32> {
+    let music = multitone(&[220.0, 277.0, 330.0, 440.0], 0.1, 20.0, RATE);
+    [speech_like(20.0, 0.5, RATE, 1), vec![0.0; 3 * RATE as usize], speech_like(20.0, 0.5, RATE, 2), music, speech_like(20.0, 0.5, RATE, 3)].concat()
+}
+
+fn settings() -> ChapterSettings {
+    ChapterSettings { min_length: 10.0, ..ChapterSettings::default() }
+}
+
+#[test]
+fn starts_chapters_at_pauses_and_music() {
+    let chapters = detect(&programme(), RATE, settings()).unwrap();
+    let starts: Vec<f64> = chapters.iter().map(|chapter| chapter.start).collect();
+    let contents: Vec<Content> = chapters.iter().map(|chapter| chapter.content).collect();
+    assert_eq!(starts.len(), 4, "{:?}", chapters);
+    // Just before the speech resumes after the pause
+    assert!((starts[1] - 22.5).abs() < 0.1, "{:?}", starts);
+    assert!((starts[2] - 43.0).abs() <= 1.0, "{:?}", starts);
+    assert!((starts[3] - 63.0).abs() <= 1.0, "{:?}", starts);
+    assert_eq!(contents, [Content::Speech, Content::Speech, Content::Music, Content::Speech]);
+    assert_eq!(chapters[3].end, 83.0);
+    assert!(chapters.windows(2).all(|pair| pair[0].end == pair[1].start));
+}
+
+#[test]
+fn keeps_chapters_at_least_the_minimum_length() {
+    let only_silences = detect(&programme(), RATE, ChapterSettings { transitions: false, ..settings() }).unwrap();
+    assert_eq!(only_silences.len(), 2);
+    let long = detect(&programme(), RATE, ChapterSettings { min_length: 30.0, ..settings() }).unwrap();
+    assert!(long.iter().all(|chapter| chapter.end - chapter.start >= 30.0), "{:?}", long);
+    assert_eq!(long.len(), 2);
+}
+
+fn chapter(title: &str, start: f64, end: f64) -> Chapter {
+    Chapter { title: title.to_string(), start, end, content: Content::Speech }
+}
+
+fn count(data: &[u8], pattern: &[u8]) -> usize {
+    data.windows(pattern.len()).filter(|window| *window == pattern).count()
+}
+
+#[test]
+fn tags_chapters_into_an_mp3() {
+    let mp3 = silent_mp3(20);
+    let chapters = [chapter("Intro", 0.0, 1.5), chapter("Café talk", 1.5, 2.25)];
+    let tagged = write_chapters(&mp3, &chapters).unwrap();
+
+    assert_eq!(&tagged[..5], b"ID3\x03\x00");
+    assert!(tagged.ends_with(&mp3));
+    assert_eq!(count(&tagged, b"CTOC"), 1);
+    assert_eq!(count(&tagged, b"CHAP"), 2);
+    assert_eq!(count(&tagged, b"\x00Intro"), 1);
+    // Latin-1 text is written as such
+    assert_eq!(count(&tagged, b"\x00Caf\xe9 talk"), 1);
+    // Second chapter from 1500 to 2250 ms
+    assert_eq!(count(&tagged, b"chp1\x00\x00\x00\x05\xdc\x00\x00\x08\xca"), 1);
+    assert_eq!(decode(&tagged, DecodeErrorPolicy::Fail).unwrap().channels[0].len(), decode(&mp3, DecodeErrorPolicy::Fail).unwrap().channels[0].len());
+}
+
+#[test]
+fn replaces_chapters_and_keeps_other_frames() {
+    let mp3 = silent_mp3(4);
+    let first = write_chapters(&mp3, &[chapter("Old", 0.0, 0.1), chapter("Older", 0.1, 0.2)]).unwrap();
+    // Add a title frame to the tag
+    let mut title = b"TIT2\x00\x00\x00\x05\x00\x00\x00Show".to_vec();
+    let mut tag = first[..10].to_vec();
+    let size = first.len() - mp3.len() - 10 + title.len();
+    tag[6..10].copy_from_slice(&[(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+    tag.append(&mut title);
+    tag.extend_from_slice(&first[10..]);
+
+    let second = write_chapters(&tag, &[chapter("New", 0.0, 0.2)]).unwrap();
+    assert!(second.ends_with(&mp3));
+    assert_eq!(count(&second, b"\x00Show"), 1);
+    assert_eq!(count(&second, b"CHAP"), 1);
+    assert_eq!(count(&second, b"Old"), 0);
+}
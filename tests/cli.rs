@@ -0,0 +1,2521 @@
+mod common;
+
+use assert_cmd::Command;
+use common::{gapless_mp3, ltc, multitone, noise, read_wav, silent_mp3, speech_like, tone_level_db, write_wav, MP3_FRAME_LEN, MP3_FRAME_SAMPLES};
+use predicates::boolean::PredicateBooleanExt;
+use tempfile::TempDir;
+
+const TONE_AMPLITUDE: f32 = 0.25;
+const PRESENT_TOLERANCE_DB: f32 = 1.0;
+const ABSENT_CEILING_DB: f32 = -40.0;
+
+/// Expected content of an output band: which tones it must contain at
+/// full level and which must be suppressed.
+struct Golden {
+    file: &'static str,
+    present: &'static [f32],
+    absent: &'static [f32],
+}
+
+/// Default two-band run with cutoffs at 300 Hz and 3000 Hz. The bands
+/// overlap between the cutoffs, so the 1 kHz tone appears in both.
+const TWO_BAND_GOLDEN: &[Golden] = &[
+    Golden { file: "low_freq.wav", present: &[100.0, 1000.0], absent: &[6000.0] },
+    Golden { file: "high_freq.wav", present: &[1000.0, 6000.0], absent: &[100.0] },
+];
+
+fn saunds() -> Command {
+    Command::cargo_bin("saunds_v2").unwrap()
+}
+
+/// Loudness of a mono 44.1 kHz signal.
+fn loudness(samples: &[f32]) -> saunds_v2::audio::loudness::Loudness {
+    saunds_v2::audio::loudness::measure(&saunds_v2::audio::AudioBuffer::from_interleaved(samples, 1, 44100)).unwrap()
+}
+
+fn assert_golden(output: &std::path::Path, golden: &[Golden], sample_rate: u32) {
+    let present_db = 20.0 * TONE_AMPLITUDE.log10();
+
+    for band in golden {
+        let path = output.join(band.file);
+        assert!(path.exists(), "missing output {}", band.file);
+        let (samples, spec) = read_wav(&path);
+        assert_eq!(spec.sample_rate, sample_rate, "{} sample rate", band.file);
+
+        for &tone in band.present {
+            let level = tone_level_db(&samples, sample_rate, tone);
+            assert!(
+                (level - present_db).abs() < PRESENT_TOLERANCE_DB,
+                "{}: {} Hz at {:.1} dB, expected {:.1} dB",
+                band.file, tone, level, present_db
+            );
+        }
+        for &tone in band.absent {
+            let level = tone_level_db(&samples, sample_rate, tone);
+            assert!(
+                level < ABSENT_CEILING_DB,
+                "{}: {} Hz at {:.1} dB, expected below {:.1} dB",
+                band.file, tone, level, ABSENT_CEILING_DB
+            );
+        }
+    }
+}
+
+#[test]
+fn splits_multitone_into_bands() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let output = dir.path().join("out");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 2.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000"])
+        .assert()
+        .success();
+
+    assert_golden(&output, TWO_BAND_GOLDEN, 44100);
+}
+
+#[test]
+fn preserves_input_sample_rate() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let output = dir.path().join("out");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 2.0, 48000), 48000, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000"])
+        .assert()
+        .success();
+
+    assert_golden(&output, TWO_BAND_GOLDEN, 48000);
+}
+
+#[test]
+fn output_length_matches_input() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let output = dir.path().join("out");
+    let samples = multitone(&[440.0], TONE_AMPLITUDE, 0.73, 44100);
+    write_wav(&input, &samples, 44100, 1);
+
+    saunds().arg("--input").arg(&input).arg("--output").arg(&output).assert().success();
+
+    for file in ["low_freq.wav", "high_freq.wav"] {
+        let (band, _) = read_wav(&output.join(file));
+        assert_eq!(band.len(), samples.len(), "{}", file);
+    }
+}
+
+#[test]
+fn seeded_dither_is_reproducible() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+
+    let render = |name: &str, seed: &str| {
+        let output = dir.path().join(name);
+        saunds()
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&output)
+            .args(["--low-cutoff", "300", "--high-cutoff", "3000", "--bit-depth", "16", "--seed", seed])
+            .assert()
+            .success();
+        output
+    };
+    let first = render("first", "7");
+    let second = render("second", "7");
+    let reseeded = render("reseeded", "8");
+
+    let low = |output: &std::path::Path| std::fs::read(output.join("low_freq.wav")).unwrap();
+    assert_eq!(low(&first), low(&second));
+    assert_ne!(low(&first), low(&reseeded));
+    assert_eq!(read_wav(&first.join("low_freq.wav")).1.bits_per_sample, 16);
+    assert_golden(&first, TWO_BAND_GOLDEN, 44100);
+}
+
+#[test]
+fn writes_broadcast_wave_and_rf64() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let output = dir.path().join("out");
+    let samples = multitone(&[100.0, 6000.0], TONE_AMPLITUDE, 0.5, 44100);
+    write_wav(&input, &samples, 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--bit-depth", "24", "--bwf", "--rf64"])
+        .env("SOURCE_DATE_EPOCH", "1700000000")
+        .assert()
+        .success();
+
+    let bytes = std::fs::read(output.join("low_freq.wav")).unwrap();
+    assert_eq!(&bytes[..4], b"RF64");
+    assert_eq!(&bytes[12..16], b"ds64");
+    let bext = bytes.windows(4).position(|id| id == b"bext").unwrap() + 8;
+    assert!(bytes[bext..].starts_with(b"Frequency band split of tones.wav"));
+    assert_eq!(&bytes[bext + 320..bext + 338], b"2023-11-1422:13:20");
+    let history = String::from_utf8_lossy(&bytes[bext + 602..bext + 640]);
+    assert!(history.starts_with("A=PCM,F=44100,W=24,M=mono,T=saunds"), "{}", history);
+
+    // RF64 outputs read back in like any other WAV
+    let resplit = dir.path().join("resplit");
+    saunds()
+        .arg("--input").arg(output.join("low_freq.wav"))
+        .arg("--output").arg(&resplit)
+        .assert()
+        .success();
+    let (band, spec) = read_wav(&resplit.join("low_freq.wav"));
+    assert_eq!((band.len(), spec.sample_rate), (samples.len(), 44100));
+}
+
+#[test]
+fn writes_aiff_and_caf() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 6000.0], TONE_AMPLITUDE, 0.25, 44100), 44100, 1);
+    let render = |name: &str, args: &[&str]| {
+        let output = dir.path().join(name);
+        saunds().arg("--input").arg(&input).arg("--output").arg(&output).args(args).assert().success();
+        output
+    };
+
+    // The sample data matches a WAV render, only big-endian
+    let wav = render("wav", &["--bit-depth", "24", "--dither", "none"]);
+    let aiff = render("aiff", &["--bit-depth", "24", "--dither", "none", "--format", "aiff"]);
+    let wav = std::fs::read(wav.join("low_freq.wav")).unwrap();
+    let aiff = std::fs::read(aiff.join("low_freq.aiff")).unwrap();
+    assert_eq!((&aiff[..4], &aiff[8..16]), (&b"FORM"[..], &b"AIFFCOMM"[..]));
+    let frames = u32::from_be_bytes(aiff[22..26].try_into().unwrap()) as usize;
+    assert_eq!(frames, 11025);
+    assert_eq!(u16::from_be_bytes([aiff[26], aiff[27]]), 24);
+    assert_eq!(&aiff[28..38], &[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&aiff[38..42], b"SSND");
+    let pcm: Vec<u8> = aiff[54..54 + 3 * frames].chunks(3).flat_map(|s| [s[2], s[1], s[0]]).collect();
+    assert_eq!(&wav[44..44 + pcm.len()], &pcm[..]);
+
+    let wav = std::fs::read(render("float", &[]).join("high_freq.wav")).unwrap();
+    let caf = std::fs::read(render("caf", &["--format", "caf"]).join("high_freq.caf")).unwrap();
+    assert_eq!(&caf[..4], b"caff");
+    assert_eq!(&caf[8..12], b"desc");
+    assert_eq!(f64::from_be_bytes(caf[20..28].try_into().unwrap()), 44100.0);
+    assert_eq!(&caf[28..32], b"lpcm");
+    assert_eq!(u32::from_be_bytes(caf[32..36].try_into().unwrap()), 1);
+    assert_eq!(&caf[52..56], b"data");
+    let pcm: Vec<u8> = caf[68..].chunks(4).flat_map(|s| [s[3], s[2], s[1], s[0]]).collect();
+    assert_eq!(pcm.len(), 4 * frames);
+    assert_eq!(&wav[44..44 + pcm.len()], &pcm[..]);
+}
+
+#[test]
+fn chains_runs_through_the_intermediate_format() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 2);
+    let render = |input: &std::path::Path, name: &str, args: &[&str]| {
+        let output = dir.path().join(name);
+        saunds().arg("--input").arg(input).arg("--output").arg(&output).args(args).assert().success();
+        output
+    };
+
+    let wav = render(&input, "wav", &[]);
+    let intermediate = render(&input, "intermediate", &["--format", "saunds"]);
+    let bytes = std::fs::read(intermediate.join("high_freq.saunds")).unwrap();
+    let (header, samples) = saunds_v2::audio::intermediate::read(bytes.as_slice()).unwrap();
+    assert_eq!((header.sample_rate, header.channels, header.frames), (44100, 2, 11025));
+    let (expected, _) = read_wav(&wav.join("high_freq.wav"));
+    assert!(expected.chunks(2).enumerate().all(|(frame, pair)| pair.iter().zip(&samples).all(|(&b, channel)| channel[frame] == b as f64)));
+
+    // A second stage reads the intermediate exactly as it reads the float WAV
+    let from_wav = render(&wav.join("high_freq.wav"), "second_wav", &["--low-cutoff", "500", "--high-cutoff", "3000"]);
+    let from_intermediate = render(
+        &intermediate.join("high_freq.saunds"),
+        "second_intermediate",
+        &["--low-cutoff", "500", "--high-cutoff", "3000"],
+    );
+    assert_eq!(
+        read_wav(&from_wav.join("low_freq.wav")).0,
+        read_wav(&from_intermediate.join("low_freq.wav")).0
+    );
+}
+
+#[test]
+fn splits_each_surround_channel_separately() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("surround.wav");
+    let output = dir.path().join("out");
+    // 5.1 with a 6 kHz tone on L, a 60 Hz tone on the LFE and silence elsewhere
+    let high = multitone(&[6000.0], TONE_AMPLITUDE, 0.5, 44100);
+    let low = multitone(&[60.0], TONE_AMPLITUDE, 0.5, 44100);
+    let interleaved: Vec<f32> = high.iter().zip(&low).flat_map(|(&l, &lfe)| [l, 0.0, 0.0, lfe, 0.0, 0.0]).collect();
+    write_wav(&input, &interleaved, 44100, 6);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000", "--channel-files"])
+        .assert()
+        .success();
+
+    let level = |file: &str, frequency: f32| {
+        let (samples, spec) = read_wav(&output.join(file));
+        assert_eq!(spec.channels, 1);
+        tone_level_db(&samples, 44100, frequency)
+    };
+    let full = 20.0 * TONE_AMPLITUDE.log10();
+    assert!((level("low_freq.LFE.wav", 60.0) - full).abs() < PRESENT_TOLERANCE_DB);
+    assert!((level("high_freq.L.wav", 6000.0) - full).abs() < PRESENT_TOLERANCE_DB);
+    assert!(level("low_freq.L.wav", 6000.0) < ABSENT_CEILING_DB);
+    assert!(level("high_freq.LFE.wav", 60.0) < ABSENT_CEILING_DB);
+    let (centre, _) = read_wav(&output.join("low_freq.C.wav"));
+    assert!(centre.iter().all(|x| x.abs() < 1e-6));
+
+    let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["bands"].as_array().unwrap().len(), 12);
+    assert_eq!(manifest["bands"][3]["channel"], "LFE");
+
+    // Without --channel-files the bands keep all six channels
+    let joined = dir.path().join("joined");
+    saunds().arg("--input").arg(&input).arg("--output").arg(&joined).assert().success();
+    let (band, spec) = read_wav(&joined.join("low_freq.wav"));
+    assert_eq!((spec.channels, band.len()), (6, interleaved.len()));
+}
+
+#[test]
+fn keeps_ambisonic_components_coherent() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("bformat.wav");
+    let output = dir.path().join("out");
+    // AmbiX W Y Z X of a single source
+    let source = multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 0.5, 44100);
+    let interleaved: Vec<f32> = source.iter().flat_map(|&w| [w, 0.5 * w, 0.0, 0.8 * w]).collect();
+    write_wav(&input, &interleaved, 44100, 4);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--filter", "iir", "--ambisonics", "ambix"])
+        .assert()
+        .success();
+
+    // Identical filters keep the components in proportion, so the source
+    // stays where it was in every band
+    for file in ["low_freq.wav", "high_freq.wav"] {
+        let (band, _) = read_wav(&output.join(file));
+        for frame in band.chunks_exact(4) {
+            assert!((frame[1] - 0.5 * frame[0]).abs() < 1e-5, "{}", file);
+            assert!((frame[3] - 0.8 * frame[0]).abs() < 1e-5, "{}", file);
+        }
+        // No speaker positions in the channel mask
+        let bytes = std::fs::read(output.join(file)).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 0);
+    }
+    let manifest: serde_json::Value = serde_json::from_slice(&std::fs::read(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["ambisonics"], "ambix");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("components"))
+        .args(["--ambisonics", "fuma", "--channel-files"])
+        .assert()
+        .success();
+    assert!(dir.path().join("components/low_freq.Z.wav").exists());
+
+    let stereo = dir.path().join("stereo.wav");
+    write_wav(&stereo, &interleaved[..8000], 44100, 2);
+    saunds()
+        .arg("--input").arg(&stereo)
+        .arg("--output").arg(dir.path().join("rejected"))
+        .args(["--ambisonics", "ambix"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("four-channel"));
+}
+
+#[test]
+fn routes_channels_through_a_matrix() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("stereo.wav");
+    let left = multitone(&[100.0], TONE_AMPLITUDE, 0.5, 44100);
+    let right = multitone(&[6000.0], TONE_AMPLITUDE, 0.5, 44100);
+    let stereo: Vec<f32> = left.iter().zip(&right).flat_map(|(&l, &r)| [l, r]).collect();
+    write_wav(&input, &stereo, 44100, 2);
+    let full = 20.0 * TONE_AMPLITUDE.log10();
+
+    // Fold down to mono at -6 dB per side
+    let folded = dir.path().join("folded");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&folded)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000", "--map", "0:0:-6,1:0:-6"])
+        .assert()
+        .success();
+    let (low, spec) = read_wav(&folded.join("low_freq.wav"));
+    assert_eq!((spec.channels, low.len()), (1, left.len()));
+    assert!((tone_level_db(&low, 44100, 100.0) - (full - 6.0)).abs() < PRESENT_TOLERANCE_DB);
+    assert!(tone_level_db(&low, 44100, 6000.0) < ABSENT_CEILING_DB);
+
+    // Swap and duplicate from a matrix file: out 0 = R, out 1 = half L, out 2 = L
+    let matrix = dir.path().join("matrix.txt");
+    std::fs::write(&matrix, "# L R\n0 1\n0.5 0\n1 0\n").unwrap();
+    let routed = dir.path().join("routed");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&routed)
+        .arg("--map-file").arg(&matrix)
+        .arg("--channel-files")
+        .assert()
+        .success();
+    let (first, _) = read_wav(&routed.join("high_freq.ch1.wav"));
+    let (second, _) = read_wav(&routed.join("low_freq.ch2.wav"));
+    assert!((tone_level_db(&first, 44100, 6000.0) - full).abs() < PRESENT_TOLERANCE_DB);
+    assert!((tone_level_db(&second, 44100, 100.0) - (full - 6.02)).abs() < PRESENT_TOLERANCE_DB);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("rejected"))
+        .args(["--map", "2:0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("input channel 2"));
+}
+
+#[test]
+fn delays_a_channel_to_realign_stereo() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("azimuth.wav");
+    // The right channel lags the left by 0.3 ms, as from a skewed tape head
+    let tone = |shift: f64| -> Vec<f32> {
+        (0..22050).map(|n| (0.25 * (2.0 * std::f64::consts::PI * 1000.0 * (n as f64 / 44100.0 - shift)).sin()) as f32).collect()
+    };
+    let stereo: Vec<f32> = tone(0.0).iter().zip(&tone(0.0003)).flat_map(|(&l, &r)| [l, r]).collect();
+    write_wav(&input, &stereo, 44100, 2);
+
+    let difference = |samples: &[f32]| -> f32 {
+        samples[2000..samples.len() - 2000].chunks_exact(2).map(|frame| (frame[0] - frame[1]).abs()).fold(0.0, f32::max)
+    };
+    let output = dir.path().join("aligned");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000", "--delay", "R=-0.3ms"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Delayed R by -0.300 ms"));
+    let (low, _) = read_wav(&output.join("low_freq.wav"));
+    assert!(difference(&stereo) > 0.3);
+    assert!(difference(&low) < 0.005);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("rejected"))
+        .args(["--delay", "C=1ms"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No channel 'C'"));
+}
+
+#[test]
+fn rejects_invalid_cutoffs() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[440.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+
+    let cases: &[(&str, &str, &str)] = &[
+        ("2000", "200", "must be below the high cutoff"),
+        ("200", "30000", "below the Nyquist frequency (22050 Hz)"),
+        ("-5", "2000", "non-negative"),
+    ];
+
+    for &(low, high, message) in cases {
+        saunds()
+            .arg("--input").arg(&input)
+            .arg("--output").arg(dir.path().join("out"))
+            .arg(format!("--low-cutoff={}", low))
+            .arg(format!("--high-cutoff={}", high))
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains(message));
+    }
+}
+
+/// Silent MP3 with the header of one frame in the middle destroyed.
+fn corrupt_mp3(dir: &std::path::Path, frames: usize, corrupt_frame: usize) -> std::path::PathBuf {
+    let mut data = silent_mp3(frames);
+    let start = corrupt_frame * MP3_FRAME_LEN;
+    data[start..start + 4].fill(0x55);
+    let path = dir.join("corrupt.mp3");
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+fn decoded_len(output: &std::path::Path) -> usize {
+    read_wav(&output.join("low_freq.wav")).0.len()
+}
+
+#[test]
+fn decode_error_policies() {
+    let dir = TempDir::new().unwrap();
+    let input = corrupt_mp3(dir.path(), 40, 20);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("fail"))
+        .args(["--on-decode-error", "fail"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Corrupt MP3 data"));
+
+    let skip = dir.path().join("skip");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&skip)
+        .args(["--on-decode-error", "skip"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Skipped 2 undecodable MP3 frames"));
+    // The frame before the broken header fails the decoder's sync check too
+    assert_eq!(decoded_len(&skip), 38 * MP3_FRAME_SAMPLES * 2);
+
+    let pad = dir.path().join("pad");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&pad)
+        .args(["--on-decode-error", "pad"])
+        .assert()
+        .success();
+    assert_eq!(decoded_len(&pad), 40 * MP3_FRAME_SAMPLES * 2);
+}
+
+#[test]
+fn truncated_mp3_is_reported() {
+    let dir = TempDir::new().unwrap();
+    let mut data = silent_mp3(10);
+    data.truncate(data.len() - MP3_FRAME_LEN / 2);
+    let input = dir.path().join("truncated.mp3");
+    std::fs::write(&input, data).unwrap();
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("fail"))
+        .args(["--on-decode-error", "fail"])
+        .assert()
+        .failure();
+
+    let pad = dir.path().join("pad");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&pad)
+        .args(["--on-decode-error", "pad"])
+        .assert()
+        .success();
+    assert_eq!(decoded_len(&pad), 10 * MP3_FRAME_SAMPLES * 2);
+}
+
+/// APEv2 tag with a header and a single item, as taggers append it.
+fn ape_tag() -> Vec<u8> {
+    let mut item = Vec::new();
+    item.extend_from_slice(&6u32.to_le_bytes());
+    item.extend_from_slice(&0u32.to_le_bytes());
+    item.extend_from_slice(b"Artist\0saunds");
+    let block = |flags: u32| {
+        let mut block = b"APETAGEX".to_vec();
+        block.extend_from_slice(&2000u32.to_le_bytes());
+        block.extend_from_slice(&(item.len() as u32 + 32).to_le_bytes());
+        block.extend_from_slice(&1u32.to_le_bytes());
+        block.extend_from_slice(&flags.to_le_bytes());
+        block.resize(32, 0);
+        block
+    };
+    [block(0xA000_0000), item.clone(), block(0x8000_0000)].concat()
+}
+
+#[test]
+fn trailing_tags_are_not_corrupt_data() {
+    let dir = TempDir::new().unwrap();
+    let lyrics = b"LYRICSBEGININD0000211000021LYRICS200";
+    let mut id3v1 = b"TAG".to_vec();
+    id3v1.resize(128, 0);
+    let trailers: [(&str, Vec<u8>); 2] = [
+        ("ape", ape_tag()),
+        ("stacked", [ape_tag(), lyrics.to_vec(), id3v1].concat()),
+    ];
+
+    for (name, trailer) in trailers {
+        let input = dir.path().join(format!("{}.mp3", name));
+        std::fs::write(&input, [silent_mp3(10), trailer].concat()).unwrap();
+        let output = dir.path().join(name);
+        saunds()
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&output)
+            .args(["--on-decode-error", "fail"])
+            .assert()
+            .success();
+        assert_eq!(decoded_len(&output), 10 * MP3_FRAME_SAMPLES * 2);
+    }
+}
+
+#[test]
+fn strips_lame_delay_and_padding() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("gapless.mp3");
+    std::fs::write(&input, gapless_mp3(20, 576, 1200)).unwrap();
+    let output = dir.path().join("out");
+
+    saunds().arg("--input").arg(&input).arg("--output").arg(&output).assert().success();
+
+    // The Info frame is dropped and delay/padding trimmed from the audio
+    assert_eq!(decoded_len(&output), (20 * MP3_FRAME_SAMPLES - 576 - 1200) * 2);
+}
+
+#[test]
+fn aligns_delayed_take() {
+    let dir = TempDir::new().unwrap();
+    let reference = noise(44100, 0.5, 7);
+    let delay = 1234;
+    let mut take: Vec<f32> = vec![0.0; delay];
+    take.extend_from_slice(&reference);
+    let reference_path = dir.path().join("ref.wav");
+    let take_path = dir.path().join("take2.wav");
+    write_wav(&reference_path, &reference, 44100, 1);
+    write_wav(&take_path, &take, 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("align")
+        .arg(&reference_path)
+        .arg(&take_path)
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("lags reference by 1234 samples"));
+
+    let (aligned, _) = read_wav(&output.join("take2.aligned.wav"));
+    assert_eq!(aligned.len(), reference.len());
+    assert!(aligned.iter().zip(&reference).all(|(a, r)| (a - r).abs() < 1e-6));
+    assert!(output.join("ref.aligned.wav").exists());
+}
+
+#[test]
+fn keeps_aligned_takes_of_the_same_name_apart() {
+    let dir = TempDir::new().unwrap();
+    let reference = noise(44100, 0.5, 9);
+    let mut take: Vec<f32> = vec![0.0; 300];
+    take.extend_from_slice(&reference);
+    for (folder, samples) in [("a", &reference), ("b", &take)] {
+        std::fs::create_dir(dir.path().join(folder)).unwrap();
+        write_wav(&dir.path().join(folder).join("take.wav"), samples, 44100, 1);
+    }
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("align")
+        .arg(dir.path().join("a/take.wav"))
+        .arg(dir.path().join("b/take.wav"))
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let (aligned_reference, _) = read_wav(&output.join("take.reference.aligned.wav"));
+    let (aligned_target, _) = read_wav(&output.join("take.target.aligned.wav"));
+    assert!(aligned_reference.iter().zip(&reference).all(|(a, r)| (a - r).abs() < 1e-6));
+    assert!(aligned_target.iter().zip(&reference).all(|(a, r)| (a - r).abs() < 1e-6));
+    assert!(!output.join("take.aligned.wav").exists());
+}
+
+#[test]
+fn leaves_polarity_alone_without_a_clear_match() {
+    let dir = TempDir::new().unwrap();
+    // Unrelated noise, whose strongest correlation within the search
+    // happens to be negative
+    let reference = noise(44100, 0.5, 9);
+    let take = noise(44100, 0.5, 109);
+    let reference_path = dir.path().join("ref.wav");
+    let take_path = dir.path().join("take.wav");
+    write_wav(&reference_path, &reference, 44100, 1);
+    write_wav(&take_path, &take, 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("align")
+        .arg(&reference_path)
+        .arg(&take_path)
+        .args(["--max-delay", "0.0005", "--output"])
+        .arg(&output)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Weak correlation peak"))
+        .stderr(predicates::str::contains("inverted polarity").not());
+
+    let (aligned, _) = read_wav(&output.join("take.aligned.wav"));
+    assert!(aligned[100..].iter().zip(&take[84..]).all(|(a, t)| (a - t).abs() < 1e-6));
+}
+
+#[test]
+fn detects_inverted_and_delayed_channels() {
+    let dir = TempDir::new().unwrap();
+    let left = noise(44100, 0.5, 11);
+    // The right mic is wired out of phase and 20 samples further away
+    let right: Vec<f32> = (0..left.len()).map(|i| if i < 20 { 0.0 } else { -left[i - 20] }).collect();
+    let stereo: Vec<f32> = left.iter().zip(&right).flat_map(|(&l, &r)| [l, r]).collect();
+    let input = dir.path().join("mics.wav");
+    write_wav(&input, &stereo, 44100, 2);
+
+    saunds()
+        .arg("align")
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("R lags L by 20 samples"))
+        .stderr(predicates::str::contains("R has inverted polarity against L"));
+
+    let output = dir.path().join("out");
+    saunds().arg("align").arg(&input).arg("--output").arg(&output).assert().success();
+    let (aligned, _) = read_wav(&output.join("mics.aligned.wav"));
+    assert_eq!(aligned.len(), stereo.len());
+    assert!(aligned[..aligned.len() - 40].chunks_exact(2).all(|frame| (frame[0] - frame[1]).abs() < 1e-6));
+
+    // Against a second recording the whole target is flipped
+    let take = dir.path().join("take.wav");
+    write_wav(&take, &left.iter().map(|x| -x).collect::<Vec<_>>(), 44100, 1);
+    let reference = dir.path().join("ref.wav");
+    write_wav(&reference, &left, 44100, 1);
+    saunds()
+        .arg("align")
+        .arg(&reference)
+        .arg(&take)
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Target has inverted polarity against reference"));
+    let (flipped, _) = read_wav(&output.join("take.aligned.wav"));
+    assert!(flipped.iter().zip(&left).all(|(a, r)| (a - r).abs() < 1e-6));
+}
+
+#[test]
+fn syncs_a_drifting_recorder_to_the_camera() {
+    let dir = TempDir::new().unwrap();
+    let camera = noise(12 * 16000, 0.5, 21);
+    // The recorder started a quarter second earlier and its clock runs
+    // 400 ppm fast
+    let positions: Vec<f64> = (0..12 * 16000).map(|n| (n as f64 - 4000.0) / 1.0004).collect();
+    let recorder = saunds_v2::audio::resample::warp(&camera, 1, &positions);
+    let camera_path = dir.path().join("camera.wav");
+    let recorder_path = dir.path().join("recorder.wav");
+    write_wav(&camera_path, &camera, 16000, 1);
+    write_wav(&recorder_path, &recorder, 16000, 1);
+    let output = dir.path().join("synced.wav");
+
+    saunds()
+        .arg("sync")
+        .arg("--reference").arg(&camera_path)
+        .arg("--target").arg(&recorder_path)
+        .arg("--output").arg(&output)
+        .args(["--segment", "2"])
+        .assert()
+        .success()
+        .stderr(predicates::str::is_match(r"Target lags reference by 250\.0 ms and its clock runs \+(399|400)\.\d ppm").unwrap());
+
+    let (synced, _) = read_wav(&output);
+    assert_eq!(synced.len(), camera.len());
+    // Compare away from the end, which the recorder doesn't cover
+    let range = 16000..11 * 16000;
+    let dot: f32 = synced[range.clone()].iter().zip(&camera[range.clone()]).map(|(a, b)| a * b).sum();
+    let energy: f32 = camera[range].iter().map(|x| x * x).sum();
+    assert!(dot / energy > 0.97, "{}", dot / energy);
+}
+
+#[test]
+fn trims_a_recording_to_its_timecode() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("take.wav");
+    // Program on the left, LTC from 01:00:00:00 on the right
+    let timecode = ltc([1, 0, 0, 0], 100, 25.0, 48000, 0.5);
+    let program: Vec<f32> = (0..timecode.len()).map(|i| i as f32 / timecode.len() as f32).collect();
+    let stereo: Vec<f32> = program.iter().zip(&timecode).flat_map(|(&p, &t)| [p, t]).collect();
+    write_wav(&input, &stereo, 48000, 2);
+    let output = dir.path().join("second.wav");
+
+    let assert = saunds()
+        .arg("timecode")
+        .arg(&input)
+        .args(["--start", "01:00:01:00", "--end", "01:00:02:00"])
+        .arg("--output").arg(&output)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("from channel 1"));
+    let report = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(report.contains("timecode at start   01:00:00:00 +0.00 frames"), "{}", report);
+    assert!(report.contains("drift               +0.0 ppm"), "{}", report);
+
+    let (trimmed, spec) = read_wav(&output);
+    assert_eq!((spec.channels, trimmed.len()), (2, 2 * 48000));
+    assert!((trimmed[0] - program[48000]).abs() < 1e-4);
+
+    saunds()
+        .arg("timecode")
+        .arg(&input)
+        .args(["--channel", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No LTC timecode found"));
+}
+
+#[test]
+fn finds_chapters_and_tags_them_into_an_mp3() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("episode.wav");
+    let programme = [speech_like(15.0, 0.5, 8000, 1), vec![0.0; 3 * 8000], speech_like(15.0, 0.5, 8000, 2)].concat();
+    write_wav(&input, &programme, 8000, 1);
+
+    let assert = saunds()
+        .arg("chapters")
+        .arg(&input)
+        .args(["--min-length", "10", "--format", "json"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Found 2 chapters"));
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let chapters = report["chapters"].as_array().unwrap();
+    assert_eq!(chapters[1]["title"], "Chapter 2");
+    assert_eq!(chapters[1]["content"], "speech");
+    assert!((chapters[1]["start"].as_f64().unwrap() - 17.5).abs() < 0.1, "{}", report);
+
+    saunds()
+        .arg("chapters")
+        .arg(&input)
+        .arg("--mp3").arg(dir.path().join("episode.mp3"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("must be an MP3 file"));
+
+    let mp3 = dir.path().join("silent.mp3");
+    std::fs::write(&mp3, silent_mp3(40)).unwrap();
+    let tagged = dir.path().join("tagged/silent.mp3");
+    saunds()
+        .arg("chapters")
+        .arg(&mp3)
+        .arg("--mp3").arg(&tagged)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("silence  Chapter 1"));
+    let data = std::fs::read(&tagged).unwrap();
+    assert!(data.starts_with(b"ID3") && data.ends_with(&silent_mp3(40)));
+}
+
+#[test]
+fn applies_weighting_before_splitting() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0], TONE_AMPLITUDE, 2.0, 44100), 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "300", "--high-cutoff", "3000", "--weighting", "a"])
+        .assert()
+        .success();
+
+    let (low, _) = read_wav(&output.join("low_freq.wav"));
+    let tone_db = 20.0 * TONE_AMPLITUDE.log10();
+    assert!((tone_level_db(&low, 44100, 100.0) - (tone_db - 19.1)).abs() < 0.5);
+    assert!((tone_level_db(&low, 44100, 1000.0) - tone_db).abs() < 0.5);
+}
+
+#[test]
+fn splits_into_perceptual_bands() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let samples = multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 1.0, 44100);
+    write_wav(&input, &samples, 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--bands", "4", "--band-scale", "mel"])
+        .assert()
+        .success();
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["band_scale"], "mel");
+    let bands = manifest["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 4);
+
+    // Edges are equally spaced in mels between 20 Hz and 20 kHz
+    let mel = |f: f64| 2595.0 * (1.0 + f / 700.0).log10();
+    let step = (mel(20_000.0) - mel(20.0)) / 4.0;
+    for (i, pair) in bands.windows(2).enumerate() {
+        let edge = pair[0]["high_hz"].as_f64().unwrap();
+        assert_eq!(edge, pair[1]["low_hz"].as_f64().unwrap());
+        assert!((mel(edge) - mel(20.0) - step * (i + 1) as f64).abs() < 0.5);
+    }
+
+    // The bands are complementary
+    let mut sum = vec![0.0f32; samples.len()];
+    for band in bands {
+        let (rendered, _) = read_wav(&output.join(band["file"].as_str().unwrap()));
+        sum.iter_mut().zip(&rendered).for_each(|(s, r)| *s += r);
+    }
+    assert!(sum.iter().zip(&samples).all(|(s, x)| (s - x).abs() < 1e-4));
+}
+
+#[test]
+fn band_scale_conflicts_with_cutoffs() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[440.0], TONE_AMPLITUDE, 0.1, 44100), 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path())
+        .args(["--bands", "3", "--low-cutoff", "100"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn suggests_cutoffs_at_spectral_valleys() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    let tones = [60.0, 80.0, 110.0, 1000.0, 1200.0, 1500.0, 6000.0, 7000.0, 8000.0];
+    write_wav(&input, &multitone(&tones, 0.1, 2.0, 44100), 44100, 1);
+
+    let output = saunds().arg("suggest-cutoffs").arg(&input).output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let values: Vec<f32> = stdout.split_whitespace().filter_map(|word| word.parse().ok()).collect();
+    let [low, high] = values[..] else { panic!("unexpected output: {}", stdout) };
+    assert!((110.0..1000.0).contains(&low), "low cutoff {}", low);
+    assert!((1500.0..6000.0).contains(&high), "high cutoff {}", high);
+}
+
+#[test]
+fn zero_phase_filter_preserves_waveform() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    let samples = multitone(&[100.0], TONE_AMPLITUDE, 1.0, 44100);
+    write_wav(&input, &samples, 44100, 1);
+
+    let max_deviation = |filter: &str| {
+        let output = dir.path().join(filter);
+        saunds()
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&output)
+            .args(["--low-cutoff", "1000", "--high-cutoff", "5000", "--filter", filter])
+            .assert()
+            .success();
+        let (low, _) = read_wav(&output.join("low_freq.wav"));
+        assert_eq!(low.len(), samples.len());
+        low.iter().zip(&samples).map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max)
+    };
+
+    // Well inside the passband the zero-phase output tracks the input
+    // sample for sample, while the causal crossover shifts its phase
+    assert!(max_deviation("zero-phase") < 1e-3);
+    assert!(max_deviation("iir") > 1e-2);
+}
+
+#[test]
+fn reports_achieved_stopband_attenuation() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("out"))
+        .args(["--filter", "iir", "--design", "elliptic", "--order", "4"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Low-pass at 2000 Hz (Elliptic, order 4)"))
+        .stderr(predicates::str::contains("dB stopband attenuation"));
+
+    // Design options have no effect on the STFT masks
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("fft"))
+        .args(["--order", "4"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("require --filter iir or zero-phase"));
+}
+
+#[test]
+fn filter_subcommand_passes_and_rejects_bands() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    let tone_db = 20.0 * TONE_AMPLITUDE.log10();
+
+    let bandpass = dir.path().join("bandpass.wav");
+    saunds()
+        .arg("filter").arg(&input)
+        .arg("--output").arg(&bandpass)
+        .args(["--type", "bandpass", "--from", "300", "--to", "3000", "--order", "8"])
+        .assert()
+        .success();
+    let (samples, _) = read_wav(&bandpass);
+    assert!((tone_level_db(&samples, 44100, 1000.0) - tone_db).abs() < PRESENT_TOLERANCE_DB);
+    assert!(tone_level_db(&samples, 44100, 100.0) < ABSENT_CEILING_DB);
+    assert!(tone_level_db(&samples, 44100, 6000.0) < ABSENT_CEILING_DB);
+
+    let bandstop = dir.path().join("bandstop.wav");
+    saunds()
+        .arg("filter").arg(&input)
+        .arg("--output").arg(&bandstop)
+        .args(["--type", "bandstop", "--from", "500", "--to", "2000", "--zero-phase"])
+        .assert()
+        .success();
+    let (samples, _) = read_wav(&bandstop);
+    assert!(tone_level_db(&samples, 44100, 1000.0) < ABSENT_CEILING_DB);
+    assert!((tone_level_db(&samples, 44100, 100.0) - tone_db).abs() < PRESENT_TOLERANCE_DB);
+    assert!((tone_level_db(&samples, 44100, 6000.0) - tone_db).abs() < PRESENT_TOLERANCE_DB);
+
+    saunds()
+        .arg("filter").arg(&input)
+        .arg("--output").arg(dir.path().join("lowpass.wav"))
+        .args(["--type", "lowpass", "--from", "500"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("requires --to"));
+}
+
+#[test]
+fn band_effects_touch_only_their_band() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 6000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    let plain = dir.path().join("plain");
+    let processed = dir.path().join("processed");
+
+    saunds().arg("--input").arg(&input).arg("--output").arg(&plain).assert().success();
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&processed)
+        .args(["--band-fx", "high:dynamic-eq:freq=6000,gain=-12,threshold=0", "--no-autogain"])
+        .assert()
+        .success();
+
+    let (plain_low, _) = read_wav(&plain.join("low_freq.wav"));
+    let (processed_low, _) = read_wav(&processed.join("low_freq.wav"));
+    assert_eq!(plain_low, processed_low);
+
+    let (plain_high, _) = read_wav(&plain.join("high_freq.wav"));
+    let (processed_high, _) = read_wav(&processed.join("high_freq.wav"));
+    let cut = tone_level_db(&processed_high, 44100, 6000.0) - tone_level_db(&plain_high, 44100, 6000.0);
+    assert!((cut + 12.0).abs() < 0.5, "cut {} dB", cut);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&processed)
+        .args(["--band-fx", "3:transient:attack=6"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Band 3 does not exist"));
+}
+
+#[test]
+fn fx_subcommand_runs_stage_chain() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    write_wav(&input, &multitone(&[1000.0], 0.5, 1.0, 44100), 44100, 1);
+    let output = dir.path().join("saturated.wav");
+
+    saunds()
+        .arg("fx").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--stage", "saturate:curve=tube,drive=12", "--stage", "dynamic-eq:freq=2000,gain=6,threshold=0"])
+        .assert()
+        .success();
+
+    let (samples, _) = read_wav(&output);
+    assert!(tone_level_db(&samples, 44100, 2000.0) > -40.0);
+}
+
+#[test]
+fn telephone_preset_keeps_only_the_voice_band() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0, 8000.0], 0.2, 1.0, 44100), 44100, 1);
+    let output = dir.path().join("phone.wav");
+
+    saunds()
+        .arg("fx").arg(&input)
+        .arg("-o").arg(&output)
+        .args(["--preset", "telephone"])
+        .assert()
+        .success();
+
+    let (samples, _) = read_wav(&output);
+    let voice = tone_level_db(&samples, 44100, 1000.0);
+    assert!(voice - tone_level_db(&samples, 44100, 100.0) > 20.0);
+    assert!(voice - tone_level_db(&samples, 44100, 8000.0) > 20.0);
+
+    saunds()
+        .arg("fx").arg(&input)
+        .arg("-o").arg(&output)
+        .args(["--preset", "walkie-talkie"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("telephone"));
+}
+
+#[test]
+fn vocoder_keeps_only_carrier_bands_the_modulator_excites() {
+    let dir = TempDir::new().unwrap();
+    let carrier = dir.path().join("carrier.wav");
+    let modulator = dir.path().join("modulator.wav");
+    let output = dir.path().join("vocoded.wav");
+    let carrier_tones: Vec<f32> = multitone(&[500.0, 5000.0], 0.25, 2.0, 44100).iter().flat_map(|&x| [x, x]).collect();
+    write_wav(&carrier, &carrier_tones, 44100, 2);
+    write_wav(&modulator, &multitone(&[500.0], 0.5, 1.5, 44100), 44100, 1);
+
+    saunds()
+        .arg("vocode")
+        .arg("--carrier").arg(&carrier)
+        .arg("--modulator").arg(&modulator)
+        .arg("--output").arg(&output)
+        .args(["--bands", "16"])
+        .assert()
+        .success();
+
+    let (samples, spec) = read_wav(&output);
+    assert_eq!(spec.channels, 2);
+    assert_eq!(samples.len(), 2 * (1.5 * 44100.0) as usize);
+    let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+    assert!(tone_level_db(&left, 44100, 500.0) - tone_level_db(&left, 44100, 5000.0) > 40.0);
+}
+
+#[test]
+fn autogain_matches_stage_levels() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    write_wav(&input, &multitone(&[1000.0], 0.1, 1.0, 44100), 44100, 1);
+    let matched = dir.path().join("matched.wav");
+    let boosted = dir.path().join("boosted.wav");
+
+    let boost = ["--stage", "dynamic-eq:freq=1000,gain=12,threshold=0"];
+    saunds().arg("fx").arg(&input).arg("-o").arg(&matched).args(boost).assert().success();
+    saunds().arg("fx").arg(&input).arg("-o").arg(&boosted).args(boost).arg("--no-autogain").assert().success();
+
+    let (matched, _) = read_wav(&matched);
+    let (boosted, _) = read_wav(&boosted);
+    assert!((tone_level_db(&matched, 44100, 1000.0) + 20.0).abs() < 0.2);
+    assert!((tone_level_db(&boosted, 44100, 1000.0) + 8.0).abs() < 0.5);
+}
+
+#[test]
+fn duck_carves_only_where_the_key_has_energy() {
+    let dir = TempDir::new().unwrap();
+    let music = dir.path().join("music.wav");
+    let voice = dir.path().join("voice.wav");
+    let output = dir.path().join("ducked.wav");
+    write_wav(&music, &multitone(&[300.0, 3000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    write_wav(&voice, &multitone(&[3000.0], 0.5, 1.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("duck").arg(&music)
+        .arg("--key").arg(&voice)
+        .arg("--output").arg(&output)
+        .args(["--depth", "12", "--threshold", "-40"])
+        .assert()
+        .success();
+
+    let (ducked, _) = read_wav(&output);
+    let full = 20.0 * TONE_AMPLITUDE.log10();
+    assert!((tone_level_db(&ducked, 44100, 3000.0) - (full - 12.0)).abs() < 0.5);
+    assert!((tone_level_db(&ducked, 44100, 300.0) - full).abs() < 0.5);
+}
+
+/// Header dictionary and payload of a `.npy` file.
+fn read_npy_header(path: &std::path::Path) -> (String, Vec<u8>) {
+    let bytes = std::fs::read(path).unwrap();
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    assert_eq!((10 + header_len) % 64, 0);
+    let header = String::from_utf8(bytes[10..10 + header_len].to_vec()).unwrap();
+    (header, bytes[10 + header_len..].to_vec())
+}
+
+/// Header dictionary and complex64 payload of a `.npy` file.
+fn read_npy(path: &std::path::Path) -> (String, Vec<(f32, f32)>) {
+    let (header, payload) = read_npy_header(path);
+    let values = payload
+        .chunks_exact(8)
+        .map(|c| (f32::from_le_bytes(c[..4].try_into().unwrap()), f32::from_le_bytes(c[4..].try_into().unwrap())))
+        .collect();
+    (header, values)
+}
+
+#[test]
+fn exports_stft_frames() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    let tone: Vec<f32> = multitone(&[1000.0], 0.5, 1.0, 44100).iter().flat_map(|&x| [x, 0.0]).collect();
+    write_wav(&input, &tone, 44100, 2);
+    let frames = dir.path().join("frames");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("out"))
+        .arg("--export-stft").arg(&frames)
+        .assert()
+        .success();
+
+    let layout: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(frames.join("stft.json")).unwrap()).unwrap();
+    assert_eq!(layout["hop"], 1024);
+    assert_eq!(layout["samples_per_channel"], 44100);
+    let (channels, count, bins) = (2, layout["frames"].as_u64().unwrap() as usize, 1025);
+
+    for stem in ["input", "low_freq", "high_freq"] {
+        let (header, values) = read_npy(&frames.join(format!("{}.npy", stem)));
+        assert!(header.contains("'descr': '<c8'"));
+        assert!(header.contains(&format!("'shape': ({}, {}, {})", channels, count, bins)));
+        assert_eq!(values.len(), channels * count * bins);
+    }
+
+    // A middle frame of the left channel peaks at the tone's bin; the right is silent
+    let (_, values) = read_npy(&frames.join("input.npy"));
+    let frame = &values[(count / 2) * bins..(count / 2 + 1) * bins];
+    let peak = (0..bins).max_by(|&a, &b| frame[a].0.hypot(frame[a].1).total_cmp(&frame[b].0.hypot(frame[b].1))).unwrap();
+    assert_eq!(peak, (1000.0f32 * 2048.0 / 44100.0).round() as usize);
+    assert!(values[count * bins..].iter().all(|&(re, im)| re == 0.0 && im == 0.0));
+}
+
+#[test]
+fn resynthesizes_exported_frames() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let tones: Vec<f32> = multitone(&[300.0, 3000.0], TONE_AMPLITUDE, 1.0, 44100).iter().flat_map(|&x| [x, -x]).collect();
+    write_wav(&input, &tones, 44100, 2);
+    let frames = dir.path().join("frames");
+    let output = dir.path().join("resynth.wav");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("out"))
+        .arg("--export-stft").arg(&frames)
+        .assert()
+        .success();
+    saunds().arg("resynth").arg(frames.join("input.npy")).arg("-o").arg(&output).assert().success();
+
+    let (samples, spec) = read_wav(&output);
+    assert_eq!((spec.channels, spec.sample_rate), (2, 44100));
+    assert_eq!(samples.len(), tones.len());
+    assert!(samples.iter().zip(&tones).all(|(a, b)| (a - b).abs() < 1e-4));
+
+    // The frames of the exported bands resynthesize to the rendered bands
+    saunds().arg("resynth").arg(frames.join("low_freq.npy")).arg("-o").arg(&output).assert().success();
+    let (low, _) = read_wav(&output);
+    let (rendered, _) = read_wav(&dir.path().join("out/low_freq.wav"));
+    assert!(low.iter().zip(&rendered).all(|(a, b)| (a - b).abs() < 1e-4));
+}
+
+#[test]
+fn writes_spectrogram_and_chroma() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    write_wav(&input, &multitone(&[440.0], 0.5, 1.0, 44100), 44100, 1);
+
+    let spectrogram = dir.path().join("cqt.npy");
+    saunds().arg("spectrogram").arg(&input).arg("-o").arg(&spectrogram).assert().success();
+    let (header, _) = read_npy_header(&spectrogram);
+    assert!(header.contains("'descr': '<f4'"));
+    assert!(header.contains("'shape': (87, 252)"), "{}", header);
+
+    let chroma = dir.path().join("chroma.npy");
+    saunds()
+        .arg("spectrogram").arg(&input)
+        .arg("-o").arg(&chroma)
+        .args(["--transform", "stft", "--chroma"])
+        .assert()
+        .success();
+    let (header, _) = read_npy_header(&chroma);
+    assert!(header.contains("'shape': (45, 12)"), "{}", header);
+    let axes: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("chroma.json")).unwrap()).unwrap();
+    assert_eq!(axes["hop"], 1024);
+    assert_eq!(axes["pitch_classes"][9], "A");
+}
+
+#[test]
+fn writes_self_contained_html_report() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    let amplitude = 10f32.powf(-23.0 / 20.0);
+    let tone: Vec<f32> = multitone(&[1000.0], amplitude, 2.0, 48000).iter().flat_map(|&x| [x, x]).collect();
+    write_wav(&input, &tone, 48000, 2);
+    let output = dir.path().join("reports");
+
+    saunds().arg("report").arg(&input).arg("-o").arg(&output).assert().success();
+
+    let html = std::fs::read_to_string(output.join("tone.html")).unwrap();
+    assert!(html.contains("<td>-23.0 LUFS</td>"), "{}", &html[..html.len().min(2000)]);
+    assert!(html.contains("data:image/bmp;base64,Qk"));
+    assert_eq!(html.matches("<svg").count(), 3);
+    assert!(!html.contains("src=\"http"));
+}
+
+#[test]
+fn sweeps_cutoffs_into_a_summary() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[150.0, 3000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    let output = dir.path().join("sweep");
+
+    let result = saunds()
+        .arg("sweep")
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--param", "low_cutoff=100..400:100", "--param", "window_size=1024,4096", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    let rows: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 8);
+    assert_eq!(rows[1]["params"]["low_cutoff"], 100.0);
+    assert_eq!(rows[1]["params"]["window_size"], 4096.0);
+
+    // The high band loses the 150 Hz tone once the low cutoff passes it
+    let high_db = |row: usize| rows[row]["high_db"].as_f64().unwrap();
+    assert!(high_db(0) - high_db(2) > 2.5, "{} vs {}", high_db(0), high_db(2));
+    assert!(rows.iter().all(|row| row["residual_db"].as_f64().unwrap() < -80.0));
+    assert!(output.join("low_cutoff=300,window_size=1024/high_freq.wav").exists());
+
+    saunds()
+        .arg("sweep")
+        .arg("--input").arg(&input)
+        .args(["--param", "order=2,4"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--filter iir"));
+}
+
+#[test]
+fn verifies_manifest_checksums() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let output = dir.path().join("out");
+    write_wav(&input, &multitone(&[100.0, 6000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+    saunds().arg("--input").arg(&input).arg("--output").arg(&output).assert().success();
+
+    let manifest = output.join("manifest.json");
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&manifest).unwrap()).unwrap();
+    assert_eq!(json["bands"][0]["sha256"].as_str().unwrap().len(), 64);
+
+    saunds()
+        .arg("verify").arg(&manifest)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("low_freq.wav: OK"));
+
+    let mut bytes = std::fs::read(output.join("high_freq.wav")).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    std::fs::write(output.join("high_freq.wav"), bytes).unwrap();
+    std::fs::remove_file(output.join("low_freq.wav")).unwrap();
+    saunds()
+        .arg("verify").arg(&manifest)
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("low_freq.wav: MISSING"))
+        .stdout(predicates::str::contains("high_freq.wav: MISMATCH"));
+}
+
+#[test]
+fn conforms_regions_from_an_edl() {
+    let dir = TempDir::new().unwrap();
+    let ramp: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+    let flat = vec![0.5f32; 1000];
+    write_wav(&dir.path().join("ramp.wav"), &ramp, 1000, 1);
+    write_wav(&dir.path().join("flat.wav"), &flat, 1000, 1);
+
+    std::fs::write(
+        dir.path().join("edit.csv"),
+        "source,in,out,gain_db,fade_in,fade_out\nramp.wav,100smp,200smp\nflat.wav,0.5,0.6,-6.0206,,10smp\n",
+    )
+    .unwrap();
+    let output = dir.path().join("program.wav");
+    saunds().arg("conform").arg(dir.path().join("edit.csv")).arg("-o").arg(&output).assert().success();
+
+    let (program, spec) = read_wav(&output);
+    assert_eq!((program.len(), spec.sample_rate), (200, 1000));
+    assert_eq!(&program[..100], &ramp[100..200]);
+    assert!((program[100] - 0.25).abs() < 1e-4);
+    // Linear fade over the last ten samples, reaching silence on the last
+    assert!((program[195] - 0.25 * 4.0 / 10.0).abs() < 1e-4);
+    assert_eq!(program[199], 0.0);
+
+    std::fs::write(dir.path().join("edit.json"), r#"[{"source": "ramp.wav", "in": "0smp", "out": 2.0}]"#).unwrap();
+    saunds()
+        .arg("conform").arg(dir.path().join("edit.json")).arg("-o").arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("has 1000 frames"));
+}
+
+#[test]
+fn exports_labeled_regions() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("take.wav");
+    let ramp: Vec<f32> = (0..4000).map(|i| i as f32 / 4000.0).collect();
+    write_wav(&input, &ramp, 1000, 1);
+
+    // A region, a repeated label and a point label running to the end
+    let labels = dir.path().join("labels.txt");
+    std::fs::write(&labels, "0.5\t1.0\tVerse: one\n1.0\t1.25\tVerse: one\n3.0\t3.0\tOutro\n").unwrap();
+    let output = dir.path().join("regions");
+    saunds().arg("regions").arg(&input).arg(&labels).arg("-o").arg(&output).assert().success();
+
+    let (verse, _) = read_wav(&output.join("Verse_ one.wav"));
+    assert_eq!(verse, &ramp[500..1000]);
+    assert_eq!(read_wav(&output.join("Verse_ one (2).wav")).0.len(), 250);
+    assert_eq!(read_wav(&output.join("Outro.wav")).0, &ramp[3000..]);
+
+    let cue = dir.path().join("album.cue");
+    std::fs::write(
+        &cue,
+        "FILE \"take.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Intro\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 00:02:30\n",
+    )
+    .unwrap();
+    let split = dir.path().join("split");
+    saunds()
+        .arg("regions").arg(&input).arg(&cue).arg("-o").arg(&split)
+        .args(["--split", "--low-cutoff", "50", "--high-cutoff", "200"])
+        .assert()
+        .success();
+    assert_eq!(read_wav(&split.join("Intro/low_freq.wav")).0.len(), 2400);
+    assert_eq!(read_wav(&split.join("Track 02/high_freq.wav")).0.len(), 1600);
+}
+
+#[test]
+fn album_mode_filters_across_track_boundaries() {
+    let dir = TempDir::new().unwrap();
+    let tracks = dir.path().join("album");
+    std::fs::create_dir(&tracks).unwrap();
+    let tone = multitone(&[80.0, 1000.0], TONE_AMPLITUDE, 1.0, 44100);
+    write_wav(&tracks.join("01 first.wav"), &tone[..30000], 44100, 1);
+    write_wav(&tracks.join("02 second.wav"), &tone[30000..], 44100, 1);
+    let whole = dir.path().join("whole.wav");
+    write_wav(&whole, &tone, 44100, 1);
+
+    let output = dir.path().join("out");
+    saunds().arg("album").arg(&tracks).arg("-o").arg(&output).args(["--filter", "iir"]).assert().success();
+    let reference = dir.path().join("reference");
+    saunds()
+        .arg("--input").arg(&whole)
+        .arg("--output").arg(&reference)
+        .args(["--filter", "iir", "--low-cutoff", "200", "--high-cutoff", "2000"])
+        .assert()
+        .success();
+
+    // The filters run on without resetting at the boundary
+    let mut joined = read_wav(&output.join("01 first/low_freq.wav")).0;
+    assert_eq!(joined.len(), 30000);
+    joined.extend(read_wav(&output.join("02 second/low_freq.wav")).0);
+    let (expected, _) = read_wav(&reference.join("low_freq.wav"));
+    assert_eq!(joined.len(), expected.len());
+    assert!(joined.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-6));
+
+    // One gain for the whole album
+    let normalized = dir.path().join("normalized");
+    saunds()
+        .arg("album").arg(&tracks).arg("-o").arg(&normalized)
+        .args(["--target-lufs", "-23"])
+        .assert()
+        .success();
+    let (second, _) = read_wav(&normalized.join("02 second/high_freq.wav"));
+    let album_lufs = loudness(&tone).integrated_lufs;
+    let expected_db = 20.0 * TONE_AMPLITUDE.log10() - 23.0 - album_lufs;
+    assert!((tone_level_db(&second, 44100, 1000.0) - expected_db).abs() < 0.2);
+}
+
+#[test]
+fn normalizes_from_a_measured_stats_sidecar() {
+    let dir = TempDir::new().unwrap();
+    let quiet = dir.path().join("quiet.wav");
+    let loud = dir.path().join("loud.wav");
+    write_wav(&quiet, &multitone(&[440.0], 0.05, 2.0, 44100), 44100, 1);
+    write_wav(&loud, &noise(88200, 0.9, 3), 44100, 1);
+
+    // Measure in two batches, as separate workers would
+    let first = dir.path().join("first.json");
+    let second = dir.path().join("second.json");
+    saunds().arg("measure").arg(&quiet).arg("--stats").arg(&first).assert().success();
+    saunds().arg("measure").arg(&loud).arg("--stats").arg(&second).assert().success();
+    let stats: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&first).unwrap()).unwrap();
+    assert_eq!(stats["files"].as_array().unwrap().len(), 1);
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("normalize").arg(&loud).arg(&quiet)
+        .arg("--stats").arg(&first)
+        .arg("--stats").arg(&second)
+        .arg("-o").arg(&output)
+        .args(["--target-lufs", "-14", "--ceiling", "-3"])
+        .assert()
+        .success();
+
+    let (normalized, _) = read_wav(&output.join("quiet.wav"));
+    let measured = loudness(&normalized);
+    assert!((measured.integrated_lufs + 14.0).abs() < 0.1);
+    // The noise peaks well above the ceiling once normalized and is limited
+    let (limited, _) = read_wav(&output.join("loud.wav"));
+    let ceiling = 10f32.powf(-3.0 / 20.0);
+    assert!(normalized.iter().chain(&limited).all(|sample| sample.abs() <= ceiling + 1e-4));
+
+    // Files missing from every sidecar are refused
+    let other = dir.path().join("other.wav");
+    write_wav(&other, &noise(4410, 0.1, 4), 44100, 1);
+    saunds()
+        .arg("normalize").arg(&other)
+        .arg("--stats").arg(&first)
+        .arg("-o").arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No stats"));
+}
+
+#[test]
+fn normalizes_an_album_by_one_gain() {
+    let dir = TempDir::new().unwrap();
+    let quiet = dir.path().join("01 quiet.wav");
+    let loud = dir.path().join("02 loud.wav");
+    write_wav(&quiet, &multitone(&[440.0], 0.02, 2.0, 44100), 44100, 1);
+    write_wav(&loud, &multitone(&[440.0], 0.2, 2.0, 44100), 44100, 1);
+
+    let stats = dir.path().join("stats.json");
+    saunds()
+        .arg("measure").arg(&quiet).arg(&loud)
+        .arg("--stats").arg(&stats)
+        .arg("--album")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("album gain"));
+    let sidecar: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&stats).unwrap()).unwrap();
+    let gains: Vec<&serde_json::Value> = sidecar["files"].as_array().unwrap().iter().map(|file| &file["replaygain"]).collect();
+    // 20 dB apart per track, one gain and peak for the album
+    let track_gain = |i: usize| gains[i]["track_gain_db"].as_f64().unwrap();
+    assert!((track_gain(0) - track_gain(1) - 20.0).abs() < 0.1, "{:?}", gains);
+    assert_eq!(gains[0]["album_gain_db"], gains[1]["album_gain_db"]);
+    assert!((gains[0]["album_peak"].as_f64().unwrap() - 0.2).abs() < 0.01, "{:?}", gains);
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("normalize").arg(&quiet).arg(&loud)
+        .arg("--stats").arg(&stats)
+        .arg("--album")
+        .arg("-o").arg(&output)
+        .args(["--target-lufs", "-20"])
+        .assert()
+        .success();
+    let (quiet, _) = read_wav(&output.join("01 quiet.wav"));
+    let (loud, _) = read_wav(&output.join("02 loud.wav"));
+    let level = |samples: &[f32]| loudness(samples).integrated_lufs;
+    assert!((level(&loud) - level(&quiet) - 20.0).abs() < 0.1);
+    // The quiet track falls below the album's relative gate
+    assert!((level(&loud) + 20.0).abs() < 0.1);
+}
+
+#[test]
+fn normalizes_bands_linked_by_their_sum() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in.wav");
+    let bands = dir.path().join("bands");
+    write_wav(&input, &multitone(&[100.0, 3000.0], TONE_AMPLITUDE, 2.0, 44100), 44100, 1);
+    saunds().arg("--input").arg(&input).arg("--output").arg(&bands).assert().success();
+    let (low, _) = read_wav(&bands.join("low_freq.wav"));
+    let (high, _) = read_wav(&bands.join("high_freq.wav"));
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("normalize").arg(bands.join("low_freq.wav")).arg(bands.join("high_freq.wav"))
+        .arg("--linked")
+        .arg("-o").arg(&output)
+        .args(["--target-lufs", "-20"])
+        .assert()
+        .success();
+    let (low_out, _) = read_wav(&output.join("low_freq.wav"));
+    let (high_out, _) = read_wav(&output.join("high_freq.wav"));
+    let sum: Vec<f32> = low_out.iter().zip(&high_out).map(|(a, b)| a + b).collect();
+    let measured = loudness(&sum);
+    assert!((measured.integrated_lufs + 20.0).abs() < 0.1, "{:?}", measured);
+    // Both bands get the same gain, keeping their balance
+    let gain = low_out[22050] / low[22050];
+    assert!(high_out.iter().zip(&high).all(|(out, band)| (out - band * gain).abs() < 1e-5));
+
+    // A target that would push a band over the ceiling lowers the shared gain
+    let capped = dir.path().join("capped");
+    saunds()
+        .arg("normalize").arg(bands.join("low_freq.wav")).arg(bands.join("high_freq.wav"))
+        .arg("--linked")
+        .arg("-o").arg(&capped)
+        .args(["--target-lufs", "0", "--ceiling", "-1"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("over the ceiling"));
+    let (low_capped, _) = read_wav(&capped.join("low_freq.wav"));
+    let (high_capped, _) = read_wav(&capped.join("high_freq.wav"));
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(low_capped.iter().chain(&high_capped).all(|sample| sample.abs() <= ceiling + 1e-4));
+    let gain = low_capped[22050] / low[22050];
+    assert!(high_capped.iter().zip(&high).all(|(out, band)| (out - band * gain).abs() < 1e-5));
+}
+
+#[test]
+fn writes_low_band_at_reduced_rate() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in.wav");
+    let output = dir.path().join("out");
+    write_wav(&input, &multitone(&[60.0, 5000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--low-cutoff", "150", "--high-cutoff", "300", "--band-rate", "low=8000"])
+        .assert()
+        .success();
+
+    let (low, spec) = read_wav(&output.join("low_freq.wav"));
+    assert_eq!(spec.sample_rate, 8000);
+    assert_eq!(low.len(), 8000);
+    assert!((tone_level_db(&low, 8000, 60.0) - 20.0 * TONE_AMPLITUDE.log10()).abs() < 0.5);
+    let (_, spec) = read_wav(&output.join("high_freq.wav"));
+    assert_eq!(spec.sample_rate, 44100);
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["bands"][0]["sample_rate"], 8000);
+    assert!(manifest["bands"][1].get("sample_rate").is_none());
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--band-rate", "low=96000"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn reports_and_compensates_filter_latency() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in.wav");
+    let tone = multitone(&[50.0], TONE_AMPLITUDE, 1.0, 44100);
+    write_wav(&input, &tone, 44100, 1);
+    let render = |name: &str, args: &[&str]| {
+        let output = dir.path().join(name);
+        saunds()
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&output)
+            .args(["--low-cutoff", "500", "--high-cutoff", "1000"])
+            .args(args)
+            .assert()
+            .success();
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+        (read_wav(&output.join("low_freq.wav")).0, manifest["bands"][0]["latency_samples"].as_f64().unwrap())
+    };
+    // Lag that best lines the band up with the input, away from the edges
+    let lag = |band: &[f32]| {
+        (-100i32..=100)
+            .max_by(|&a, &b| {
+                let score = |lag: i32| -> f32 {
+                    (10000..30000).map(|i| tone[i] * band[(i as i32 + lag) as usize]).sum()
+                };
+                score(a).total_cmp(&score(b))
+            })
+            .unwrap()
+    };
+
+    let (_, latency) = render("fft", &[]);
+    assert_eq!(latency, 0.0);
+
+    let (delayed, latency) = render("iir", &["--filter", "iir"]);
+    assert!(latency > 5.0, "latency {}", latency);
+    assert!((lag(&delayed) as f64 - latency).abs() <= 2.0);
+
+    let (compensated, residual) = render("compensated", &["--filter", "iir", "--compensate-latency"]);
+    assert_eq!(compensated.len(), tone.len());
+    assert!(residual.abs() <= 0.5);
+    assert!(lag(&compensated).abs() <= 2);
+}
+
+#[test]
+fn batch_mode_honours_sidecar_configs() {
+    let dir = TempDir::new().unwrap();
+    let inputs = dir.path().join("in");
+    std::fs::create_dir(&inputs).unwrap();
+    let tone = multitone(&[80.0, 3000.0], TONE_AMPLITUDE, 0.5, 44100);
+    write_wav(&inputs.join("a.wav"), &tone, 44100, 1);
+    write_wav(&inputs.join("b.wav"), &tone, 44100, 1);
+    std::fs::write(inputs.join("notes.txt"), "not audio").unwrap();
+    std::fs::write(
+        inputs.join("b.wav.saunds.toml"),
+        "low-cutoff = 100\nhigh-cutoff = 400.5\nfilter = \"zero-phase\"\ncompensate-latency = true\n",
+    )
+    .unwrap();
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("--input").arg(&inputs)
+        .arg("--output").arg(&output)
+        .args(["--high-cutoff", "1000", "--filter", "iir"])
+        .assert()
+        .success();
+
+    let manifest = |name: &str| -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(output.join(name).join("manifest.json")).unwrap()).unwrap()
+    };
+    let a = manifest("a");
+    assert_eq!(a["bands"][0]["high_hz"], 1000.0);
+    assert_eq!(a["bands"][1]["low_hz"], 200.0);
+    assert!(a["bands"][0]["latency_samples"].as_f64().unwrap() > 0.0);
+    let b = manifest("b");
+    assert_eq!(b["bands"][0]["high_hz"], 400.5);
+    assert_eq!(b["bands"][1]["low_hz"], 100.0);
+    assert_eq!(b["bands"][0]["latency_samples"], 0.0);
+    assert!(!output.join("notes").exists());
+
+    // A bad sidecar fails that file and the run, but not the others
+    std::fs::write(inputs.join("a.wav.saunds.toml"), "low-cutof = 100\n").unwrap();
+    let failed = dir.path().join("failed");
+    saunds()
+        .arg("--input").arg(&inputs)
+        .arg("--output").arg(&failed)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("unknown option 'low-cutof'"))
+        .stderr(predicates::str::contains("1 of 2 files failed"));
+    assert!(failed.join("b/low_freq.wav").exists());
+}
+
+#[test]
+fn batch_mode_filters_inputs() {
+    let dir = TempDir::new().unwrap();
+    let inputs = dir.path().join("in");
+    std::fs::create_dir(&inputs).unwrap();
+    for (name, seconds) in [("take1.wav", 0.5), ("take2.wav", 2.0), ("take3.wav", 0.1), ("take4_old.wav", 0.5), ("room.wav", 0.5)] {
+        write_wav(&inputs.join(name), &multitone(&[440.0], TONE_AMPLITUDE, seconds, 8000), 8000, 1);
+    }
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("--input").arg(&inputs)
+        .arg("--output").arg(&output)
+        .args(["--include", "take?*.wav", "--exclude", "*_old.*"])
+        .args(["--min-duration", "0.25", "--max-duration", "1.5"])
+        .args(["--high-cutoff", "1000"])
+        .assert()
+        .success();
+    let mut written: Vec<String> = std::fs::read_dir(&output)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["take1"]);
+
+    saunds()
+        .arg("--input").arg(&inputs)
+        .arg("--output").arg(&output)
+        .args(["--include", "*.mp3"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No matching"));
+}
+
+#[test]
+fn batch_mode_decodes_ahead_with_each_files_options() {
+    let dir = TempDir::new().unwrap();
+    let inputs = dir.path().join("in");
+    std::fs::create_dir(&inputs).unwrap();
+    write_wav(&inputs.join("a.wav"), &multitone(&[440.0], TONE_AMPLITUDE, 0.5, 8000), 8000, 1);
+    let corrupt = std::fs::read(corrupt_mp3(dir.path(), 40, 20)).unwrap();
+    std::fs::write(inputs.join("b.mp3"), &corrupt).unwrap();
+    std::fs::write(inputs.join("b.mp3.saunds.toml"), "on-decode-error = \"fail\"\n").unwrap();
+    std::fs::write(inputs.join("c.mp3"), &corrupt).unwrap();
+
+    let output = dir.path().join("out");
+    saunds()
+        .arg("--input").arg(&inputs)
+        .arg("--output").arg(&output)
+        .args(["--on-decode-error", "skip"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Corrupt MP3 data"))
+        .stderr(predicates::str::contains("1 of 3 files failed"));
+    assert!(output.join("a/low_freq.wav").exists());
+    assert!(!output.join("b/manifest.json").exists());
+    assert_eq!(read_wav(&output.join("c/low_freq.wav")).0.len(), 38 * MP3_FRAME_SAMPLES * 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_success_and_failure_hooks() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    let output = dir.path().join("out");
+    let log = dir.path().join("hook.log");
+    write_wav(&input, &multitone(&[440.0], TONE_AMPLITUDE, 0.5, 8000), 8000, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .arg("--on-success").arg(format!("printf '%s %s\\n' \"$SAUNDS_STATUS\" >> '{}'", log.display()))
+        .assert()
+        .success();
+    let manifest = output.join("manifest.json");
+    assert!(manifest.exists());
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), format!("success {}\n", manifest.display()));
+
+    // A corrupt input fails the split and fires only the failure hook
+    std::fs::remove_file(&log).unwrap();
+    std::fs::write(&input, b"not a wave file").unwrap();
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .arg("--on-success").arg(format!("echo wrong >> '{}'", log.display()))
+        .arg("--on-failure").arg(format!("test -n \"$SAUNDS_ERROR\" && printf '%s %s\\n' \"$SAUNDS_STATUS\" >> '{}'", log.display()))
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), format!("failure {}\n", manifest.display()));
+
+    // A failing success hook fails the run
+    write_wav(&input, &multitone(&[440.0], TONE_AMPLITUDE, 0.5, 8000), 8000, 1);
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--on-success", "false"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Hook 'false' exited"));
+}
+
+/// Stands in for the AWS CLI, keeping buckets under `$FAKE_S3`.
+#[cfg(unix)]
+const FAKE_AWS: &str = r#"#!/bin/sh
+op=$2; from=$4; to=$5
+map() { case $1 in s3://*) echo "$FAKE_S3/${1#s3://}";; *) echo "$1";; esac; }
+from=$(map "$from"); to=$(map "$to")
+if [ "$op" = sync ]; then mkdir -p "$to" && cp -R "$from"/. "$to"
+else mkdir -p "$(dirname "$to")" && cp "$from" "$to"; fi
+"#;
+
+#[cfg(unix)]
+#[test]
+fn splits_from_and_to_object_storage() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let bin = dir.path().join("bin");
+    let buckets = dir.path().join("s3");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::create_dir_all(buckets.join("media/in")).unwrap();
+    let aws = bin.join("aws");
+    std::fs::write(&aws, FAKE_AWS).unwrap();
+    std::fs::set_permissions(&aws, std::fs::Permissions::from_mode(0o755)).unwrap();
+    write_wav(&buckets.join("media/in/mix.wav"), &multitone(&[100.0, 3000.0], TONE_AMPLITUDE, 0.5, 8000), 8000, 1);
+
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    saunds()
+        .env("PATH", &path)
+        .env("FAKE_S3", &buckets)
+        .args(["--input", "s3://media/in/mix.wav", "--output", "s3://media/out/mix"])
+        .assert()
+        .success();
+    let uploaded = buckets.join("media/out/mix");
+    assert!(uploaded.join("manifest.json").exists());
+    assert!(uploaded.join("low_freq.wav").exists());
+
+    // A missing object fails the run
+    saunds()
+        .env("PATH", &path)
+        .env("FAKE_S3", &buckets)
+        .args(["--input", "s3://media/in/absent.wav", "--output", "s3://media/out/absent"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Copying s3://media/in/absent.wav"));
+}
+
+#[test]
+fn saves_and_rerenders_a_project() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    let project = dir.path().join("mix.toml");
+    write_wav(&input, &multitone(&[100.0, 3000.0], TONE_AMPLITUDE, 0.5, 8000), 8000, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("first"))
+        .args(["--low-cutoff", "150", "--fx", "saturate:drive=2", "--marker", "0.25", "--marker", "0.1"])
+        .arg("--save-project").arg(&project)
+        .assert()
+        .success();
+    let saved = std::fs::read_to_string(&project).unwrap();
+    assert!(saved.contains("low-cutoff = 150\n"), "{}", saved);
+    assert!(saved.contains("fx = [\"saturate:drive=2\"]\n"), "{}", saved);
+    assert!(saved.contains("marker = [0.25, 0.1]\n"), "{}", saved);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("first/markers.txt")).unwrap(),
+        "0.250000\t0.250000\tMarker 1\n0.100000\t0.100000\tMarker 2\n"
+    );
+
+    let second = dir.path().join("second");
+    saunds().args(["project", "render"]).arg(&project).arg("--output").arg(&second).assert().success();
+    for file in ["low_freq.wav", "high_freq.wav", "markers.txt"] {
+        assert_eq!(
+            std::fs::read(dir.path().join("first").join(file)).unwrap(),
+            std::fs::read(second.join(file)).unwrap(),
+            "{} differs",
+            file
+        );
+    }
+
+    let shown = saunds().args(["project", "show"]).arg(&project).output().unwrap();
+    let shown = String::from_utf8(shown.stdout).unwrap();
+    assert!(shown.contains("--low-cutoff=150") && shown.contains("--marker=0.1"), "{}", shown);
+}
+
+#[test]
+fn undoes_in_place_normalization() {
+    let dir = TempDir::new().unwrap();
+    let track = dir.path().join("track.wav");
+    let stats = dir.path().join("stats.json");
+    write_wav(&track, &multitone(&[440.0], 0.05, 1.0, 8000), 8000, 1);
+    let original = std::fs::read(&track).unwrap();
+
+    saunds().arg("measure").arg(&track).arg("--stats").arg(&stats).assert().success();
+    saunds()
+        .arg("normalize").arg(&track)
+        .arg("--stats").arg(&stats)
+        .args(["--in-place", "--target-lufs", "-14"])
+        .assert()
+        .success();
+    let edited = std::fs::read(&track).unwrap();
+    assert_ne!(edited, original);
+    let listed = saunds().arg("undo").arg(&track).arg("--list").output().unwrap();
+    assert!(String::from_utf8(listed.stdout).unwrap().ends_with("\tnormalize\n"));
+
+    // A file changed after the edit is only restored with --force
+    std::fs::write(&track, b"changed").unwrap();
+    saunds().arg("undo").arg(&track).assert().failure().stderr(predicates::str::contains("--force"));
+    std::fs::write(&track, &edited).unwrap();
+
+    saunds().arg("undo").arg(&track).assert().success();
+    assert_eq!(std::fs::read(&track).unwrap(), original);
+    saunds().arg("undo").arg(&track).assert().failure().stderr(predicates::str::contains("No journaled edits"));
+}
+
+#[test]
+fn prints_completions_and_schema() {
+    let completions = saunds().args(["completions", "bash"]).output().unwrap();
+    assert!(completions.status.success());
+    assert!(String::from_utf8(completions.stdout).unwrap().contains("--low-cutoff"));
+
+    let schema = saunds().args(["schema", "--json"]).output().unwrap();
+    assert!(schema.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&schema.stdout).unwrap();
+    let arg = |command: &serde_json::Value, id: &str| {
+        command["args"].as_array().unwrap().iter().find(|arg| arg["id"] == id).cloned().unwrap()
+    };
+    let low_cutoff = arg(&schema, "low_cutoff");
+    assert_eq!(low_cutoff["long"], "low-cutoff");
+    assert_eq!(low_cutoff["default"][0], "200");
+    assert!(arg(&schema, "format")["choices"].as_array().unwrap().iter().any(|choice| choice["name"] == "aiff"));
+
+    let normalize = schema["subcommands"].as_array().unwrap().iter().find(|sub| sub["name"] == "normalize").unwrap();
+    let in_place = arg(normalize, "in_place");
+    assert_eq!(in_place["flag"], true);
+    assert_eq!(in_place["conflicts_with"][0], "output");
+    assert_eq!(arg(normalize, "inputs")["positional"], true);
+}
+
+#[test]
+fn repl_runs_commands_on_the_loaded_buffer() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    write_wav(&input, &multitone(&[440.0], TONE_AMPLITUDE, 1.0, 8000), 8000, 1);
+    let (trimmed, full) = (dir.path().join("trimmed.wav"), dir.path().join("full.wav"));
+
+    let script = format!(
+        "info\ngain -6\ntrim 0.25 0.75\nbogus\nsave {}\nundo\nsave {}\nquit\ninfo\n",
+        trimmed.display(),
+        full.display()
+    );
+    let output = saunds().arg("repl").arg(&input).write_stdin(script).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // Nothing runs after quit
+    assert_eq!(stdout, "8000 Hz, 1 channels, 8000 frames (1.000 s)\n");
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Unknown command 'bogus'"));
+
+    let (trimmed, _) = read_wav(&trimmed);
+    assert_eq!(trimmed.len(), 4000);
+    let (full, _) = read_wav(&full);
+    assert_eq!(full.len(), 8000);
+    let level = tone_level_db(&full, 8000, 440.0);
+    let expected = 20.0 * TONE_AMPLITUDE.log10() - 6.0;
+    assert!((level - expected).abs() < 0.5, "level {} dB, expected {} dB", level, expected);
+}
+
+#[test]
+fn previews_the_loudest_excerpt() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    let mut samples = multitone(&[440.0], 0.01, 3.0, 8000);
+    for (sample, loud) in samples[16000..20000].iter_mut().zip(multitone(&[440.0], 0.5, 0.5, 8000)) {
+        *sample = loud;
+    }
+    write_wav(&input, &samples, 8000, 1);
+
+    let output = dir.path().join("loudest");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--preview", "0.5s", "--preview-pick", "loudest"])
+        .assert()
+        .success();
+    let (low, _) = read_wav(&output.join("low_freq.wav"));
+    assert_eq!(low.len(), 4000);
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    // The burst starts at a zero crossing, so a frame either way is as loud
+    assert!((manifest["preview"]["start_seconds"].as_f64().unwrap() - 2.0).abs() < 0.001);
+    assert_eq!(manifest["preview"]["duration_seconds"], 0.5);
+
+    let output = dir.path().join("chosen");
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--preview", "10", "--preview-start", "0:02.5"])
+        .assert()
+        .success();
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["preview"]["start_seconds"], 2.5);
+    assert_eq!(manifest["preview"]["duration_seconds"], 0.5);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--preview", "ten"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid time 'ten'"));
+}
+
+#[test]
+fn reports_skipped_silent_windows() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("speech.wav");
+    let mut samples = multitone(&[440.0], TONE_AMPLITUDE, 0.5, 44100);
+    samples.resize(44100 * 2, 0.0);
+    write_wav(&input, &samples, 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("out"))
+        .assert()
+        .success()
+        .stderr(predicates::str::is_match(r"Skipped \d+ of \d+ windows \(7\d\.\d%\) as silent").unwrap());
+}
+
+#[test]
+fn streams_inputs_over_the_memory_budget() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("long.wav");
+    let tones = multitone(&[100.0, 1000.0, 6000.0], TONE_AMPLITUDE, 2.0, 44100);
+    let stereo: Vec<f32> = tones.iter().flat_map(|&sample| [sample, -0.5 * sample]).collect();
+    write_wav(&input, &stereo, 44100, 2);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("whole"))
+        .args(["--max-memory", "1G"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("within --max-memory"));
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("streamed"))
+        .args(["--max-memory", "1M"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("streaming in chunks"));
+
+    for band in ["low_freq.wav", "high_freq.wav"] {
+        let (whole, _) = read_wav(&dir.path().join("whole").join(band));
+        let (streamed, spec) = read_wav(&dir.path().join("streamed").join(band));
+        assert_eq!(spec.channels, 2);
+        assert_eq!(streamed.len(), whole.len());
+        let error = whole.iter().zip(&streamed).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        assert!(error < 1e-4, "{} differs by {}", band, error);
+    }
+    assert!(dir.path().join("streamed/manifest.json").exists());
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("fx"))
+        .args(["--max-memory", "1M", "--fx", "saturate:drive=2"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("streaming doesn't support --fx"));
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("tiny"))
+        .args(["--max-memory", "64K"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("too small to stream"));
+}
+
+#[test]
+fn prints_stage_timings() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+
+    let output = saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(dir.path().join("out"))
+        .arg("--timings")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report = String::from_utf8(output.stdout).unwrap();
+    let stages: Vec<&str> = report.lines().skip(1).filter_map(|line| line.split_whitespace().next()).collect();
+    assert_eq!(stages, ["decode", "window", "fft", "mask", "ifft", "overlap-add", "encode", "total"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_stops_cleanly_without_partial_files() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("long.wav");
+    write_wav(&input, &noise(44100 * 120, 0.5, 7), 44100, 1);
+
+    for (output, budget) in [("whole", "1G"), ("streamed", "1M")] {
+        let output = dir.path().join(output);
+        let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("saunds_v2"))
+            .arg("--input").arg(&input)
+            .arg("--output").arg(&output)
+            .args(["--max-memory", budget])
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(700));
+        std::process::Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+        let result = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert_eq!(result.status.code(), Some(130), "{}", stderr);
+        assert!(stderr.contains("Stopped"), "{}", stderr);
+
+        assert!(!output.join("manifest.json").exists());
+        for entry in std::fs::read_dir(&output).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().unwrap() == ".saunds.lock" {
+                continue;
+            }
+            assert_eq!(path.extension().unwrap(), "wav", "left {}", path.display());
+            // Whatever is left must be a complete, readable file
+            read_wav(&path);
+        }
+    }
+}
+
+#[test]
+fn waits_for_another_run_writing_the_same_output() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+    let output = dir.path().join("out");
+    std::fs::create_dir(&output).unwrap();
+
+    let held = std::fs::File::create(output.join(".saunds.lock")).unwrap();
+    held.lock().unwrap();
+    let child = std::process::Command::new(assert_cmd::cargo::cargo_bin("saunds_v2"))
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(!output.join("manifest.json").exists());
+
+    drop(held);
+    let result = child.wait_with_output().unwrap();
+    assert!(result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("Waiting for another saunds run"));
+    assert!(output.join("manifest.json").exists());
+}
+
+#[test]
+fn records_warnings_in_the_manifest() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[100.0, 1000.0], TONE_AMPLITUDE, 0.5, 44100), 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--band-rate", "high=16000"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Finished with 1 warning:"));
+
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    let warnings = manifest["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["source"], "saunds_v2");
+    assert!(warnings[0]["message"].as_str().unwrap().contains("filtered out at 16000 Hz"));
+
+    // A clean split records none
+    saunds().arg("--input").arg(&input).arg("--output").arg(&output).assert().success();
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    assert!(manifest.get("warnings").is_none());
+}
+
+#[test]
+fn reports_clipping_and_band_headroom() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("square.wav");
+    let square: Vec<f32> = (0..22050).map(|i| if (i / 220) % 2 == 0 { 1.0 } else { -1.0 }).collect();
+    write_wav(&input, &square, 44100, 1);
+    let output = dir.path().join("out");
+
+    saunds().arg("--input").arg(&input).arg("--output").arg(&output).assert().success();
+
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["input_peaks"]["clipped_samples"], 22050);
+    let messages: Vec<&str> = manifest["warnings"].as_array().unwrap().iter().map(|w| w["message"].as_str().unwrap()).collect();
+    assert!(messages.iter().any(|message| message.contains("22050 clipped samples")), "{:?}", messages);
+    for band in manifest["bands"].as_array().unwrap() {
+        let peaks = &band["peaks"];
+        assert!(peaks["true_peak_dbtp"].as_f64().unwrap() >= peaks["sample_peak_dbfs"].as_f64().unwrap());
+    }
+    // The square's harmonics ring past full scale once split
+    assert!(messages.iter().any(|message| message.contains("inter-sample peaks")), "{:?}", messages);
+}
+
+#[test]
+fn scales_bands_to_the_headroom_target() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("loud.wav");
+    write_wav(&input, &multitone(&[110.0, 5000.0], 0.49, 1.0, 44100), 44100, 1);
+
+    let manifest_of = |output: &std::path::Path| -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap()
+    };
+    let per_band = dir.path().join("per_band");
+    saunds()
+        .arg("--input").arg(&input).arg("--output").arg(&per_band)
+        .arg("--headroom").arg("12dB")
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("for 12.0 dB of headroom"));
+    for band in manifest_of(&per_band)["bands"].as_array().unwrap() {
+        assert!(band["peaks"]["true_peak_dbtp"].as_f64().unwrap() <= -11.99, "{}", band);
+        assert!(band["gain_db"].as_f64().unwrap() < 0.0, "{}", band);
+    }
+
+    let linked = dir.path().join("linked");
+    saunds()
+        .arg("--input").arg(&input).arg("--output").arg(&linked)
+        .arg("--headroom").arg("12").arg("--headroom-scaling").arg("linked")
+        .assert()
+        .success();
+    let bands = manifest_of(&linked)["bands"].as_array().unwrap().clone();
+    assert_eq!(bands[0]["gain_db"], bands[1]["gain_db"]);
+    let peaks: Vec<f64> = bands.iter().map(|band| band["peaks"]["true_peak_dbtp"].as_f64().unwrap()).collect();
+    assert!(peaks.iter().all(|&peak| peak <= -11.99), "{:?}", peaks);
+    assert!(peaks.iter().any(|&peak| peak < -12.01), "{:?}", peaks);
+}
+
+#[test]
+fn matches_the_tilt_of_a_reference() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("white.wav");
+    write_wav(&input, &noise(3 * 44100, 0.3, 11), 44100, 1);
+
+    let pink = dir.path().join("pink.wav");
+    saunds()
+        .arg("match-tilt").arg(&input).arg("--pink")
+        .arg("-o").arg(&pink)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("dB/octave around 1000 Hz"));
+
+    // Matching the pinked copy brings the white noise to the same tilt
+    let matched = dir.path().join("matched.wav");
+    saunds()
+        .arg("match-tilt").arg(&input).arg("--reference").arg(&pink)
+        .arg("-o").arg(&matched)
+        .assert()
+        .success();
+    let (pinked, _) = read_wav(&pink);
+    let (matched, _) = read_wav(&matched);
+    let tilt = saunds_v2::audio::matching::spectral_tilt(&matched, 44100).unwrap();
+    let target = saunds_v2::audio::matching::spectral_tilt(&pinked, 44100).unwrap();
+    assert!((tilt - target).abs() < 0.2, "{} vs {}", tilt, target);
+}
+
+#[test]
+fn matches_a_reference_spectrum_within_the_boost_limit() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in.wav");
+    let source = noise(3 * 44100, 0.05, 5);
+    write_wav(&input, &source, 44100, 1);
+    let reference = dir.path().join("mastered.wav");
+    write_wav(&reference, &source.iter().map(|x| x * 10.0).collect::<Vec<_>>(), 44100, 1);
+
+    let output = dir.path().join("matched.wav");
+    saunds()
+        .arg("match").arg(&input).arg("--reference").arg(&reference)
+        .arg("-o").arg(&output)
+        .args(["--smoothing", "1/6oct", "--max-boost", "6"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Boost held at 6 dB"));
+
+    let (matched, _) = read_wav(&output);
+    let rms = |samples: &[f32]| (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt();
+    let gain_db = 20.0 * (rms(&matched) / rms(&source)).log10();
+    assert!((gain_db - 6.0).abs() < 0.3, "{}", gain_db);
+
+    saunds()
+        .arg("match").arg(&input).arg("--reference").arg(&reference)
+        .arg("-o").arg(&output)
+        .args(["--smoothing", "third"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid smoothing"));
+}
+
+#[test]
+fn turns_down_breaths_between_phrases() {
+    let dir = TempDir::new().unwrap();
+    let rate = 44100;
+    let phrase = multitone(&[150.0, 300.0, 450.0, 600.0, 900.0], 0.1, 1.0, rate);
+    let room = |seconds: f32, seed| noise((seconds * rate as f32) as usize, 0.0005, seed);
+    let breath: Vec<f32> = noise(rate as usize * 3 / 10, 0.03, 4).iter().zip(room(0.3, 5)).map(|(b, r)| b + r).collect();
+    let voice: Vec<f32> = [phrase.clone(), room(0.3, 1), breath.clone(), room(0.3, 2), phrase.clone()].concat();
+    let input = dir.path().join("voice.wav");
+    write_wav(&input, &voice, rate, 1);
+
+    let output = dir.path().join("clean.wav");
+    let labels = dir.path().join("breaths.txt");
+    saunds()
+        .arg("debreath").arg(&input)
+        .arg("-o").arg(&output)
+        .arg("--labels").arg(&labels)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Found 1 breaths"));
+
+    let label = std::fs::read_to_string(&labels).unwrap();
+    let times: Vec<f64> = label.split('\t').take(2).map(|time| time.parse().unwrap()).collect();
+    assert!((times[0] - 1.3).abs() < 0.05 && (times[1] - 1.6).abs() < 0.05, "{}", label);
+
+    let (clean, _) = read_wav(&output);
+    let rms = |samples: &[f32]| (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt();
+    let breath_at = |samples: &[f32]| rms(&samples[rate as usize * 14 / 10..rate as usize * 15 / 10]);
+    assert!(20.0 * (breath_at(&clean) / breath_at(&voice)).log10() < -11.0);
+    // The phrases pass untouched
+    assert_eq!(&clean[..rate as usize], &voice[..rate as usize]);
+}
+
+#[test]
+fn fills_silent_gaps_with_matching_room_tone() {
+    let dir = TempDir::new().unwrap();
+    let rate = 44100usize;
+    let room = noise(4 * rate, 0.01, 8);
+    let speech = multitone(&[200.0, 700.0], 0.2, 1.0, rate as u32);
+    let mut recording: Vec<f32> = speech.iter().zip(&room).map(|(s, r)| s + r).chain(room[rate..].iter().copied()).collect();
+    recording[2 * rate..3 * rate].fill(0.0);
+    let input = dir.path().join("trimmed.wav");
+    write_wav(&input, &recording, rate as u32, 1);
+
+    let output = dir.path().join("filled.wav");
+    saunds()
+        .arg("fill-gaps").arg(&input)
+        .arg("-o").arg(&output)
+        .args(["--profile-length", "0.5"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Filled 1 gaps"));
+
+    let (filled, _) = read_wav(&output);
+    let rms_db = |samples: &[f32]| 10.0 * (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).log10();
+    let gap = &filled[2 * rate + 1000..3 * rate - 1000];
+    assert!((rms_db(gap) - rms_db(&room[rate..2 * rate])).abs() < 1.0, "{} vs {}", rms_db(gap), rms_db(&room));
+    assert_eq!(&filled[..2 * rate], &recording[..2 * rate]);
+
+    saunds()
+        .arg("fill-gaps").arg(&input)
+        .arg("-o").arg(&output)
+        .args(["--profile", "1.5-1"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("the end must be after the start"));
+}
+
+#[test]
+fn scores_bands_against_reference_stems() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("mix.wav");
+    let low = dir.path().join("bass.wav");
+    let high = dir.path().join("hats.wav");
+    let output = dir.path().join("out");
+    write_wav(&input, &multitone(&[100.0, 6000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    write_wav(&low, &multitone(&[100.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+    write_wav(&high, &multitone(&[6000.0], TONE_AMPLITUDE, 1.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .arg("--reference").arg(&low)
+        .arg("--reference").arg(&high)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("SI-SDR"))
+        .stdout(predicates::str::contains("low_freq.wav"));
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output.join("manifest.json")).unwrap()).unwrap();
+    for band in manifest["bands"].as_array().unwrap() {
+        assert!(band["metrics"]["sir_db"].as_f64().unwrap() > 40.0, "{}", band);
+    }
+
+    saunds()
+        .arg("--input").arg(&input)
+        .arg("--output").arg(&output)
+        .arg("--reference").arg(&low)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("one per band"));
+}
+
+/// Level of the band centered nearest `center` in an `analyze --format json` report.
+fn band_level(report: &serde_json::Value, center: f64) -> f64 {
+    report["bands"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|band| (band["center"].as_f64().unwrap() / center - 1.0).abs() < 0.02)
+        .unwrap()["level_db"]
+        .as_f64()
+        .unwrap()
+}
+
+fn analyze_json(input: &std::path::Path, args: &[&str]) -> serde_json::Value {
+    let output = saunds().arg("analyze").arg(input).args(args).args(["--format", "json"]).output().unwrap();
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn reports_wow_and_flutter_of_a_test_tone() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("test_tape.wav");
+    // A 3000 Hz tone wavering by 0.2% at 2 Hz
+    let mut phase = 0.0f64;
+    let tone: Vec<f32> = (0..5 * 44100)
+        .map(|n| {
+            let t = n as f64 / 44100.0;
+            phase += 2.0 * std::f64::consts::PI * 3000.0 * (1.0 + 0.002 * (2.0 * std::f64::consts::PI * 2.0 * t).sin()) / 44100.0;
+            (0.5 * phase.sin()) as f32
+        })
+        .collect();
+    write_wav(&input, &tone, 44100, 1);
+
+    let report = analyze_json(&input, &["--wow-flutter", "3000"]);
+    let measured = &report["wow_flutter"];
+    assert!((measured["wow_rms_percent"].as_f64().unwrap() - 0.141).abs() < 0.015, "{}", measured);
+    assert!(measured["flutter_rms_percent"].as_f64().unwrap() < 0.03, "{}", measured);
+
+    // Against the default 3150 Hz tone it reads as running slow
+    let report = analyze_json(&input, &["--wow-flutter"]);
+    assert!((report["wow_flutter"]["speed_error_percent"].as_f64().unwrap() + 4.76).abs() < 0.01);
+}
+
+#[test]
+fn dewows_a_transfer_from_its_pilot_tone() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("transfer.wav");
+    let output = dir.path().join("steady.wav");
+    // A 3150 Hz tone running 2% slow with 0.5% of wow at 1 Hz
+    let mut phase = 0.0f64;
+    let tone: Vec<f32> = (0..4 * 44100)
+        .map(|n| {
+            let t = n as f64 / 44100.0;
+            phase += 2.0 * std::f64::consts::PI * 3087.0 * (1.0 + 0.005 * (2.0 * std::f64::consts::PI * t).sin()) / 44100.0;
+            (0.5 * phase.sin()) as f32
+        })
+        .collect();
+    write_wav(&input, &tone, 44100, 1);
+
+    saunds()
+        .arg("dewow")
+        .arg(&input)
+        .arg("--output").arg(&output)
+        .args(["--pilot", "3150"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("Speed varied from -2.4"));
+    let (steady, _) = read_wav(&output);
+    assert!((steady.len() as f64 / tone.len() as f64 - 0.98).abs() < 0.001);
+    let report = analyze_json(&output, &["--wow-flutter"]);
+    assert!(report["wow_flutter"]["wow_rms_percent"].as_f64().unwrap() < 0.03, "{}", report);
+}
+
+#[test]
+fn estimates_the_noise_floor_under_bursts() {
+    let dir = TempDir::new().unwrap();
+    let hiss = noise(8 * 44100, 0.01, 9);
+    let tone = multitone(&[1000.0, 4000.0], 0.3, 8.0, 44100);
+    // One-second bursts with pauses between them
+    let bursts: Vec<f32> = hiss.iter().zip(&tone).enumerate().map(|(i, (n, t))| if (i / 44100) % 2 == 0 { n + t } else { *n }).collect();
+    let noisy = dir.path().join("bursts.wav");
+    let quiet = dir.path().join("hiss.wav");
+    write_wav(&noisy, &bursts, 44100, 1);
+    write_wav(&quiet, &hiss, 44100, 1);
+
+    let report = analyze_json(&noisy, &["--octave-bands", "1"]);
+    let reference = analyze_json(&quiet, &["--octave-bands", "1"]);
+    // The pauses set the floor at the hiss level, not the bursts'
+    let bands = report["noise_floor"].as_array().unwrap().iter().zip(reference["bands"].as_array().unwrap());
+    for (band, expected) in bands.filter(|(band, _)| band["center"].as_f64().unwrap() >= 125.0) {
+        let (floor, level) = (band["level_db"].as_f64().unwrap(), expected["level_db"].as_f64().unwrap());
+        assert!((floor - level).abs() < 3.0, "{} Hz: floor {:.1} dB, hiss {:.1} dB", band["center"], floor, level);
+    }
+    assert!(report["noise_floor_dbfs"].as_f64().unwrap() < -40.0);
+}
+
+#[test]
+fn reports_octave_band_levels() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[125.0, 1000.0], TONE_AMPLITUDE, 2.0, 44100), 44100, 1);
+    let tone_db = 20.0 * (TONE_AMPLITUDE as f64).log10();
+
+    let report = analyze_json(&input, &["--octave-bands", "3"]);
+    assert!((band_level(&report, 125.0) - tone_db).abs() < 0.5);
+    assert!((band_level(&report, 1000.0) - tone_db).abs() < 0.5);
+    assert!(band_level(&report, 400.0) < tone_db - 30.0);
+
+    // A-weighting attenuates 125 Hz by about 16 dB and leaves 1 kHz alone
+    let weighted = analyze_json(&input, &["--octave-bands", "3", "--weighting", "a"]);
+    assert!((band_level(&weighted, 125.0) - (tone_db - 16.1)).abs() < 0.5);
+    assert!((band_level(&weighted, 1000.0) - tone_db).abs() < 0.5);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use anyhow::Result;
+use common::write_wav;
+use saunds_v2::audio::codec::{DecodeOptions, Decoded, Decoder, EncodeOptions, Encoder, Registry};
+use saunds_v2::audio::{AudioBuffer, AudioProcessor, DecodeStats};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+/// Reads each byte of a file as one sample of a stereo 8 kHz signal.
+struct Bytes {
+    extensions: &'static [&'static str],
+}
+
+impl Decoder for Bytes {
+    fn name(&self) -> &str {
+        "bytes"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+
+    fn decode(&self, reader: &mut dyn Read, _: &DecodeOptions) -> Result<Decoded> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let samples: Vec<f32> = data.iter().map(|&byte| byte as f32 / 255.0).collect();
+        Ok(Decoded { buffer: AudioBuffer::from_interleaved(&samples, 2, 8000), stats: DecodeStats::default() })
+    }
+}
+
+/// File name, samples and sample rate of a write.
+type Written = (String, Vec<f32>, u32);
+
+/// Keeps what it's asked to write instead of writing it.
+#[derive(Clone, Default)]
+struct Capture {
+    written: Arc<Mutex<Vec<Written>>>,
+}
+
+impl Encoder for Capture {
+    fn name(&self) -> &str {
+        "capture"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cap"]
+    }
+
+    fn encode(&self, path: &Path, buffer: &AudioBuffer, options: &EncodeOptions) -> Result<()> {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        self.written.lock().unwrap().push((name, buffer.to_interleaved(), options.spec.sample_rate));
+        Ok(())
+    }
+}
+
+#[test]
+fn loads_and_saves_through_registered_codecs() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("in.bytes");
+    std::fs::write(&input, [0u8, 255, 51, 102]).unwrap();
+    let capture = Capture::default();
+    let codecs = Registry::default().with_decoder(Bytes { extensions: &["bytes"] }).with_encoder(capture.clone());
+
+    let mut processor = AudioProcessor::new().unwrap().with_codecs(codecs);
+    let samples = processor.load_audio(&input).unwrap();
+    assert_eq!(samples, [0.0, 1.0, 0.2, 0.4]);
+    assert_eq!((processor.sample_rate(), processor.channels()), (8000, 2));
+
+    processor.save_audio(dir.path().join("out.cap"), &samples).unwrap();
+    assert_eq!(*capture.written.lock().unwrap(), [("out.cap".to_string(), samples.clone(), 8000)]);
+    // Built-in formats still work alongside
+    let wav = dir.path().join("out.wav");
+    processor.save_audio(&wav, &samples).unwrap();
+    assert_eq!(processor.load_audio(&wav).unwrap(), samples);
+}
+
+#[test]
+fn later_codecs_take_over_an_extension() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    write_wav(&input, &[0.5; 100], 44100, 1);
+
+    let mut processor = AudioProcessor::new().unwrap().with_codecs(Registry::default().with_decoder(Bytes { extensions: &["wav"] }));
+    processor.load_audio(&input).unwrap();
+    assert_eq!((processor.sample_rate(), processor.channels()), (8000, 2));
+
+    let mut processor = AudioProcessor::new().unwrap().with_codecs(Registry::empty());
+    let error = processor.load_audio(&input).unwrap_err();
+    assert!(error.to_string().contains("No decoder"), "{:#}", error);
+}
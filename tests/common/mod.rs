@@ -0,0 +1,163 @@
+//! Shared helpers for the integration tests: synthesized fixtures and
+//! spectral measurements of rendered outputs.
+
+#![allow(dead_code)]
+
+use std::path::Path;
+
+/// Sum of equal-amplitude sine tones.
+pub fn multitone(frequencies: &[f32], amplitude: f32, seconds: f32, sample_rate: u32) -> Vec<f32> {
+    let len = (seconds * sample_rate as f32) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            frequencies
+                .iter()
+                .map(|&f| amplitude * (2.0 * std::f32::consts::PI * f * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+/// Deterministic white noise in [-amplitude, amplitude].
+pub fn noise(len: usize, amplitude: f32, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            amplitude * ((state >> 8) as f32 / (1u32 << 23) as f32 - 1.0)
+        })
+        .collect()
+}
+
+/// Noise shaped into four syllables a second with pauses between them,
+/// dipping like speech does.
+pub fn speech_like(seconds: f32, amplitude: f32, sample_rate: u32, seed: u32) -> Vec<f32> {
+    noise((seconds * sample_rate as f32) as usize, amplitude, seed)
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| x * (2.0 * std::f32::consts::PI * 4.0 * i as f32 / sample_rate as f32).sin().max(0.0).powi(2))
+        .collect()
+}
+
+/// Non-drop LTC for `count` frames counting up from `start` as (hours,
+/// minutes, seconds, frames), running at `fps` on a `sample_rate` clock.
+pub fn ltc(start: [u32; 4], count: usize, fps: f64, sample_rate: u32, amplitude: f32) -> Vec<f32> {
+    let base = fps.round() as u32;
+    let [hours, minutes, seconds, frames] = start;
+    let first = ((hours * 60 + minutes) * 60 + seconds) * base + frames;
+    let mut bits = Vec::new();
+    for number in first..first + count as u32 {
+        let (frames, seconds, minutes, hours) = (number % base, number / base % 60, number / base / 60 % 60, number / base / 3600);
+        let mut frame = [0u8; 80];
+        let mut put = |start: usize, len: usize, value: u32| (0..len).for_each(|i| frame[start + i] = ((value >> i) & 1) as u8);
+        put(0, 4, frames % 10);
+        put(8, 2, frames / 10);
+        put(16, 4, seconds % 10);
+        put(24, 3, seconds / 10);
+        put(32, 4, minutes % 10);
+        put(40, 3, minutes / 10);
+        put(48, 4, hours % 10);
+        put(56, 2, hours / 10);
+        frame[64..].copy_from_slice(&[0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 1]);
+        bits.extend(frame);
+    }
+    // Biphase mark: the level flips at every bit edge and mid-bit for ones
+    let bit = sample_rate as f64 / (80.0 * fps);
+    let mut level = amplitude;
+    let mut samples = Vec::new();
+    for (i, &value) in bits.iter().enumerate() {
+        level = -level;
+        let middle = ((i as f64 + 0.5) * bit).round() as usize;
+        let end = ((i + 1) as f64 * bit).round() as usize;
+        samples.resize(middle, level);
+        if value == 1 {
+            level = -level;
+        }
+        samples.resize(end, level);
+    }
+    samples
+}
+
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32, channels: u16) {
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for &sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+pub fn read_wav(path: &Path) -> (Vec<f32>, hound::WavSpec) {
+    let reader = hound::WavReader::open(path).unwrap();
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().map(Result::unwrap).collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.into_samples::<i32>().map(|sample| sample.unwrap() as f32 / scale).collect()
+        }
+    };
+    (samples, spec)
+}
+
+/// Amplitude of the component at `frequency` in dBFS, measured with a
+/// Hann-windowed single-bin DFT over the middle half of the signal so the
+/// edges don't bias the estimate.
+pub fn tone_level_db(samples: &[f32], sample_rate: u32, frequency: f32) -> f32 {
+    let section = &samples[samples.len() / 4..samples.len() * 3 / 4];
+    let n = section.len() as f64;
+    let omega = 2.0 * std::f64::consts::PI * frequency as f64 / sample_rate as f64;
+
+    let (mut re, mut im, mut window_sum) = (0.0f64, 0.0f64, 0.0f64);
+    for (i, &s) in section.iter().enumerate() {
+        let w = 0.5 * (1.0 - (2.0 * std::f64::consts::PI * i as f64 / n).cos());
+        re += s as f64 * w * (omega * i as f64).cos();
+        im -= s as f64 * w * (omega * i as f64).sin();
+        window_sum += w;
+    }
+
+    let amplitude = 2.0 * (re * re + im * im).sqrt() / window_sum;
+    20.0 * amplitude.max(1e-12).log10() as f32
+}
+
+/// Length of each frame produced by [`silent_mp3`].
+pub const MP3_FRAME_LEN: usize = 417;
+/// Samples per channel in an MPEG-1 Layer III frame.
+pub const MP3_FRAME_SAMPLES: usize = 1152;
+
+/// MPEG-1 Layer III stream (128 kbps, 44.1 kHz, stereo) of silent frames.
+/// All-zero side info and main data is a valid frame, which lets the tests
+/// build MP3 fixtures without an encoder.
+pub fn silent_mp3(frames: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(frames * MP3_FRAME_LEN);
+    for _ in 0..frames {
+        data.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        data.resize(data.len() + MP3_FRAME_LEN - 4, 0);
+    }
+    data
+}
+
+/// [`silent_mp3`] preceded by a Xing/Info frame whose LAME extension
+/// records the given encoder delay and padding.
+pub fn gapless_mp3(frames: usize, encoder_delay: usize, padding: usize) -> Vec<u8> {
+    let mut info = silent_mp3(1);
+    // Header (4 bytes) + MPEG-1 stereo side info (32 bytes)
+    let tag = 36;
+    info[tag..tag + 4].copy_from_slice(b"Info");
+    info[tag + 4..tag + 8].copy_from_slice(&1u32.to_be_bytes());
+    info[tag + 8..tag + 12].copy_from_slice(&(frames as u32).to_be_bytes());
+    let lame = tag + 12;
+    info[lame..lame + 9].copy_from_slice(b"LAME3.100");
+    info[lame + 21] = (encoder_delay >> 4) as u8;
+    info[lame + 22] = (((encoder_delay & 0x0f) << 4) | (padding >> 8)) as u8;
+    info[lame + 23] = (padding & 0xff) as u8;
+
+    info.extend(silent_mp3(frames));
+    info
+}
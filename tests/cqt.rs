@@ -0,0 +1,42 @@
+mod common;
+
+use common::multitone;
+use saunds_v2::audio::cqt::{chroma, Cqt};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Third-of-a-semitone bins from A1 over four octaves.
+const CQT: Cqt = Cqt { min_frequency: 55.0, bins_per_octave: 36, octaves: 4 };
+
+#[test]
+fn resolves_semitones_in_the_bass() {
+    // A1 and A#1 are 3.3 Hz apart, under two bins of a 2048-point FFT
+    let tones = multitone(&[55.0, 55.0 * 2f32.powf(1.0 / 12.0)], 0.5, 2.0, SAMPLE_RATE);
+    let frames = CQT.transform(&tones, SAMPLE_RATE, 4096).unwrap();
+    let frame = &frames[frames.len() / 2];
+
+    for bin in [0, 3] {
+        assert!((frame[bin] - 0.5).abs() < 0.02, "bin {} reads {}", bin, frame[bin]);
+    }
+    assert!(frame[6] < 0.02, "bin 6 reads {}", frame[6]);
+}
+
+#[test]
+fn chroma_names_the_chord() {
+    // A major: A, C# and E across octaves
+    let chord = multitone(&[110.0, 277.18, 329.63, 440.0], 0.2, 1.0, SAMPLE_RATE);
+    let frames = CQT.transform(&chord, SAMPLE_RATE, 4096).unwrap();
+    let classes = chroma(&CQT.frequencies(), &frames[frames.len() / 2]);
+
+    let (a, c_sharp, e) = (9, 1, 4);
+    for (class, &energy) in classes.iter().enumerate() {
+        if [a, c_sharp, e].contains(&class) {
+            assert!(energy > 0.2, "class {} reads {}", class, energy);
+        } else {
+            assert!(energy < 0.05, "class {} reads {}", class, energy);
+        }
+    }
+    assert_eq!(classes[a], 1.0);
+    assert!(CQT.transform(&chord, 8000, 512).is_ok());
+    assert!(Cqt { octaves: 8, ..CQT }.transform(&chord, 22050, 512).is_err());
+}
@@ -0,0 +1,52 @@
+use saunds_v2::audio::{dewow, wow_flutter};
+use std::f64::consts::PI;
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// `seconds` of harmonics of `hz` played at a speed of `speed` (1 plus a
+/// `depth` swing at `rate` Hz).
+fn wavering(hz: f64, harmonics: usize, speed: f64, depth: f64, rate: f64, seconds: f64) -> Vec<f32> {
+    let mut phase = 0.0;
+    (0..(seconds * SAMPLE_RATE as f64) as usize)
+        .map(|n| {
+            let t = n as f64 / SAMPLE_RATE as f64;
+            phase += 2.0 * PI * hz * speed * (1.0 + depth * (2.0 * PI * rate * t).sin()) / SAMPLE_RATE as f64;
+            let sum: f64 = (1..=harmonics).map(|k| (k as f64 * phase).sin() / k as f64).sum();
+            (0.3 * sum) as f32
+        })
+        .collect()
+}
+
+#[test]
+fn pilot_tone_corrects_wow_and_speed() {
+    // 0.5% wow at 0.7 Hz, running 1% fast
+    let input = wavering(3150.0, 1, 1.01, 0.005, 0.7, 8.0);
+    let before = wow_flutter::measure(&input, SAMPLE_RATE, 3150.0).unwrap();
+    assert!(before.wow_rms_percent > 0.3, "{:?}", before);
+
+    let curve = dewow::from_pilot(&input, SAMPLE_RATE, 3150.0).unwrap();
+    let corrected = dewow::correct(&input, 1, SAMPLE_RATE, &curve);
+    assert!((corrected.len() as f64 / input.len() as f64 - 1.01).abs() < 0.001);
+    let after = wow_flutter::measure(&corrected, SAMPLE_RATE, 3150.0).unwrap();
+    assert!(after.wow_rms_percent < 0.02, "{:?}", after);
+    assert!(after.speed_error_percent.abs() < 0.02, "{:?}", after);
+}
+
+#[test]
+fn spectral_tracking_flattens_drift_of_music() {
+    let input = wavering(220.0, 8, 1.0, 0.008, 1.0, 5.0);
+    let curve = dewow::from_spectrum(&input, SAMPLE_RATE).unwrap();
+    let (slowest, fastest) = curve.range();
+    assert!(slowest < 0.9925 && fastest > 1.0075, "{} to {}", slowest, fastest);
+
+    let corrected = dewow::correct(&input, 1, SAMPLE_RATE, &curve);
+    assert!((corrected.len() as f64 / input.len() as f64 - 1.0).abs() < 0.002);
+    let (slowest, fastest) = dewow::from_spectrum(&corrected, SAMPLE_RATE).unwrap().range();
+    assert!(slowest > 0.999 && fastest < 1.001, "{} to {}", slowest, fastest);
+}
+
+#[test]
+fn rejects_missing_pilot_tones() {
+    let input = wavering(440.0, 1, 1.0, 0.0, 1.0, 3.0);
+    assert!(dewow::from_pilot(&input, SAMPLE_RATE, 19000.0).is_err());
+}
@@ -0,0 +1,148 @@
+use proptest::prelude::*;
+use saunds_v2::audio::{AudioProcessor, FilterMode, Precision, WINDOW_SIZE};
+
+const SAMPLE_RATE: u32 = 44100;
+const NYQUIST: f32 = SAMPLE_RATE as f32 / 2.0;
+const TOLERANCE: f32 = 1e-4;
+
+fn processor(window_size: usize) -> AudioProcessor {
+    AudioProcessor::new()
+        .unwrap()
+        .with_sample_rate(SAMPLE_RATE)
+        .with_window_size(window_size)
+}
+
+fn window_size() -> impl Strategy<Value = usize> {
+    prop::sample::select(vec![256usize, 512, 1024, 2048])
+}
+
+fn signal() -> impl Strategy<Value = Vec<f32>> {
+    prop::collection::vec(-1.0f32..1.0, 0..6000)
+}
+
+fn cutoffs(max: usize) -> impl Strategy<Value = Vec<f32>> {
+    prop::collection::vec(0.0f32..NYQUIST, 0..=max).prop_map(|mut cutoffs| {
+        cutoffs.sort_by(f32::total_cmp);
+        cutoffs
+    })
+}
+
+fn max_error(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+}
+
+fn sum_bands(bands: &[Vec<f32>], len: usize) -> Vec<f32> {
+    let mut sum = vec![0.0; len];
+    for band in bands {
+        for (s, &b) in sum.iter_mut().zip(band) {
+            *s += b;
+        }
+    }
+    sum
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn bands_sum_to_input(samples in signal(), window_size in window_size(), cutoffs in cutoffs(4)) {
+        let bands = processor(window_size).split_bands(&samples, &cutoffs).unwrap();
+        prop_assert_eq!(bands.len(), cutoffs.len() + 1);
+        let sum = sum_bands(&bands, samples.len());
+        prop_assert!(max_error(&sum, &samples) < TOLERANCE, "error {}", max_error(&sum, &samples));
+    }
+
+    #[test]
+    fn zero_phase_bands_sum_to_input(samples in signal(), cutoffs in cutoffs(4)) {
+        let processor = processor(WINDOW_SIZE).with_filter_mode(FilterMode::ZeroPhase);
+        let bands = processor.split_bands(&samples, &cutoffs).unwrap();
+        prop_assert_eq!(bands.len(), cutoffs.len() + 1);
+        let sum = sum_bands(&bands, samples.len());
+        prop_assert!(max_error(&sum, &samples) < TOLERANCE, "error {}", max_error(&sum, &samples));
+    }
+
+    #[test]
+    fn double_precision_bands_sum_to_input(samples in signal(), window_size in window_size(), cutoffs in cutoffs(4)) {
+        for mode in [FilterMode::Fft, FilterMode::ZeroPhase] {
+            let processor = processor(window_size).with_filter_mode(mode).with_precision(Precision::Double);
+            let sum = sum_bands(&processor.split_bands(&samples, &cutoffs).unwrap(), samples.len());
+            // Only the final rounding of each band to f32 remains
+            prop_assert!(max_error(&sum, &samples) < 1e-6, "{:?} error {}", mode, max_error(&sum, &samples));
+        }
+    }
+
+    #[test]
+    fn output_length_matches_input(samples in signal(), window_size in window_size(), low in 0.0f32..NYQUIST, high in 0.0f32..NYQUIST) {
+        let processor = processor(window_size);
+        let (low_freq, high_freq) = processor.separate_frequencies(&samples, low.min(high), low.max(high)).unwrap();
+        prop_assert_eq!(low_freq.len(), samples.len());
+        prop_assert_eq!(high_freq.len(), samples.len());
+
+        for band in processor.split_bands(&samples, &[low.min(high), low.max(high)]).unwrap() {
+            prop_assert_eq!(band.len(), samples.len());
+        }
+    }
+
+    #[test]
+    fn silence_in_silence_out(len in 0usize..6000, window_size in window_size(), cutoffs in cutoffs(3)) {
+        let samples = vec![0.0; len];
+        for band in processor(window_size).split_bands(&samples, &cutoffs).unwrap() {
+            prop_assert!(band.iter().all(|&s| s == 0.0));
+        }
+    }
+
+    #[test]
+    fn zero_cutoff_leaves_lowest_band_empty(samples in signal(), window_size in window_size()) {
+        let bands = processor(window_size).split_bands(&samples, &[0.0]).unwrap();
+        prop_assert!(bands[0].iter().all(|&s| s == 0.0));
+        prop_assert!(max_error(&bands[1], &samples) < TOLERANCE);
+
+        let (low_freq, high_freq) = processor(window_size).separate_frequencies(&samples, 0.0, 0.0).unwrap();
+        prop_assert!(low_freq.iter().all(|&s| s == 0.0));
+        prop_assert!(max_error(&high_freq, &samples) < TOLERANCE);
+    }
+
+    #[test]
+    fn nyquist_cutoff_leaves_highest_band_empty(samples in signal(), window_size in window_size()) {
+        let bands = processor(window_size).split_bands(&samples, &[NYQUIST]).unwrap();
+        prop_assert!(max_error(&bands[0], &samples) < TOLERANCE);
+        prop_assert!(bands[1].iter().all(|&s| s == 0.0));
+
+        let (low_freq, high_freq) = processor(window_size).separate_frequencies(&samples, NYQUIST, NYQUIST).unwrap();
+        prop_assert!(max_error(&low_freq, &samples) < TOLERANCE);
+        prop_assert!(high_freq.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn processing_is_deterministic(samples in signal(), window_size in window_size(), cutoffs in cutoffs(3)) {
+        let processor = processor(window_size);
+        let first = processor.split_bands(&samples, &cutoffs).unwrap();
+        let second = processor.split_bands(&samples, &cutoffs).unwrap();
+        prop_assert_eq!(first, second);
+    }
+}
+
+#[test]
+fn descending_cutoffs_are_rejected() {
+    assert!(processor(1024).split_bands(&[0.0; 16], &[2000.0, 200.0]).is_err());
+}
+
+#[test]
+fn skipping_silent_windows_keeps_the_split_exact() {
+    let tone = |len: usize| (0..len).map(|i| (i as f32 * 0.05).sin() * 0.5);
+    let samples: Vec<f32> = tone(3000).chain(std::iter::repeat_n(0.0, 20000)).chain(tone(3000)).collect();
+    let bands = processor(1024).with_channels(1).split_bands(&samples, &[1000.0]).unwrap();
+    let sum = sum_bands(&bands, samples.len());
+    assert!(max_error(&sum, &samples) < TOLERANCE, "error {}", max_error(&sum, &samples));
+    assert!(bands.iter().all(|band| band[5000..20000].iter().all(|&s| s == 0.0)));
+}
+
+#[test]
+fn windows_below_the_silence_threshold_are_left_silent() {
+    let hiss: Vec<f32> = (0..8000).map(|i| if i % 2 == 0 { 1e-5 } else { -1e-5 }).collect();
+    let bands = processor(1024).with_channels(1).split_bands(&hiss, &[1000.0]).unwrap();
+    assert!(bands[1].iter().any(|&s| s != 0.0));
+
+    let bands = processor(1024).with_channels(1).with_silence_threshold(-80.0).split_bands(&hiss, &[1000.0]).unwrap();
+    assert!(bands.iter().all(|band| band.iter().all(|&s| s == 0.0)));
+}
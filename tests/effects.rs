@@ -0,0 +1,356 @@
+mod common;
+
+use common::{multitone, noise, tone_level_db};
+use saunds_v2::audio::analysis::{estimate_tempo, stereo_correlation};
+use saunds_v2::audio::effects::StageSpec;
+use saunds_v2::audio::AudioBuffer;
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn run(spec: &str, samples: &[f32]) -> Vec<f32> {
+    let mut output = AudioBuffer::from_planar(vec![samples.to_vec()], SAMPLE_RATE).unwrap();
+    let stage: StageSpec = spec.parse().unwrap();
+    stage.build(SAMPLE_RATE).unwrap().process(&mut output);
+    output.into_planar().remove(0)
+}
+
+fn stereo(left: &[f32], right: &[f32]) -> AudioBuffer {
+    AudioBuffer::from_planar(vec![left.to_vec(), right.to_vec()], SAMPLE_RATE).unwrap()
+}
+
+/// Like [`run`] for stereo.
+fn run_stereo(spec: &str, input: &AudioBuffer) -> AudioBuffer {
+    let mut output = input.clone();
+    let stage: StageSpec = spec.parse().unwrap();
+    stage.build(SAMPLE_RATE).unwrap().process(&mut output);
+    output
+}
+
+fn level(samples: &[f32], frequency: f32) -> f32 {
+    tone_level_db(samples, SAMPLE_RATE, frequency)
+}
+
+#[test]
+fn dynamic_eq_cuts_only_above_threshold() {
+    let spec = "dynamic-eq:freq=1000,threshold=-20,ratio=4,range=12";
+
+    // -6 dBFS is 14 dB over the threshold: 10.5 dB of cut
+    let loud = multitone(&[1000.0], 0.5, 1.0, SAMPLE_RATE);
+    let cut = level(&run(spec, &loud), 1000.0) - level(&loud, 1000.0);
+    assert!((cut + 10.5).abs() < 1.0, "cut {} dB", cut);
+
+    // Below the threshold the band passes untouched
+    let quiet = multitone(&[1000.0], 0.05, 1.0, SAMPLE_RATE);
+    let change = level(&run(spec, &quiet), 1000.0) - level(&quiet, 1000.0);
+    assert!(change.abs() < 0.2, "change {} dB", change);
+
+    // Content away from the band is unaffected even when the band is cut
+    let mixed = multitone(&[100.0, 1000.0], 0.5, 1.0, SAMPLE_RATE);
+    let change = level(&run(spec, &mixed), 100.0) - level(&mixed, 100.0);
+    assert!(change.abs() < 0.5, "change {} dB", change);
+}
+
+#[test]
+fn dynamic_eq_range_limits_the_cut() {
+    let loud = multitone(&[1000.0], 0.9, 1.0, SAMPLE_RATE);
+    let processed = run("dynamic-eq:freq=1000,threshold=-60,ratio=100,range=6", &loud);
+    let cut = level(&processed, 1000.0) - level(&loud, 1000.0);
+    assert!((cut + 6.0).abs() < 0.5, "cut {} dB", cut);
+}
+
+#[test]
+fn rejects_malformed_stages() {
+    assert!("no-such-effect".parse::<StageSpec>().is_err());
+    assert!("dynamic-eq:freq".parse::<StageSpec>().is_err());
+    assert!("dynamic-eq:frequency=1000".parse::<StageSpec>().is_err());
+    assert!("dynamic-eq:ratio=0.5".parse::<StageSpec>().is_err());
+}
+
+/// 1 kHz bursts with an instant onset and exponential decay, every 250 ms.
+fn bursts() -> Vec<f32> {
+    (0..SAMPLE_RATE as usize)
+        .map(|i| {
+            let t = (i % (SAMPLE_RATE as usize / 4)) as f32 / SAMPLE_RATE as f32;
+            0.5 * (-t / 0.05).exp() * (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+        })
+        .collect()
+}
+
+/// RMS level in dB of the burst segment between `from` and `to` seconds
+/// after each onset.
+fn segment_db(samples: &[f32], from: f32, to: f32) -> f32 {
+    let period = SAMPLE_RATE as usize / 4;
+    let range = (from * SAMPLE_RATE as f32) as usize..(to * SAMPLE_RATE as f32) as usize;
+    let (sum, count) = samples
+        .chunks(period)
+        .flat_map(|burst| burst[range.clone()].iter())
+        .fold((0.0, 0), |(sum, count), x| (sum + x * x, count + 1));
+    10.0 * (sum / count as f32).log10()
+}
+
+#[test]
+fn transient_shaper_separates_attack_and_sustain() {
+    let input = bursts();
+    let onset = |samples: &[f32]| segment_db(samples, 0.0, 0.005);
+    let tail = |samples: &[f32]| segment_db(samples, 0.1, 0.2);
+
+    let punchy = run("transient:attack=9", &input);
+    assert!(onset(&punchy) - onset(&input) > 3.0, "onset +{} dB", onset(&punchy) - onset(&input));
+    assert!((tail(&punchy) - tail(&input)).abs() < 1.0);
+
+    let dry = run("transient:sustain=-9", &input);
+    assert!(tail(&dry) - tail(&input) < -3.0, "tail {} dB", tail(&dry) - tail(&input));
+    assert!((onset(&dry) - onset(&input)).abs() < 1.0);
+}
+
+#[test]
+fn exciter_adds_harmonics_above_its_band() {
+    let input = multitone(&[200.0, 4000.0], 0.25, 1.0, SAMPLE_RATE);
+    let excited = run("exciter:freq=3000,drive=18,mix=0.5", &input);
+
+    // tanh saturation adds odd harmonics of the high tone
+    assert!(level(&input, 12000.0) < -80.0);
+    assert!(level(&excited, 12000.0) > -50.0, "{} dB", level(&excited, 12000.0));
+
+    // The low tone is left alone and grows no harmonics
+    assert!((level(&excited, 200.0) - level(&input, 200.0)).abs() < 0.1);
+    assert!(level(&excited, 600.0) < -80.0, "{} dB", level(&excited, 600.0));
+}
+
+#[test]
+fn oversampling_suppresses_aliasing() {
+    // The 3rd harmonic of 15 kHz folds back to 900 Hz without oversampling
+    let input = multitone(&[15000.0], 0.5, 1.0, SAMPLE_RATE);
+    let aliased = run("saturate:drive=24,oversample=1", &input);
+    let clean = run("saturate:drive=24,oversample=8", &input);
+    assert!(level(&aliased, 900.0) > -30.0, "{} dB", level(&aliased, 900.0));
+    assert!(level(&clean, 900.0) < -70.0, "{} dB", level(&clean, 900.0));
+
+    // The fundamental stays aligned with the input through the filters
+    let low = multitone(&[1000.0], 0.01, 1.0, SAMPLE_RATE);
+    let passed = run("saturate:drive=0,oversample=4", &low);
+    let tanh_gain = 1.0 / 1f32.tanh();
+    let error = passed[1000..passed.len() - 1000]
+        .iter()
+        .zip(&low[1000..])
+        .map(|(y, x)| (y - tanh_gain * x).abs())
+        .fold(0.0f32, f32::max);
+    assert!(error < 1e-4, "error {}", error);
+}
+
+#[test]
+fn tube_curve_adds_even_harmonics() {
+    let input = multitone(&[1000.0], 0.5, 1.0, SAMPLE_RATE);
+    let symmetric = run("saturate:curve=tanh,drive=12", &input);
+    let tube = run("saturate:curve=tube,drive=12", &input);
+    assert!(level(&symmetric, 2000.0) < -80.0, "{} dB", level(&symmetric, 2000.0));
+    assert!(level(&tube, 2000.0) > -40.0, "{} dB", level(&tube, 2000.0));
+    assert!("saturate:curve=fuzz".parse::<StageSpec>().is_err());
+}
+
+/// Level in dB of the signal between `from` and `to` seconds.
+fn rms_db(samples: &[f32], from: f32, to: f32) -> f32 {
+    let section = &samples[(from * SAMPLE_RATE as f32) as usize..(to * SAMPLE_RATE as f32) as usize];
+    let power = section.iter().map(|x| x * x).sum::<f32>() / section.len() as f32;
+    10.0 * power.max(1e-20).log10()
+}
+
+#[test]
+fn reverb_tail_grows_with_room_size() {
+    let mut impulse = vec![0.0; 2 * SAMPLE_RATE as usize];
+    impulse[0] = 1.0;
+
+    let small = run("reverb:room=0.1,mix=1", &impulse);
+    let large = run("reverb:room=0.9,mix=1", &impulse);
+    assert!(rms_db(&large, 1.0, 1.5) > rms_db(&small, 1.0, 1.5) + 20.0);
+
+    // Nothing reaches the output before the predelay has passed
+    let delayed = run("reverb:predelay=100,mix=1", &impulse);
+    assert!(delayed[..(0.1 * SAMPLE_RATE as f32) as usize].iter().all(|&x| x == 0.0));
+    assert!(rms_db(&delayed, 0.1, 0.3) > -80.0);
+
+    // A dry mix leaves the input untouched
+    let tone = multitone(&[440.0], 0.5, 0.5, SAMPLE_RATE);
+    assert_eq!(run("reverb:mix=0", &tone), tone);
+}
+
+/// Short noise clicks every `period` seconds.
+fn click_track(period: f32, seconds: f32) -> Vec<f32> {
+    let spacing = (period * SAMPLE_RATE as f32) as usize;
+    let click = noise(200, 0.8, 7);
+    (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|i| click.get(i % spacing).copied().unwrap_or(0.0))
+        .collect()
+}
+
+#[test]
+fn delay_repeats_decay_by_feedback() {
+    // A 1 kHz burst sits in the passband of the feedback filter
+    let mut burst = multitone(&[1000.0], 1.0, 1.0, SAMPLE_RATE);
+    burst[220..].fill(0.0);
+    let echoes = run("delay:time=100,feedback=0.5,mix=1", &burst);
+
+    let peak = |at: f32| {
+        let centre = (at * SAMPLE_RATE as f32) as usize;
+        echoes[centre - 50..centre + 50].iter().fold(0.0f32, |peak, x| peak.max(x.abs()))
+    };
+    assert!(peak(0.05) < 1e-3);
+    let (first, second) = (peak(0.1), peak(0.2));
+    assert!(first > 0.5, "first echo {}", first);
+    assert!((20.0 * (second / first).log10() + 6.0).abs() < 1.0);
+}
+
+#[test]
+fn delay_syncs_to_detected_tempo() {
+    // 100 BPM: a beat every 600 ms
+    let clicks = click_track(0.6, 8.0);
+    let bpm = estimate_tempo(&clicks, SAMPLE_RATE).unwrap().unwrap();
+    assert!((bpm - 100.0).abs() < 1.0, "detected {} BPM", bpm);
+
+    // Eighth notes land halfway between the beats
+    let echoes = run("delay:sync=1/8,feedback=0,mix=1", &clicks);
+    let energy = |from: f32| rms_db(&echoes, from, from + 0.02);
+    assert!(energy(6.3) > energy(6.0) + 30.0);
+
+    assert!("delay:bpm=120".parse::<StageSpec>().is_err());
+    assert!(estimate_tempo(&vec![0.0; 8 * SAMPLE_RATE as usize], SAMPLE_RATE).unwrap().is_none());
+}
+
+#[test]
+fn chorus_spreads_the_pitch() {
+    let tone = multitone(&[1000.0], 0.5, 2.0, SAMPLE_RATE);
+    let wet = run("chorus:mix=1", &tone);
+    assert!(level(&wet, 1000.0) < level(&tone, 1000.0) - 6.0);
+}
+
+#[test]
+fn flanger_without_sweep_is_a_comb() {
+    // A 1 ms delay mixed equally with the dry signal cancels 500 Hz
+    let tones = multitone(&[500.0, 1000.0], 0.25, 1.0, SAMPLE_RATE);
+    let combed = run("flanger:delay=1,depth=0,feedback=0,mix=0.5", &tones);
+    assert!(level(&combed, 500.0) < -50.0);
+    assert!((level(&combed, 1000.0) - level(&tones, 1000.0)).abs() < 0.5);
+}
+
+#[test]
+fn phaser_without_sweep_notches_where_the_chain_inverts() {
+    // Four allpasses with a 1 kHz corner shift by 180 degrees where each
+    // contributes 45 degrees
+    let t = (std::f32::consts::PI * 1000.0 / SAMPLE_RATE as f32).tan() * std::f32::consts::FRAC_PI_8.tan();
+    let notch = t.atan() * SAMPLE_RATE as f32 / std::f32::consts::PI;
+
+    let tones = multitone(&[notch, 50.0], 0.25, 1.0, SAMPLE_RATE);
+    let phased = run("phaser:min=1000,max=1000,mix=0.5", &tones);
+    assert!(level(&phased, notch) < -50.0);
+    assert!(level(&phased, 50.0) > level(&tones, 50.0) - 1.0);
+}
+
+#[test]
+fn tremolo_adds_sidebands_at_the_rate() {
+    // Gain 0.75 + 0.25 cos: sidebands 15.6 dB below the carrier
+    let tone = multitone(&[1000.0], 0.5, 2.0, SAMPLE_RATE);
+    let modulated = run("tremolo:rate=5,depth=0.5", &tone);
+    let sideband = level(&modulated, 1005.0) - level(&modulated, 1000.0);
+    assert!((sideband + 15.6).abs() < 0.5, "sideband {} dB", sideband);
+}
+
+#[test]
+fn auto_pan_keeps_power_constant() {
+    let tone = multitone(&[440.0], 0.5, 2.0, SAMPLE_RATE);
+    let panned = run_stereo("auto-pan:sync=1/4,bpm=120,shape=triangle", &stereo(&tone, &tone));
+
+    for ((&l, &r), &x) in panned.channel(0).iter().zip(panned.channel(1)).zip(&tone) {
+        assert!((l * l + r * r - 2.0 * x * x).abs() < 1e-5);
+    }
+    // Two pan cycles per second at a quarter note of 120 BPM: hard right
+    // at 250 ms, hard left at 500 ms
+    let left = panned.channel(0);
+    assert!(rms_db(left, 0.24, 0.26) < rms_db(left, 0.49, 0.51) - 20.0);
+}
+
+#[test]
+fn bitcrusher_quantizes_and_aliases_without_the_filter() {
+    let tone = multitone(&[3000.0], 0.5, 1.0, SAMPLE_RATE);
+
+    let crushed = run("bitcrush:bits=4", &tone);
+    let mut levels: Vec<i32> = crushed.iter().map(|x| (x * 8.0).round() as i32).collect();
+    levels.sort_unstable();
+    levels.dedup();
+    assert!(levels.len() <= 16);
+    assert!(crushed.iter().all(|x| (x * 8.0 - (x * 8.0).round()).abs() < 1e-6));
+
+    // Held at 4410 Hz, a 3 kHz tone folds down to 1410 Hz
+    let aliased = run("bitcrush:bits=24,rate=4410,antialias=off", &tone);
+    let filtered = run("bitcrush:bits=24,rate=4410", &tone);
+    assert!(level(&aliased, 1410.0) > -20.0);
+    assert!(level(&filtered, 1410.0) < level(&aliased, 1410.0) - 40.0);
+}
+
+#[test]
+fn widener_lowers_correlation() {
+    // Partly correlated noise: a shared component plus independent ones
+    let shared = noise(SAMPLE_RATE as usize, 0.3, 1);
+    let (left, right) = (noise(shared.len(), 0.2, 2), noise(shared.len(), 0.2, 3));
+    let mix = |other: &[f32]| shared.iter().zip(other).map(|(a, b)| a + b).collect::<Vec<f32>>();
+    let input = stereo(&mix(&left), &mix(&right));
+    let before = stereo_correlation(&input);
+
+    assert!(stereo_correlation(&run_stereo("widen:width=2", &input)) < before - 0.2);
+    assert!((stereo_correlation(&run_stereo("widen:width=0", &input)) - 1.0).abs() < 1e-6);
+    assert!(stereo_correlation(&run_stereo("widen:mode=haas,delay=15", &input)).abs() < 0.1);
+
+    let inverted: Vec<f32> = shared.iter().map(|&x| -x).collect();
+    assert!((stereo_correlation(&stereo(&shared, &inverted)) + 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn filter_cutoff_follows_its_automation() {
+    let tone = multitone(&[2000.0], 0.5, 3.0, SAMPLE_RATE);
+    let output = run("filter:type=lowpass,cutoff=0:200;1:200;2:10000", &tone);
+    let second = SAMPLE_RATE as usize;
+    // Closed for the first second, then opening past the tone
+    assert!(level(&output[second / 4..second], 2000.0) < level(&tone, 2000.0) - 25.0);
+    assert!((level(&output[5 * second / 2..], 2000.0) - level(&tone, 2000.0)).abs() < 0.5);
+
+    // No step between hops: the sweep stays smooth
+    let peak_step = output.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+    let tone_step = tone.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max);
+    assert!(peak_step <= tone_step * 1.1);
+}
+
+#[test]
+fn rejects_malformed_automation() {
+    for spec in [
+        "filter:cutoff=1:200;0:800",
+        "filter:cutoff=0:5",
+        "filter:cutoff=0:200;x:800",
+        "tremolo:rate=0:2;1:4",
+    ] {
+        assert!(spec.parse::<StageSpec>().is_err(), "{}", spec);
+    }
+    assert!("filter:type=peak,cutoff=0:300;4:3000,gain=0:6;4:-6,q=2".parse::<StageSpec>().is_ok());
+}
+
+#[test]
+fn leveler_rides_sections_to_the_target() {
+    let loud = multitone(&[300.0], 0.5, 3.0, SAMPLE_RATE);
+    let quiet = multitone(&[300.0], 0.05, 3.0, SAMPLE_RATE);
+    let pause = noise(3 * SAMPLE_RATE as usize, 0.0005, 2);
+    let input: Vec<f32> = loud.iter().chain(&pause).chain(&quiet).copied().collect();
+
+    let mut output = AudioBuffer::from_planar(vec![input], SAMPLE_RATE).unwrap();
+    let stages = ["level:target=-20,window=1,range=24".parse().unwrap()];
+    // Auto-gain leaves the level the stage set
+    saunds_v2::audio::effects::apply_chain(&stages, &mut output, true).unwrap();
+    let output = output.channel(0);
+
+    let rms_db = |samples: &[f32]| 10.0 * (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).log10();
+    let second = SAMPLE_RATE as usize;
+    // The middle second of each section, clear of the transitions
+    assert!((rms_db(&output[second..2 * second]) + 20.0).abs() < 1.0);
+    assert!((rms_db(&output[7 * second..8 * second]) + 20.0).abs() < 1.0);
+    // The pause holds the gain rather than being pulled up to the target
+    assert!(rms_db(&output[4 * second..5 * second]) < -50.0);
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(output.iter().all(|sample| sample.abs() <= ceiling + 1e-4));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::{multitone, noise};
+use saunds_v2::audio::excerpt::{loudest, most_interesting, Pick};
+use saunds_v2::audio::AudioBuffer;
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// A second of loud pure tone, a second of quieter broadband noise, then a
+/// second of silence.
+fn tone_noise_silence() -> Vec<f32> {
+    let mut samples = multitone(&[440.0], 0.5, 1.0, SAMPLE_RATE);
+    samples.extend(noise(SAMPLE_RATE as usize, 0.3, 5));
+    samples.extend(vec![0.0; SAMPLE_RATE as usize]);
+    samples
+}
+
+#[test]
+fn loudest_finds_the_tone() {
+    let samples = tone_noise_silence();
+    let length = SAMPLE_RATE as usize;
+    // Within a few frames: the tone's first samples are quieter than the noise
+    let excerpt = loudest(&samples, 1, length);
+    assert!(excerpt.start < 10 && excerpt.len() == length, "{:?}", excerpt);
+    // Interleaved channels are scored together
+    let stereo: Vec<f32> = samples.iter().flat_map(|&x| [x, x]).collect();
+    assert_eq!(loudest(&stereo, 2, length), excerpt);
+    // Short inputs are taken whole
+    assert_eq!(loudest(&samples[..100], 1, 1000), 0..100);
+}
+
+#[test]
+fn most_interesting_prefers_broadband_material() {
+    let samples = tone_noise_silence();
+    let length = SAMPLE_RATE as usize;
+    let excerpt = most_interesting(&samples, 1, SAMPLE_RATE, length).unwrap();
+    assert_eq!(excerpt.len(), length);
+    let start = excerpt.start as f32 / SAMPLE_RATE as f32;
+    assert!((start - 1.0).abs() < 0.1, "excerpt starts at {} s", start);
+
+    assert_eq!(Pick::Loudest.pick(&AudioBuffer::from_interleaved(&samples, 1, SAMPLE_RATE), length).unwrap(), loudest(&samples, 1, length));
+    assert_eq!(Pick::default(), Pick::Interesting);
+    // The excerpt never runs past the end
+    let tail = most_interesting(&samples, 1, SAMPLE_RATE, samples.len() - 10).unwrap();
+    assert!(tail.end <= samples.len());
+}
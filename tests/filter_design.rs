@@ -0,0 +1,139 @@
+use saunds_v2::audio::biquad::{cascade_response_db, group_delay_samples, Biquad};
+use saunds_v2::audio::{FilterDesign, FilterFamily};
+
+const SAMPLE_RATE: u32 = 48000;
+const CUTOFF: f32 = 1000.0;
+const RIPPLE: f64 = 1.0;
+const ATTENUATION: f64 = 60.0;
+const TOLERANCE: f32 = 0.05;
+
+fn design(family: FilterFamily, order: usize) -> FilterDesign {
+    FilterDesign { family, order, passband_ripple_db: RIPPLE, stopband_attenuation_db: ATTENUATION }
+}
+
+/// Response on a logarithmic grid over `from..to` Hz.
+fn sweep(cascade: &[Biquad], from: f32, to: f32) -> Vec<(f32, f32)> {
+    (0..=2000)
+        .map(|i| from * (to / from).powf(i as f32 / 2000.0))
+        .map(|f| (f, cascade_response_db(cascade, f, SAMPLE_RATE)))
+        .collect()
+}
+
+fn extremes(points: &[(f32, f32)]) -> (f32, f32) {
+    points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &(_, db)| (lo.min(db), hi.max(db)))
+}
+
+#[test]
+fn butterworth_is_3_db_down_at_cutoff() {
+    for order in 1..=8 {
+        let low = design(FilterFamily::Butterworth, order).lowpass(CUTOFF, SAMPLE_RATE).unwrap();
+        let high = design(FilterFamily::Butterworth, order).highpass(CUTOFF, SAMPLE_RATE).unwrap();
+        assert!((cascade_response_db(&low, CUTOFF, SAMPLE_RATE) + 3.01).abs() < TOLERANCE, "order {}", order);
+        assert!((cascade_response_db(&high, CUTOFF, SAMPLE_RATE) + 3.01).abs() < TOLERANCE, "order {}", order);
+        assert!(cascade_response_db(&low, 10.0, SAMPLE_RATE).abs() < TOLERANCE);
+        assert!(cascade_response_db(&high, 20000.0, SAMPLE_RATE).abs() < TOLERANCE);
+
+        // Bilinear Butterworth: |H|² = 1 / (1 + (tan(πf/fs) / tan(πfc/fs))^2n)
+        for f in [250.0f32, 2000.0, 4000.0, 8000.0] {
+            let warped = |f: f32| (std::f32::consts::PI * f / SAMPLE_RATE as f32).tan();
+            let expected = -10.0 * (1.0 + (warped(f) / warped(CUTOFF)).powi(2 * order as i32)).log10();
+            let actual = cascade_response_db(&low, f, SAMPLE_RATE);
+            assert!((actual - expected).abs() < TOLERANCE, "order {} at {} Hz: {} dB, expected {} dB", order, f, actual, expected);
+        }
+    }
+}
+
+#[test]
+fn equiripple_passbands() {
+    for family in [FilterFamily::Chebyshev1, FilterFamily::Elliptic] {
+        for order in 2..=8 {
+            let low = design(family, order).lowpass(CUTOFF, SAMPLE_RATE).unwrap();
+            let (min, max) = extremes(&sweep(&low, 1.0, CUTOFF));
+            assert!(max.abs() < TOLERANCE, "{:?} order {}: passband peak {} dB", family, order, max);
+            assert!((min + RIPPLE as f32).abs() < TOLERANCE, "{:?} order {}: passband trough {} dB", family, order, min);
+
+            let high = design(family, order).highpass(CUTOFF, SAMPLE_RATE).unwrap();
+            let (min, max) = extremes(&sweep(&high, CUTOFF, 20000.0));
+            assert!(max.abs() < TOLERANCE, "{:?} order {}: passband peak {} dB", family, order, max);
+            assert!(min > -RIPPLE as f32 - TOLERANCE, "{:?} order {}: passband trough {} dB", family, order, min);
+        }
+    }
+}
+
+#[test]
+fn equiripple_stopbands() {
+    for family in [FilterFamily::Chebyshev2, FilterFamily::Elliptic] {
+        for order in 2..=8 {
+            let low = design(family, order).lowpass(CUTOFF, SAMPLE_RATE).unwrap();
+            // The Chebyshev II cutoff is the stopband edge; find the elliptic one
+            let response = sweep(&low, CUTOFF, 23900.0);
+            let edge = response.iter().position(|&(_, db)| db <= -ATTENUATION as f32 + TOLERANCE).unwrap();
+            let (_, max) = extremes(&response[edge..]);
+            assert!(
+                (max + ATTENUATION as f32).abs() < 0.1,
+                "{:?} order {}: stopband peak {} dB",
+                family, order, max
+            );
+            if family == FilterFamily::Chebyshev2 {
+                assert!(response[edge].0 < CUTOFF * 1.01);
+                assert!(cascade_response_db(&low, 10.0, SAMPLE_RATE).abs() < TOLERANCE);
+            }
+        }
+    }
+}
+
+#[test]
+fn elliptic_is_steepest() {
+    let transition = |family| {
+        let low = design(family, 5).lowpass(CUTOFF, SAMPLE_RATE).unwrap();
+        cascade_response_db(&low, 1.5 * CUTOFF, SAMPLE_RATE)
+    };
+    let butterworth = transition(FilterFamily::Butterworth);
+    let chebyshev = transition(FilterFamily::Chebyshev1);
+    let elliptic = transition(FilterFamily::Elliptic);
+    assert!(elliptic < chebyshev && chebyshev < butterworth, "{} {} {}", elliptic, chebyshev, butterworth);
+}
+
+#[test]
+fn band_transforms_place_edges() {
+    let (from, to) = (300.0f32, 3000.0f32);
+    // Geometric center of the band edges in the prewarped analog domain
+    let warp = |f: f32| (std::f32::consts::PI * f / SAMPLE_RATE as f32).tan();
+    let center = (warp(from) * warp(to)).sqrt().atan() * SAMPLE_RATE as f32 / std::f32::consts::PI;
+    for order in 1..=6 {
+        let design = design(FilterFamily::Butterworth, order);
+        let pass = design.bandpass(from, to, SAMPLE_RATE).unwrap();
+        let stop = design.bandstop(from, to, SAMPLE_RATE).unwrap();
+        assert_eq!(pass.len(), order);
+        assert!(cascade_response_db(&pass, center, SAMPLE_RATE).abs() < TOLERANCE, "order {}", order);
+        assert!(cascade_response_db(&stop, center, SAMPLE_RATE) < -100.0, "order {}", order);
+        for edge in [from, to] {
+            assert!((cascade_response_db(&pass, edge, SAMPLE_RATE) + 3.01).abs() < TOLERANCE, "order {} at {} Hz", order, edge);
+            assert!((cascade_response_db(&stop, edge, SAMPLE_RATE) + 3.01).abs() < TOLERANCE, "order {} at {} Hz", order, edge);
+        }
+        assert!(cascade_response_db(&stop, 20.0, SAMPLE_RATE).abs() < TOLERANCE);
+        assert!(cascade_response_db(&stop, 20000.0, SAMPLE_RATE).abs() < TOLERANCE);
+    }
+}
+
+#[test]
+fn rejects_invalid_designs() {
+    assert!(design(FilterFamily::Butterworth, 0).lowpass(CUTOFF, SAMPLE_RATE).is_err());
+    assert!(design(FilterFamily::Elliptic, 17).lowpass(CUTOFF, SAMPLE_RATE).is_err());
+    let mut inverted = design(FilterFamily::Elliptic, 4);
+    inverted.stopband_attenuation_db = 0.5;
+    assert!(inverted.lowpass(CUTOFF, SAMPLE_RATE).is_err());
+}
+
+#[test]
+fn group_delay_of_a_pure_delay() {
+    let delay = Biquad { b0: 0.0, b1: 0.0, b2: 1.0, a1: 0.0, a2: 0.0 };
+    for f in [10.0, 1000.0, 20000.0] {
+        assert!((group_delay_samples(&[delay, delay], f, SAMPLE_RATE) - 4.0).abs() < 1e-6);
+    }
+
+    // A first-order Butterworth low-pass delays DC by its time constant
+    let low = design(FilterFamily::Butterworth, 1).lowpass(CUTOFF, SAMPLE_RATE).unwrap();
+    let warped = (std::f64::consts::PI * CUTOFF as f64 / SAMPLE_RATE as f64).tan();
+    assert!((group_delay_samples(&low, 1.0, SAMPLE_RATE) - 1.0 / (2.0 * warped)).abs() < 0.01);
+}
@@ -0,0 +1,197 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use common::multitone;
+use saunds_v2::audio::AudioProcessor;
+use saunds_v2::grpc::{
+    separator_client::SeparatorClient, separator_server::SeparatorServer, Limits, SeparatorService, SplitRequest,
+    StreamConfig,
+};
+use tokio_stream::wrappers::TcpListenerStream;
+
+/// Serves the separator on a free local port and returns its address.
+async fn serve() -> String {
+    serve_with(SeparatorService::default()).await
+}
+
+async fn serve_with(service: SeparatorService) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(SeparatorServer::new(service))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    address
+}
+
+#[tokio::test]
+async fn streams_bands_matching_the_offline_split() {
+    let mut client = SeparatorClient::connect(serve().await).await.unwrap();
+    let samples = multitone(&[100.0, 3000.0], 0.4, 0.5, 16000);
+    let stereo: Vec<f32> = samples.iter().flat_map(|&sample| [sample, -sample]).collect();
+
+    let config = StreamConfig { sample_rate: 16000, channels: 2, cutoffs: vec![1000.0], window_size: 512 };
+    let mut requests: Vec<SplitRequest> =
+        stereo.chunks(2 * 300).map(|chunk| SplitRequest { config: None, samples: chunk.to_vec() }).collect();
+    requests[0].config = Some(config);
+    let mut responses = client.split(tokio_stream::iter(requests)).await.unwrap().into_inner();
+
+    let mut bands = vec![Vec::new(); 2];
+    let mut messages = 0;
+    while let Some(response) = responses.message().await.unwrap() {
+        assert_eq!(response.latency_frames, 256);
+        for (band, frames) in bands.iter_mut().zip(response.bands) {
+            band.extend(frames.samples);
+        }
+        messages += 1;
+    }
+    assert!(messages > 2, "output should arrive while input is still streaming");
+
+    let processor = AudioProcessor::builder().sample_rate(16000).channels(1).fft_size(512).build().unwrap();
+    let expected = processor.split_bands(&samples, &[1000.0]).unwrap();
+    for (band, expected) in bands.iter().zip(&expected) {
+        assert_eq!(band.len(), stereo.len());
+        let left = band.iter().step_by(2).zip(expected).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+        let right = band.iter().skip(1).step_by(2).zip(expected).map(|(a, b)| (a + b).abs()).fold(0.0, f32::max);
+        assert!(left < 1e-5 && right < 1e-5, "errors {} {}", left, right);
+    }
+}
+
+#[tokio::test]
+async fn rejects_a_stream_without_config() {
+    let mut client = SeparatorClient::connect(serve().await).await.unwrap();
+    let request = SplitRequest { config: None, samples: vec![0.0; 64] };
+    let status = client.split(tokio_stream::iter([request])).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn rejects_oversized_streams() {
+    let mut client = SeparatorClient::connect(serve().await).await.unwrap();
+    let configs = [
+        StreamConfig { sample_rate: 16000, channels: 1, cutoffs: vec![1000.0], window_size: 4_000_000_000 },
+        StreamConfig { sample_rate: 16000, channels: 0, cutoffs: vec![1000.0], window_size: 512 },
+        StreamConfig { sample_rate: 16000, channels: 100_000, cutoffs: vec![1000.0], window_size: 512 },
+    ];
+    for config in configs {
+        let request = SplitRequest { config: Some(config), samples: vec![0.0; 64] };
+        let status = client.split(tokio_stream::iter([request])).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
+
+/// Streams `request` alone and waits for the response to end.
+async fn split_once(client: &mut SeparatorClient<tonic::transport::Channel>, request: &SplitRequest) -> Result<(), tonic::Status> {
+    let mut responses = client.split(tokio_stream::iter([request.clone()])).await?.into_inner();
+    while responses.message().await?.is_some() {}
+    Ok(())
+}
+
+/// Retries `request` while the server may still be releasing an ended
+/// stream, returning the first error that isn't about open streams.
+async fn split_when_free(client: &mut SeparatorClient<tonic::transport::Channel>, request: &SplitRequest) -> Result<(), tonic::Status> {
+    for _ in 0..100 {
+        match split_once(client, request).await {
+            Err(status) if status.message().contains("streams open") || status.message().contains("already serving") => {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await
+            }
+            result => return result,
+        }
+    }
+    panic!("the server never released the earlier streams");
+}
+
+#[tokio::test]
+async fn refuses_streams_over_the_limits() {
+    let limits = Limits { max_streams: 1, max_client_streams: 1, client_streams_per_minute: 2 };
+    let mut client = SeparatorClient::connect(serve_with(SeparatorService::default().with_limits(limits)).await).await.unwrap();
+    let config = StreamConfig { sample_rate: 16000, channels: 1, cutoffs: vec![1000.0], window_size: 512 };
+    let request = SplitRequest { config: Some(config), samples: vec![0.0; 64] };
+
+    // A stream left open holds the only place
+    let (sender, receiver) = tokio::sync::mpsc::channel(1);
+    sender.send(request.clone()).await.unwrap();
+    let open = client.split(tokio_stream::wrappers::ReceiverStream::new(receiver)).await.unwrap();
+    let status = split_once(&mut client, &request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+    // Closing it frees the place, until the client has used up its rate
+    drop(sender);
+    let mut responses = open.into_inner();
+    while responses.message().await.unwrap().is_some() {}
+    split_when_free(&mut client, &request).await.unwrap();
+    let status = split_when_free(&mut client, &request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    assert!(status.message().contains("last minute"), "{}", status.message());
+}
+
+#[tokio::test]
+async fn counts_streams_in_its_metrics() {
+    let service = SeparatorService::default();
+    let metrics = service.metrics();
+    let mut client = SeparatorClient::connect(serve_with(service).await).await.unwrap();
+    let config = StreamConfig { sample_rate: 16000, channels: 2, cutoffs: vec![1000.0], window_size: 512 };
+    split_once(&mut client, &SplitRequest { config: Some(config), samples: vec![0.0; 600] }).await.unwrap();
+    let status = split_once(&mut client, &SplitRequest { config: None, samples: vec![0.0; 64] }).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    // The server may still be finishing the first stream
+    for _ in 0..100 {
+        if metrics.render().contains("saunds_grpc_streams_active 0") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let text = metrics.render();
+    for line in [
+        "saunds_grpc_streams_started_total 2",
+        "saunds_grpc_streams_failed_total 1",
+        "saunds_grpc_input_frames_total 300",
+        "saunds_grpc_stream_duration_seconds_count 2",
+        "# TYPE saunds_grpc_streams_active gauge",
+    ] {
+        assert!(text.lines().any(|l| l == line), "{} missing from\n{}", line, text);
+    }
+}
+
+/// Status line of a plain HTTP GET, or None while nothing is listening.
+fn http_status(address: &str, path: &str) -> Option<String> {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(address).ok()?;
+    write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, address).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    response.lines().next().map(str::to_string)
+}
+
+fn free_address() -> String {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn serves_probes_and_shuts_down_on_sigterm() {
+    let (listen, health) = (free_address(), free_address());
+    let mut server = std::process::Command::new(env!("CARGO_BIN_EXE_saunds_v2"))
+        .args(["grpc", "--listen", &listen, "--health-listen", &health])
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut ready = None;
+    for _ in 0..100 {
+        ready = http_status(&health, "/readyz");
+        if ready.is_some() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert_eq!(ready.as_deref(), Some("HTTP/1.1 200 OK"));
+    assert_eq!(http_status(&health, "/healthz").as_deref(), Some("HTTP/1.1 200 OK"));
+    assert_eq!(http_status(&health, "/metrics").as_deref(), Some("HTTP/1.1 200 OK"));
+
+    std::process::Command::new("kill").args(["-TERM", &server.id().to_string()]).status().unwrap();
+    assert!(server.wait().unwrap().success());
+}
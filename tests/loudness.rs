@@ -0,0 +1,103 @@
+mod common;
+
+use common::multitone;
+use saunds_v2::audio::loudness::{measure, measure_with_blocks, BlockHistogram, SILENCE_LUFS};
+use saunds_v2::audio::AudioBuffer;
+
+const SAMPLE_RATE: u32 = 48000;
+
+fn mono(samples: &[f32]) -> AudioBuffer {
+    AudioBuffer::from_planar(vec![samples.to_vec()], SAMPLE_RATE).unwrap()
+}
+
+fn stereo(samples: &[f32]) -> AudioBuffer {
+    AudioBuffer::from_planar(vec![samples.to_vec(), samples.to_vec()], SAMPLE_RATE).unwrap()
+}
+
+#[test]
+fn reference_tone_reads_its_level() {
+    // EBU Tech 3341 case 1: a stereo 1 kHz sine at -23 dBFS reads -23 LUFS
+    let amplitude = 10f32.powf(-23.0 / 20.0);
+    let tone = stereo(&multitone(&[1000.0], amplitude, 5.0, SAMPLE_RATE));
+    let loudness = measure(&tone).unwrap();
+
+    assert!((loudness.integrated_lufs + 23.0).abs() < 0.1, "{:?}", loudness);
+    assert!((loudness.max_short_term_lufs + 23.0).abs() < 0.1, "{:?}", loudness);
+    assert!(loudness.loudness_range_lu < 0.1, "{:?}", loudness);
+
+    let silence = measure(&stereo(&[0.0; 48000])).unwrap();
+    assert_eq!(silence.integrated_lufs, SILENCE_LUFS);
+}
+
+#[test]
+fn pooled_blocks_gate_tracks_as_one_programme() {
+    let loud = multitone(&[1000.0], 0.1, 4.0, SAMPLE_RATE);
+    let quiet = multitone(&[500.0], 0.05, 6.0, SAMPLE_RATE);
+    let (track, blocks) = measure_with_blocks(&mono(&loud)).unwrap();
+    assert!((blocks.integrated_lufs() - track.integrated_lufs).abs() < 0.05);
+
+    let mut album = BlockHistogram::default();
+    album.merge(&blocks);
+    album.merge(&measure_with_blocks(&mono(&quiet)).unwrap().1);
+    let whole = measure(&mono(&[loud, quiet].concat())).unwrap();
+    assert!((album.integrated_lufs() - whole.integrated_lufs).abs() < 0.1, "{} vs {:?}", album.integrated_lufs(), whole);
+    assert_eq!(BlockHistogram::default().integrated_lufs(), SILENCE_LUFS);
+}
+
+#[test]
+fn loudness_range_spans_a_level_step() {
+    // EBU Tech 3342 case 1, shortened: -20 dBFS then -30 dBFS gives 10 LU
+    let mut signal = multitone(&[1000.0], 0.1, 10.0, SAMPLE_RATE);
+    signal.extend(multitone(&[1000.0], 0.1 * 10f32.powf(-0.5), 10.0, SAMPLE_RATE));
+    let loudness = measure(&mono(&signal)).unwrap();
+
+    assert!((loudness.loudness_range_lu - 10.0).abs() < 1.0, "{:?}", loudness);
+    // Both halves pass the relative gate, so their powers average
+    let expected = -23.0 + 10.0 * (0.55f32).log10();
+    assert!((loudness.integrated_lufs - expected).abs() < 0.1, "{:?}", loudness);
+}
+
+#[test]
+fn true_peak_finds_intersample_overs() {
+    // A quarter-rate sine sampled 45 degrees off its peaks
+    let tone: Vec<f32> = (0..48000)
+        .map(|i| (std::f32::consts::FRAC_PI_2 * (i % 4) as f32 + std::f32::consts::FRAC_PI_4).sin())
+        .collect();
+    let loudness = measure(&mono(&tone)).unwrap();
+
+    assert!((loudness.sample_peak_dbfs + 3.01).abs() < 0.05, "{:?}", loudness);
+    assert!(loudness.true_peak_dbtp > -0.5, "{:?}", loudness);
+}
+
+#[test]
+fn peaks_count_clipped_samples_and_headroom() {
+    let mut tone = multitone(&[1000.0], 0.5, 1.0, SAMPLE_RATE);
+    let peaks = saunds_v2::audio::peaks::measure(&mono(&tone));
+    assert_eq!(peaks.clipped_samples, 0);
+    assert!((peaks.headroom_db() - 6.02).abs() < 0.1, "{:?}", peaks);
+
+    // The largest positive 16-bit code counts as clipped
+    tone[10] = 32767.0 / 32768.0;
+    tone[20] = -1.0;
+    tone[30] = 1.5;
+    assert_eq!(saunds_v2::audio::peaks::measure(&mono(&tone)).clipped_samples, 3);
+}
+
+#[test]
+fn headroom_gains_scale_per_band_or_linked() {
+    use saunds_v2::audio::peaks::{headroom_gains, true_peak, Scaling};
+
+    let loud = multitone(&[1000.0], 0.9, 0.5, SAMPLE_RATE);
+    let quiet = multitone(&[100.0], 0.1, 0.5, SAMPLE_RATE);
+    let (loud, quiet) = (mono(&loud), mono(&quiet));
+    let bands = [&loud, &quiet];
+
+    let gains = headroom_gains(&bands, 3.0, Scaling::PerBand);
+    assert_eq!(gains[1], 1.0);
+    let scaled: Vec<f32> = loud.channel(0).iter().map(|x| x * gains[0]).collect();
+    let peak_db = 20.0 * true_peak(&mono(&scaled)).log10();
+    assert!((peak_db + 3.0).abs() < 0.01, "{}", peak_db);
+
+    let linked = headroom_gains(&bands, 3.0, Scaling::Linked);
+    assert_eq!(linked, vec![gains[0]; 2]);
+}
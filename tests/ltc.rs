@@ -0,0 +1,56 @@
+mod common;
+
+use common::{ltc, noise};
+use saunds_v2::audio::ltc::{decode, Timecode, TimecodeTrack};
+
+#[test]
+fn decodes_frames_and_their_positions() {
+    let signal = ltc([10, 59, 59, 20], 12, 25.0, 48000, 0.5);
+    let frames = decode(&signal, 48000);
+    // The first and last frames lack the edges before and after them
+    assert_eq!(frames.len(), 10);
+    assert_eq!(frames[0].timecode.to_string(), "10:59:59:21");
+    assert_eq!(frames[4].timecode.to_string(), "11:00:00:00");
+    // Each frame is 1920 samples long at 25 fps
+    assert!(frames.iter().enumerate().all(|(i, frame)| (frame.position - 1920.0 * (i + 1) as f64).abs() < 1.0));
+}
+
+#[test]
+fn decodes_through_noise_and_inversion() {
+    let signal: Vec<f32> = ltc([1, 0, 0, 0], 50, 30.0, 44100, 0.3)
+        .iter()
+        .zip(noise(100_000, 0.05, 3))
+        .map(|(x, n)| n - x)
+        .collect();
+    let frames = decode(&signal, 44100);
+    assert!(frames.len() >= 48);
+    assert!(frames.iter().all(|frame| frame.timecode.hours == 1 && frame.timecode.seconds <= 1));
+}
+
+#[test]
+fn fits_start_and_drift() {
+    // 25 fps timecode written on a recorder whose clock runs 100 ppm slow,
+    // so the timecode advances 100 ppm fast against its samples
+    let mut signal = vec![0.0; 960];
+    signal.extend(ltc([1, 0, 0, 0], 250, 25.0 * 1.0001, 48000, 0.5));
+    let track = TimecodeTrack::fit(&decode(&signal, 48000), 48000, None).unwrap();
+    assert_eq!(track.fps, 25.0);
+    assert_eq!(track.first.to_string(), "01:00:00:01");
+    assert_eq!(track.start.to_string(), "00:59:59:24");
+    assert!((track.start_subframe - 0.5).abs() < 0.01);
+    assert!((track.drift_ppm - 100.0).abs() < 5.0, "{}", track.drift_ppm);
+    assert_eq!(track.discontinuities, 0);
+    let ten_seconds: Timecode = "01:00:10:00".parse().unwrap();
+    assert!((track.sample_at(&ten_seconds) - (960.0 + 480000.0 / 1.0001)).abs() < 1.0);
+}
+
+#[test]
+fn counts_drop_frame_timecode() {
+    let timecode: Timecode = "00:10:00;00".parse().unwrap();
+    assert_eq!(timecode.count(30), 17982);
+    assert_eq!(Timecode::from_count(1800, 30, true).to_string(), "00:01:00;02");
+    assert_eq!(Timecode::from_count(1799, 30, true).to_string(), "00:00:59;29");
+    for count in [0, 1799, 1800, 17981, 17982, 123_456] {
+        assert_eq!(Timecode::from_count(count, 30, true).count(30), count);
+    }
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{multitone, noise};
+use saunds_v2::audio::matching::{
+    apply_fir, apply_tilt, design_fir, matching_curve, spectral_tilt, PINK_TILT_DB_PER_OCTAVE,
+};
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[test]
+fn tilt_of_white_noise_is_flat_until_tilted() {
+    let mut white = noise(5 * SAMPLE_RATE as usize, 0.3, 7);
+    let tilt = spectral_tilt(&white, SAMPLE_RATE).unwrap();
+    assert!(tilt.abs() < 0.2, "white noise tilts {} dB/octave", tilt);
+
+    apply_tilt(&mut white, 1, SAMPLE_RATE, PINK_TILT_DB_PER_OCTAVE, 24.0).unwrap();
+    let tilt = spectral_tilt(&white, SAMPLE_RATE).unwrap();
+    assert!((tilt - PINK_TILT_DB_PER_OCTAVE).abs() < 0.2, "tilted noise reads {} dB/octave", tilt);
+}
+
+#[test]
+fn flat_fir_passes_the_signal_through_aligned() {
+    let tone = multitone(&[440.0, 3000.0], 0.4, 0.5, SAMPLE_RATE);
+    let mut filtered = tone.clone();
+    let fir = design_fir(&[0.0; 4097]).unwrap();
+    apply_fir(&mut filtered, 1, &fir).unwrap();
+    let error = tone.iter().zip(&filtered).fold(0.0f32, |max, (a, b)| max.max((a - b).abs()));
+    assert!(error < 1e-3, "{}", error);
+}
+
+#[test]
+fn matching_curve_brings_the_source_to_the_reference() {
+    let white = noise(5 * SAMPLE_RATE as usize, 0.3, 3);
+    let mut tilted = white.clone();
+    apply_tilt(&mut tilted, 1, SAMPLE_RATE, -2.0, 24.0).unwrap();
+
+    let curve = matching_curve(&white, &tilted, 1.0 / 3.0, 12.0).unwrap();
+    let mut matched = white.clone();
+    apply_fir(&mut matched, 1, &design_fir(&curve).unwrap()).unwrap();
+    let residual = matching_curve(&matched, &tilted, 1.0 / 3.0, 12.0).unwrap();
+    // Between 100 Hz and 10 kHz, bins of the 8192-point transform
+    assert!(residual[19..1858].iter().all(|db| db.abs() < 1.0), "{:?}", residual);
+
+    // A reference 20 dB louder is only matched up to the boost limit
+    let loud: Vec<f32> = white.iter().map(|x| x * 10.0).collect();
+    let curve = matching_curve(&white, &loud, 1.0 / 3.0, 6.0).unwrap();
+    assert!(curve.iter().all(|&db| db == 6.0));
+}
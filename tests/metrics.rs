@@ -0,0 +1,38 @@
+mod common;
+
+use common::{multitone, noise};
+use saunds_v2::audio::metrics::evaluate;
+
+#[test]
+fn separates_interference_from_artifacts() {
+    let voice = multitone(&[440.0], 0.5, 1.0, 44100);
+    let music = multitone(&[3000.0], 0.5, 1.0, 44100);
+    let hiss = noise(voice.len(), 0.05, 9);
+
+    // Leakage of the other source at -20 dB and independent noise
+    let leaky: Vec<f32> = voice.iter().zip(&music).map(|(v, m)| v + 0.1 * m).collect();
+    let noisy: Vec<f32> = music.iter().zip(&hiss).map(|(m, h)| m + h).collect();
+
+    // Leakage from a source without a reference counts as an artifact
+    let metrics = evaluate(&[&leaky], &[&voice]).unwrap();
+    assert!((metrics[0].sar_db - 20.0).abs() < 0.1, "SAR {}", metrics[0].sar_db);
+
+    let metrics = evaluate(&[&leaky, &noisy], &[&voice, &music]).unwrap();
+    assert!((metrics[0].sir_db - 20.0).abs() < 0.1, "SIR {}", metrics[0].sir_db);
+    assert!(metrics[0].sar_db > 60.0);
+    assert!(metrics[1].sir_db > 40.0);
+    // Hiss power is 0.05^2 / 3 against 0.5^2 / 2 for the tone
+    let expected = 10.0 * (0.125f32 / (0.0025 / 3.0)).log10();
+    assert!((metrics[1].sar_db - expected).abs() < 0.5, "SAR {}", metrics[1].sar_db);
+    assert!((metrics[1].sdr_db - expected).abs() < 0.5);
+}
+
+#[test]
+fn scale_invariant_sdr_ignores_gain() {
+    let voice = multitone(&[440.0], 0.5, 1.0, 44100);
+    let quiet: Vec<f32> = voice.iter().map(|v| 0.5 * v).collect();
+    let metrics = evaluate(&[&quiet], &[&voice]).unwrap();
+    assert!((metrics[0].sdr_db - 6.02).abs() < 0.01);
+    assert!(metrics[0].si_sdr_db > 60.0);
+    assert!(evaluate(&[&quiet], &[]).is_err());
+}
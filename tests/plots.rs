@@ -0,0 +1,63 @@
+#![cfg(feature = "plots")]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{multitone, write_wav};
+use tempfile::TempDir;
+
+fn saunds() -> Command {
+    Command::cargo_bin("saunds_v2").unwrap()
+}
+
+#[test]
+fn plots_filter_response_as_svg_and_png() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tone.wav");
+    write_wav(&input, &multitone(&[440.0], 0.25, 0.5, 44100), 44100, 1);
+
+    for plot in ["response.svg", "response.png"] {
+        saunds()
+            .arg("filter").arg(&input)
+            .arg("-o").arg(dir.path().join("filtered.wav"))
+            .args(["--type", "lowpass", "--to", "1000", "--order", "4"])
+            .arg("--plot").arg(dir.path().join(plot))
+            .assert()
+            .success();
+    }
+
+    let svg = std::fs::read_to_string(dir.path().join("response.svg")).unwrap();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("Magnitude (dB)") && svg.contains("Phase (degrees)"));
+    let png = std::fs::read(dir.path().join("response.png")).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+
+    saunds()
+        .arg("filter").arg(&input)
+        .arg("-o").arg(dir.path().join("filtered.wav"))
+        .args(["--type", "lowpass", "--to", "1000"])
+        .arg("--plot").arg(dir.path().join("response.pdf"))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("use .svg or .png"));
+}
+
+#[test]
+fn plots_spectrum_and_loudness_history() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    write_wav(&input, &multitone(&[125.0, 1000.0], 0.25, 4.0, 44100), 44100, 1);
+
+    saunds()
+        .arg("analyze").arg(&input)
+        .args(["--octave-bands", "1"])
+        .arg("--plot-spectrum").arg(dir.path().join("spectrum.svg"))
+        .arg("--plot-loudness").arg(dir.path().join("plots/loudness.svg"))
+        .assert()
+        .success();
+
+    let spectrum = std::fs::read_to_string(dir.path().join("spectrum.svg")).unwrap();
+    assert!(spectrum.contains("Long-term average spectrum"));
+    let loudness = std::fs::read_to_string(dir.path().join("plots/loudness.svg")).unwrap();
+    assert!(loudness.contains("Short-term") && loudness.contains("Integrated"));
+}
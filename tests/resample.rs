@@ -0,0 +1,51 @@
+mod common;
+
+use common::{multitone, tone_level_db};
+use saunds_v2::audio::resample::{delay, resample};
+
+#[test]
+fn resampling_keeps_tones_and_removes_what_no_longer_fits() {
+    let tones = multitone(&[1000.0, 20000.0], 0.25, 1.0, 44100);
+
+    let up = resample(&tones, 1, 44100, 48000);
+    assert_eq!(up.len(), 48000);
+    assert!((tone_level_db(&up, 48000, 1000.0) - tone_level_db(&tones, 44100, 1000.0)).abs() < 0.1);
+
+    // 20 kHz is above the 16 kHz Nyquist of the lower rate
+    let down = resample(&tones, 1, 44100, 32000);
+    assert_eq!(down.len(), 32000);
+    assert!((tone_level_db(&down, 32000, 1000.0) - tone_level_db(&tones, 44100, 1000.0)).abs() < 0.1);
+    assert!(down.iter().map(|x| x.abs()).fold(0.0, f32::max) < 0.3);
+}
+
+#[test]
+fn resampling_interleaved_stereo_keeps_channels_apart() {
+    let left = multitone(&[500.0], 0.25, 0.5, 44100);
+    let stereo: Vec<f32> = left.iter().flat_map(|&x| [x, 0.0]).collect();
+
+    let out = resample(&stereo, 2, 44100, 48000);
+    let (left, right): (Vec<f32>, Vec<f32>) = out.chunks_exact(2).map(|frame| (frame[0], frame[1])).unzip();
+    assert!(tone_level_db(&left, 48000, 500.0) > -12.5);
+    assert!(right.iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn delays_one_channel_by_a_fraction_of_a_sample() {
+    let rate = 48000.0;
+    let tone = |shift: f64| -> Vec<f32> {
+        (0..4800).map(|n| (0.5 * (2.0 * std::f64::consts::PI * 1000.0 * (n as f64 - shift) / rate).sin()) as f32).collect()
+    };
+    let stereo: Vec<f32> = tone(0.0).iter().flat_map(|&x| [x, x]).collect();
+
+    for shift in [0.3, -2.75, 5.0] {
+        let mut delayed = stereo.clone();
+        delay(&mut delayed, 2, 1, shift);
+        assert_eq!(delayed.len(), stereo.len());
+        let expected = tone(shift);
+        // Away from the edges the kernel sees a full span
+        for frame in 100..4700 {
+            assert_eq!(delayed[frame * 2], stereo[frame * 2]);
+            assert!((delayed[frame * 2 + 1] - expected[frame]).abs() < 1e-3, "shift {} at frame {}", shift, frame);
+        }
+    }
+}
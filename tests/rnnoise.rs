@@ -0,0 +1,29 @@
+#![cfg(feature = "rnnoise")]
+
+mod common;
+
+use common::noise;
+use saunds_v2::audio::effects::StageSpec;
+use saunds_v2::audio::AudioBuffer;
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn denoise_removes_steady_hiss() {
+    let hiss = noise(44100 * 2, 0.05, 1);
+    let mut denoised = AudioBuffer::from_planar(vec![hiss.clone()], 44100).unwrap();
+    let stage: StageSpec = "denoise".parse().unwrap();
+    stage.build(44100).unwrap().process(&mut denoised);
+    let denoised = denoised.channel(0);
+
+    assert_eq!(denoised.len(), hiss.len());
+    // Skip the network's first second while it adapts
+    assert!(rms(&denoised[44100..]) < 0.5 * rms(&hiss[44100..]));
+
+    let mut dry = AudioBuffer::from_planar(vec![hiss.clone()], 44100).unwrap();
+    let stage: StageSpec = "denoise:mix=0".parse().unwrap();
+    stage.build(44100).unwrap().process(&mut dry);
+    assert_eq!(dry.channel(0), hiss);
+}
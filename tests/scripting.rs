@@ -0,0 +1,64 @@
+#![cfg(feature = "scripting")]
+
+mod common;
+
+use assert_cmd::Command;
+use common::{multitone, read_wav, tone_level_db, write_wav};
+use saunds_v2::audio::script::SpectralScript;
+use tempfile::TempDir;
+
+const SAMPLE_RATE: u32 = 44100;
+
+const LOWPASS: &str = r#"
+for i in 0..mags.len() {
+    if freqs[i] > 1000.0 {
+        mags[i] = 0.0;
+    }
+}
+"#;
+
+#[test]
+fn script_rewrites_magnitudes() {
+    let tones = multitone(&[300.0, 3000.0], 0.25, 1.0, SAMPLE_RATE);
+    let mut filtered = tones.clone();
+    SpectralScript::compile(LOWPASS).unwrap().apply(&mut filtered, 1, SAMPLE_RATE, 2048).unwrap();
+
+    assert!((tone_level_db(&filtered, SAMPLE_RATE, 300.0) - tone_level_db(&tones, SAMPLE_RATE, 300.0)).abs() < 0.1);
+    assert!(tone_level_db(&filtered, SAMPLE_RATE, 3000.0) < -80.0);
+
+    // A script that leaves the frame alone reconstructs the input
+    let mut unchanged = tones.clone();
+    SpectralScript::compile("").unwrap().apply(&mut unchanged, 1, SAMPLE_RATE, 2048).unwrap();
+    assert!(unchanged.iter().zip(&tones).all(|(a, b)| (a - b).abs() < 1e-4));
+}
+
+#[test]
+fn script_errors_are_reported() {
+    let mut samples = multitone(&[300.0], 0.25, 0.1, SAMPLE_RATE);
+    assert!(SpectralScript::compile("let = ;").is_err());
+    let resized = SpectralScript::compile("mags.pop();").unwrap();
+    assert!(resized.apply(&mut samples, 1, SAMPLE_RATE, 2048).is_err());
+    let typed = SpectralScript::compile(r#"mags[0] = "loud";"#).unwrap();
+    assert!(typed.apply(&mut samples, 1, SAMPLE_RATE, 2048).is_err());
+}
+
+#[test]
+fn script_subcommand_runs_file() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("tones.wav");
+    let script = dir.path().join("lowpass.rhai");
+    let output = dir.path().join("out.wav");
+    write_wav(&input, &multitone(&[300.0, 3000.0], 0.25, 1.0, SAMPLE_RATE), SAMPLE_RATE, 1);
+    std::fs::write(&script, LOWPASS).unwrap();
+
+    Command::cargo_bin("saunds_v2")
+        .unwrap()
+        .arg("script").arg(&input)
+        .arg("--script").arg(&script)
+        .arg("--output").arg(&output)
+        .assert()
+        .success();
+
+    let (samples, _) = read_wav(&output);
+    assert!(tone_level_db(&samples, SAMPLE_RATE, 3000.0) < -80.0);
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::multitone;
+use saunds_v2::audio::spectral::{band_levels, frame_magnitudes};
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[test]
+fn band_levels_read_a_sine_at_its_amplitude() {
+    let cutoffs = [250.0, 2000.0, 8000.0];
+    for (amplitude, expected_db) in [(1.0, 0.0), (0.25, -12.04)] {
+        let tone = multitone(&[1000.0], amplitude, 0.5, SAMPLE_RATE);
+        let frames = frame_magnitudes(&tone, 2048).unwrap();
+        let levels = band_levels(&frames[frames.len() / 2], 2048, SAMPLE_RATE, &cutoffs);
+
+        assert_eq!(levels.len(), 4);
+        assert!((levels[1] - expected_db).abs() < 0.1, "1 kHz band reads {} dB", levels[1]);
+        for band in [0, 2, 3] {
+            assert!(levels[band] < expected_db - 60.0, "band {} reads {} dB", band, levels[band]);
+        }
+    }
+}
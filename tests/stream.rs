@@ -0,0 +1,51 @@
+mod common;
+
+use common::{multitone, noise};
+use saunds_v2::audio::{AudioProcessor, StftProcessor};
+
+#[test]
+fn streaming_matches_the_offline_split() {
+    let mut samples = multitone(&[110.0, 3000.0], 0.4, 0.5, 44100);
+    for (sample, n) in samples.iter_mut().zip(noise(22050, 0.1, 7)) {
+        *sample += n;
+    }
+    let processor = AudioProcessor::new().unwrap().with_sample_rate(44100).with_channels(1).with_window_size(1024);
+    let expected = processor.split_bands(&samples, &[500.0]).unwrap();
+
+    let mut stream = processor.band_stream(0.0, 500.0).unwrap();
+    assert_eq!(stream.latency(), 512);
+    let mut output = Vec::new();
+    // Uneven block sizes, some smaller than a hop
+    let mut rest = samples.as_slice();
+    for size in [1, 37, 700, 64, 2000].iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (block, tail) = rest.split_at((*size).min(rest.len()));
+        stream.push(block);
+        output.extend(stream.pull());
+        rest = tail;
+    }
+    assert!(output.len() <= samples.len() - stream.latency());
+    output.extend(stream.flush());
+    assert_eq!(output.len(), samples.len());
+    let error = output.iter().zip(&expected[0]).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max);
+    assert!(error < 1e-5, "error {}", error);
+
+    // Reusable after a flush
+    stream.push(&samples);
+    let mut again = stream.pull();
+    again.extend(stream.flush());
+    assert_eq!(again, output);
+}
+
+#[test]
+fn identity_stream_passes_the_input() {
+    let samples = noise(5000, 0.5, 3);
+    let mut stream = StftProcessor::new(256, |_| {}).unwrap();
+    stream.push(&samples);
+    let mut output = stream.pull();
+    output.extend(stream.flush());
+    assert!(output.iter().zip(&samples).all(|(a, b)| (a - b).abs() < 1e-5));
+    assert!(StftProcessor::new(255, |_| {}).is_err());
+}
@@ -0,0 +1,94 @@
+use saunds_v2::audio::{biquad::cascade_response_db, weighting::Weighting};
+
+/// IEC 61672-1 design goals at the exact base-10 third-octave frequencies:
+/// (band index relative to 1 kHz, A-weighting dB, C-weighting dB, class 1
+/// upper tolerance, class 1 lower tolerance). `None` marks an unbounded
+/// lower limit.
+const IEC_61672: &[(i32, f32, f32, f32, Option<f32>)] = &[
+    (-20, -70.4, -14.3, 3.5, None),
+    (-19, -63.4, -11.2, 3.0, None),
+    (-18, -56.7, -8.5, 2.5, Some(4.5)),
+    (-17, -50.5, -6.2, 2.5, Some(2.5)),
+    (-16, -44.7, -4.4, 2.5, Some(2.0)),
+    (-15, -39.4, -3.0, 2.0, Some(1.5)),
+    (-14, -34.6, -2.0, 1.0, Some(1.0)),
+    (-13, -30.2, -1.3, 1.0, Some(1.0)),
+    (-12, -26.2, -0.8, 1.0, Some(1.0)),
+    (-11, -22.5, -0.5, 1.0, Some(1.0)),
+    (-10, -19.1, -0.3, 1.0, Some(1.0)),
+    (-9, -16.1, -0.2, 1.0, Some(1.0)),
+    (-8, -13.4, -0.1, 1.0, Some(1.0)),
+    (-7, -10.9, 0.0, 1.0, Some(1.0)),
+    (-6, -8.6, 0.0, 1.0, Some(1.0)),
+    (-5, -6.6, 0.0, 1.0, Some(1.0)),
+    (-4, -4.8, 0.0, 1.0, Some(1.0)),
+    (-3, -3.2, 0.0, 1.0, Some(1.0)),
+    (-2, -1.9, 0.0, 1.0, Some(1.0)),
+    (-1, -0.8, 0.0, 1.0, Some(1.0)),
+    (0, 0.0, 0.0, 0.7, Some(0.7)),
+    (1, 0.6, 0.0, 1.0, Some(1.0)),
+    (2, 1.0, -0.1, 1.0, Some(1.0)),
+    (3, 1.2, -0.2, 1.0, Some(1.0)),
+    (4, 1.3, -0.3, 1.0, Some(1.0)),
+    (5, 1.2, -0.5, 1.0, Some(1.0)),
+    (6, 1.0, -0.8, 1.0, Some(1.0)),
+    (7, 0.5, -1.3, 1.5, Some(1.5)),
+    (8, -0.1, -2.0, 1.5, Some(2.0)),
+    (9, -1.1, -3.0, 1.5, Some(2.5)),
+    (10, -2.5, -4.4, 2.0, Some(3.0)),
+    (11, -4.3, -6.2, 2.0, Some(5.0)),
+    (12, -6.6, -8.5, 2.5, Some(16.0)),
+    (13, -9.3, -11.2, 3.0, None),
+];
+
+fn band_frequency(index: i32) -> f32 {
+    1000.0 * 10f32.powf(index as f32 / 10.0)
+}
+
+fn design_goal(weighting: Weighting, a: f32, c: f32) -> f32 {
+    match weighting {
+        Weighting::A => a,
+        Weighting::C => c,
+        Weighting::Z => 0.0,
+    }
+}
+
+#[test]
+fn analytic_curves_match_design_goals() {
+    for weighting in [Weighting::A, Weighting::C] {
+        for &(index, a, c, _, _) in IEC_61672 {
+            let frequency = band_frequency(index);
+            let gain = weighting.gain_db(frequency);
+            let goal = design_goal(weighting, a, c);
+            assert!((gain - goal).abs() <= 0.051, "{:?} at {} Hz: {:.2} dB, expected {}", weighting, frequency, gain, goal);
+        }
+    }
+}
+
+#[test]
+fn biquad_cascades_meet_class_1_tolerances() {
+    for sample_rate in [44100, 48000, 96000] {
+        for weighting in [Weighting::A, Weighting::C] {
+            let cascade = weighting.filter(sample_rate);
+            for &(index, a, c, upper, lower) in IEC_61672 {
+                let frequency = band_frequency(index);
+                if frequency >= sample_rate as f32 / 2.0 {
+                    continue;
+                }
+                let response = cascade_response_db(&cascade, frequency, sample_rate);
+                let goal = design_goal(weighting, a, c);
+                assert!(
+                    response <= goal + upper && lower.is_none_or(|lower| response >= goal - lower),
+                    "{:?} at {} Hz ({} Hz): {:.2} dB, expected {} dB",
+                    weighting, frequency, sample_rate, response, goal
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn z_weighting_is_flat() {
+    assert!(Weighting::Z.filter(48000).is_empty());
+    assert_eq!(Weighting::Z.gain_db(50.0), 0.0);
+}
@@ -0,0 +1,45 @@
+use saunds_v2::audio::wow_flutter;
+use std::f64::consts::PI;
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// `seconds` of a tone at `hz` whose frequency swings by `depth` (a
+/// fraction) at `rate` Hz.
+fn modulated(hz: f64, depth: f64, rate: f64, seconds: f64) -> Vec<f32> {
+    let mut phase = 0.0;
+    (0..(seconds * SAMPLE_RATE as f64) as usize)
+        .map(|n| {
+            let t = n as f64 / SAMPLE_RATE as f64;
+            phase += 2.0 * PI * hz * (1.0 + depth * (2.0 * PI * rate * t).sin()) / SAMPLE_RATE as f64;
+            (0.5 * phase.sin()) as f32
+        })
+        .collect()
+}
+
+#[test]
+fn measures_wow_at_the_weighting_peak() {
+    // 0.1% deviation at 4 Hz, where the weighting is 0 dB
+    let measured = wow_flutter::measure(&modulated(3150.0, 0.001, 4.0, 10.0), SAMPLE_RATE, 3150.0).unwrap();
+    assert!((measured.weighted_peak_percent - 0.1).abs() < 0.005, "{:?}", measured);
+    assert!((measured.weighted_rms_percent - 0.0707).abs() < 0.004, "{:?}", measured);
+    assert!(measured.wow_rms_percent > 2.0 * measured.flutter_rms_percent, "{:?}", measured);
+    assert!(measured.speed_error_percent.abs() < 0.001);
+}
+
+#[test]
+fn separates_flutter_and_speed_error() {
+    // 0.05% deviation at 30 Hz, played 1% fast
+    let measured = wow_flutter::measure(&modulated(3181.5, 0.0005, 30.0, 10.0), SAMPLE_RATE, 3150.0).unwrap();
+    assert!((measured.flutter_rms_percent - 0.0354).abs() < 0.002, "{:?}", measured);
+    assert!(measured.wow_rms_percent < 0.1 * measured.flutter_rms_percent, "{:?}", measured);
+    // The weighting is about 8 dB down at 30 Hz
+    assert!((measured.weighted_rms_percent / measured.flutter_rms_percent - 0.4).abs() < 0.1, "{:?}", measured);
+    assert!((measured.speed_error_percent - 1.0).abs() < 0.01, "{:?}", measured);
+}
+
+#[test]
+fn rejects_recordings_without_the_tone() {
+    let silence = vec![0.0; 5 * SAMPLE_RATE as usize];
+    assert!(wow_flutter::measure(&silence, SAMPLE_RATE, 3150.0).is_err());
+    assert!(wow_flutter::measure(&modulated(1000.0, 0.0, 1.0, 5.0), SAMPLE_RATE, 3150.0).is_err());
+}